@@ -30,6 +30,16 @@ pub enum Error {
     BadEnvConfig(String),
     CantUploadGossipToml,
     ChannelNotFound,
+    /// Occurs when a configuration does not satisfy its package's configuration schema. Each
+    /// element describes one violation, prefixed with the path of the offending value.
+    ConfigInvalid(Vec<String>),
+    /// Occurs when a package's `CONFIG_PATHS` entry is malformed. Holds the entry and the reason.
+    ConfigPath(String, String),
+    /// Occurs when a package's configuration schema cannot be read or compiled.
+    ConfigSchema(PathBuf, String),
+    /// When an external configuration source is invalid or cannot be read. Holds the source and
+    /// the reason.
+    ConfigSource(String, String),
     CryptoKeyError(String),
     DownloadFailed(String),
     EditorEnv(env::VarError),
@@ -39,8 +49,11 @@ pub enum Error {
     FileNotFound(String),
     GossipFileRelativePath(String),
     HabitatCore(hcore::Error),
+    /// When a hook timeout given for a service cannot be parsed.
+    HookTimeout(String),
     InstallHookFailed(PackageIdent),
     InvalidInstallHookMode(String),
+    InvalidTraceEndpoint(String),
     /// Occurs when making lower level IO calls.
     IO(io::Error),
     /// Errors when joining paths :)
@@ -50,11 +63,20 @@ pub enum Error {
     OfflineOriginKeyNotFound(String),
     OfflinePackageNotFound(PackageIdent),
     PackageNotFound(String),
+    /// When a package's metadata file holds an invalid value. Holds the file name and the reason.
+    PackageMetafile(String, String),
     /// Occurs upon errors related to file or directory permissions.
     PermissionFailed(String),
     /// When an error occurs serializing rendering context
     RenderContextSerialization(serde_json::Error),
+    /// When a fixture for rendering templates offline cannot be read or parsed.
+    RenderFixture(PathBuf, String),
+    /// When a resource limit given for a service cannot be parsed.
+    ResourceLimit(String),
     RootRequired,
+    /// When a secret reference in a service's configuration is malformed or cannot be resolved.
+    /// Holds the reference and the reason, never the secret itself.
+    SecretReference(String, String),
     StatusFileCorrupt(PathBuf),
     StrFromUtf8Error(str::Utf8Error),
     StringFromUtf8Error(string::FromUtf8Error),
@@ -73,6 +95,12 @@ pub enum Error {
     /// When an error occurs parsing toml
     TomlParser(toml::de::Error),
     TomlSerializeError(toml::ser::Error),
+    TraceExport(String),
+    /// When a template rendered in strict mode references a missing path. The error is
+    /// constructed with the template name, the line of the reference and the path.
+    UndefinedTemplatePath(String, usize, String),
+    /// When a capability given for a service's sandbox is not one known to Linux.
+    UnknownCapability(String),
     WireDecode(String),
 }
 
@@ -91,6 +119,19 @@ impl fmt::Display for Error {
                 "Can't upload gossip.toml, it's a reserved file name".to_string()
             }
             Error::ChannelNotFound => "Channel not found".to_string(),
+            Error::ConfigInvalid(ref errors) => format!(
+                "Configuration does not match its schema: {}",
+                errors.join("; ")
+            ),
+            Error::ConfigPath(ref entry, ref e) => {
+                format!("Invalid configuration path {}, {}", entry, e)
+            }
+            Error::ConfigSchema(ref path, ref e) => {
+                format!("Invalid configuration schema {}: {}", path.display(), e)
+            }
+            Error::ConfigSource(ref source, ref e) => {
+                format!("Invalid configuration source {}, {}", source, e)
+            }
             Error::CryptoKeyError(ref s) => format!("Missing or invalid key: {}", s),
             Error::DownloadFailed(ref msg) => msg.to_string(),
             Error::EditorEnv(ref e) => format!("Missing EDITOR environment variable: {}", e),
@@ -102,12 +143,14 @@ impl fmt::Display for Error {
                 s
             ),
             Error::HabitatCore(ref e) => format!("{}", e),
+            Error::HookTimeout(ref e) => format!("Invalid hook timeout, {}", e),
             Error::InstallHookFailed(ref ident) => {
                 format!("Install hook exited unsuccessfully: {}", ident)
             }
             Error::InvalidInstallHookMode(ref e) => {
                 format!("Invalid InstallHookMode conversion from {}", e)
             }
+            Error::InvalidTraceEndpoint(ref e) => format!("Invalid trace endpoint: {}", e),
             Error::IO(ref err) => format!("{}", err),
             Error::JoinPathsError(ref err) => format!("{}", err),
             Error::NetParseError(ref err) => format!("{}", err),
@@ -124,13 +167,26 @@ impl fmt::Display for Error {
                 ident
             ),
             Error::PackageNotFound(ref e) => format!("Package not found. {}", e),
+            Error::PackageMetafile(ref name, ref e) => {
+                format!("Invalid {} package metadata, {}", name, e)
+            }
             Error::PermissionFailed(ref e) => e.to_string(),
             Error::RenderContextSerialization(ref e) => {
                 format!("Unable to serialize rendering context, {}", e)
             }
+            Error::RenderFixture(ref path, ref e) => {
+                format!("Invalid render fixture {}, {}", path.display(), e)
+            }
+            Error::ResourceLimit(ref e) => format!("Invalid resource limit, {}", e),
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation".to_string()
             }
+            Error::SecretReference(ref reference, ref e) => {
+                format!(
+                    "Unable to resolve secret reference \"{}\", {}",
+                    reference, e
+                )
+            }
             Error::StatusFileCorrupt(ref path) => format!(
                 "Unable to decode contents of INSTALL_STATUS file, {}",
                 path.display()
@@ -142,6 +198,16 @@ impl fmt::Display for Error {
             Error::TomlMergeError(ref e) => format!("Failed to merge TOML: {}", e),
             Error::TomlParser(ref err) => format!("Failed to parse TOML: {}", err),
             Error::TomlSerializeError(ref e) => format!("Can't serialize TOML: {}", e),
+            Error::TraceExport(ref e) => format!("Failed to export trace spans: {}", e),
+            Error::UndefinedTemplatePath(ref template, line, ref path) => format!(
+                "Template {} references {} on line {}, which is not defined",
+                template, path, line
+            ),
+            Error::UnknownCapability(ref name) => format!(
+                "Unknown capability '{}', expected a Linux capability such as \
+                 CAP_NET_BIND_SERVICE",
+                name
+            ),
             Error::WireDecode(ref m) => format!("Failed to decode wire message: {}", m),
         };
         write!(f, "{}", msg)
@@ -156,6 +222,10 @@ impl error::Error for Error {
             Error::BadEnvConfig(_) => "Unknown syntax in Env Configuration",
             Error::CantUploadGossipToml => "Can't upload gossip.toml, it's a reserved filename",
             Error::ChannelNotFound => "Channel not found",
+            Error::ConfigInvalid(_) => "Configuration does not match its schema",
+            Error::ConfigPath(..) => "Invalid configuration template destination",
+            Error::ConfigSchema(..) => "Invalid configuration schema",
+            Error::ConfigSource(..) => "Invalid or unreadable configuration source",
             Error::CryptoKeyError(_) => "Missing or invalid key",
             Error::DownloadFailed(_) => "Failed to download from remote",
             Error::EditorEnv(_) => "Missing EDITOR environment variable",
@@ -166,8 +236,10 @@ impl error::Error for Error {
                 "Path for gossip file cannot have relative components (eg: ..)"
             }
             Error::HabitatCore(ref err) => err.description(),
+            Error::HookTimeout(_) => "Invalid hook timeout",
             Error::InstallHookFailed(_) => "Install hook exited unsuccessfully",
             Error::InvalidInstallHookMode(_) => "Invalid InstallHookMode",
+            Error::InvalidTraceEndpoint(_) => "Invalid trace endpoint",
             Error::IO(ref err) => err.description(),
             Error::JoinPathsError(ref err) => err.description(),
             Error::NetParseError(_) => "Can't parse IP:port",
@@ -177,11 +249,15 @@ impl error::Error for Error {
                 "No installed package or cached artifact could be found locally in offline mode"
             }
             Error::PackageNotFound(_) => "Package not found",
+            Error::PackageMetafile(..) => "Invalid package metadata",
             Error::PermissionFailed(_) => "File system permissions error",
             Error::RenderContextSerialization(_) => "Unable to serialize rendering context",
+            Error::RenderFixture(..) => "Invalid or unreadable render fixture",
+            Error::ResourceLimit(_) => "Invalid resource limit",
             Error::RootRequired => {
                 "Root or administrator permissions required to complete operation"
             }
+            Error::SecretReference(..) => "Unable to resolve secret reference",
             Error::StatusFileCorrupt(_) => "Unable to decode contents of INSTALL_STATUS file",
             Error::StrFromUtf8Error(_) => "Failed to convert a string as UTF-8",
             Error::StringFromUtf8Error(_) => "Failed to convert a string as UTF-8",
@@ -190,6 +266,9 @@ impl error::Error for Error {
             Error::TomlMergeError(_) => "Failed to merge TOML!",
             Error::TomlParser(_) => "Failed to parse TOML!",
             Error::TomlSerializeError(_) => "Can't serialize TOML",
+            Error::TraceExport(_) => "Failed to export trace spans",
+            Error::UndefinedTemplatePath(..) => "Template references an undefined path",
+            Error::UnknownCapability(_) => "Unknown capability",
             Error::WireDecode(_) => "Failed to decode wire message",
         }
     }
@@ -14,6 +14,7 @@
 
 use habitat_api_client as api_client;
 use habitat_core as hcore;
+use habitat_launcher_protocol as launcher_protocol;
 extern crate json;
 #[macro_use]
 extern crate log;
@@ -24,7 +25,7 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
-#[cfg_attr(test, macro_use)]
+#[macro_use]
 extern crate serde_json;
 
 #[cfg(windows)]
@@ -38,6 +39,7 @@ pub mod error;
 pub mod locked_env_var;
 pub mod package_graph;
 pub mod templating;
+pub mod trace;
 pub mod types;
 pub mod ui;
 pub mod util;
@@ -20,21 +20,28 @@ use crate::{
         fs::{self, USER_CONFIG_FILE},
         outputln,
     },
-    templating::{package::Pkg, TemplateRenderer},
+    templating::{
+        config_source::{ConfigSource, ExternalPrecedence},
+        package::{ConfigPath, Pkg},
+        secret, TemplateRenderer,
+    },
+    util::diff,
 };
 use serde::{Serialize, Serializer};
 use serde_json;
 use serde_transcode;
 use std::{
     self,
-    borrow::Cow,
-    env,
+    collections::HashMap,
+    env, fmt,
     fs::File,
     io::prelude::*,
     path::{Path, PathBuf},
     result,
 };
+use tempfile::NamedTempFile;
 use toml;
+use valico::json_schema;
 
 static LOGKEY: &'static str = "CF";
 static ENV_VAR_PREFIX: &'static str = "HAB";
@@ -48,6 +55,48 @@ static TOML_MAX_MERGE_DEPTH: u16 = 30;
 pub const CONFIG_PERMISSIONS: u32 = 0o740;
 #[cfg(unix)]
 pub const CONFIG_DIR_PERMISSIONS: u32 = 0o770;
+/// Name of the file, next to a package's `default.toml`, containing its configuration schema.
+pub const CONFIG_SCHEMA_FILE: &str = "config_schema.json";
+
+/// A layer of a service's configuration. Layers are merged in the order they are declared here,
+/// so a key set in a later layer overrides the same key in an earlier one. The external layer is
+/// the exception, its place among the others is given by its `ExternalPrecedence`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CfgLayer {
+    Default,
+    Environment,
+    User,
+    Gossip,
+    External,
+}
+
+impl fmt::Display for CfgLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match *self {
+            CfgLayer::Default => "default",
+            CfgLayer::Environment => "environment",
+            CfgLayer::User => "user",
+            CfgLayer::Gossip => "gossip",
+            CfgLayer::External => "external",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// The effective value of a single configuration key and the layer which set it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CfgProvenance {
+    /// Dotted path of the key within the configuration, e.g. `server.port`.
+    pub key: String,
+    pub value: toml::Value,
+    pub layer: CfgLayer,
+    /// File, environment variable or external source the layer was loaded from. Gossip has no
+    /// source.
+    pub source: Option<String>,
+    /// Incarnation of the service group's configuration, for keys set by gossip.
+    pub incarnation: Option<u64>,
+}
 
 /// Describes the path to user configuration that is used by the
 /// service.
@@ -114,6 +163,12 @@ pub struct Cfg {
     pub gossip: Option<toml::value::Table>,
     /// Environment level configuration loaded by the Supervisor's process environment
     pub environment: Option<toml::value::Table>,
+    /// External level configuration loaded from a Service's configuration source
+    pub external: Option<toml::value::Table>,
+    /// Where the external level sits among the other levels
+    pub external_precedence: ExternalPrecedence,
+    /// Schema the merged configuration must satisfy, loaded from a Package's `config_schema.json`
+    pub schema: Option<ConfigSchema>,
     /// Source of the user configuration
     pub user_config_path: UserConfigPath,
     /// Last known incarnation number of the census group's service config
@@ -121,6 +176,12 @@ pub struct Cfg {
     /// The path to an optional dev-time configuration directory that
     /// is being used.
     override_config_dir: Option<PathBuf>,
+    /// The directory the default level configuration was loaded from
+    default_config_dir: PathBuf,
+    /// The environment variable the environment level configuration is loaded from
+    environment_var: String,
+    /// The source the external level configuration is loaded from
+    external_source: Option<String>,
 }
 
 impl Cfg {
@@ -129,24 +190,32 @@ impl Cfg {
         P: PackageConfigPaths,
     {
         let override_config_dir = config_from.and_then(|c| Some(c.clone()));
-        let default = {
-            let pkg_root = match override_config_dir {
-                Some(ref path) => Cow::Borrowed(path),
-                None => Cow::Owned(package.default_config_dir()),
-            };
-            Self::load_default(pkg_root.as_ref())?
+        let default_config_dir = match override_config_dir {
+            Some(ref path) => path.clone(),
+            None => package.default_config_dir(),
         };
+        let default = Self::load_default(&default_config_dir)?;
+        let schema = ConfigSchema::load(&default_config_dir)?;
         let user_config_path = Self::determine_user_config_path(package);
         let user = Self::load_user(user_config_path.get_path())?;
         let environment = Self::load_environment(&package.name())?;
+        if let Some(ref environment) = environment {
+            secret::reject_local_references(environment)?;
+        }
         return Ok(Self {
             default,
             user,
             gossip: None,
             environment,
+            external: None,
+            external_precedence: ExternalPrecedence::default(),
+            schema,
             gossip_incarnation: 0,
             user_config_path,
             override_config_dir,
+            default_config_dir,
+            environment_var: Self::environment_var(&package.name()),
+            external_source: None,
         });
     }
 
@@ -170,6 +239,38 @@ impl Cfg {
         }
     }
 
+    /// Validates the merged configuration against the package's configuration schema. A package
+    /// without a schema accepts any configuration.
+    pub fn validate_schema(&self) -> Result<()> {
+        let schema = match self.schema {
+            Some(ref schema) => schema,
+            None => return Ok(()),
+        };
+        let merged = match toml::Value::try_from(self)? {
+            toml::Value::Table(table) => table,
+            _ => unreachable!("Cfg always serializes to a table"),
+        };
+        let errors = schema.validate(&merged);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ConfigInvalid(errors))
+        }
+    }
+
+    /// Validates the configuration which would result from replacing the gossip layer with the
+    /// given table, without changing this configuration. Gossip may only reference `box:`
+    /// secrets.
+    pub fn validate_gossip(&self, gossip: &toml::value::Table) -> Result<()> {
+        secret::reject_local_references(gossip)?;
+        if self.schema.is_none() {
+            return Ok(());
+        }
+        let mut candidate = self.clone();
+        candidate.gossip = Some(gossip.clone());
+        candidate.validate_schema()
+    }
+
     /// A structured interface which describes configuration keys which are configurable and their
     /// optional default values.
     pub fn interface(&self) -> Option<&toml::value::Table> {
@@ -198,13 +299,13 @@ impl Cfg {
     where
         P: PackageConfigPaths,
     {
-        let incoming_defaults = {
-            let pkg_root = match self.override_config_dir {
-                Some(ref path) => Cow::Borrowed(path),
-                None => Cow::Owned(package.default_config_dir()),
-            };
-            Self::load_default(pkg_root.as_ref())?
+        let default_config_dir = match self.override_config_dir {
+            Some(ref path) => path.clone(),
+            None => package.default_config_dir(),
         };
+        let incoming_defaults = Self::load_default(&default_config_dir)?;
+        self.schema = ConfigSchema::load(&default_config_dir)?;
+        self.default_config_dir = default_config_dir;
 
         if incoming_defaults != self.default {
             self.default = incoming_defaults;
@@ -214,10 +315,83 @@ impl Cfg {
         }
     }
 
-    /// Updates the service configuration with data from a census group
-    pub fn set_gossip(&mut self, incarnation: u64, gossip: toml::value::Table) {
+    /// Updates the service configuration with data from a census group.
+    ///
+    /// Configuration referencing a `file:` or `env:` secret is rejected, leaving the gossip layer
+    /// as it was. The incarnation is recorded either way, so a rejected configuration is not
+    /// retried.
+    pub fn set_gossip(&mut self, incarnation: u64, gossip: toml::value::Table) -> Result<()> {
         self.gossip_incarnation = incarnation;
+        secret::reject_local_references(&gossip)?;
         self.gossip = Some(gossip);
+        Ok(())
+    }
+
+    /// Loads the external layer of the configuration from the given source, placing it among the
+    /// other layers according to `precedence`.
+    ///
+    /// The source is kept even if it cannot be read, so that it is reported with the keys it sets
+    /// once it can.
+    pub fn load_external(
+        &mut self,
+        source: &dyn ConfigSource,
+        precedence: ExternalPrecedence,
+    ) -> Result<()> {
+        self.set_external_source(source, precedence);
+        let external = source.load()?;
+        if let Some(ref external) = external {
+            secret::reject_local_references(external)?;
+        }
+        self.external = external;
+        Ok(())
+    }
+
+    /// Records where the external layer of the configuration comes from and where it sits among
+    /// the other layers, without reading the source. The layer stays empty until it is set with
+    /// `set_external`.
+    pub fn set_external_source(
+        &mut self,
+        source: &dyn ConfigSource,
+        precedence: ExternalPrecedence,
+    ) {
+        self.external_source = Some(source.to_string());
+        self.external_precedence = precedence;
+    }
+
+    /// Replaces the external layer of the configuration with what its source now holds.
+    ///
+    /// If the new external configuration references a `file:` or `env:` secret, or the package
+    /// has a configuration schema the new external configuration would not satisfy, the previous
+    /// external configuration is kept and an error is returned.
+    pub fn set_external(&mut self, external: Option<toml::value::Table>) -> Result<()> {
+        if let Some(ref external) = external {
+            secret::reject_local_references(external)?;
+        }
+        if self.schema.is_some() {
+            let mut candidate = self.clone();
+            candidate.external = external.clone();
+            candidate.validate_schema()?;
+        }
+        self.external = external;
+        Ok(())
+    }
+
+    /// Returns each layer of the configuration in the order they are merged, lowest precedence
+    /// first.
+    fn layers(&self) -> Vec<(CfgLayer, Option<&toml::value::Table>)> {
+        let mut layers = vec![
+            (CfgLayer::Default, self.default.as_ref()),
+            (CfgLayer::Environment, self.environment.as_ref()),
+            (CfgLayer::User, self.user.as_ref()),
+            (CfgLayer::Gossip, self.gossip.as_ref()),
+        ];
+        let position = match self.external_precedence {
+            ExternalPrecedence::BelowUser => 2,
+            ExternalPrecedence::AboveUser => 3,
+            ExternalPrecedence::AboveGossip => 4,
+        };
+        layers.insert(position, (CfgLayer::External, self.external.as_ref()));
+        layers
     }
 
     /// Returns a subset of the overall configuration which intersects with the given package
@@ -247,9 +421,70 @@ impl Cfg {
                 map.insert(key.clone(), curr.clone());
             }
         }
+        // Exports are gossiped to the whole ring, so secrets are never resolved into them.
+        secret::redact(&mut map);
         Ok(map)
     }
 
+    /// Returns every key of the merged configuration along with its effective value and the
+    /// layer which set it, ordered by key. Tables are descended into, so only keys holding other
+    /// values (including arrays) are listed.
+    pub fn provenance(&self) -> Vec<CfgProvenance> {
+        let merged = match toml::Value::try_from(self) {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => unreachable!("Cfg always serializes to a table"),
+            Err(err) => {
+                outputln!("Error merging configuration layers, {}", err);
+                return vec![];
+            }
+        };
+        // Highest precedence first, so the first layer holding a key is the one which set it.
+        let mut layers = self.layers();
+        layers.reverse();
+        let mut keys = vec![];
+        flatten_toml(&merged, &mut vec![], &mut keys);
+        keys.into_iter()
+            .filter_map(|(path, value)| {
+                let layer = layers
+                    .iter()
+                    .find(|(_, table)| table.map_or(false, |t| toml_leaf(t, &path).is_some()))
+                    .map(|(layer, _)| *layer)?;
+                Some(CfgProvenance {
+                    key: path.join("."),
+                    value: value.clone(),
+                    layer,
+                    source: self.layer_source(layer),
+                    incarnation: if layer == CfgLayer::Gossip {
+                        Some(self.gossip_incarnation)
+                    } else {
+                        None
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn layer_source(&self, layer: CfgLayer) -> Option<String> {
+        match layer {
+            CfgLayer::Default => Some(
+                self.default_config_dir
+                    .join("default.toml")
+                    .display()
+                    .to_string(),
+            ),
+            CfgLayer::Environment => Some(self.environment_var.clone()),
+            CfgLayer::User => Some(
+                self.user_config_path
+                    .get_path()
+                    .join(USER_CONFIG_FILE)
+                    .display()
+                    .to_string(),
+            ),
+            CfgLayer::Gossip => None,
+            CfgLayer::External => self.external_source.clone(),
+        }
+    }
+
     fn load_toml_file<T1, T2>(dir: T1, file: T2) -> Result<Option<toml::value::Table>>
     where
         T1: AsRef<Path>,
@@ -331,16 +566,29 @@ impl Cfg {
     }
 
     /// Reloads the user configuration file.
+    ///
+    /// If the package has a configuration schema and the new user configuration would not satisfy
+    /// it, the previous user configuration is kept and an error describing each violation is
+    /// returned.
     pub fn reload_user(&mut self) -> Result<()> {
         let user = Self::load_user(self.user_config_path.get_path())?;
+        if self.schema.is_some() {
+            let mut candidate = self.clone();
+            candidate.user = user.clone();
+            candidate.validate_schema()?;
+        }
         self.user = user;
         Ok(())
     }
 
-    fn load_environment(package_name: &str) -> Result<Option<toml::value::Table>> {
-        let var_name = format!("{}_{}", ENV_VAR_PREFIX, package_name)
+    fn environment_var(package_name: &str) -> String {
+        format!("{}_{}", ENV_VAR_PREFIX, package_name)
             .to_ascii_uppercase()
-            .replace("-", "_");
+            .replace("-", "_")
+    }
+
+    fn load_environment(package_name: &str) -> Result<Option<toml::value::Table>> {
+        let var_name = Self::environment_var(package_name);
         match env::var(&var_name) {
             Ok(config) => {
                 // If we've got an environment variable, we'll parsing
@@ -406,24 +654,11 @@ impl Serialize for Cfg {
         S: Serializer,
     {
         let mut table = toml::value::Table::new();
-        if let Some(ref default_cfg) = self.default {
-            if let Err(err) = toml_merge(&mut table, default_cfg) {
-                outputln!("Error merging default-cfg into config, {}", err);
-            }
-        }
-        if let Some(ref env_cfg) = self.environment {
-            if let Err(err) = toml_merge(&mut table, env_cfg) {
-                outputln!("Error merging environment-cfg into config, {}", err);
-            }
-        }
-        if let Some(ref user_cfg) = self.user {
-            if let Err(err) = toml_merge(&mut table, user_cfg) {
-                outputln!("Error merging user-cfg into config, {}", err);
-            }
-        }
-        if let Some(ref gossip_cfg) = self.gossip {
-            if let Err(err) = toml_merge(&mut table, gossip_cfg) {
-                outputln!("Error merging gossip-cfg into config, {}", err);
+        for (layer, cfg) in self.layers() {
+            if let Some(cfg) = cfg {
+                if let Err(err) = toml_merge(&mut table, cfg) {
+                    outputln!("Error merging {}-cfg into config, {}", layer, err);
+                }
             }
         }
 
@@ -431,9 +666,91 @@ impl Serialize for Cfg {
     }
 }
 
+/// A JSON Schema describing the configuration a package accepts.
+///
+/// The schema is applied to the merged configuration (all layers, from `default.toml` to gossip),
+/// converted to JSON. It can describe value types, ranges, enumerations, required keys and nested
+/// tables, which the key-only check of `Cfg::validate` cannot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigSchema(serde_json::Value);
+
+impl ConfigSchema {
+    /// Parses and compiles a JSON Schema. The `path` is only used to describe errors.
+    pub fn from_json<T>(path: T, content: &str) -> Result<Self>
+    where
+        T: AsRef<Path>,
+    {
+        let value = serde_json::from_str(content)
+            .map_err(|e| Error::ConfigSchema(path.as_ref().to_path_buf(), e.to_string()))?;
+        // Compile once up front so a broken schema is reported when the package is loaded rather
+        // than whenever a configuration is validated.
+        let mut scope = json_schema::scope::Scope::new();
+        scope
+            .compile_and_return(value.clone(), false)
+            .map_err(|e| Error::ConfigSchema(path.as_ref().to_path_buf(), format!("{:?}", e)))?;
+        Ok(ConfigSchema(value))
+    }
+
+    fn load<T>(dir: T) -> Result<Option<Self>>
+    where
+        T: AsRef<Path>,
+    {
+        let path = dir.as_ref().join(CONFIG_SCHEMA_FILE);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Failed to open '{}', {}", path.display(), e);
+                return Ok(None);
+            }
+        };
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|e| Error::ConfigSchema(path.clone(), e.to_string()))?;
+        Self::from_json(&path, &content).map(Some)
+    }
+
+    /// Validates a configuration table, returning a description of each violation prefixed with
+    /// the JSON pointer of the offending value, e.g. `/server/port: Maximum condition is not met`.
+    pub fn validate(&self, cfg: &toml::value::Table) -> Vec<String> {
+        let mut scope = json_schema::scope::Scope::new();
+        let schema = match scope.compile_and_return(self.0.clone(), false) {
+            Ok(schema) => schema,
+            Err(e) => return vec![format!("Invalid configuration schema, {:?}", e)],
+        };
+        // Secret references can't be checked without resolving them, so they are validated as
+        // strings.
+        let mut cfg = cfg.clone();
+        secret::redact(&mut cfg);
+        let value = match serde_json::to_value(&cfg) {
+            Ok(value) => value,
+            Err(e) => return vec![format!("Unable to convert configuration to JSON, {}", e)],
+        };
+        schema
+            .validate(&value)
+            .errors
+            .iter()
+            .map(|e| {
+                let path = if e.get_path().is_empty() {
+                    "/"
+                } else {
+                    e.get_path()
+                };
+                match e.get_detail() {
+                    Some(detail) => format!("{}: {}", path, detail),
+                    None => format!("{}: {}", path, e.get_title()),
+                }
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 /// Renders configuration templates into config files.
-pub struct CfgRenderer(TemplateRenderer);
+pub struct CfgRenderer {
+    renderer: TemplateRenderer,
+    /// Destinations of templates rendered outside of the render path, by template name.
+    paths: HashMap<String, ConfigPath>,
+}
 
 impl CfgRenderer {
     /// Create a new `CfgRenderer` and load template files from a
@@ -448,9 +765,28 @@ impl CfgRenderer {
                 &PathBuf::new(),
                 TemplateRenderer::new(),
             )
-            .map(CfgRenderer)
+            .map(CfgRenderer::from)
         } else {
-            Ok(CfgRenderer(TemplateRenderer::new()))
+            Ok(CfgRenderer::from(TemplateRenderer::new()))
+        }
+    }
+
+    /// Render templates in strict mode, failing on references to undefined paths.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        self.renderer.set_strict_mode(strict);
+    }
+
+    /// Render the templates with a destination in `paths` there instead of the render path.
+    pub fn set_config_paths(&mut self, paths: HashMap<String, ConfigPath>) {
+        self.paths = paths;
+    }
+
+    /// Where a template is rendered to: its configured destination if it has one, otherwise the
+    /// same relative path under `render_path`.
+    fn destination(&self, render_path: &Path, template: &str) -> PathBuf {
+        match self.paths.get(template) {
+            Some(config_path) => config_path.path.clone(),
+            None => render_path.join(template),
         }
     }
 
@@ -474,10 +810,18 @@ impl CfgRenderer {
         // having issues and be more descriptive about what happened.
 
         let mut changed = false;
-        for template in self.0.get_templates().keys() {
-            let compiled = self.0.render(&template, ctx)?;
+        for template in self.renderer.get_templates().keys() {
+            let compiled = self.renderer.render(&template, ctx)?;
             let compiled_hash = crypto::hash::hash_string(&compiled);
-            let cfg_dest = render_path.as_ref().join(&template);
+            let cfg_dest = self.destination(render_path.as_ref(), template);
+            let config_path = self.paths.get(template);
+            let user = config_path
+                .and_then(|p| p.user.as_ref())
+                .unwrap_or(&pkg.svc_user);
+            let group = config_path
+                .and_then(|p| p.group.as_ref())
+                .unwrap_or(&pkg.svc_group);
+            let mode = config_path.and_then(|p| p.mode);
             let file_hash = match crypto::hash::hash_file(&cfg_dest) {
                 Ok(file_hash) => file_hash,
                 Err(e) => {
@@ -491,13 +835,17 @@ impl CfgRenderer {
                     cfg_dest.display()
                 );
 
-                ensure_directory_structure(
-                    render_path.as_ref(),
-                    &cfg_dest,
-                    &pkg.svc_user,
-                    &pkg.svc_group,
-                )?;
-                write_templated_file(&cfg_dest, &compiled, &pkg.svc_user, &pkg.svc_group)?;
+                if config_path.is_some() {
+                    std::fs::create_dir_all(cfg_dest.parent().expect("Destination has a parent"))?;
+                } else {
+                    ensure_directory_structure(
+                        render_path.as_ref(),
+                        &cfg_dest,
+                        &pkg.svc_user,
+                        &pkg.svc_group,
+                    )?;
+                }
+                write_templated_file(&cfg_dest, &compiled, user, group, mode)?;
                 outputln!(
                     preamble service_group_name,
                     "Created configuration file {}",
@@ -517,7 +865,7 @@ impl CfgRenderer {
                     "Configuration {} has changed; restarting",
                     cfg_dest.display()
                 );
-                write_templated_file(&cfg_dest, &compiled, &pkg.svc_user, &pkg.svc_group)?;
+                write_templated_file(&cfg_dest, &compiled, user, group, mode)?;
                 outputln!(
                     preamble service_group_name,
                     "Modified configuration file {}",
@@ -528,10 +876,70 @@ impl CfgRenderer {
         }
         Ok(changed)
     }
+
+    /// Render all configuration files without writing them, returning the path of each file
+    /// relative to the configuration directory along with its content, ordered by path.
+    pub fn render<T>(&self, ctx: &T) -> Result<Vec<(String, String)>>
+    where
+        T: Serialize,
+    {
+        let mut templates: Vec<&String> = self.renderer.get_templates().keys().collect();
+        templates.sort();
+        templates
+            .into_iter()
+            .map(|template| Ok((template.clone(), self.renderer.render(template, ctx)?)))
+            .collect()
+    }
+
+    /// Render all configuration files and compare them to the files currently in `render_path`,
+    /// without writing anything.
+    ///
+    /// Returns the relative path and a unified diff of every file which would change. Secrets the
+    /// configuration references are redacted from both sides of the diff.
+    pub fn diff<P, T>(&self, render_path: P, ctx: &T) -> Result<Vec<(String, String)>>
+    where
+        P: AsRef<Path>,
+        T: Serialize,
+    {
+        let raw = serde_json::to_value(ctx).map_err(Error::RenderContextSerialization)?;
+        let secrets = match raw.get("cfg") {
+            Some(cfg) => secret::resolved(cfg)?,
+            None => vec![],
+        };
+        let mut diffs = vec![];
+        for (template, compiled) in self.render(ctx)? {
+            let compiled = secret::redact_text(&compiled, &secrets);
+            let cfg_dest = self.destination(render_path.as_ref(), &template);
+            let (current, old_name) = match std::fs::read_to_string(&cfg_dest) {
+                Ok(current) => (
+                    secret::redact_text(&current, &secrets),
+                    format!("a/{}", template),
+                ),
+                Err(e) => {
+                    debug!("Cannot read {} to compare it, {}", cfg_dest.display(), e);
+                    (String::new(), "/dev/null".to_string())
+                }
+            };
+            let new_name = format!("b/{}", template);
+            if let Some(diff) = diff::unified(&old_name, &new_name, &current, &compiled) {
+                diffs.push((template, diff));
+            }
+        }
+        Ok(diffs)
+    }
+}
+
+impl From<TemplateRenderer> for CfgRenderer {
+    fn from(renderer: TemplateRenderer) -> Self {
+        CfgRenderer {
+            renderer,
+            paths: HashMap::new(),
+        }
+    }
 }
 
 // Recursively merges the `other` TOML table into `me`
-fn toml_merge(me: &mut toml::value::Table, other: &toml::value::Table) -> Result<()> {
+pub(crate) fn toml_merge(me: &mut toml::value::Table, other: &toml::value::Table) -> Result<()> {
     toml_merge_recurse(me, other, 0)
 }
 
@@ -572,6 +980,36 @@ fn toml_merge_recurse(
     Ok(())
 }
 
+/// Collects the path and value of every non-table value nested in `table`. Secret references
+/// are collected as values.
+fn flatten_toml<'a>(
+    table: &'a toml::value::Table,
+    path: &mut Vec<String>,
+    keys: &mut Vec<(Vec<String>, &'a toml::Value)>,
+) {
+    for (key, value) in table.iter() {
+        path.push(key.clone());
+        match value.as_table() {
+            Some(nested) if secret::toml_reference(value).is_none() => {
+                flatten_toml(nested, path, keys)
+            }
+            _ => keys.push((path.clone(), value)),
+        }
+        path.pop();
+    }
+}
+
+/// Returns the non-table value at the given path of nested tables, if there is one.
+fn toml_leaf<'a>(table: &'a toml::value::Table, path: &[String]) -> Option<&'a toml::Value> {
+    let (last, parents) = path.split_last()?;
+    let mut curr = table;
+    for key in parents {
+        curr = curr.get(key)?.as_table()?;
+    }
+    curr.get(last)
+        .filter(|value| !value.is_table() || secret::toml_reference(value).is_some())
+}
+
 fn is_toml_value_a_table(key: &str, table: &toml::value::Table) -> bool {
     match table.get(key) {
         None => return false,
@@ -583,23 +1021,33 @@ fn is_toml_value_a_table(key: &str, table: &toml::value::Table) -> bool {
 }
 
 #[cfg(unix)]
-fn set_permissions(path: &Path, user: &str, group: &str) -> hcore::error::Result<()> {
+fn set_permissions(
+    path: &Path,
+    user: &str,
+    group: &str,
+    mode: Option<u32>,
+) -> hcore::error::Result<()> {
     use crate::hcore::{os::users, util::posix_perm};
 
     if users::can_run_services_as_svc_user() {
         posix_perm::set_owner(path, &user, &group)?;
     }
 
-    let permissions = if path.is_dir() {
-        CONFIG_DIR_PERMISSIONS
-    } else {
-        CONFIG_PERMISSIONS
+    let permissions = match mode {
+        Some(mode) => mode,
+        None if path.is_dir() => CONFIG_DIR_PERMISSIONS,
+        None => CONFIG_PERMISSIONS,
     };
     posix_perm::set_permissions(&path, permissions)
 }
 
 #[cfg(windows)]
-fn set_permissions(path: &Path, _user: &str, _group: &str) -> hcore::error::Result<()> {
+fn set_permissions(
+    path: &Path,
+    _user: &str,
+    _group: &str,
+    _mode: Option<u32>,
+) -> hcore::error::Result<()> {
     use crate::hcore::util::win_perm;
 
     win_perm::harden_path(path)
@@ -653,16 +1101,27 @@ fn ensure_directory_structure(root: &Path, file: &Path, user: &str, group: &str)
     if !dir.exists() {
         std::fs::create_dir_all(&dir)?;
         for anc in dir.ancestors().take_while(|&d| d != root) {
-            set_permissions(&anc, &user, &group)?;
+            set_permissions(&anc, &user, &group, None)?;
         }
     }
     Ok(())
 }
 
-fn write_templated_file(path: &Path, compiled: &str, user: &str, group: &str) -> Result<()> {
-    File::create(path).and_then(|mut file| file.write_all(compiled.as_bytes()))?;
-
-    set_permissions(&path, &user, &group)?;
+/// Writes a rendered template atomically, so the service never reads a partially written file.
+/// The content is written to a temporary file in the same directory, which is given its owner
+/// and permissions before being renamed over `path`.
+fn write_templated_file(
+    path: &Path,
+    compiled: &str,
+    user: &str,
+    group: &str,
+    mode: Option<u32>,
+) -> Result<()> {
+    let dir = path.parent().expect("Rendered file has a parent directory");
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(compiled.as_bytes())?;
+    set_permissions(file.path(), &user, &group, mode)?;
+    file.persist(path).map_err(|e| e.error)?;
     Ok(())
 }
 
@@ -675,7 +1134,7 @@ mod test {
             os::users,
             package::{PackageIdent, PackageInstall},
         },
-        templating::{context::RenderContext, test_helpers::*},
+        templating::{context::RenderContext, package::CONFIG_PATHS_METAFILE, test_helpers::*},
     };
     use std::{
         env,
@@ -1040,6 +1499,237 @@ mod test {
         assert_eq!(default_toml, toml::to_string(&cfg).unwrap());
     }
 
+    #[test]
+    fn provenance_reports_layer_of_each_key() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join("default.toml"),
+            "port = 6379\nmode = \"primary\"\n[tls]\nenabled = false\nciphers = [\"a\"]",
+        );
+        write_toml(&cfg_data.rucp, "mode = \"replica\"");
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        cfg.set_gossip(7, toml_from_str("[tls]\nenabled = true"))
+            .expect("set gossip config");
+
+        let provenance = cfg.provenance();
+        let keys: Vec<&str> = provenance.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, vec!["mode", "port", "tls.ciphers", "tls.enabled"]);
+
+        assert_eq!(provenance[0].layer, CfgLayer::User);
+        assert_eq!(provenance[0].value, toml::Value::from("replica"));
+        assert_eq!(
+            provenance[0].source,
+            Some(cfg_data.rucp.display().to_string())
+        );
+        assert_eq!(provenance[1].layer, CfgLayer::Default);
+        assert_eq!(
+            provenance[1].source,
+            Some(
+                cfg_data
+                    .pkg
+                    .default_config_dir()
+                    .join("default.toml")
+                    .display()
+                    .to_string()
+            )
+        );
+        assert_eq!(provenance[1].incarnation, None);
+        assert_eq!(provenance[2].layer, CfgLayer::Default);
+        assert_eq!(provenance[3].layer, CfgLayer::Gossip);
+        assert_eq!(provenance[3].value, toml::Value::Boolean(true));
+        assert_eq!(provenance[3].source, None);
+        assert_eq!(provenance[3].incarnation, Some(7));
+    }
+
+    #[derive(Debug)]
+    struct TestSource(Option<toml::value::Table>);
+
+    impl ConfigSource for TestSource {
+        fn load(&self) -> Result<Option<toml::value::Table>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    impl fmt::Display for TestSource {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "/etc/config/testing")
+        }
+    }
+
+    #[test]
+    fn external_layer_follows_its_precedence() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join("default.toml"),
+            "port = 6379\nmode = \"primary\"\nbind = \"0.0.0.0\"",
+        );
+        write_toml(&cfg_data.rucp, "mode = \"replica\"\nport = 6380");
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        cfg.set_gossip(3, toml_from_str("port = 6381"))
+            .expect("set gossip config");
+        let source = TestSource(Some(toml_from_str(
+            "port = 7000\nmode = \"sentinel\"\nbind = \"127.0.0.1\"",
+        )));
+
+        let merged = |cfg: &Cfg| toml::Value::try_from(cfg).expect("serialize config");
+        cfg.load_external(&source, ExternalPrecedence::BelowUser)
+            .expect("load external config");
+        let value = merged(&cfg);
+        assert_eq!(value["bind"].as_str(), Some("127.0.0.1"));
+        assert_eq!(value["mode"].as_str(), Some("replica"));
+        assert_eq!(value["port"].as_integer(), Some(6381));
+
+        cfg.load_external(&source, ExternalPrecedence::AboveUser)
+            .expect("load external config");
+        let value = merged(&cfg);
+        assert_eq!(value["mode"].as_str(), Some("sentinel"));
+        assert_eq!(value["port"].as_integer(), Some(6381));
+
+        cfg.load_external(&source, ExternalPrecedence::AboveGossip)
+            .expect("load external config");
+        assert_eq!(merged(&cfg)["port"].as_integer(), Some(7000));
+    }
+
+    #[test]
+    fn provenance_reports_external_source() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join("default.toml"),
+            "port = 6379",
+        );
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        cfg.load_external(
+            &TestSource(Some(toml_from_str("port = 7000"))),
+            ExternalPrecedence::default(),
+        )
+        .expect("load external config");
+
+        let provenance = cfg.provenance();
+        assert_eq!(provenance[0].layer, CfgLayer::External);
+        assert_eq!(provenance[0].value, toml::Value::Integer(7000));
+        assert_eq!(
+            provenance[0].source,
+            Some("/etc/config/testing".to_string())
+        );
+        assert_eq!(provenance[0].incarnation, None);
+    }
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["port"],
+        "properties": {
+            "port": { "type": "integer", "minimum": 1, "maximum": 65535 },
+            "mode": { "enum": ["primary", "replica"] },
+            "tls": {
+                "type": "object",
+                "properties": { "enabled": { "type": "boolean" } }
+            }
+        }
+    }"#;
+
+    fn write_schema(pkg: &TestPkg) {
+        write_toml(&pkg.default_config_dir().join(CONFIG_SCHEMA_FILE), SCHEMA);
+    }
+
+    #[test]
+    fn load_config_schema() {
+        let cfg_data = CfgTestData::new();
+        write_schema(&cfg_data.pkg);
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        assert!(cfg.schema.is_some());
+    }
+
+    #[test]
+    fn invalid_config_schema_fails_to_load() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join(CONFIG_SCHEMA_FILE),
+            "{ not json",
+        );
+        match Cfg::new(&cfg_data.pkg, None) {
+            Err(Error::ConfigSchema(..)) => (),
+            other => panic!("Expected a config schema error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn schema_reports_error_paths() {
+        let schema = ConfigSchema::from_json("config_schema.json", SCHEMA).unwrap();
+        let errors = schema.validate(&toml_from_str(
+            "port = 70000\nmode = \"leader\"\n[tls]\nenabled = 1",
+        ));
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors.iter().any(|e| e.starts_with("/port: ")));
+        assert!(errors.iter().any(|e| e.starts_with("/mode: ")));
+        assert!(errors.iter().any(|e| e.starts_with("/tls/enabled: ")));
+        assert_eq!(
+            schema.validate(&toml_from_str("port = 6379")),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn schema_reports_missing_required_keys() {
+        let schema = ConfigSchema::from_json("config_schema.json", SCHEMA).unwrap();
+        let errors = schema.validate(&toml_from_str("mode = \"primary\""));
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("port"), "{:?}", errors);
+    }
+
+    #[test]
+    fn validate_gossip_against_merged_config() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join("default.toml"),
+            "port = 6379",
+        );
+        write_schema(&cfg_data.pkg);
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        cfg.validate_schema().expect("defaults satisfy the schema");
+        cfg.validate_gossip(&toml_from_str("mode = \"replica\""))
+            .expect("port is provided by the defaults");
+        match cfg.validate_gossip(&toml_from_str("port = 0")) {
+            Err(Error::ConfigInvalid(errors)) => assert!(errors[0].starts_with("/port: ")),
+            other => panic!("Expected an invalid config error, got {:?}", other),
+        }
+        assert!(cfg.gossip.is_none());
+    }
+
+    #[test]
+    fn reload_user_keeps_previous_config_when_invalid() {
+        let cfg_data = CfgTestData::new();
+        write_schema(&cfg_data.pkg);
+        write_toml(&cfg_data.rucp, "port = 6379");
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        write_toml(&cfg_data.rucp, "port = \"6380\"");
+        assert!(cfg.reload_user().is_err());
+        assert_eq!(cfg.user, Some(toml_from_str("port = 6379")));
+
+        write_toml(&cfg_data.rucp, "port = 6380");
+        cfg.reload_user().expect("reload user config");
+        assert_eq!(cfg.user, Some(toml_from_str("port = 6380")));
+    }
+
+    #[test]
+    fn set_external_keeps_previous_config_when_invalid() {
+        let cfg_data = CfgTestData::new();
+        write_schema(&cfg_data.pkg);
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join("default.toml"),
+            "port = 6379",
+        );
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        cfg.set_external(Some(toml_from_str("port = 6380")))
+            .expect("set external config");
+        assert!(cfg.set_external(Some(toml_from_str("port = 0"))).is_err());
+        assert_eq!(cfg.external, Some(toml_from_str("port = 6380")));
+
+        cfg.set_external(None).expect("clear external config");
+        assert!(cfg.external.is_none());
+    }
+
     // env_key: the name of the environment variable the config should
     //     be read from
     // package_name: the name of the package that would read
@@ -1271,4 +1961,243 @@ mod test {
             "config message is Hello"
         );
     }
+
+    #[test]
+    fn test_compile_to_config_path() {
+        let root = TempDir::new().expect("create temp dir").into_path();
+        let pkg_dir = root.join("pkg/testing/test");
+        fs::create_dir_all(&pkg_dir).expect("create pkg dir");
+        let pg_id = PackageIdent::new("testing", "test", Some("1.0.0"), Some("20170712000000"));
+        let pkg_install = PackageInstall::new_from_parts(
+            pg_id.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+        );
+        create_with_content(
+            pkg_dir.join("default.toml"),
+            &String::from("message = \"Hello\""),
+        );
+        let dest = root.join("etc").join("app").join("app.conf");
+        create_with_content(
+            pkg_dir.join(CONFIG_PATHS_METAFILE),
+            format!("app.conf={} 0640\n", dest.display()),
+        );
+        let config_dir = pkg_dir.join("config");
+        fs::create_dir_all(&config_dir).expect("create config dir");
+        create_with_content(
+            config_dir.join("app.conf"),
+            &String::from("config message is {{cfg.message}}"),
+        );
+        let output_dir = root.join("output");
+        fs::create_dir_all(&output_dir).expect("create output dir");
+
+        let pkg = Pkg::from_install(&pkg_install).unwrap();
+        let cfg = Cfg::new(&pkg, None).unwrap();
+        let mut renderer = CfgRenderer::new(&config_dir).expect("create cfg renderer");
+        renderer.set_config_paths(pkg.config_paths.clone());
+        let ctx = RenderContext::new(&pkg, &cfg);
+        assert!(renderer
+            .compile("test", &pkg, &output_dir, &ctx)
+            .expect("compile"));
+        assert!(!renderer
+            .compile("test", &pkg, &output_dir, &ctx)
+            .expect("compile"));
+
+        assert_eq!(file_content(&dest), "config message is Hello");
+        assert!(!output_dir.join("app.conf").exists());
+        assert_eq!(fs::read_dir(dest.parent().unwrap()).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&dest).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+    }
+
+    #[test]
+    fn test_diff_does_not_touch_rendered_config() {
+        let root = TempDir::new().expect("create temp dir").into_path();
+        let pkg_dir = root.join("pkg/testing/test");
+        fs::create_dir_all(&pkg_dir).expect("create pkg dir");
+        let pg_id = PackageIdent::new("testing", "test", Some("1.0.0"), Some("20170712000000"));
+        let pkg_install = PackageInstall::new_from_parts(
+            pg_id.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+        );
+        create_with_content(
+            pkg_dir.join("default.toml"),
+            &String::from("message = \"Hello\""),
+        );
+        let config_dir = pkg_dir.join("config");
+        fs::create_dir_all(&config_dir).expect("create config dir");
+        create_with_content(
+            config_dir.join("config.txt"),
+            &String::from("first line\nconfig message is {{cfg.message}}"),
+        );
+        let output_dir = root.join("output");
+        fs::create_dir_all(&output_dir).expect("create output dir");
+
+        let pkg = Pkg::from_install(&pkg_install).unwrap();
+        let cfg = Cfg::new(&pkg, None).unwrap();
+        let renderer = CfgRenderer::new(&config_dir).expect("create cfg renderer");
+        renderer
+            .compile("test", &pkg, &output_dir, &RenderContext::new(&pkg, &cfg))
+            .expect("compile");
+
+        let diffs = renderer
+            .diff(&output_dir, &RenderContext::new(&pkg, &cfg))
+            .expect("diff");
+        assert!(diffs.is_empty());
+
+        let mut candidate = cfg.clone();
+        candidate
+            .set_gossip(1, toml_from_str("message = \"Goodbye\""))
+            .expect("set gossip config");
+        let diffs = renderer
+            .diff(&output_dir, &RenderContext::new(&pkg, &candidate))
+            .expect("diff");
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].0, "config.txt");
+        assert_eq!(
+            diffs[0].1,
+            "--- a/config.txt\n+++ b/config.txt\n@@ -1,2 +1,2 @@\n first line\n\
+             -config message is Hello\n+config message is Goodbye\n"
+        );
+        assert_eq!(
+            file_content(output_dir.join("config.txt")),
+            "first line\nconfig message is Hello"
+        );
+    }
+
+    #[test]
+    fn test_secret_references_are_only_resolved_when_rendering() {
+        let root = TempDir::new().expect("create temp dir").into_path();
+        let pkg_dir = root.join("pkg/testing/test");
+        fs::create_dir_all(&pkg_dir).expect("create pkg dir");
+        let pg_id = PackageIdent::new("testing", "test", Some("1.0.0"), Some("20170712000000"));
+        let pkg_install = PackageInstall::new_from_parts(
+            pg_id.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+        );
+        create_with_content(
+            pkg_dir.join("default.toml"),
+            &String::from("password = { secret = \"env:HAB_TEST_CFG_SECRET\" }"),
+        );
+        let config_dir = pkg_dir.join("config");
+        fs::create_dir_all(&config_dir).expect("create config dir");
+        create_with_content(
+            config_dir.join("config.txt"),
+            &String::from("password is {{cfg.password}}"),
+        );
+        let output_dir = root.join("output");
+        fs::create_dir_all(&output_dir).expect("create output dir");
+        env::set_var("HAB_TEST_CFG_SECRET", "s3cret");
+
+        let mut pkg = Pkg::from_install(&pkg_install).unwrap();
+        pkg.exports
+            .insert("password".to_string(), "password".to_string());
+        let cfg = Cfg::new(&pkg, None).unwrap();
+        let renderer = CfgRenderer::new(&config_dir).expect("create cfg renderer");
+        renderer
+            .compile("test", &pkg, &output_dir, &RenderContext::new(&pkg, &cfg))
+            .expect("compile");
+        assert_eq!(
+            file_content(output_dir.join("config.txt")),
+            "password is s3cret"
+        );
+
+        let serialized = toml::Value::try_from(&cfg).unwrap();
+        assert_eq!(
+            serialized["password"]["secret"].as_str(),
+            Some("env:HAB_TEST_CFG_SECRET")
+        );
+        let exported = cfg.to_exported(&pkg).unwrap();
+        assert_eq!(exported["password"].as_str(), Some(secret::REDACTED));
+    }
+
+    #[test]
+    fn test_secret_references_through_gossip_are_rejected() {
+        let cfg_data = CfgTestData::new();
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        cfg.set_gossip(1, toml_from_str("port = 6380"))
+            .expect("set gossip config");
+
+        let stolen = toml_from_str("[tls]\nkey = { secret = \"file:/etc/shadow\" }");
+        assert!(cfg.validate_gossip(&stolen).is_err());
+        assert!(cfg.set_gossip(2, stolen.clone()).is_err());
+        assert_eq!(cfg.gossip, Some(toml_from_str("port = 6380")));
+        assert_eq!(cfg.gossip_incarnation, 2);
+        assert!(cfg.set_external(Some(stolen)).is_err());
+        assert_eq!(cfg.external, None);
+    }
+
+    #[test]
+    fn test_box_secret_references_through_gossip_are_accepted() {
+        let cfg_data = CfgTestData::new();
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        let sealed = toml_from_str("[tls]\nkey = { secret = \"box:BOX-1\\nsealed\" }");
+        assert!(cfg.validate_gossip(&sealed).is_ok());
+        cfg.set_gossip(1, sealed.clone())
+            .expect("set gossip config");
+        assert_eq!(cfg.gossip, Some(sealed.clone()));
+        cfg.set_external(Some(sealed.clone()))
+            .expect("set external config");
+        assert_eq!(cfg.external, Some(sealed));
+    }
+
+    #[test]
+    fn test_diff_redacts_secrets() {
+        let root = TempDir::new().expect("create temp dir").into_path();
+        let pkg_dir = root.join("pkg/testing/test");
+        fs::create_dir_all(&pkg_dir).expect("create pkg dir");
+        let pg_id = PackageIdent::new("testing", "test", Some("1.0.0"), Some("20170712000000"));
+        let pkg_install = PackageInstall::new_from_parts(
+            pg_id.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+            pkg_dir.clone(),
+        );
+        create_with_content(
+            pkg_dir.join("default.toml"),
+            &String::from(
+                "message = \"Hello\"\npassword = { secret = \"env:HAB_TEST_DIFF_SECRET\" }",
+            ),
+        );
+        let config_dir = pkg_dir.join("config");
+        fs::create_dir_all(&config_dir).expect("create config dir");
+        create_with_content(
+            config_dir.join("config.txt"),
+            &String::from("{{cfg.message}} with password {{cfg.password}}"),
+        );
+        let output_dir = root.join("output");
+        fs::create_dir_all(&output_dir).expect("create output dir");
+        env::set_var("HAB_TEST_DIFF_SECRET", "s3cret");
+
+        let pkg = Pkg::from_install(&pkg_install).unwrap();
+        let cfg = Cfg::new(&pkg, None).unwrap();
+        let renderer = CfgRenderer::new(&config_dir).expect("create cfg renderer");
+        renderer
+            .compile("test", &pkg, &output_dir, &RenderContext::new(&pkg, &cfg))
+            .expect("compile");
+
+        let mut candidate = cfg.clone();
+        candidate
+            .set_gossip(1, toml_from_str("message = \"Goodbye\""))
+            .expect("set gossip config");
+        let diffs = renderer
+            .diff(&output_dir, &RenderContext::new(&pkg, &candidate))
+            .expect("diff");
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(
+            diffs[0].1,
+            "--- a/config.txt\n+++ b/config.txt\n@@ -1 +1 @@\n\
+             -Hello with password <redacted>\n+Goodbye with password <redacted>\n"
+        );
+    }
 }
@@ -0,0 +1,330 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! External sources for the `external` layer of a service's configuration.
+//!
+//! A service may be loaded with a configuration source which is read in addition to
+//! `default.toml`, the environment, `user.toml` and gossip. The Supervisor polls the source and
+//! reconfigures the service whenever what it holds changes. Sources are given as either:
+//!
+//! * an absolute path to a directory of `.toml` and `.json` fragments, such as a mounted
+//!   Kubernetes ConfigMap, which are merged in file name order
+//! * an `http://` URL returning a TOML or JSON document
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    result,
+    str::FromStr,
+    time::Duration,
+};
+
+use hyper::{
+    self,
+    net::{HttpStream, NetworkConnector},
+    Url,
+};
+use serde_json;
+use toml;
+
+use crate::{
+    error::{Error, Result},
+    templating::config::toml_merge,
+};
+
+/// How long connecting to, writing to or reading from an HTTP source may take before it fails.
+pub const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A source of configuration for the `external` layer.
+pub trait ConfigSource: fmt::Debug + fmt::Display + Send + Sync {
+    /// Reads the configuration the source currently holds, or `None` if it holds none.
+    fn load(&self) -> Result<Option<toml::value::Table>>;
+
+    /// Whether reading the source means a request to a remote endpoint, which may take too long
+    /// to be done on the Supervisor's main loop.
+    fn is_remote(&self) -> bool {
+        false
+    }
+}
+
+/// Parses a configuration source, as given to `hab svc load --config-source`.
+pub fn parse(source: &str) -> Result<Box<dyn ConfigSource>> {
+    if source.starts_with("http://") {
+        let url = Url::parse(source)
+            .map_err(|e| Error::ConfigSource(source.to_string(), e.to_string()))?;
+        return Ok(Box::new(HttpSource(url)));
+    }
+    let path = PathBuf::from(source);
+    if path.is_absolute() {
+        Ok(Box::new(DirSource(path)))
+    } else {
+        Err(Error::ConfigSource(
+            source.to_string(),
+            "expected an absolute directory path or an http:// URL".to_string(),
+        ))
+    }
+}
+
+/// Where the `external` layer sits among the other layers of a service's configuration.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExternalPrecedence {
+    /// Overrides the environment and is overridden by `user.toml` and gossip.
+    BelowUser,
+    /// Overrides `user.toml` and is overridden by gossip.
+    AboveUser,
+    /// Overrides every other layer, including gossip.
+    AboveGossip,
+}
+
+impl Default for ExternalPrecedence {
+    fn default() -> Self {
+        ExternalPrecedence::BelowUser
+    }
+}
+
+impl FromStr for ExternalPrecedence {
+    type Err = Error;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value {
+            "below-user" => Ok(ExternalPrecedence::BelowUser),
+            "above-user" => Ok(ExternalPrecedence::AboveUser),
+            "above-gossip" => Ok(ExternalPrecedence::AboveGossip),
+            _ => Err(Error::ConfigSource(
+                value.to_string(),
+                "precedence must be one of below-user, above-user or above-gossip".to_string(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExternalPrecedence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match *self {
+            ExternalPrecedence::BelowUser => "below-user",
+            ExternalPrecedence::AboveUser => "above-user",
+            ExternalPrecedence::AboveGossip => "above-gossip",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+/// A directory of configuration fragments. Files ending in `.toml` or `.json` are merged in file
+/// name order, so a key in a later file overrides the same key in an earlier one. Hidden files
+/// are ignored, which skips the bookkeeping entries Kubernetes adds to mounted ConfigMaps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirSource(pub PathBuf);
+
+impl ConfigSource for DirSource {
+    fn load(&self) -> Result<Option<toml::value::Table>> {
+        if !self.0.is_dir() {
+            return Ok(None);
+        }
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.0)
+            .map_err(|e| self.error(e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                let hidden = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(true, |name| name.starts_with('.'));
+                let fragment = match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("toml") | Some("json") => true,
+                    _ => false,
+                };
+                !hidden && fragment && path.is_file()
+            })
+            .collect();
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        paths.sort();
+        let mut merged = toml::value::Table::new();
+        for path in paths {
+            let fragment = self.load_fragment(&path)?;
+            toml_merge(&mut merged, &fragment)?;
+        }
+        Ok(Some(merged))
+    }
+}
+
+impl DirSource {
+    fn load_fragment(&self, path: &Path) -> Result<toml::value::Table> {
+        let mut content = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .map_err(|e| self.error(format!("{}, {}", path.display(), e)))?;
+        let is_json = path.extension().map_or(false, |ext| ext == "json");
+        parse_document(&content, is_json)
+            .map_err(|e| self.error(format!("{}, {}", path.display(), e)))
+    }
+
+    fn error<T: ToString>(&self, err: T) -> Error {
+        Error::ConfigSource(self.to_string(), err.to_string())
+    }
+}
+
+impl fmt::Display for DirSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
+/// An HTTP endpoint returning a TOML or JSON document. A response with a JSON content type is
+/// parsed as JSON, anything else as TOML. A `404 Not Found` response means the endpoint holds no
+/// configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpSource(pub Url);
+
+impl ConfigSource for HttpSource {
+    fn load(&self) -> Result<Option<toml::value::Table>> {
+        let err = |e: String| Error::ConfigSource(self.to_string(), e);
+        let mut client = hyper::Client::with_connector(TimeoutConnector);
+        client.set_read_timeout(Some(HTTP_TIMEOUT));
+        client.set_write_timeout(Some(HTTP_TIMEOUT));
+        let mut res = client
+            .get(self.0.clone())
+            .send()
+            .map_err(|e| err(e.to_string()))?;
+        if res.status == hyper::status::StatusCode::NotFound {
+            return Ok(None);
+        }
+        if !res.status.is_success() {
+            return Err(err(format!("responded with {}", res.status)));
+        }
+        let is_json = res
+            .headers
+            .get::<hyper::header::ContentType>()
+            .map_or(false, |ct| ct.to_string().contains("json"));
+        let mut content = String::new();
+        res.read_to_string(&mut content)
+            .map_err(|e| err(e.to_string()))?;
+        parse_document(&content, is_json).map(Some).map_err(err)
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+}
+
+impl fmt::Display for HttpSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Connects like hyper's `HttpConnector`, but gives up on each address after `HTTP_TIMEOUT`.
+struct TimeoutConnector;
+
+impl NetworkConnector for TimeoutConnector {
+    type Stream = HttpStream;
+
+    fn connect(&self, host: &str, port: u16, _scheme: &str) -> hyper::Result<HttpStream> {
+        let mut last_err = None;
+        for addr in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, HTTP_TIMEOUT) {
+                Ok(stream) => return Ok(HttpStream(stream)),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address for host"))
+            .into())
+    }
+}
+
+fn parse_document(content: &str, is_json: bool) -> result::Result<toml::value::Table, String> {
+    if is_json {
+        serde_json::from_str(content).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(content).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        File::create(dir.join(name))
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn parse_sources() {
+        assert!(parse("/etc/config").is_ok());
+        assert!(parse("http://config.local/redis").is_ok());
+        assert!(parse("relative/config").is_err());
+    }
+
+    #[test]
+    fn parse_precedence() {
+        assert_eq!(
+            "above-user".parse::<ExternalPrecedence>().unwrap(),
+            ExternalPrecedence::AboveUser
+        );
+        assert_eq!(
+            ExternalPrecedence::AboveGossip.to_string(),
+            "above-gossip".to_string()
+        );
+        assert!("highest".parse::<ExternalPrecedence>().is_err());
+    }
+
+    #[test]
+    fn missing_directory_holds_no_config() {
+        let tmpdir = TempDir::new().unwrap();
+        let source = DirSource(tmpdir.path().join("missing"));
+        assert_eq!(source.load().unwrap(), None);
+        let source = DirSource(tmpdir.path().to_path_buf());
+        assert_eq!(source.load().unwrap(), None);
+    }
+
+    #[test]
+    fn directory_fragments_merge_in_name_order() {
+        let tmpdir = TempDir::new().unwrap();
+        write(
+            tmpdir.path(),
+            "10-base.toml",
+            "port = 80\n[tls]\nenabled = false\n",
+        );
+        write(
+            tmpdir.path(),
+            "20-override.json",
+            r#"{"tls": {"enabled": true}}"#,
+        );
+        write(tmpdir.path(), ".hidden.toml", "port = 1");
+        write(tmpdir.path(), "README.md", "not config");
+        let table = DirSource(tmpdir.path().to_path_buf())
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(table["port"].as_integer(), Some(80));
+        assert_eq!(table["tls"]["enabled"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn invalid_fragment_fails() {
+        let tmpdir = TempDir::new().unwrap();
+        write(tmpdir.path(), "broken.toml", "port = ");
+        assert!(DirSource(tmpdir.path().to_path_buf()).load().is_err());
+    }
+}
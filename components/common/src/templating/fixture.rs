@@ -0,0 +1,508 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Synthetic render contexts for rendering templates without a Supervisor.
+//!
+//! The render context of a running service is built from the Supervisor's census, so templates
+//! referencing `svc` or `bind` can normally only be rendered by a Supervisor with real peers. A
+//! `RenderFixture` describes that census instead: the local Supervisor, the other members of the
+//! service group, and the members of each bound service group. Fixtures are TOML or JSON
+//! documents:
+//!
+//! ```toml
+//! [sys]
+//! ip = "10.0.0.5"
+//!
+//! [cfg]
+//! port = 8080
+//!
+//! [[svc.members]]
+//! sys = { ip = "10.0.0.6" }
+//! leader = true
+//!
+//! [[bind.database.members]]
+//! sys = { ip = "10.0.0.10" }
+//! cfg = { port = 5432 }
+//! ```
+//!
+//! The context built from a fixture has the same shape as the one the Supervisor renders
+//! templates with, as described by `render_context_schema.json`. Every member gets the values a
+//! healthy member of a census without elections would have, which the fixture may override.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use serde_json::{self, Map, Value as Json};
+use toml;
+
+use super::{config::Cfg, package::Pkg, RenderContext};
+use crate::{
+    error::{Error, Result},
+    hcore::{fs as hfs, package::PackageIdent},
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderFixture {
+    /// Overrides of the local Supervisor's system information.
+    pub sys: Map<String, Json>,
+    /// Metadata of a package which hasn't been built yet. Ignored for installed packages.
+    pub pkg: PkgFixture,
+    /// Configuration layered over `default.toml`, in place of `user.toml`.
+    pub cfg: toml::value::Table,
+    /// The service group of the service.
+    pub svc: GroupFixture,
+    /// The bound service groups, by bind name.
+    pub bind: BTreeMap<String, GroupFixture>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PkgFixture {
+    pub origin: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub release: Option<String>,
+    pub exports: HashMap<String, String>,
+    pub exposes: Vec<String>,
+    pub svc_user: Option<String>,
+    pub svc_group: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupFixture {
+    /// The service of a bound group. Defaults to the bind name.
+    pub service: Option<String>,
+    /// Defaults to `default`.
+    pub group: Option<String>,
+    pub org: Option<String>,
+    /// Members of the group, as overrides of a member's values. For the service's own group,
+    /// these are the members besides the local one.
+    pub members: Vec<Map<String, Json>>,
+}
+
+impl RenderFixture {
+    /// Reads a fixture from a `.json` file, or any other file as TOML.
+    pub fn from_file<T>(path: T) -> Result<Self>
+    where
+        T: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let err = |e: String| Error::RenderFixture(path.to_path_buf(), e);
+        let content = fs::read_to_string(path).map_err(|e| err(e.to_string()))?;
+        if path.extension().map_or(false, |ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|e| err(e.to_string()))
+        } else {
+            toml::from_str(&content).map_err(|e| err(e.to_string()))
+        }
+    }
+
+    /// Returns the `Pkg` of a plan which hasn't been built, found in `plan_context`.
+    pub fn plan_pkg(&self, plan_context: &Path) -> Pkg {
+        let name = self
+            .pkg
+            .name
+            .clone()
+            .unwrap_or_else(|| plan_name(plan_context));
+        let origin = self
+            .pkg
+            .origin
+            .clone()
+            .unwrap_or_else(|| "local".to_string());
+        let version = self
+            .pkg
+            .version
+            .clone()
+            .unwrap_or_else(|| "0.0.0".to_string());
+        let release = self
+            .pkg
+            .release
+            .clone()
+            .unwrap_or_else(|| "00000000000000".to_string());
+        let svc_user = self
+            .pkg
+            .svc_user
+            .clone()
+            .unwrap_or_else(|| "hab".to_string());
+        let svc_group = self
+            .pkg
+            .svc_group
+            .clone()
+            .unwrap_or_else(|| svc_user.clone());
+        Pkg {
+            ident: PackageIdent::new(
+                origin.clone(),
+                name.clone(),
+                Some(version.clone()),
+                Some(release.clone()),
+            ),
+            svc_path: hfs::svc_path(&name),
+            svc_config_path: hfs::svc_config_path(&name),
+            svc_config_install_path: hfs::svc_config_install_path(&name),
+            svc_data_path: hfs::svc_data_path(&name),
+            svc_files_path: hfs::svc_files_path(&name),
+            svc_run: hfs::svc_path(&name).join("run"),
+            svc_static_path: hfs::svc_static_path(&name),
+            svc_var_path: hfs::svc_var_path(&name),
+            svc_pid_file: hfs::svc_pid_file(&name),
+            svc_user,
+            svc_group,
+            env: HashMap::new().into(),
+            deps: vec![],
+            exposes: self.pkg.exposes.clone(),
+            exports: self.pkg.exports.clone(),
+            strict_templates: false,
+            config_paths: HashMap::new(),
+            shutdown_signal: None,
+            shutdown_timeout: None,
+            hook_timeouts: HashMap::new(),
+            path: plan_context.to_path_buf(),
+            origin,
+            name,
+            version,
+            release,
+        }
+    }
+
+    /// Layers the fixture's configuration over the package's, in place of `user.toml`.
+    pub fn apply_cfg(&self, cfg: &mut Cfg) -> Result<()> {
+        cfg.user = if self.cfg.is_empty() {
+            None
+        } else {
+            Some(self.cfg.clone())
+        };
+        cfg.validate_schema()
+    }
+
+    /// Builds the render context of a service of the given package and configuration.
+    /// `version` is the Supervisor version reported in `sys`.
+    pub fn render_context(&self, pkg: &Pkg, cfg: &Cfg, version: &str) -> Result<Json> {
+        let mut ctx = serde_json::to_value(RenderContext::new(pkg, cfg))
+            .map_err(Error::RenderContextSerialization)?;
+        let mut sys = json!({
+            "version": version,
+            "member_id": member_id(0),
+            "ip": "127.0.0.1",
+            "hostname": "localhost",
+            "gossip_ip": "0.0.0.0",
+            "gossip_port": 9638,
+            "http_gateway_ip": "0.0.0.0",
+            "http_gateway_port": 9631,
+            "ctl_gateway_ip": "127.0.0.1",
+            "ctl_gateway_port": 9632,
+            "permanent": false,
+        });
+        json_merge(&mut sys, &Json::Object(self.sys.clone()));
+
+        let exported = cfg.to_exported(pkg)?;
+        let pkg_ident = json!({
+            "origin": pkg.origin,
+            "name": pkg.name,
+            "version": pkg.version,
+            "release": pkg.release,
+        });
+        let group = self.svc.group.as_ref().map_or("default", String::as_str);
+        let mut me = member(&pkg.name, group, self.svc.org.as_ref(), &sys);
+        me["member_id"] = sys["member_id"].clone();
+        me["pkg"] = pkg_ident.clone();
+        me["cfg"] = json!(exported);
+        let mut members = vec![me.clone()];
+        for (i, overrides) in self.svc.members.iter().enumerate() {
+            let mut other = member(&pkg.name, group, self.svc.org.as_ref(), &sys);
+            other["member_id"] = json!(member_id(i + 1));
+            other["pkg"] = pkg_ident.clone();
+            json_merge(&mut other, &Json::Object(overrides.clone()));
+            members.push(other);
+        }
+        let mut svc = json!({
+            "service": pkg.name,
+            "group": group,
+            "org": self.svc.org,
+            "election_is_running": false,
+            "election_is_no_quorum": false,
+            "election_is_finished": false,
+            "update_election_is_running": false,
+            "update_election_is_no_quorum": false,
+            "update_election_is_finished": false,
+            "me": me,
+        });
+        svc["leader"] = find_member(&members, "leader");
+        svc["update_leader"] = find_member(&members, "update_leader");
+        svc["first"] = first_member(&members);
+        svc["members"] = Json::Array(members);
+
+        let mut binds = Map::new();
+        for (offset, (name, fixture)) in self.bind.iter().enumerate() {
+            let service = fixture.service.as_ref().unwrap_or(name);
+            let group = fixture.group.as_ref().map_or("default", String::as_str);
+            let members: Vec<Json> = fixture
+                .members
+                .iter()
+                .enumerate()
+                .map(|(i, overrides)| {
+                    let mut m = member(service, group, fixture.org.as_ref(), &sys);
+                    m["member_id"] = json!(member_id((offset + 1) * 1000 + i));
+                    json_merge(&mut m, &Json::Object(overrides.clone()));
+                    m
+                })
+                .collect();
+            binds.insert(
+                name.clone(),
+                json!({
+                    "first": first_member(&members),
+                    "leader": find_member(&members, "leader"),
+                    "members": members,
+                }),
+            );
+        }
+
+        ctx["sys"] = sys;
+        ctx["svc"] = svc;
+        ctx["bind"] = Json::Object(binds);
+        Ok(ctx)
+    }
+}
+
+/// The name of a plan, taken from its directory. Plans kept in a `habitat` directory are named
+/// after the directory containing it.
+fn plan_name(plan_context: &Path) -> String {
+    let dir = match plan_context.file_name() {
+        Some(name) if name == "habitat" => plan_context.parent().and_then(Path::file_name),
+        name => name,
+    };
+    dir.map_or_else(
+        || "plan".to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+fn member_id(n: usize) -> String {
+    format!("{:032x}", n)
+}
+
+/// A healthy member of a census without elections, which runs on a Supervisor like the local one.
+/// Its package is an unbuilt plan named after the service, unless the fixture says otherwise.
+fn member(service: &str, group: &str, org: Option<&String>, sys: &Json) -> Json {
+    json!({
+        "pkg": {
+            "origin": "local",
+            "name": service,
+            "version": "0.0.0",
+            "release": "00000000000000",
+        },
+        "service": service,
+        "group": group,
+        "application": null,
+        "environment": null,
+        "org": org,
+        "persistent": false,
+        "leader": false,
+        "follower": false,
+        "update_leader": false,
+        "update_follower": false,
+        "election_is_running": false,
+        "election_is_no_quorum": false,
+        "election_is_finished": false,
+        "update_election_is_running": false,
+        "update_election_is_no_quorum": false,
+        "update_election_is_finished": false,
+        "sys": {
+            "ip": sys["ip"],
+            "hostname": sys["hostname"],
+            "gossip_ip": sys["gossip_ip"],
+            "gossip_port": sys["gossip_port"],
+            "http_gateway_ip": sys["http_gateway_ip"],
+            "http_gateway_port": sys["http_gateway_port"],
+            "ctl_gateway_ip": sys["ctl_gateway_ip"],
+            "ctl_gateway_port": sys["ctl_gateway_port"],
+        },
+        "alive": true,
+        "suspect": false,
+        "confirmed": false,
+        "departed": false,
+        "cfg": {},
+    })
+}
+
+/// The first member whose boolean `flag` is set, or `null`.
+fn find_member(members: &[Json], flag: &str) -> Json {
+    members
+        .iter()
+        .find(|m| m[flag].as_bool().unwrap_or(false))
+        .cloned()
+        .unwrap_or(Json::Null)
+}
+
+/// The leader if there is one, otherwise the first member, like `first` in a real census.
+fn first_member(members: &[Json]) -> Json {
+    match find_member(members, "leader") {
+        Json::Null => members.first().cloned().unwrap_or(Json::Null),
+        leader => leader,
+    }
+}
+
+// Recursively merges the `other` JSON value into `me`
+fn json_merge(me: &mut Json, other: &Json) {
+    match (me, other) {
+        (&mut Json::Object(ref mut me), &Json::Object(ref other)) => {
+            for (key, value) in other {
+                json_merge(me.entry(key.clone()).or_insert(Json::Null), value);
+            }
+        }
+        (me, other) => *me = other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::templating::test_helpers::*;
+    use std::{fs::File, io::Write, path::PathBuf};
+    use tempfile::TempDir;
+
+    fn fixture(content: &str) -> RenderFixture {
+        toml::from_str(content).unwrap()
+    }
+
+    fn plan(tmp: &TempDir) -> PathBuf {
+        let plan = tmp.path().join("redis").join("habitat");
+        fs::create_dir_all(&plan).unwrap();
+        create_with_content(
+            plan.join("default.toml"),
+            "port = 6379\nmode = \"standalone\"\n",
+        );
+        plan
+    }
+
+    #[test]
+    fn plan_pkg_is_named_after_its_directory() {
+        let tmp = TempDir::new().unwrap();
+        let pkg = fixture("").plan_pkg(&plan(&tmp));
+        assert_eq!(pkg.name, "redis");
+        assert_eq!(pkg.ident.to_string(), "local/redis/0.0.0/00000000000000");
+
+        let pkg = fixture("[pkg]\norigin = \"core\"\nname = \"cache\"").plan_pkg(&plan(&tmp));
+        assert_eq!(pkg.ident.to_string(), "core/cache/0.0.0/00000000000000");
+    }
+
+    #[test]
+    fn render_context_from_fixture() {
+        let tmp = TempDir::new().unwrap();
+        let fixture = fixture(
+            r#"
+            [sys]
+            ip = "10.0.0.5"
+
+            [cfg]
+            port = 7000
+
+            [pkg.exports]
+            port = "port"
+
+            [[svc.members]]
+            sys = { ip = "10.0.0.6" }
+            cfg = { port = 7001 }
+            leader = true
+
+            [[bind.database.members]]
+            sys = { ip = "10.0.0.10" }
+            cfg = { port = 5432 }
+            "#,
+        );
+        let pkg = fixture.plan_pkg(&plan(&tmp));
+        let mut cfg = Cfg::new(&pkg, Some(&pkg.path)).unwrap();
+        fixture.apply_cfg(&mut cfg).unwrap();
+        let ctx = fixture.render_context(&pkg, &cfg, "0.76.0").unwrap();
+
+        assert_eq!(ctx["sys"]["ip"], "10.0.0.5");
+        assert_eq!(ctx["sys"]["version"], "0.76.0");
+        assert_eq!(ctx["cfg"]["port"], 7000);
+        assert_eq!(ctx["cfg"]["mode"], "standalone");
+        assert_eq!(ctx["pkg"]["name"], "redis");
+        assert_eq!(ctx["svc"]["service"], "redis");
+        assert_eq!(ctx["svc"]["me"]["sys"]["ip"], "10.0.0.5");
+        assert_eq!(ctx["svc"]["me"]["cfg"]["port"], 7000);
+        assert_eq!(ctx["svc"]["members"].as_array().unwrap().len(), 2);
+        assert_eq!(ctx["svc"]["leader"]["sys"]["ip"], "10.0.0.6");
+        assert_eq!(ctx["svc"]["first"]["sys"]["ip"], "10.0.0.6");
+        assert_eq!(ctx["svc"]["update_leader"], Json::Null);
+        assert_eq!(ctx["bind"]["database"]["first"]["cfg"]["port"], 5432);
+        assert_eq!(ctx["bind"]["database"]["members"][0]["service"], "database");
+        assert_eq!(ctx["bind"]["database"]["leader"], Json::Null);
+    }
+
+    #[test]
+    fn render_context_from_fixture_is_valid() {
+        let tmp = TempDir::new().unwrap();
+        let fixture = fixture(
+            r#"
+            [svc]
+            org = "acme"
+
+            [[svc.members]]
+            leader = true
+
+            [[bind.database.members]]
+            cfg = { port = 5432 }
+
+            [[bind.cache.members]]
+            "#,
+        );
+        let pkg = fixture.plan_pkg(&plan(&tmp));
+        let mut cfg = Cfg::new(&pkg, Some(&pkg.path)).unwrap();
+        fixture.apply_cfg(&mut cfg).unwrap();
+        let ctx = fixture.render_context(&pkg, &cfg, "0.76.0").unwrap();
+
+        assert_valid_against(&ctx.to_string(), supervisor_render_context_schema());
+    }
+
+    /// The schema of the Supervisor's render context, with the system information it refers to
+    /// in the HTTP gateway's schema inlined.
+    fn supervisor_render_context_schema() -> Json {
+        let doc = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("sup")
+            .join("doc");
+        let mut schema: Json =
+            serde_json::from_str(&file_content(doc.join("render_context_schema.json"))).unwrap();
+        let services: Json =
+            serde_json::from_str(&file_content(doc.join("http_gateway_services_schema.json")))
+                .unwrap();
+        schema["properties"]["sys"] = services["definitions"]["sys_extended"].clone();
+        schema
+    }
+
+    #[test]
+    fn fixture_from_json_file() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("fixture.json");
+        File::create(&path)
+            .unwrap()
+            .write_all(br#"{"sys": {"hostname": "web-1"}, "cfg": {"port": 80}}"#)
+            .unwrap();
+        let fixture = RenderFixture::from_file(&path).unwrap();
+        assert_eq!(fixture.sys["hostname"], "web-1");
+        assert_eq!(fixture.cfg["port"].as_integer(), Some(80));
+
+        File::create(&path)
+            .unwrap()
+            .write_all(br#"{"unknown": true}"#)
+            .unwrap();
+        assert!(RenderFixture::from_file(&path).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::{super::RenderResult, Number};
+
+#[derive(Clone, Copy)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+impl Operator {
+    fn name(self) -> &'static str {
+        match self {
+            Operator::Add => "add",
+            Operator::Subtract => "subtract",
+            Operator::Multiply => "multiply",
+            Operator::Divide => "divide",
+            Operator::Modulo => "modulo",
+        }
+    }
+
+    /// Integers stay integers, so dividing integers truncates. If either operand is a float, so
+    /// is the result.
+    fn apply(self, lhs: Number, rhs: Number) -> RenderResult<Number> {
+        match (lhs, rhs) {
+            (Number::Int(a), Number::Int(b)) => {
+                let result = match self {
+                    Operator::Add => a.checked_add(b),
+                    Operator::Subtract => a.checked_sub(b),
+                    Operator::Multiply => a.checked_mul(b),
+                    Operator::Divide => a.checked_div(b),
+                    Operator::Modulo => a.checked_rem(b),
+                };
+                result.map(Number::Int).ok_or_else(|| {
+                    RenderError::new(format!(
+                        "Integer overflow or division by zero in \"{}\"",
+                        self.name()
+                    ))
+                })
+            }
+            (lhs, rhs) => {
+                let (a, b) = (lhs.as_f64(), rhs.as_f64());
+                let result = match self {
+                    Operator::Add => a + b,
+                    Operator::Subtract => a - b,
+                    Operator::Multiply => a * b,
+                    Operator::Divide => a / b,
+                    Operator::Modulo => a % b,
+                };
+                if result.is_finite() {
+                    Ok(Number::Float(result))
+                } else {
+                    Err(RenderError::new(format!(
+                        "Division by zero in \"{}\"",
+                        self.name()
+                    )))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ArithmeticHelper(Operator);
+
+impl HelperDef for ArithmeticHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let err = || {
+            RenderError::new(format!(
+                "Expected 2 numeric parameters for \"{}\"",
+                self.0.name()
+            ))
+        };
+        let lhs = h
+            .param(0)
+            .and_then(|v| Number::from_json(v.value()))
+            .ok_or_else(err)?;
+        let rhs = h
+            .param(1)
+            .and_then(|v| Number::from_json(v.value()))
+            .ok_or_else(err)?;
+        let result = self.0.apply(lhs, rhs)?;
+        rc.writer
+            .write_all(result.to_string().into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static ADD: ArithmeticHelper = ArithmeticHelper(Operator::Add);
+pub static SUBTRACT: ArithmeticHelper = ArithmeticHelper(Operator::Subtract);
+pub static MULTIPLY: ArithmeticHelper = ArithmeticHelper(Operator::Multiply);
+pub static DIVIDE: ArithmeticHelper = ArithmeticHelper(Operator::Divide);
+pub static MODULO: ArithmeticHelper = ArithmeticHelper(Operator::Modulo);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use handlebars::TemplateRenderError;
+
+    fn render(template: &str) -> Result<String, TemplateRenderError> {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("add", Box::new(ADD));
+        handlebars.register_helper("subtract", Box::new(SUBTRACT));
+        handlebars.register_helper("multiply", Box::new(MULTIPLY));
+        handlebars.register_helper("divide", Box::new(DIVIDE));
+        handlebars.register_helper("modulo", Box::new(MODULO));
+        handlebars.template_render(template, &json!({"port": 8080, "ratio": 0.5, "count": "3"}))
+    }
+
+    #[test]
+    fn integer_arithmetic() {
+        assert_eq!(render("{{add port 1}}").unwrap(), "8081");
+        assert_eq!(render("{{subtract port 80}}").unwrap(), "8000");
+        assert_eq!(render("{{multiply count 2}}").unwrap(), "6");
+        assert_eq!(render("{{divide 7 2}}").unwrap(), "3");
+        assert_eq!(render("{{modulo 7 2}}").unwrap(), "1");
+    }
+
+    #[test]
+    fn float_arithmetic() {
+        assert_eq!(render("{{multiply port ratio}}").unwrap(), "4040");
+        assert_eq!(render("{{divide 7.0 2}}").unwrap(), "3.5");
+    }
+
+    #[test]
+    fn nested_arithmetic() {
+        assert_eq!(render("{{multiply (add count 1) 2}}").unwrap(), "8");
+    }
+
+    #[test]
+    fn invalid_arithmetic_fails() {
+        assert!(render("{{divide port 0}}").is_err());
+        assert!(render("{{divide ratio 0}}").is_err());
+        assert!(render("{{add port \"eighty\"}}").is_err());
+        assert!(render("{{add port}}").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::{super::RenderResult, compare_values, render_condition};
+
+#[derive(Clone, Copy)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl Comparison {
+    fn name(self) -> &'static str {
+        match self {
+            Comparison::Eq => "eq",
+            Comparison::Ne => "ne",
+            Comparison::Lt => "lt",
+            Comparison::Lte => "lte",
+            Comparison::Gt => "gt",
+            Comparison::Gte => "gte",
+        }
+    }
+
+    fn holds(self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (Comparison::Eq, Some(Ordering::Equal)) => true,
+            (Comparison::Ne, Some(Ordering::Equal)) => false,
+            (Comparison::Ne, _) => true,
+            (Comparison::Lt, Some(Ordering::Less)) => true,
+            (Comparison::Lte, Some(Ordering::Less)) | (Comparison::Lte, Some(Ordering::Equal)) => {
+                true
+            }
+            (Comparison::Gt, Some(Ordering::Greater)) => true,
+            (Comparison::Gte, Some(Ordering::Greater))
+            | (Comparison::Gte, Some(Ordering::Equal)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Compares two values. Used as a block, renders the block if the comparison holds and its
+/// `else` block otherwise; used inline, writes `true` or `false`.
+#[derive(Clone, Copy)]
+pub struct ComparisonHelper(Comparison);
+
+impl HelperDef for ComparisonHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let err = || RenderError::new(format!("Expected 2 parameters for \"{}\"", self.0.name()));
+        let lhs = h.param(0).ok_or_else(err)?.value();
+        let rhs = h.param(1).ok_or_else(err)?.value();
+        let holds = self.0.holds(compare_values(lhs, rhs));
+        render_condition(h, r, rc, holds)
+    }
+}
+
+pub static EQ: ComparisonHelper = ComparisonHelper(Comparison::Eq);
+pub static NE: ComparisonHelper = ComparisonHelper(Comparison::Ne);
+pub static LT: ComparisonHelper = ComparisonHelper(Comparison::Lt);
+pub static LTE: ComparisonHelper = ComparisonHelper(Comparison::Lte);
+pub static GT: ComparisonHelper = ComparisonHelper(Comparison::Gt);
+pub static GTE: ComparisonHelper = ComparisonHelper(Comparison::Gte);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("eq", Box::new(EQ));
+        handlebars.register_helper("ne", Box::new(NE));
+        handlebars.register_helper("lt", Box::new(LT));
+        handlebars.register_helper("lte", Box::new(LTE));
+        handlebars.register_helper("gt", Box::new(GT));
+        handlebars.register_helper("gte", Box::new(GTE));
+        handlebars
+            .template_render(
+                template,
+                &json!({"workers": 4, "mode": "replica", "max": "4"}),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn inline_comparisons() {
+        assert_eq!(render("{{gt workers 2}}"), "true");
+        assert_eq!(render("{{lt workers 2}}"), "false");
+        assert_eq!(render("{{gte workers max}}"), "true");
+        assert_eq!(render("{{lte workers 3.5}}"), "false");
+        assert_eq!(render("{{eq mode \"replica\"}}"), "true");
+        assert_eq!(render("{{ne mode \"replica\"}}"), "false");
+        assert_eq!(render("{{eq mode workers}}"), "false");
+        assert_eq!(render("{{ne missing \"replica\"}}"), "true");
+    }
+
+    #[test]
+    fn block_comparisons() {
+        assert_eq!(
+            render("{{#eq mode \"primary\"}}primary{{else}}not primary{{/eq}}"),
+            "not primary"
+        );
+        assert_eq!(render("{{#gte workers 4}}many{{/gte}}"), "many");
+    }
+}
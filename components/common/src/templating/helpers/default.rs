@@ -0,0 +1,96 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult, value_to_string};
+
+/// Whether a value is set. `null`, e.g. a missing key, and the empty string are not.
+fn is_set(value: &Json) -> bool {
+    match *value {
+        Json::Null => false,
+        Json::String(ref s) => !s.is_empty(),
+        _ => true,
+    }
+}
+
+/// Writes the given value, or the fallback if the value is not set.
+#[derive(Clone, Copy)]
+pub struct DefaultHelper;
+
+impl HelperDef for DefaultHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let value = h
+            .param(0)
+            .ok_or_else(|| RenderError::new("Expected 2 parameters for \"default\""))?
+            .value();
+        let fallback = h
+            .param(1)
+            .ok_or_else(|| RenderError::new("Expected 2 parameters for \"default\""))?
+            .value();
+        let out = if is_set(value) { value } else { fallback };
+        rc.writer
+            .write_all(value_to_string(out).into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+/// Writes the first of the given values which is set.
+#[derive(Clone, Copy)]
+pub struct CoalesceHelper;
+
+impl HelperDef for CoalesceHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        if let Some(value) = h.params().iter().map(|v| v.value()).find(|v| is_set(v)) {
+            rc.writer
+                .write_all(value_to_string(value).into_bytes().as_ref())?;
+        }
+        Ok(())
+    }
+}
+
+pub static DEFAULT: DefaultHelper = DefaultHelper;
+pub static COALESCE: CoalesceHelper = CoalesceHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("default", Box::new(DEFAULT));
+        handlebars.register_helper("coalesce", Box::new(COALESCE));
+        handlebars
+            .template_render(
+                template,
+                &json!({"port": 8080, "host": "", "enabled": false}),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn default_helper() {
+        assert_eq!(render("{{default port 80}}"), "8080");
+        assert_eq!(render("{{default missing 80}}"), "80");
+        assert_eq!(render("{{default host \"localhost\"}}"), "localhost");
+        assert_eq!(render("{{default enabled true}}"), "false");
+    }
+
+    #[test]
+    fn coalesce_helper() {
+        assert_eq!(render("{{coalesce missing host port}}"), "8080");
+        assert_eq!(render("{{coalesce missing host}}"), "");
+    }
+}
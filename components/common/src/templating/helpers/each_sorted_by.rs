@@ -0,0 +1,86 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult, compare_values, field, render_each};
+
+/// Iterates over a list, such as the members of a bound service group, ordered by the value of
+/// the given dotted field, e.g. `sys.hostname`. Items without the field come first.
+#[derive(Clone, Copy)]
+pub struct EachSortedByHelper;
+
+impl HelperDef for EachSortedByHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let list = h
+            .param(0)
+            .and_then(|v| v.value().as_array())
+            .ok_or_else(|| RenderError::new("Expected a list and a field for \"eachSortedBy\""))?;
+        let path = h
+            .param(1)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected a list and a field for \"eachSortedBy\""))?;
+        let mut items: Vec<Json> = list.clone();
+        items.sort_by(|a, b| match (field(a, path), field(b, path)) {
+            (Some(a), Some(b)) => compare_values(a, b).unwrap_or(Ordering::Equal),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        render_each(h, r, rc, &items)
+    }
+}
+
+pub static EACH_SORTED_BY: EachSortedByHelper = EachSortedByHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_each_sorted_by_helper() {
+        let json = json!({
+            "members": [
+                {"sys": {"hostname": "c"}, "weight": 1},
+                {"sys": {"hostname": "a"}, "weight": 10},
+                {"sys": {"hostname": "b"}, "weight": 2},
+            ]
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("eachSortedBy", Box::new(EACH_SORTED_BY));
+        assert_eq!(
+            handlebars
+                .template_render(
+                    "{{#eachSortedBy members \"sys.hostname\" as |m|}}{{m.sys.hostname}}\
+                     {{/eachSortedBy}}",
+                    &json
+                )
+                .unwrap(),
+            "abc"
+        );
+        assert_eq!(
+            handlebars
+                .template_render(
+                    "{{#eachSortedBy members \"weight\" as |m|}}{{@index}}:{{m.weight}} \
+                     {{/eachSortedBy}}",
+                    &json
+                )
+                .unwrap(),
+            "0:1 1:2 2:10 "
+        );
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult, compare_values, field, render_each};
+
+/// Iterates over the items of a list, such as the members of a bound service group, whose
+/// dotted field, e.g. `cfg.role`, equals the given value. Renders the `else` block if there are
+/// none.
+#[derive(Clone, Copy)]
+pub struct EachWhereHelper;
+
+impl HelperDef for EachWhereHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let err = || RenderError::new("Expected a list, a field and a value for \"eachWhere\"");
+        let list = h
+            .param(0)
+            .and_then(|v| v.value().as_array())
+            .ok_or_else(err)?;
+        let path = h
+            .param(1)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(err)?;
+        let value = h.param(2).ok_or_else(err)?.value();
+        let items: Vec<Json> = list
+            .iter()
+            .filter(|item| {
+                field(item, path).and_then(|f| compare_values(f, value)) == Some(Ordering::Equal)
+            })
+            .cloned()
+            .collect();
+        render_each(h, r, rc, &items)
+    }
+}
+
+pub static EACH_WHERE: EachWhereHelper = EachWhereHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_each_where_helper() {
+        let json = json!({
+            "members": [
+                {"member_id": "a", "leader": true, "cfg": {"role": "primary"}},
+                {"member_id": "b", "leader": false, "cfg": {"role": "replica"}},
+                {"member_id": "c", "leader": false, "cfg": {"role": "replica"}},
+            ]
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("eachWhere", Box::new(EACH_WHERE));
+        assert_eq!(
+            handlebars
+                .template_render(
+                    "{{#eachWhere members \"cfg.role\" \"replica\" as |m|}}{{m.member_id}}\
+                     {{/eachWhere}}",
+                    &json
+                )
+                .unwrap(),
+            "bc"
+        );
+        assert_eq!(
+            handlebars
+                .template_render(
+                    "{{#eachWhere members \"leader\" true as |m|}}{{m.member_id}}{{/eachWhere}}",
+                    &json
+                )
+                .unwrap(),
+            "a"
+        );
+        assert_eq!(
+            handlebars
+                .template_render(
+                    "{{#eachWhere members \"cfg.role\" \"arbiter\"}}x{{else}}none{{/eachWhere}}",
+                    &json
+                )
+                .unwrap(),
+            "none"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::{super::RenderResult, Number};
+
+/// Formats a number of bytes with binary units, e.g. `1536` as `1.5 KiB`.
+#[derive(Clone, Copy)]
+pub struct FormatBytesHelper;
+
+impl HelperDef for FormatBytesHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let bytes = h
+            .param(0)
+            .and_then(|v| Number::from_json(v.value()))
+            .map(Number::as_f64)
+            .filter(|b| *b >= 0.0)
+            .ok_or_else(|| {
+                RenderError::new("Expected 1 non-negative number of bytes for \"formatBytes\"")
+            })?;
+        rc.writer
+            .write_all(format_bytes(bytes).into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+fn format_bytes(bytes: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        return format!("{} {}", value as u64, units[unit]);
+    }
+    let formatted = format!("{:.1}", value);
+    format!("{} {}", formatted.trim_end_matches(".0"), units[unit])
+}
+
+pub static FORMAT_BYTES: FormatBytesHelper = FormatBytesHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_byte_sizes() {
+        assert_eq!(format_bytes(512.0), "512 B");
+        assert_eq!(format_bytes(1536.0), "1.5 KiB");
+        assert_eq!(format_bytes(2.0 * 1024.0 * 1024.0), "2 MiB");
+        assert_eq!(
+            format_bytes(1024.0 * 1024.0 * 1024.0 * 1024.0 * 3.5),
+            "3.5 TiB"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes_helper() {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("formatBytes", Box::new(FORMAT_BYTES));
+        assert_eq!(
+            handlebars
+                .template_render("{{formatBytes size}}", &json!({"size": "1048576"}))
+                .unwrap(),
+            "1 MiB"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::{super::RenderResult, Number};
+
+/// Formats a number of seconds as a duration, e.g. `90` as `1m30s`, the format Go programs
+/// accept. Fractions of a second are dropped.
+#[derive(Clone, Copy)]
+pub struct FormatDurationHelper;
+
+impl HelperDef for FormatDurationHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let seconds = h
+            .param(0)
+            .and_then(|v| Number::from_json(v.value()))
+            .map(Number::as_f64)
+            .filter(|s| *s >= 0.0)
+            .ok_or_else(|| {
+                RenderError::new("Expected 1 non-negative number of seconds for \"formatDuration\"")
+            })?;
+        rc.writer
+            .write_all(format_duration(seconds as u64).into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    if seconds == 0 {
+        return "0s".to_string();
+    }
+    let units = [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)];
+    let mut remaining = seconds;
+    let mut out = String::new();
+    for &(unit, size) in units.iter() {
+        if remaining >= size {
+            out.push_str(&format!("{}{}", remaining / size, unit));
+            remaining %= size;
+        }
+    }
+    out
+}
+
+pub static FORMAT_DURATION: FormatDurationHelper = FormatDurationHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(90), "1m30s");
+        assert_eq!(format_duration(3_600), "1h");
+        assert_eq!(format_duration(93_784), "1d2h3m4s");
+    }
+
+    #[test]
+    fn test_format_duration_helper() {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("formatDuration", Box::new(FORMAT_DURATION));
+        let json = json!({"timeout": 300, "negative": -1});
+        assert_eq!(
+            handlebars
+                .template_render("{{formatDuration timeout}}", &json)
+                .unwrap(),
+            "5m"
+        );
+        assert!(handlebars
+            .template_render("{{formatDuration negative}}", &json)
+            .is_err());
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::{super::RenderResult, value_to_string};
+
+/// Indents every line of a value by the given number of spaces, e.g. to nest the output of
+/// `toYaml` in a larger YAML document. Empty lines are left empty. `nindent` does the same, and
+/// starts with a newline.
+#[derive(Clone, Copy)]
+pub struct IndentHelper {
+    newline: bool,
+}
+
+impl IndentHelper {
+    fn name(self) -> &'static str {
+        if self.newline {
+            "nindent"
+        } else {
+            "indent"
+        }
+    }
+}
+
+impl HelperDef for IndentHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let err = || {
+            RenderError::new(format!(
+                "Expected a number of spaces and a value for \"{}\"",
+                self.name()
+            ))
+        };
+        let width = h
+            .param(0)
+            .and_then(|v| v.value().as_u64())
+            .ok_or_else(err)?;
+        let value = value_to_string(h.param(1).ok_or_else(err)?.value());
+        let padding = " ".repeat(width as usize);
+        let mut out: String = value
+            .split('\n')
+            .map(|line| {
+                if line.is_empty() {
+                    line.to_string()
+                } else {
+                    format!("{}{}", padding, line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if self.newline {
+            out.insert(0, '\n');
+        }
+        rc.writer.write_all(out.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static INDENT: IndentHelper = IndentHelper { newline: false };
+pub static NINDENT: IndentHelper = IndentHelper { newline: true };
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_indent_helpers() {
+        let json = json!({"block": "a: 1\n\nb: 2"});
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("indent", Box::new(INDENT));
+        handlebars.register_helper("nindent", Box::new(NINDENT));
+        assert_eq!(
+            handlebars
+                .template_render("{{indent 2 block}}", &json)
+                .unwrap(),
+            "  a: 1\n\n  b: 2"
+        );
+        assert_eq!(
+            handlebars
+                .template_render("config:{{nindent 4 block}}", &json)
+                .unwrap(),
+            "config:\n    a: 1\n\n    b: 2"
+        );
+    }
+}
@@ -12,24 +12,62 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod arithmetic;
+mod compare;
+mod default;
 mod each_alive;
+mod each_sorted_by;
+mod each_where;
+mod format_bytes;
+mod format_duration;
+mod indent;
 mod pkg_path_for;
+mod sha256;
+mod str_base64;
 mod str_concat;
 mod str_join;
+mod str_regex;
 mod str_replace;
+mod to_ini;
 mod to_json;
 mod to_lowercase;
+mod to_properties;
 mod to_toml;
 mod to_uppercase;
 mod to_yaml;
 
+use std::{cmp::Ordering, collections::BTreeMap, fmt};
+
+use handlebars::{Handlebars, Helper, RenderContext, Renderable};
 use serde::Serialize;
 use serde_json::{self, Value as Json};
 
+use super::RenderResult;
+
 pub use self::{
-    each_alive::EACH_ALIVE, pkg_path_for::PKG_PATH_FOR, str_concat::STR_CONCAT, str_join::STR_JOIN,
-    str_replace::STR_REPLACE, to_json::TO_JSON, to_lowercase::TO_LOWERCASE, to_toml::TO_TOML,
-    to_uppercase::TO_UPPERCASE, to_yaml::TO_YAML,
+    arithmetic::{ADD, DIVIDE, MODULO, MULTIPLY, SUBTRACT},
+    compare::{EQ, GT, GTE, LT, LTE, NE},
+    default::{COALESCE, DEFAULT},
+    each_alive::EACH_ALIVE,
+    each_sorted_by::EACH_SORTED_BY,
+    each_where::EACH_WHERE,
+    format_bytes::FORMAT_BYTES,
+    format_duration::FORMAT_DURATION,
+    indent::{INDENT, NINDENT},
+    pkg_path_for::PKG_PATH_FOR,
+    sha256::SHA256,
+    str_base64::{BASE64_DECODE, BASE64_ENCODE},
+    str_concat::STR_CONCAT,
+    str_join::STR_JOIN,
+    str_regex::{REGEX_MATCH, REGEX_REPLACE},
+    str_replace::STR_REPLACE,
+    to_ini::TO_INI,
+    to_json::TO_JSON,
+    to_lowercase::TO_LOWERCASE,
+    to_properties::TO_PROPERTIES,
+    to_toml::TO_TOML,
+    to_uppercase::TO_UPPERCASE,
+    to_yaml::TO_YAML,
 };
 
 // Taken from `handlebars::context::JsonTruthy`. The trait is marked public but it's in a private
@@ -58,3 +96,147 @@ where
 {
     serde_json::to_value(src).unwrap_or(Json::Null)
 }
+
+/// A number given to a helper. Integers are kept apart from floats so that integer arithmetic
+/// stays exact.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    /// Reads a number from a parameter. Subexpressions always evaluate to strings, so strings
+    /// holding a number are accepted too.
+    fn from_json(value: &Json) -> Option<Self> {
+        match *value {
+            Json::Number(ref n) => n
+                .as_i64()
+                .map(Number::Int)
+                .or_else(|| n.as_f64().map(Number::Float)),
+            Json::String(ref s) => {
+                let s = s.trim();
+                s.parse::<i64>()
+                    .map(Number::Int)
+                    .or_else(|_| s.parse::<f64>().map(Number::Float))
+                    .ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    fn compare(self, other: Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+            (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Number::Int(i) => write!(f, "{}", i),
+            Number::Float(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Compares two values. Values are compared as numbers if either of them is a number, as
+/// strings if both are strings and are otherwise only ever equal or unordered.
+fn compare_values(lhs: &Json, rhs: &Json) -> Option<Ordering> {
+    if lhs.is_number() || rhs.is_number() {
+        return match (Number::from_json(lhs), Number::from_json(rhs)) {
+            (Some(a), Some(b)) => a.compare(b),
+            _ => None,
+        };
+    }
+    match (lhs, rhs) {
+        (&Json::String(ref a), &Json::String(ref b)) => Some(a.cmp(b)),
+        (a, b) if a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+/// Returns the value at a dotted path, e.g. `sys.hostname`, within the given value.
+fn field<'a>(value: &'a Json, path: &str) -> Option<&'a Json> {
+    path.split('.').try_fold(value, |v, key| v.get(key))
+}
+
+/// Formats a value for the template output: strings as they are, `null` as nothing and anything
+/// else as JSON.
+fn value_to_string(value: &Json) -> String {
+    match *value {
+        Json::String(ref s) => s.clone(),
+        Json::Null => String::new(),
+        ref other => other.to_string(),
+    }
+}
+
+/// Renders a helper testing a condition. Used as a block, the block is rendered if the condition
+/// holds and its `else` block if it doesn't. Used inline, `true` or `false` is written.
+fn render_condition(
+    h: &Helper<'_>,
+    r: &Handlebars,
+    rc: &mut RenderContext<'_>,
+    condition: bool,
+) -> RenderResult<()> {
+    match h.template() {
+        Some(template) if condition => template.render(r, rc),
+        Some(_) => match h.inverse() {
+            Some(inverse) => inverse.render(r, rc),
+            None => Ok(()),
+        },
+        None => {
+            rc.writer.write_all(condition.to_string().as_bytes())?;
+            Ok(())
+        }
+    }
+}
+
+/// Renders the block of a helper once for each item, like the built-in `each` helper. Each item
+/// is available as the block parameter, if one is given, or as the context of the block.
+fn render_each(
+    h: &Helper<'_>,
+    r: &Handlebars,
+    rc: &mut RenderContext<'_>,
+    items: &[Json],
+) -> RenderResult<()> {
+    let template = match h.template() {
+        Some(template) => template,
+        None => return Ok(()),
+    };
+    if items.is_empty() {
+        if let Some(inverse) = h.inverse() {
+            inverse.render(r, rc)?;
+        }
+        return Ok(());
+    }
+    rc.promote_local_vars();
+    let len = items.len();
+    for (i, item) in items.iter().enumerate() {
+        let mut local_rc = rc.derive();
+        local_rc.set_local_var("@first".to_string(), to_json(&(i == 0usize)));
+        local_rc.set_local_var("@last".to_string(), to_json(&(i == len - 1)));
+        local_rc.set_local_var("@index".to_string(), to_json(&i));
+        match h.block_param() {
+            Some(block_param) => {
+                let mut map = BTreeMap::new();
+                map.insert(block_param.to_string(), item.clone());
+                local_rc.push_block_context(&map)?;
+            }
+            None => local_rc.push_block_context(item)?,
+        }
+        template.render(r, &mut local_rc)?;
+        local_rc.pop_block_context();
+    }
+    rc.demote_local_vars();
+    Ok(())
+}
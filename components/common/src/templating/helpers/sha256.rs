@@ -0,0 +1,56 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use sha2::{Digest, Sha256};
+
+use super::super::RenderResult;
+
+/// Writes the hex encoded SHA-256 digest of a string, e.g. to checksum a rendered value.
+#[derive(Clone, Copy)]
+pub struct Sha256Helper;
+
+impl HelperDef for Sha256Helper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected 1 string parameter for \"sha256\""))?;
+        let digest: String = Sha256::digest(param.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        rc.writer.write_all(digest.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static SHA256: Sha256Helper = Sha256Helper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sha256_helper() {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("sha256", Box::new(SHA256));
+        assert_eq!(
+            handlebars
+                .template_render("{{sha256 \"abc\"}}", &json!({}))
+                .unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
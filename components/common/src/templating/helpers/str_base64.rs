@@ -0,0 +1,82 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use base64;
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::super::RenderResult;
+
+#[derive(Clone, Copy)]
+pub struct Base64EncodeHelper;
+
+impl HelperDef for Base64EncodeHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected 1 string parameter for \"base64Encode\""))?;
+        rc.writer
+            .write_all(base64::encode(param).into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Base64DecodeHelper;
+
+impl HelperDef for Base64DecodeHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected 1 string parameter for \"base64Decode\""))?;
+        let bytes = base64::decode(param.trim())
+            .map_err(|e| RenderError::new(format!("Can't decode parameter as base64: {}", e)))?;
+        let decoded = String::from_utf8(bytes)
+            .map_err(|e| RenderError::new(format!("Decoded base64 is not UTF-8: {}", e)))?;
+        rc.writer.write_all(decoded.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+pub static BASE64_ENCODE: Base64EncodeHelper = Base64EncodeHelper;
+pub static BASE64_DECODE: Base64DecodeHelper = Base64DecodeHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_base64_helpers() {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("base64Encode", Box::new(BASE64_ENCODE));
+        handlebars.register_helper("base64Decode", Box::new(BASE64_DECODE));
+        let json = json!({"user": "admin:hunter2", "encoded": "YWRtaW46aHVudGVyMg=="});
+        assert_eq!(
+            handlebars
+                .template_render("{{base64Encode user}}", &json)
+                .unwrap(),
+            "YWRtaW46aHVudGVyMg=="
+        );
+        assert_eq!(
+            handlebars
+                .template_render("{{base64Decode encoded}}", &json)
+                .unwrap(),
+            "admin:hunter2"
+        );
+        assert!(handlebars
+            .template_render("{{base64Decode user}}", &json)
+            .is_err());
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use regex::Regex;
+
+use super::{super::RenderResult, render_condition};
+
+fn regex_param(h: &Helper<'_>, idx: usize, helper: &str) -> RenderResult<Regex> {
+    let pattern = h
+        .param(idx)
+        .and_then(|v| v.value().as_str())
+        .ok_or_else(|| {
+            RenderError::new(format!("Expected a pattern parameter for \"{}\"", helper))
+        })?;
+    Regex::new(pattern).map_err(|e| RenderError::new(format!("Invalid pattern: {}", e)))
+}
+
+/// Tests whether a string matches a regular expression. Used as a block, renders the block if it
+/// matches and its `else` block otherwise; used inline, writes `true` or `false`.
+#[derive(Clone, Copy)]
+pub struct RegexMatchHelper;
+
+impl HelperDef for RegexMatchHelper {
+    fn call(&self, h: &Helper<'_>, r: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected 2 string parameters for \"regexMatch\""))?;
+        let regex = regex_param(h, 1, "regexMatch")?;
+        render_condition(h, r, rc, regex.is_match(param))
+    }
+}
+
+/// Replaces every match of a regular expression. The replacement may refer to capture groups as
+/// `$1` or `${name}`.
+#[derive(Clone, Copy)]
+pub struct RegexReplaceHelper;
+
+impl HelperDef for RegexReplaceHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected 3 string parameters for \"regexReplace\""))?;
+        let regex = regex_param(h, 1, "regexReplace")?;
+        let replacement = h
+            .param(2)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected 3 string parameters for \"regexReplace\""))?;
+        rc.writer.write_all(
+            regex
+                .replace_all(param, replacement)
+                .into_owned()
+                .into_bytes()
+                .as_ref(),
+        )?;
+        Ok(())
+    }
+}
+
+pub static REGEX_MATCH: RegexMatchHelper = RegexMatchHelper;
+pub static REGEX_REPLACE: RegexReplaceHelper = RegexReplaceHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("regexMatch", Box::new(REGEX_MATCH));
+        handlebars.register_helper("regexReplace", Box::new(REGEX_REPLACE));
+        handlebars
+            .template_render(template, &json!({"host": "db-02.prod.example.com"}))
+            .unwrap()
+    }
+
+    #[test]
+    fn regex_match_helper() {
+        assert_eq!(render("{{regexMatch host \"^db-[0-9]+\"}}"), "true");
+        assert_eq!(
+            render("{{#regexMatch host \"[.]staging[.]\"}}staging{{else}}other{{/regexMatch}}"),
+            "other"
+        );
+    }
+
+    #[test]
+    fn regex_replace_helper() {
+        assert_eq!(
+            render("{{regexReplace host \"^([a-z]+)-([0-9]+)[.].*$\" \"${1}${2}\"}}"),
+            "db02"
+        );
+    }
+
+    #[test]
+    fn invalid_pattern_fails() {
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("regexMatch", Box::new(REGEX_MATCH));
+        assert!(handlebars
+            .template_render("{{regexMatch \"a\" \"(\"}}", &json!({}))
+            .is_err());
+    }
+}
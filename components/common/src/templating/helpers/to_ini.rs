@@ -0,0 +1,93 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::{map::Map, Value as Json};
+
+use super::{super::RenderResult, value_to_string};
+
+/// Serializes a table to INI. Keys holding tables become sections, named with the dotted path
+/// of the table, and lists are written as comma separated values.
+#[derive(Clone, Copy)]
+pub struct ToIniHelper;
+
+impl HelperDef for ToIniHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .and_then(|v| v.value().as_object())
+            .ok_or_else(|| RenderError::new("Expected 1 table parameter for \"toIni\""))?;
+        let mut out = String::new();
+        write_section(&mut out, None, param);
+        rc.writer.write_all(out.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+fn write_section(out: &mut String, name: Option<&str>, table: &Map<String, Json>) {
+    let (tables, values): (Vec<_>, Vec<_>) = table.iter().partition(|(_, v)| v.is_object());
+    if let Some(name) = name {
+        if !values.is_empty() || tables.is_empty() {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("[{}]\n", name));
+        }
+    }
+    for (key, value) in values {
+        let value = match *value {
+            Json::Array(ref list) => list
+                .iter()
+                .map(value_to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+            ref other => value_to_string(other),
+        };
+        out.push_str(&format!("{} = {}\n", key, value));
+    }
+    for (key, value) in tables {
+        let section = match name {
+            Some(name) => format!("{}.{}", name, key),
+            None => key.to_string(),
+        };
+        if let Json::Object(ref nested) = *value {
+            write_section(out, Some(&section), nested);
+        }
+    }
+}
+
+pub static TO_INI: ToIniHelper = ToIniHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_ini_helper() {
+        let json = json!({
+            "cfg": {
+                "debug": false,
+                "database": {"host": "db", "port": 5432, "options": {"ssl": true}},
+                "paths": {"include": ["a", "b"]},
+            }
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("toIni", Box::new(TO_INI));
+        assert_eq!(
+            handlebars.template_render("{{toIni cfg}}", &json).unwrap(),
+            "debug = false\n\n[database]\nhost = db\nport = 5432\n\n[database.options]\nssl = \
+             true\n\n[paths]\ninclude = a,b\n"
+        );
+    }
+}
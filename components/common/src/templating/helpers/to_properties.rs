@@ -0,0 +1,95 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::{super::RenderResult, value_to_string};
+
+/// Serializes a table to a Java properties file. Nested keys are joined with dots and list items
+/// are keyed by their index, e.g. `servers.0.host=a`.
+#[derive(Clone, Copy)]
+pub struct ToPropertiesHelper;
+
+impl HelperDef for ToPropertiesHelper {
+    fn call(&self, h: &Helper<'_>, _: &Handlebars, rc: &mut RenderContext<'_>) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .map(|v| v.value())
+            .filter(|v| v.is_object())
+            .ok_or_else(|| RenderError::new("Expected 1 table parameter for \"toProperties\""))?;
+        let mut out = String::new();
+        write_properties(&mut out, "", param);
+        rc.writer.write_all(out.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
+
+fn write_properties(out: &mut String, prefix: &str, value: &Json) {
+    let key = |k: &str| {
+        if prefix.is_empty() {
+            k.to_string()
+        } else {
+            format!("{}.{}", prefix, k)
+        }
+    };
+    match *value {
+        Json::Object(ref table) => {
+            for (k, v) in table {
+                write_properties(out, &key(k), v);
+            }
+        }
+        Json::Array(ref list) => {
+            for (i, v) in list.iter().enumerate() {
+                write_properties(out, &key(&i.to_string()), v);
+            }
+        }
+        ref other => {
+            out.push_str(&format!("{}={}\n", prefix, escape(&value_to_string(other))));
+        }
+    }
+}
+
+// Escapes the characters which would otherwise end or continue a property value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+pub static TO_PROPERTIES: ToPropertiesHelper = ToPropertiesHelper;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_properties_helper() {
+        let json = json!({
+            "cfg": {
+                "server": {"port": 8080, "name": "app\nserver"},
+                "hosts": ["a", "b"],
+            }
+        });
+        let mut handlebars = Handlebars::new();
+        handlebars.register_helper("toProperties", Box::new(TO_PROPERTIES));
+        assert_eq!(
+            handlebars
+                .template_render("{{toProperties cfg}}", &json)
+                .unwrap(),
+            "hosts.0=a\nhosts.1=b\nserver.name=app\\nserver\nserver.port=8080\n"
+        );
+    }
+}
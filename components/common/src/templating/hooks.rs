@@ -16,6 +16,8 @@
 use std::os::unix::process::{CommandExt, ExitStatusExt};
 #[cfg(not(windows))]
 use std::process::{Child, Command, ExitStatus, Stdio};
+#[cfg(unix)]
+use std::time::Instant;
 use std::{
     ffi::OsStr,
     fmt,
@@ -23,6 +25,9 @@ use std::{
     io::{prelude::*, BufReader},
     path::{Path, PathBuf},
     result,
+    sync::mpsc,
+    thread,
+    time::Duration,
 };
 
 #[cfg(windows)]
@@ -31,11 +36,17 @@ use crate::hcore::{self, crypto, fs, outputln};
 use serde::{Serialize, Serializer};
 
 use super::{package::Pkg, TemplateRenderer};
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    trace,
+};
 
 #[cfg(not(windows))]
 pub const HOOK_PERMISSIONS: u32 = 0o755;
 static LOGKEY: &'static str = "HK";
+/// How long a timed out hook's processes are given to exit once asked to before they are killed.
+#[cfg(unix)]
+const HOOK_TERMINATE_GRACE: Duration = Duration::from_secs(5);
 
 pub fn stdout_log_path<T>(package_name: &str) -> PathBuf
 where
@@ -60,6 +71,41 @@ impl Default for ExitCode {
     }
 }
 
+/// How a run of a hook ended.
+#[derive(Debug, PartialEq)]
+pub enum HookRun<T> {
+    /// The hook exited, or could not be started, with the given result.
+    Completed(T),
+    /// The hook was still running once its timeout passed and was terminated.
+    TimedOut,
+}
+
+impl<T: Default> HookRun<T> {
+    /// The result of the hook, or the hook's default result if it timed out.
+    pub fn into_value(self) -> T {
+        match self {
+            HookRun::Completed(value) => value,
+            HookRun::TimedOut => T::default(),
+        }
+    }
+
+    pub fn timed_out(&self) -> bool {
+        match *self {
+            HookRun::Completed(_) => false,
+            HookRun::TimedOut => true,
+        }
+    }
+
+    /// Name of how the run ended, `completed` or `timeout`, used to label metrics.
+    pub fn outcome(&self) -> &'static str {
+        if self.timed_out() {
+            "timeout"
+        } else {
+            "completed"
+        }
+    }
+}
+
 pub trait Hook: fmt::Debug + Sized {
     type ExitValue: Default;
 
@@ -191,7 +237,8 @@ pub trait Hook: fmt::Debug + Sized {
         );
     }
 
-    /// Run a compiled hook.
+    /// Run a compiled hook, terminating it if it runs for longer than the package's timeout for
+    /// it.
     fn run<T>(
         &self,
         service_group: &str,
@@ -201,22 +248,61 @@ pub trait Hook: fmt::Debug + Sized {
     where
         T: ToString,
     {
+        let timeout = pkg
+            .hook_timeouts
+            .get(Self::file_name())
+            .map(|seconds| Duration::from_secs(u64::from(*seconds)));
+        self.run_with_timeout(service_group, pkg, svc_encrypted_password, timeout)
+            .into_value()
+    }
+
+    /// Run a compiled hook. If it is still running once `timeout` has passed, the hook and every
+    /// process it started are terminated.
+    fn run_with_timeout<T>(
+        &self,
+        service_group: &str,
+        pkg: &Pkg,
+        svc_encrypted_password: Option<T>,
+        timeout: Option<Duration>,
+    ) -> HookRun<Self::ExitValue>
+    where
+        T: ToString,
+    {
+        let mut span = trace::span("hook")
+            .attr("hook", Self::file_name())
+            .attr("service_group", service_group);
         let mut child = match Self::exec(self.path(), &pkg, svc_encrypted_password) {
             Ok(child) => child,
             Err(err) => {
                 outputln!(preamble service_group,
                     "Hook failed to run, {}, {}", Self::file_name(), err);
-                return Self::ExitValue::default();
+                span.set_error(err);
+                return HookRun::Completed(Self::ExitValue::default());
             }
         };
+        let watchdog = timeout
+            .map(|timeout| Watchdog::start(Self::file_name(), service_group, child.id(), timeout));
         let mut hook_output = HookOutput::new(self.stdout_log_path(), self.stderr_log_path());
         hook_output.stream_output::<Self>(service_group, &mut child);
-        match child.wait() {
-            Ok(status) => self.handle_exit(pkg, &hook_output, &status),
+        let status = child.wait();
+        if watchdog.map_or(false, Watchdog::stop) {
+            span.set_error("timed out");
+            return HookRun::TimedOut;
+        }
+        match status {
+            Ok(status) => {
+                match status.code() {
+                    Some(0) => (),
+                    Some(code) => span.set_error(format!("exited with code {}", code)),
+                    None => span.set_error("terminated by a signal"),
+                }
+                HookRun::Completed(self.handle_exit(pkg, &hook_output, &status))
+            }
             Err(err) => {
                 outputln!(preamble service_group,
                     "Hook failed to run, {}, {}", Self::file_name(), err);
-                Self::ExitValue::default()
+                span.set_error(err);
+                HookRun::Completed(Self::ExitValue::default())
             }
         }
     }
@@ -250,6 +336,14 @@ pub trait Hook: fmt::Debug + Sized {
         cmd.stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
+        // Run the hook in its own process group so that it can be terminated along with every
+        // process it starts if it times out.
+        cmd.before_exec(|| {
+            unsafe {
+                libc::setpgid(0, 0);
+            }
+            Ok(())
+        });
         for (key, val) in pkg.env.iter() {
             cmd.env(key, val);
         }
@@ -299,6 +393,66 @@ pub trait Hook: fmt::Debug + Sized {
     fn stderr_log_path(&self) -> &Path;
 }
 
+/// Terminates a hook's processes if the hook is still running once its timeout has passed.
+struct Watchdog {
+    done: mpsc::Sender<()>,
+    handle: thread::JoinHandle<bool>,
+}
+
+impl Watchdog {
+    fn start(hook: &'static str, service_group: &str, pid: u32, timeout: Duration) -> Self {
+        let (done, finished) = mpsc::channel();
+        let service_group = service_group.to_string();
+        let handle = thread::spawn(move || match finished.recv_timeout(timeout) {
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                outputln!(preamble service_group,
+                    "{} timed out after {}s, terminating it", hook, timeout.as_secs());
+                terminate_process_group(pid);
+                true
+            }
+            _ => false,
+        });
+        Watchdog { done, handle }
+    }
+
+    /// Stops the watchdog once the hook has exited, returning whether it timed out.
+    fn stop(self) -> bool {
+        self.done.send(()).ok();
+        self.handle.join().unwrap_or(false)
+    }
+}
+
+/// Asks every process in the process group led by `pid` to exit, killing them if any are left
+/// once `HOOK_TERMINATE_GRACE` has passed.
+#[cfg(unix)]
+fn terminate_process_group(pid: u32) {
+    let pgid = -(pid as libc::pid_t);
+    unsafe {
+        libc::kill(pgid, libc::SIGTERM);
+    }
+    let deadline = Instant::now() + HOOK_TERMINATE_GRACE;
+    while Instant::now() < deadline {
+        if unsafe { libc::kill(pgid, 0) } != 0 {
+            return;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    unsafe {
+        libc::kill(pgid, libc::SIGKILL);
+    }
+}
+
+/// Kills the process `pid` and every process it started.
+#[cfg(windows)]
+fn terminate_process_group(pid: u32) {
+    if let Err(err) = std::process::Command::new("taskkill")
+        .args(&["/F", "/T", "/PID", &pid.to_string()])
+        .output()
+    {
+        debug!("Failed to kill process tree of pid {}, {}", pid, err);
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct InstallHook {
     render_pair: RenderPair,
@@ -308,6 +462,11 @@ pub struct InstallHook {
 
 impl InstallHook {
     pub const STATUS_FILE: &'static str = "INSTALL_HOOK_STATUS";
+
+    /// Render the hook in strict mode, failing on references to undefined paths.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        self.render_pair.renderer.set_strict_mode(strict);
+    }
 }
 
 impl Hook for InstallHook {
@@ -770,6 +929,125 @@ echo "The message is Hello"
         assert_eq!(post_second_change_content, post_change_content);
     }
 
+    #[test]
+    fn compile_a_hook_in_strict_mode() {
+        let service_group = service_group();
+        let concrete_path = rendered_hooks_path();
+        let template_path = hook_templates_path();
+
+        let mut hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+            .expect("Could not create testing install hook");
+
+        let pg_id = PackageIdent::new(
+            "testing",
+            &service_group.service(),
+            Some("1.0.0"),
+            Some("20170712000000"),
+        );
+        let pkg_install = PackageInstall::new_from_parts(
+            pg_id.clone(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+        );
+        let pkg = Pkg::from_install(&pkg_install).expect("Could not create package!");
+
+        // The template renders `cfg.message`, which this configuration lacks.
+        let cfg_path = concrete_path.as_ref().join("default.toml");
+        create_with_content(cfg_path, &String::from("greeting = \"Hello\""));
+        let cfg = Cfg::new(&pkg, Some(&concrete_path.as_ref().to_path_buf()))
+            .expect("Could not create config");
+        let ctx = RenderContext::new(&pkg, &cfg);
+
+        hook.set_strict_mode(true);
+        assert!(hook.compile(&service_group, &ctx).is_err());
+        assert_eq!(hook.as_ref().exists(), false);
+
+        hook.set_strict_mode(false);
+        assert_eq!(hook.compile(&service_group, &ctx).unwrap(), true);
+        assert_eq!(
+            file_content(&hook),
+            "#!/bin/bash\n\necho \"The message is \"\n"
+        );
+    }
+
+    #[test]
+    fn hook_run_outcome() {
+        assert_eq!(HookRun::Completed(true).outcome(), "completed");
+        assert_eq!(HookRun::Completed(true).into_value(), true);
+        assert_eq!(HookRun::<bool>::TimedOut.outcome(), "timeout");
+        assert_eq!(HookRun::<bool>::TimedOut.into_value(), false);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn a_hook_which_times_out_is_terminated_with_every_process_it_started() {
+        use crate::hcore::os::users;
+        use std::{fs as stdfs, os::unix::fs::PermissionsExt};
+
+        /// Whether `pid` is still running; a zombie waiting to be reaped is not.
+        fn is_running(pid: &str) -> bool {
+            match stdfs::read_to_string(format!("/proc/{}/stat", pid)) {
+                Ok(stat) => !stat
+                    .rsplit(')')
+                    .next()
+                    .map_or(false, |rest| rest.trim_start().starts_with('Z')),
+                Err(_) => false,
+            }
+        }
+
+        let service_group = service_group();
+        let concrete_path = rendered_hooks_path();
+        let template_path = hook_templates_path();
+        let mut hook = InstallHook::load(&service_group, &concrete_path, &template_path)
+            .expect("Could not create testing install hook");
+        hook.stdout_log_path = concrete_path.path().join("install.stdout.log");
+        hook.stderr_log_path = concrete_path.path().join("install.stderr.log");
+
+        // The hook records its own PID and that of a child it forks, then outlives its timeout.
+        let pids_path = concrete_path.path().join("pids");
+        create_with_content(
+            &hook,
+            format!(
+                "#!/bin/sh\nsleep 60 &\necho \"$$ $!\" > {}\nsleep 60\n",
+                pids_path.display()
+            ),
+        );
+        stdfs::set_permissions(hook.path(), stdfs::Permissions::from_mode(0o755))
+            .expect("couldn't make hook executable");
+
+        let pg_id = PackageIdent::new(
+            "testing",
+            &service_group.service(),
+            Some("1.0.0"),
+            Some("20170712000000"),
+        );
+        let pkg_install = PackageInstall::new_from_parts(
+            pg_id.clone(),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+        );
+        let mut pkg = Pkg::from_install(&pkg_install).expect("Could not create package!");
+        pkg.svc_user = users::get_current_username().expect("Can get current username");
+        pkg.svc_group = users::get_current_groupname().expect("Can get current groupname");
+
+        let started = Instant::now();
+        let run = hook.run_with_timeout(
+            &service_group.to_string(),
+            &pkg,
+            None::<String>,
+            Some(Duration::from_secs(1)),
+        );
+        assert_eq!(run, HookRun::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(30));
+
+        let pids = file_content(&pids_path);
+        for pid in pids.split_whitespace() {
+            assert!(!is_running(pid), "process {} is still running", pid);
+        }
+    }
+
     ////////////////////////////////////////////////////////////////////////
 
     #[test]
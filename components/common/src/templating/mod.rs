@@ -13,19 +13,25 @@
 // limitations under the License.
 
 pub mod config;
+pub mod config_source;
 mod context;
+pub mod fixture;
 pub mod helpers;
 pub mod hooks;
 pub mod package;
+pub mod secret;
+pub mod strict;
 pub mod test_helpers;
 
 use std::{
+    collections::HashMap,
     fmt,
     ops::{Deref, DerefMut},
+    path::Path,
     result,
 };
 
-use handlebars::{Handlebars, RenderError};
+use handlebars::{Handlebars, RenderError, TemplateError, TemplateFileError};
 use serde::Serialize;
 use serde_json;
 
@@ -46,14 +52,16 @@ pub fn compile_for_package_install(package: &PackageInstall) -> Result<()> {
 
     let cfg = config::Cfg::new(&pkg, None)?;
     let ctx = RenderContext::new(&pkg, &cfg);
-    let cfg_renderer = config::CfgRenderer::new(pkg.path.join("config_install"))?;
+    let mut cfg_renderer = config::CfgRenderer::new(pkg.path.join("config_install"))?;
+    cfg_renderer.set_strict_mode(pkg.strict_templates);
     cfg_renderer.compile(&pkg.name, &pkg, &pkg.svc_config_install_path, &ctx)?;
 
-    if let Some(ref hook) = InstallHook::load(
+    if let Some(mut hook) = InstallHook::load(
         &pkg.name,
         &fs::svc_hooks_path(&pkg.name),
         &package.installed_path.join("hooks"),
     ) {
+        hook.set_strict_mode(pkg.strict_templates);
         hook.compile(&pkg.name, &ctx)?;
     };
 
@@ -62,7 +70,12 @@ pub fn compile_for_package_install(package: &PackageInstall) -> Result<()> {
 
 pub type RenderResult<T> = result::Result<T, RenderError>;
 
-pub struct TemplateRenderer(Handlebars);
+pub struct TemplateRenderer {
+    handlebars: Handlebars,
+    /// Sources of the registered templates, which are checked before rendering in strict mode.
+    sources: HashMap<String, String>,
+    strict: bool,
+}
 
 impl TemplateRenderer {
     pub fn new() -> Self {
@@ -77,18 +90,96 @@ impl TemplateRenderer {
         handlebars.register_helper("toJson", Box::new(helpers::TO_JSON));
         handlebars.register_helper("toToml", Box::new(helpers::TO_TOML));
         handlebars.register_helper("toYaml", Box::new(helpers::TO_YAML));
+        handlebars.register_helper("add", Box::new(helpers::ADD));
+        handlebars.register_helper("subtract", Box::new(helpers::SUBTRACT));
+        handlebars.register_helper("multiply", Box::new(helpers::MULTIPLY));
+        handlebars.register_helper("divide", Box::new(helpers::DIVIDE));
+        handlebars.register_helper("modulo", Box::new(helpers::MODULO));
+        handlebars.register_helper("eq", Box::new(helpers::EQ));
+        handlebars.register_helper("ne", Box::new(helpers::NE));
+        handlebars.register_helper("lt", Box::new(helpers::LT));
+        handlebars.register_helper("lte", Box::new(helpers::LTE));
+        handlebars.register_helper("gt", Box::new(helpers::GT));
+        handlebars.register_helper("gte", Box::new(helpers::GTE));
+        handlebars.register_helper("default", Box::new(helpers::DEFAULT));
+        handlebars.register_helper("coalesce", Box::new(helpers::COALESCE));
+        handlebars.register_helper("base64Encode", Box::new(helpers::BASE64_ENCODE));
+        handlebars.register_helper("base64Decode", Box::new(helpers::BASE64_DECODE));
+        handlebars.register_helper("sha256", Box::new(helpers::SHA256));
+        handlebars.register_helper("regexMatch", Box::new(helpers::REGEX_MATCH));
+        handlebars.register_helper("regexReplace", Box::new(helpers::REGEX_REPLACE));
+        handlebars.register_helper("eachSortedBy", Box::new(helpers::EACH_SORTED_BY));
+        handlebars.register_helper("eachWhere", Box::new(helpers::EACH_WHERE));
+        handlebars.register_helper("toIni", Box::new(helpers::TO_INI));
+        handlebars.register_helper("toProperties", Box::new(helpers::TO_PROPERTIES));
+        handlebars.register_helper("indent", Box::new(helpers::INDENT));
+        handlebars.register_helper("nindent", Box::new(helpers::NINDENT));
+        handlebars.register_helper("formatDuration", Box::new(helpers::FORMAT_DURATION));
+        handlebars.register_helper("formatBytes", Box::new(helpers::FORMAT_BYTES));
 
         handlebars.register_escape_fn(never_escape);
-        TemplateRenderer(handlebars)
+        TemplateRenderer {
+            handlebars,
+            sources: HashMap::new(),
+            strict: false,
+        }
+    }
+
+    /// In strict mode, rendering a template which references a path missing from the render
+    /// context fails instead of rendering an empty string. See `strict::check`.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn register_template_file<P>(
+        &mut self,
+        name: &str,
+        path: P,
+    ) -> result::Result<(), TemplateFileError>
+    where
+        P: AsRef<Path>,
+    {
+        self.handlebars
+            .register_template_file(name, path.as_ref())?;
+        // Handlebars has just read the file, so this rarely fails. A template whose source isn't
+        // kept is not checked in strict mode.
+        if let Ok(source) = std::fs::read_to_string(path.as_ref()) {
+            self.sources.insert(name.to_string(), source);
+        }
+        Ok(())
+    }
+
+    pub fn register_template_string<S>(
+        &mut self,
+        name: &str,
+        source: S,
+    ) -> result::Result<(), TemplateError>
+    where
+        S: AsRef<str>,
+    {
+        self.handlebars
+            .register_template_string(name, source.as_ref())?;
+        self.sources
+            .insert(name.to_string(), source.as_ref().to_string());
+        Ok(())
     }
 
     pub fn render<T>(&self, template: &str, ctx: &T) -> Result<String>
     where
         T: Serialize,
     {
-        let raw = serde_json::to_value(ctx).map_err(Error::RenderContextSerialization)?;
+        let mut raw = serde_json::to_value(ctx).map_err(Error::RenderContextSerialization)?;
         debug!("Rendering template with context, {}, {}", template, raw);
-        self.0
+        // Secrets are only resolved for the duration of a render, after the context is logged.
+        if let Some(cfg) = raw.get_mut("cfg") {
+            secret::resolve(cfg)?;
+        }
+        if self.strict {
+            if let Some(source) = self.sources.get(template) {
+                strict::check(template, source, &raw)?;
+            }
+        }
+        self.handlebars
             .render(template, &raw)
             .map_err(|e| Error::TemplateRenderError(format!("{}", e)))
     }
@@ -104,13 +195,13 @@ impl Deref for TemplateRenderer {
     type Target = Handlebars;
 
     fn deref(&self) -> &Handlebars {
-        &self.0
+        &self.handlebars
     }
 }
 
 impl DerefMut for TemplateRenderer {
     fn deref_mut(&mut self) -> &mut Handlebars {
-        &mut self.0
+        &mut self.handlebars
     }
 }
 
@@ -300,6 +391,26 @@ test: something"#
         assert_eq!(rendered, "172.17.0.5");
     }
 
+    #[test]
+    fn strict_mode_fails_on_undefined_path() {
+        let content = "{{bind.foo.members[0].sys.ip}}:{{bind.foo.members[0].cfg.prot}}";
+        let mut renderer = TemplateRenderer::new();
+        let data = service_config_json_from_toml_file("complex_config.toml");
+
+        renderer.register_template_string("t", content).unwrap();
+        assert_eq!(renderer.render("t", &data).unwrap(), "172.17.0.5:");
+
+        renderer.set_strict_mode(true);
+        match renderer.render("t", &data) {
+            Err(Error::UndefinedTemplatePath(template, line, path)) => {
+                assert_eq!(template, "t");
+                assert_eq!(line, 1);
+                assert_eq!(path, "bind.foo.members[0].cfg.prot");
+            }
+            other => panic!("Expected an undefined path error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn pkg_path_for_helper() {
         let content = "{{pkgPathFor \"core/acl\"}}".to_string();
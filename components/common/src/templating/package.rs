@@ -12,7 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, env, ops::Deref, path::PathBuf, result};
+use std::{
+    collections::HashMap,
+    env, fs as stdfs,
+    ops::Deref,
+    path::{Component, Path, PathBuf},
+    result,
+    str::FromStr,
+};
 
 use crate::hcore::{
     fs,
@@ -24,6 +31,7 @@ use serde::{ser::SerializeStruct, Serialize, Serializer};
 
 use crate::{
     error::{Error, Result},
+    types::HookTimeout,
     util::path,
 };
 
@@ -32,6 +40,135 @@ const DEFAULT_GROUP: &str = "hab";
 
 const PATH_KEY: &str = "PATH";
 
+/// Name of the metadata file present in packages built with `pkg_strict_templates=true`.
+pub const STRICT_TEMPLATES_METAFILE: &str = "STRICT_TEMPLATES";
+/// Name of the metadata file mapping configuration templates to destinations, written from
+/// `pkg_config_paths`.
+pub const CONFIG_PATHS_METAFILE: &str = "CONFIG_PATHS";
+
+/// Name of the metadata file holding the seconds each hook may run for before it is terminated,
+/// written from `pkg_hook_timeouts`.
+pub const HOOK_TIMEOUTS_METAFILE: &str = "HOOK_TIMEOUTS";
+
+/// Name of the metadata file holding the signal which stops the service, from
+/// `pkg_shutdown_signal`.
+pub const SHUTDOWN_SIGNAL_METAFILE: &str = "SHUTDOWN_SIGNAL";
+/// Name of the metadata file holding the seconds the service is given to stop before it is
+/// killed, from `pkg_shutdown_timeout_sec`.
+pub const SHUTDOWN_TIMEOUT_METAFILE: &str = "SHUTDOWN_TIMEOUT";
+
+/// Where a configuration template is rendered to instead of the service's config directory.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConfigPath {
+    /// Absolute destination of the rendered file.
+    pub path: PathBuf,
+    /// File mode of the rendered file, the default configuration permissions if unset.
+    pub mode: Option<u32>,
+    /// Owner of the rendered file, the service user if unset.
+    pub user: Option<String>,
+    /// Group of the rendered file, the service group if unset.
+    pub group: Option<String>,
+}
+
+impl ConfigPath {
+    /// Parses the value of a `pkg_config_paths` entry, `<path> [<mode> [<user>[:<group>]]]`.
+    /// Relative paths are relative to `svc_path` and may not leave it. Only the permission bits
+    /// of the mode are kept.
+    pub fn parse(entry: &str, svc_path: &Path) -> Result<Self> {
+        let err = |e: &str| Error::ConfigPath(entry.to_string(), e.to_string());
+        let mut fields = entry.split_whitespace();
+        let path = match fields.next().map(Path::new) {
+            Some(path)
+                if path.is_relative() && path.components().any(|c| c == Component::ParentDir) =>
+            {
+                return Err(err("relative path may not contain `..`"));
+            }
+            Some(path) => svc_path.join(path),
+            None => return Err(err("missing destination path")),
+        };
+        let mode = match fields.next() {
+            Some(mode) => {
+                Some(u32::from_str_radix(mode, 8).map_err(|_| err("mode must be octal"))? & 0o777)
+            }
+            None => None,
+        };
+        let (user, group) = match fields.next() {
+            Some(owner) => {
+                let mut owner = owner.splitn(2, ':');
+                let user = owner.next().filter(|u| !u.is_empty()).map(str::to_string);
+                let group = owner.next().filter(|g| !g.is_empty()).map(str::to_string);
+                (user, group)
+            }
+            None => (None, None),
+        };
+        if fields.next().is_some() {
+            return Err(err("expected <path> [<mode> [<user>[:<group>]]]"));
+        }
+        Ok(ConfigPath {
+            path,
+            mode,
+            user,
+            group,
+        })
+    }
+}
+
+/// Reads the trimmed content of a metadata file of an installed package, if it has one.
+fn read_metafile(package: &PackageInstall, name: &str) -> Result<Option<String>> {
+    let metafile = package.installed_path.join(name);
+    if !metafile.is_file() {
+        return Ok(None);
+    }
+    let content = stdfs::read_to_string(&metafile)?;
+    Ok(Some(content.trim().to_string()).filter(|c| !c.is_empty()))
+}
+
+/// Reads the `CONFIG_PATHS` metadata file of an installed package, keyed by template path
+/// relative to the package's config directory.
+fn config_paths(package: &PackageInstall) -> Result<HashMap<String, ConfigPath>> {
+    let metafile = package.installed_path.join(CONFIG_PATHS_METAFILE);
+    if !metafile.is_file() {
+        return Ok(HashMap::new());
+    }
+    let svc_path = fs::svc_path(&package.ident.name);
+    let mut paths = HashMap::new();
+    for line in stdfs::read_to_string(&metafile)?.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(template), Some(entry)) => {
+                paths.insert(
+                    template.trim().to_string(),
+                    ConfigPath::parse(entry, &svc_path)?,
+                );
+            }
+            _ => {
+                return Err(Error::ConfigPath(
+                    line.to_string(),
+                    "expected <template>=<path>".to_string(),
+                ));
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Reads the `HOOK_TIMEOUTS` metadata file of an installed package, keyed by hook name.
+fn hook_timeouts(package: &PackageInstall) -> Result<HashMap<String, u32>> {
+    let mut timeouts = HashMap::new();
+    if let Some(content) = read_metafile(package, HOOK_TIMEOUTS_METAFILE)? {
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            let timeout = HookTimeout::from_str(line).map_err(|e| {
+                Error::PackageMetafile(HOOK_TIMEOUTS_METAFILE.to_string(), e.to_string())
+            })?;
+            timeouts.insert(timeout.hook, timeout.seconds);
+        }
+    }
+    Ok(timeouts)
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Env(HashMap<String, String>);
 
@@ -100,6 +237,22 @@ pub struct Pkg {
     pub svc_run: PathBuf,
     pub svc_user: String,
     pub svc_group: String,
+    /// Whether the package's configuration templates are rendered in strict mode.
+    #[serde(default)]
+    pub strict_templates: bool,
+    /// Destinations of configuration templates rendered outside of the config directory.
+    #[serde(default)]
+    pub config_paths: HashMap<String, ConfigPath>,
+    /// Name of the signal which stops the service, the Launcher's default if unset.
+    #[serde(default)]
+    pub shutdown_signal: Option<String>,
+    /// Seconds the service is given to stop before it is killed, the Launcher's default if unset.
+    #[serde(default)]
+    pub shutdown_timeout: Option<u32>,
+    /// Seconds each hook may run for before it is terminated, keyed by hook name. Hooks without
+    /// a timeout run until they exit.
+    #[serde(default)]
+    pub hook_timeouts: HashMap<String, u32>,
 }
 
 impl Pkg {
@@ -121,6 +274,22 @@ impl Pkg {
             deps: package.tdeps()?,
             exposes: package.exposes()?,
             exports: package.exports()?,
+            strict_templates: package
+                .installed_path
+                .join(STRICT_TEMPLATES_METAFILE)
+                .is_file(),
+            config_paths: config_paths(&package)?,
+            shutdown_signal: read_metafile(&package, SHUTDOWN_SIGNAL_METAFILE)?,
+            shutdown_timeout: match read_metafile(&package, SHUTDOWN_TIMEOUT_METAFILE)? {
+                Some(timeout) => Some(timeout.parse().map_err(|_| {
+                    Error::PackageMetafile(
+                        SHUTDOWN_TIMEOUT_METAFILE.to_string(),
+                        format!("{} is not a number of seconds", timeout),
+                    )
+                })?),
+                None => None,
+            },
+            hook_timeouts: hook_timeouts(&package)?,
             path: package.installed_path.clone(),
             ident: package.ident.clone(),
             origin: package.ident.origin.clone(),
@@ -258,3 +427,49 @@ fn default_user_and_group() -> Result<(String, String)> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_config_path() {
+        let svc_path = Path::new("/hab/svc/nginx");
+        assert_eq!(
+            ConfigPath::parse("/etc/nginx/nginx.conf 0644 root:wheel", svc_path).unwrap(),
+            ConfigPath {
+                path: PathBuf::from("/etc/nginx/nginx.conf"),
+                mode: Some(0o644),
+                user: Some("root".to_string()),
+                group: Some("wheel".to_string()),
+            }
+        );
+        assert_eq!(
+            ConfigPath::parse("data/nginx.conf", svc_path).unwrap(),
+            ConfigPath {
+                path: PathBuf::from("/hab/svc/nginx/data/nginx.conf"),
+                mode: None,
+                user: None,
+                group: None,
+            }
+        );
+        let config_path = ConfigPath::parse("data/nginx.conf 600 root", svc_path).unwrap();
+        assert_eq!(config_path.mode, Some(0o600));
+        assert_eq!(config_path.user, Some("root".to_string()));
+        assert_eq!(config_path.group, None);
+    }
+
+    #[test]
+    fn parse_invalid_config_path() {
+        let svc_path = Path::new("/hab/svc/nginx");
+        assert!(ConfigPath::parse("", svc_path).is_err());
+        assert!(ConfigPath::parse("nginx.conf rw-r--r--", svc_path).is_err());
+        assert!(ConfigPath::parse("nginx.conf 0644 root:root extra", svc_path).is_err());
+        assert!(ConfigPath::parse("../nginx/nginx.conf", svc_path).is_err());
+        assert!(ConfigPath::parse("config/../../nginx.conf 0644", svc_path).is_err());
+        assert_eq!(
+            ConfigPath::parse("nginx.conf 4755", svc_path).unwrap().mode,
+            Some(0o755)
+        );
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! References to secrets from a service's configuration.
+//!
+//! Any configuration value may be given as a table holding a single `secret` key instead of the
+//! value itself:
+//!
+//! ```toml
+//! [db]
+//! password = { secret = "file:/run/secrets/db" }
+//! ```
+//!
+//! The reference is kept as-is in every configuration layer, so the secret never appears in a
+//! `Cfg`, the HTTP gateway or gossip. It is only resolved when templates are rendered. A
+//! reference is one of:
+//!
+//! * `file:<absolute path>` - the content of the file, without a trailing newline
+//! * `env:<variable>` - the value of an environment variable of the Supervisor
+//! * `box:<encrypted message>` - a message encrypted for the service group, in the format
+//!   `hab config apply` uses, decrypted with the keys in the key cache
+//!
+//! Values exported to the census are redacted rather than resolved.
+//!
+//! `file:` and `env:` references may only be made from `default.toml` and `user.toml`. Gossip,
+//! the environment and external configuration sources can be set by anyone able to reach the ring
+//! or the source, so such a reference arriving through them is rejected rather than read from the
+//! Supervisor's files. `box:` references may come from any layer, since only a message encrypted
+//! for the service group can be decrypted.
+
+use std::{env, fs, path::PathBuf};
+
+use serde_json;
+use toml;
+
+use crate::{
+    error::{Error, Result},
+    hcore::crypto::{default_cache_key_path, keys::box_key_pair::WrappedSealedBox, BoxKeyPair},
+};
+
+/// Key of the single-entry table which marks a value as a secret reference.
+pub const SECRET_KEY: &str = "secret";
+
+/// Value substituted for a secret reference wherever it must not be resolved.
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecretRef {
+    File(PathBuf),
+    Env(String),
+    Box(String),
+}
+
+impl SecretRef {
+    pub fn parse(reference: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::SecretReference(reference.to_string(), reason.into());
+        let mut parts = reference.splitn(2, ':');
+        let (kind, value) = match (parts.next(), parts.next()) {
+            (Some(kind), Some(value)) if !value.is_empty() => (kind, value),
+            _ => return Err(invalid("expected <file|env|box>:<value>")),
+        };
+        match kind {
+            "file" => {
+                let path = PathBuf::from(value);
+                if path.is_relative() {
+                    return Err(invalid("file references must be absolute paths"));
+                }
+                Ok(SecretRef::File(path))
+            }
+            "env" => Ok(SecretRef::Env(value.to_string())),
+            "box" => Ok(SecretRef::Box(value.to_string())),
+            _ => Err(invalid("expected <file|env|box>:<value>")),
+        }
+    }
+
+    /// Reads the secret. `reference` is the original reference, used in errors.
+    pub fn resolve(&self, reference: &str) -> Result<String> {
+        let err = |e: String| Error::SecretReference(reference.to_string(), e);
+        match *self {
+            SecretRef::File(ref path) => fs::read_to_string(path)
+                .map(|content| content.trim_end_matches(&['\r', '\n'][..]).to_string())
+                .map_err(|e| err(e.to_string())),
+            SecretRef::Env(ref var) => env::var(var).map_err(|e| err(e.to_string())),
+            SecretRef::Box(ref message) => {
+                let sealed = WrappedSealedBox::from_bytes(message.as_bytes())
+                    .map_err(|e| err(e.to_string()))?;
+                let bytes = BoxKeyPair::decrypt_with_path(&sealed, &default_cache_key_path(None))
+                    .map_err(|e| err(e.to_string()))?;
+                String::from_utf8(bytes).map_err(|e| err(e.to_string()))
+            }
+        }
+    }
+}
+
+/// Returns the reference if the given TOML value is a secret reference.
+pub fn toml_reference(value: &toml::Value) -> Option<&str> {
+    match value.as_table() {
+        Some(table) if table.len() == 1 => table.get(SECRET_KEY).and_then(toml::Value::as_str),
+        _ => None,
+    }
+}
+
+fn json_reference(value: &serde_json::Value) -> Option<&str> {
+    match value.as_object() {
+        Some(map) if map.len() == 1 => map.get(SECRET_KEY).and_then(serde_json::Value::as_str),
+        _ => None,
+    }
+}
+
+/// Replaces every secret reference nested in the given value with the secret it refers to.
+pub fn resolve(value: &mut serde_json::Value) -> Result<()> {
+    if let Some(reference) = json_reference(value).map(str::to_string) {
+        let secret = SecretRef::parse(&reference)?.resolve(&reference)?;
+        *value = serde_json::Value::String(secret);
+        return Ok(());
+    }
+    match *value {
+        serde_json::Value::Object(ref mut map) => {
+            for nested in map.values_mut() {
+                resolve(nested)?;
+            }
+        }
+        serde_json::Value::Array(ref mut list) => {
+            for nested in list.iter_mut() {
+                resolve(nested)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Fails if a reference to a secret on this host, or one which is invalid, is nested in the given
+/// table. Used for configuration layers other than `default.toml` and `user.toml`.
+pub fn reject_local_references(table: &toml::value::Table) -> Result<()> {
+    match table.values().filter_map(find_local_reference).next() {
+        Some(reference) => Err(Error::SecretReference(
+            reference.to_string(),
+            "file and env secrets may only be referenced from default.toml or user.toml"
+                .to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn find_local_reference(value: &toml::Value) -> Option<&str> {
+    if let Some(reference) = toml_reference(value) {
+        return match SecretRef::parse(reference) {
+            Ok(SecretRef::Box(_)) => None,
+            _ => Some(reference),
+        };
+    }
+    match *value {
+        toml::Value::Table(ref table) => table.values().filter_map(find_local_reference).next(),
+        toml::Value::Array(ref list) => list.iter().filter_map(find_local_reference).next(),
+        _ => None,
+    }
+}
+
+/// Returns the secret each reference nested in the given value refers to.
+pub fn resolved(value: &serde_json::Value) -> Result<Vec<String>> {
+    let mut references = vec![];
+    collect_references(value, &mut references);
+    references
+        .iter()
+        .map(|reference| SecretRef::parse(reference)?.resolve(reference))
+        .collect()
+}
+
+fn collect_references(value: &serde_json::Value, references: &mut Vec<String>) {
+    if let Some(reference) = json_reference(value) {
+        references.push(reference.to_string());
+        return;
+    }
+    match *value {
+        serde_json::Value::Object(ref map) => {
+            map.values().for_each(|v| collect_references(v, references))
+        }
+        serde_json::Value::Array(ref list) => {
+            list.iter().for_each(|v| collect_references(v, references))
+        }
+        _ => (),
+    }
+}
+
+/// Replaces every occurrence of the given secrets in rendered text with `REDACTED`.
+pub fn redact_text(text: &str, secrets: &[String]) -> String {
+    let mut secrets: Vec<&String> = secrets.iter().filter(|s| !s.is_empty()).collect();
+    // Longest first, so a secret containing another is redacted whole.
+    secrets.sort_by(|a, b| b.len().cmp(&a.len()));
+    secrets.into_iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret.as_str(), REDACTED)
+    })
+}
+
+/// Replaces every secret reference nested in the given table with `REDACTED`.
+pub fn redact(table: &mut toml::value::Table) {
+    for value in table.values_mut() {
+        redact_value(value);
+    }
+}
+
+fn redact_value(value: &mut toml::Value) {
+    if toml_reference(value).is_some() {
+        *value = toml::Value::String(REDACTED.to_string());
+        return;
+    }
+    match *value {
+        toml::Value::Table(ref mut table) => redact(table),
+        toml::Value::Array(ref mut list) => list.iter_mut().for_each(redact_value),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{fs::File, io::Write};
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_references() {
+        assert_eq!(
+            SecretRef::parse("file:/run/secrets/db").unwrap(),
+            SecretRef::File(PathBuf::from("/run/secrets/db"))
+        );
+        assert_eq!(
+            SecretRef::parse("env:DB_PASSWORD").unwrap(),
+            SecretRef::Env("DB_PASSWORD".to_string())
+        );
+        assert!(SecretRef::parse("file:relative/db").is_err());
+        assert!(SecretRef::parse("vault:db").is_err());
+        assert!(SecretRef::parse("env:").is_err());
+        assert!(SecretRef::parse("DB_PASSWORD").is_err());
+    }
+
+    #[test]
+    fn resolve_nested_references() {
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("db");
+        File::create(&path)
+            .unwrap()
+            .write_all(b"hunter2\n")
+            .unwrap();
+        env::set_var("HAB_TEST_SECRET_REFERENCE", "s3cret");
+        let mut value = json!({
+            "port": 5432,
+            "db": { "password": { "secret": format!("file:{}", path.display()) } },
+            "tokens": [{ "secret": "env:HAB_TEST_SECRET_REFERENCE" }],
+            "not_a_reference": { "secret": "file:/etc/passwd", "other": true },
+        });
+        resolve(&mut value).unwrap();
+        assert_eq!(value["db"]["password"], "hunter2");
+        assert_eq!(value["tokens"][0], "s3cret");
+        assert_eq!(value["port"], 5432);
+        assert_eq!(value["not_a_reference"]["secret"], "file:/etc/passwd");
+    }
+
+    #[test]
+    fn unresolvable_reference_fails() {
+        let mut value = json!({ "password": { "secret": "env:HAB_TEST_SECRET_UNSET" } });
+        assert!(resolve(&mut value).is_err());
+    }
+
+    #[test]
+    fn only_local_references_are_rejected() {
+        let boxed: toml::value::Table =
+            toml::from_str("[tls]\nkey = { secret = \"box:BOX-1\\nsealed\" }").unwrap();
+        assert!(reject_local_references(&boxed).is_ok());
+
+        for reference in &["file:/etc/shadow", "env:HOME", "vault:db"] {
+            let table: toml::value::Table =
+                toml::from_str(&format!("keys = [{{ secret = \"{}\" }}]", reference)).unwrap();
+            assert!(reject_local_references(&table).is_err());
+        }
+    }
+
+    #[test]
+    fn redact_references() {
+        let mut table: toml::value::Table = toml::from_str(
+            r#"
+            port = 5432
+            password = { secret = "env:DB_PASSWORD" }
+            [nested]
+            tokens = [{ secret = "file:/run/secrets/token" }]
+            "#,
+        )
+        .unwrap();
+        redact(&mut table);
+        assert_eq!(table["password"].as_str(), Some(REDACTED));
+        assert_eq!(table["nested"]["tokens"][0].as_str(), Some(REDACTED));
+        assert_eq!(table["port"].as_integer(), Some(5432));
+    }
+}
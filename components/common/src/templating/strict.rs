@@ -0,0 +1,335 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Strict rendering of templates.
+//!
+//! Handlebars renders a reference to a missing path as an empty string, so a typo such as
+//! `{{cfg.prot}}` goes unnoticed until the rendered file is used. In strict mode, every path a
+//! template references from the root of the render context, that is `cfg`, `bind`, `svc` or
+//! `sys`, must exist in the data the template is rendered with. Otherwise rendering fails,
+//! naming the template and the line of the reference.
+//!
+//! Paths are only checked where they are resolved against the root of the render context. Inside
+//! a block which changes the context, such as `each` or `with`, the same names may refer to
+//! something else. The parameters of `if`, `unless`, `default` and `coalesce` are never checked,
+//! since those helpers exist to handle missing values.
+
+use serde_json::Value as Json;
+
+use crate::error::{Error, Result};
+
+/// Names at the root of the render context whose references are checked.
+const ROOTS: &[&str] = &["cfg", "bind", "svc", "sys"];
+
+/// Block helpers which render their blocks with the context of the enclosing template.
+const CONTEXT_PRESERVING_BLOCKS: &[&str] = &[
+    "if",
+    "unless",
+    "eq",
+    "ne",
+    "lt",
+    "lte",
+    "gt",
+    "gte",
+    "regexMatch",
+];
+
+/// Helpers whose parameters may reference missing paths.
+const OPTIONAL_PARAMETERS: &[&str] = &["if", "unless", "default", "coalesce"];
+
+/// Checks that every path the template `source` references from the root of the render context
+/// exists in `data`. `name` is the name of the template, used in errors.
+pub fn check(name: &str, source: &str, data: &Json) -> Result<()> {
+    // The helpers of the blocks the current expression is nested in.
+    let mut blocks: Vec<&str> = vec![];
+    for expression in expressions(source) {
+        let body = expression.body;
+        if body.starts_with('!') || body.starts_with('>') || body == "^" {
+            continue;
+        }
+        if body.starts_with('/') {
+            blocks.pop();
+            continue;
+        }
+        let opens_block = body.starts_with('#') || body.starts_with('^');
+        let tokens = if opens_block {
+            tokenize(body[1..].trim_start())
+        } else {
+            tokenize(body)
+        };
+        match tokens.first() {
+            Some(Token::Word("else")) | None => continue,
+            _ => (),
+        }
+        let checked = blocks
+            .iter()
+            .all(|block| CONTEXT_PRESERVING_BLOCKS.contains(block));
+        if checked {
+            let has_helper = opens_block || tokens.len() > 1;
+            for path in referenced_paths(&tokens, has_helper) {
+                if !is_defined(data, path) {
+                    return Err(Error::UndefinedTemplatePath(
+                        name.to_string(),
+                        expression.line,
+                        path.to_string(),
+                    ));
+                }
+            }
+        }
+        if opens_block {
+            blocks.push(match tokens[0] {
+                Token::Word(helper) => helper,
+                _ => "",
+            });
+        }
+    }
+    Ok(())
+}
+
+/// A `{{...}}` expression of a template.
+#[derive(Debug, PartialEq)]
+struct Expression<'a> {
+    /// The line the expression starts on, counting from 1.
+    line: usize,
+    /// The content of the expression, without braces or whitespace control.
+    body: &'a str,
+}
+
+fn expressions(source: &str) -> Vec<Expression<'_>> {
+    let mut expressions = vec![];
+    let mut line = 1;
+    let mut pos = 0;
+    while let Some(offset) = source[pos..].find("{{") {
+        let start = pos + offset;
+        line += source[pos..start].matches('\n').count();
+        // Long comments may themselves contain `}}`.
+        let close = if source[start..].starts_with("{{!--") {
+            "--}}"
+        } else {
+            "}}"
+        };
+        let end = match source[start..].find(close) {
+            Some(len) => start + len,
+            None => break,
+        };
+        if !source[..start].ends_with('\\') {
+            let body = source[start + 2..end]
+                .trim_start_matches(|c| c == '{' || c == '~')
+                .trim_end_matches(|c| c == '}' || c == '~')
+                .trim();
+            expressions.push(Expression { line, body });
+        }
+        line += source[start..end].matches('\n').count();
+        pos = end + close.len();
+    }
+    expressions
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'a> {
+    /// Opens a subexpression.
+    Open,
+    /// Closes a subexpression.
+    Close,
+    /// A string literal.
+    Literal,
+    /// A helper name, path, number or keyword.
+    Word(&'a str),
+}
+
+fn tokenize(body: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut chars = body.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' | '\'' => {
+                while let Some((_, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c {
+                        break;
+                    }
+                }
+                tokens.push(Token::Literal);
+            }
+            // Block parameters, as in `as |member|`, only name values of the block.
+            '|' => {
+                if tokens.last() == Some(&Token::Word("as")) {
+                    tokens.pop();
+                }
+                break;
+            }
+            c if c.is_whitespace() => (),
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, next)) = chars.peek() {
+                    if next.is_whitespace() || "()=|\"'".contains(next) {
+                        break;
+                    }
+                    end = i + next.len_utf8();
+                    chars.next();
+                }
+                // The key of a hash parameter names no value; its value follows the `=`.
+                if chars.peek().map(|&(_, next)| next) == Some('=') {
+                    chars.next();
+                } else {
+                    tokens.push(Token::Word(&body[start..end]));
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// Returns the paths from the root of the render context referenced by the tokens of an
+/// expression. `has_helper` is whether the first token names a helper.
+fn referenced_paths<'a>(tokens: &[Token<'a>], has_helper: bool) -> Vec<&'a str> {
+    let mut paths = vec![];
+    // Whether the parameters of each enclosing expression may be missing.
+    let mut enclosing = vec![];
+    let mut optional = false;
+    let mut helper_next = has_helper;
+    for token in tokens {
+        match *token {
+            Token::Open => {
+                enclosing.push(optional);
+                helper_next = true;
+            }
+            Token::Close => optional = enclosing.pop().unwrap_or(false),
+            Token::Literal => helper_next = false,
+            Token::Word(word) if helper_next => {
+                helper_next = false;
+                optional = OPTIONAL_PARAMETERS.contains(&word);
+            }
+            Token::Word(word) => {
+                let skipped = optional || enclosing.iter().any(|o| *o);
+                if !skipped && is_root_path(word) {
+                    paths.push(word);
+                }
+            }
+        }
+    }
+    paths
+}
+
+fn is_root_path(word: &str) -> bool {
+    // Relative paths such as `../cfg` never start at the root.
+    !word.starts_with('.') && segments(word).first().map_or(false, |s| ROOTS.contains(s))
+}
+
+/// Splits a path into its segments, accepting both `members.[0]` and `members[0]`.
+fn segments(path: &str) -> Vec<&str> {
+    path.split(|c| c == '.' || c == '/' || c == '[')
+        .map(|segment| segment.trim_end_matches(']'))
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+fn is_defined(data: &Json, path: &str) -> bool {
+    let mut current = data;
+    for segment in segments(path) {
+        let next = match *current {
+            Json::Object(ref map) => map.get(segment),
+            Json::Array(ref list) => segment.parse::<usize>().ok().and_then(|i| list.get(i)),
+            _ => None,
+        };
+        match next {
+            Some(value) => current = value,
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn data() -> Json {
+        json!({
+            "cfg": { "port": 6379, "tls": { "enabled": true }, "hosts": ["a", "b"] },
+            "sys": { "ip": "10.0.0.1" },
+            "bind": { "backend": { "members": [{ "sys": { "ip": "10.0.0.2" } }] } },
+        })
+    }
+
+    fn undefined(source: &str) -> Option<(usize, String)> {
+        match check("redis.conf", source, &data()) {
+            Ok(()) => None,
+            Err(Error::UndefinedTemplatePath(_, line, path)) => Some((line, path)),
+            Err(err) => panic!("Unexpected error {}", err),
+        }
+    }
+
+    #[test]
+    fn defined_paths_pass() {
+        assert_eq!(
+            undefined(
+                "port {{cfg.port}}\n{{#if cfg.tls.enabled}}tls yes{{/if}}\n{{cfg.hosts.[1]}}"
+            ),
+            None
+        );
+        assert_eq!(undefined("{{{toJson cfg.tls}}} {{~sys.ip~}}"), None);
+        assert_eq!(undefined("{{bind.backend.members[0].sys.ip}}"), None);
+    }
+
+    #[test]
+    fn undefined_path_reports_line() {
+        assert_eq!(
+            undefined("port {{cfg.port}}\nbind {{sys.ip}}\nprotected {{cfg.prot}}\n"),
+            Some((3, "cfg.prot".to_string()))
+        );
+        assert_eq!(
+            undefined("{{!-- {{cfg.in_comment}} --}}\n{{toJson (add cfg.port cfg.missing)}}"),
+            Some((2, "cfg.missing".to_string()))
+        );
+        assert_eq!(
+            undefined("{{#eq cfg.mode \"replica\"}}{{/eq}}"),
+            Some((1, "cfg.mode".to_string()))
+        );
+    }
+
+    #[test]
+    fn optional_parameters_are_not_checked() {
+        assert_eq!(
+            undefined("{{#if cfg.missing}}{{cfg.port}}{{else}}none{{/if}}"),
+            None
+        );
+        assert_eq!(undefined("{{default cfg.missing 80}}"), None);
+        assert_eq!(undefined("{{coalesce cfg.missing (toJson sys.ip)}}"), None);
+        assert_eq!(undefined("{{strJoin cfg.hosts \",\" key=cfg.port}}"), None);
+        assert_eq!(
+            undefined("{{strJoin cfg.hosts sep=cfg.sep}}"),
+            Some((1, "cfg.sep".to_string()))
+        );
+    }
+
+    #[test]
+    fn paths_inside_context_changing_blocks_are_not_checked() {
+        assert_eq!(
+            undefined("{{#eachAlive bind.backend.members as |m|}}{{sys.hostname}}{{/eachAlive}}"),
+            None
+        );
+        assert_eq!(
+            undefined("{{#with cfg.tls}}{{cfg.anything}}{{/with}}{{cfg.after}}"),
+            Some((1, "cfg.after".to_string()))
+        );
+        assert_eq!(
+            undefined("{{#each bind.missing.members}}{{/each}}"),
+            Some((1, "bind.missing.members".to_string()))
+        );
+    }
+}
@@ -49,7 +49,12 @@ where
 /// rendered, along with a pretty-printed formatting of the
 /// offending JSON.
 pub fn assert_valid(json_string: &str, schema: &str) {
-    let result = validate_string(json_string, schema);
+    assert_valid_against(json_string, read_schema(schema));
+}
+
+/// Asserts that `json_string` is valid according to an already parsed JSON schema.
+pub fn assert_valid_against(json_string: &str, schema: serde_json::Value) {
+    let result = validate_against(json_string, schema);
     if !result.is_valid() {
         let error_string = result
             .errors
@@ -81,17 +86,15 @@ JSON:
 ///
 /// In general, you should prefer using `assert_valid` directly.
 pub fn validate_string(input: &str, schema: &str) -> json_schema::ValidationState {
-    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .join("tests")
-        .join("fixtures")
-        .join(schema);
-    let mut schema_file = File::open(path).expect("could not open schema file");
-    let mut raw_schema = String::new();
-    schema_file
-        .read_to_string(&mut raw_schema)
-        .expect("could not read schema file");
-    let parsed_schema: serde_json::Value =
-        serde_json::from_str(&raw_schema).expect("Could not parse schema as JSON");
+    validate_against(input, read_schema(schema))
+}
+
+/// Compares the incoming JSON string against an already parsed JSON
+/// schema and returns the resulting `ValidationState`.
+pub fn validate_against(
+    input: &str,
+    parsed_schema: serde_json::Value,
+) -> json_schema::ValidationState {
     let mut scope = json_schema::scope::Scope::new();
     // NOTE: using `false` instead of `true` allows us to use
     // `$comment` keyword, as well as our own `$deprecated` and
@@ -104,3 +107,16 @@ pub fn validate_string(input: &str, schema: &str) -> json_schema::ValidationStat
         serde_json::from_str(input).expect("Could not parse input as JSON");
     schema.validate(&input_json)
 }
+
+fn read_schema(schema: &str) -> serde_json::Value {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(schema);
+    let mut schema_file = File::open(path).expect("could not open schema file");
+    let mut raw_schema = String::new();
+    schema_file
+        .read_to_string(&mut raw_schema)
+        .expect("could not read schema file");
+    serde_json::from_str(&raw_schema).expect("Could not parse schema as JSON")
+}
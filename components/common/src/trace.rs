@@ -0,0 +1,494 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tracing of Supervisor activity, exported with the OpenTelemetry protocol (OTLP).
+//!
+//! A span is started with [`span`] and finished when it is dropped. A span started while another
+//! span is open on the same thread becomes that span's child, so work such as a reconfigure hook
+//! run because a gossiped configuration changed shares a trace with the run loop iteration which
+//! applied it.
+//!
+//! Finished spans are batched on a background thread and exported as OTLP/JSON, either by POSTing
+//! them to a collector's OTLP/HTTP endpoint or by appending them to a file, one
+//! `ExportTraceServiceRequest` per line. Until [`init`] is called spans are not recorded at all.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{self, header::ContentType, Url};
+use serde_json;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+/// Path OTLP/HTTP collectors receive traces on.
+const OTLP_TRACES_PATH: &str = "/v1/traces";
+/// Name reported as the `service.name` resource attribute of every span.
+const SERVICE_NAME: &str = "hab-sup";
+/// Maximum number of spans sent in a single export.
+const MAX_BATCH_SIZE: usize = 512;
+/// Maximum time, in seconds, a finished span waits before it is exported.
+const EXPORT_INTERVAL_SECS: u64 = 5;
+
+/// OTLP `SPAN_KIND_INTERNAL`.
+const SPAN_KIND_INTERNAL: u8 = 1;
+/// OTLP `STATUS_CODE_OK`.
+const STATUS_CODE_OK: u8 = 1;
+/// OTLP `STATUS_CODE_ERROR`.
+const STATUS_CODE_ERROR: u8 = 2;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref EXPORTER: Mutex<Option<mpsc::Sender<SpanData>>> = Mutex::new(None);
+}
+
+thread_local! {
+    /// Spans currently open on this thread, innermost last.
+    static OPEN_SPANS: RefCell<Vec<SpanContext>> = RefCell::new(Vec::new());
+}
+
+/// Where finished spans are sent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceExporter {
+    /// POST spans to an OTLP/HTTP collector.
+    Endpoint(Url),
+    /// Append spans to a file.
+    File(PathBuf),
+}
+
+impl TraceExporter {
+    /// Parse the URL of an OTLP/HTTP collector. A URL without a path is given the default traces
+    /// path, so `http://localhost:4318` becomes `http://localhost:4318/v1/traces`.
+    pub fn endpoint(url: &str) -> Result<Self> {
+        let mut url =
+            Url::parse(url).map_err(|e| Error::InvalidTraceEndpoint(format!("{}, {}", url, e)))?;
+        if url.scheme() != "http" {
+            return Err(Error::InvalidTraceEndpoint(format!(
+                "{}, only http endpoints are supported",
+                url
+            )));
+        }
+        if url.path() == "/" {
+            url.set_path(OTLP_TRACES_PATH);
+        }
+        Ok(TraceExporter::Endpoint(url))
+    }
+
+    fn export(&self, spans: &[SpanData]) -> Result<()> {
+        let body = serde_json::to_string(&ExportRequest::new(spans))
+            .map_err(|e| Error::TraceExport(e.to_string()))?;
+        match *self {
+            TraceExporter::Endpoint(ref url) => {
+                let res = hyper::Client::new()
+                    .post(url.clone())
+                    .header(ContentType::json())
+                    .body(body.as_str())
+                    .send()
+                    .map_err(|e| Error::TraceExport(e.to_string()))?;
+                if res.status.is_success() {
+                    Ok(())
+                } else {
+                    Err(Error::TraceExport(format!(
+                        "{} responded with {}",
+                        url, res.status
+                    )))
+                }
+            }
+            TraceExporter::File(ref path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", body)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for TraceExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TraceExporter::Endpoint(ref url) => write!(f, "{}", url),
+            TraceExporter::File(ref path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Start exporting spans. Spans started before this is called are not recorded.
+pub fn init(exporter: TraceExporter) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::Builder::new()
+        .name("trace-exporter".to_string())
+        .spawn(move || export_loop(&rx, &exporter))?;
+    *EXPORTER.lock().expect("Trace exporter lock is poisoned") = Some(tx);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Start a span with the given name. It is a child of the innermost span open on this thread, if
+/// any, and is finished and exported when dropped.
+pub fn span<T>(name: T) -> Span
+where
+    T: Into<String>,
+{
+    if ENABLED.load(Ordering::Relaxed) {
+        Span(Some(SpanData::start(name.into())))
+    } else {
+        Span(None)
+    }
+}
+
+/// A unit of work being traced. Dropping the span finishes it.
+pub struct Span(Option<SpanData>);
+
+impl Span {
+    /// Add an attribute to the span.
+    pub fn attr<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: ToString,
+    {
+        self.set_attr(key, value);
+        self
+    }
+
+    pub fn set_attr<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: ToString,
+    {
+        if let Some(ref mut data) = self.0 {
+            data.attributes.push((key.into(), value.to_string()));
+        }
+    }
+
+    /// Mark the work the span represents as failed.
+    pub fn set_error<T>(&mut self, message: T)
+    where
+        T: ToString,
+    {
+        if let Some(ref mut data) = self.0 {
+            data.error = Some(message.to_string());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(mut data) = self.0.take() {
+            data.end = SystemTime::now();
+            OPEN_SPANS.with(|open| {
+                open.borrow_mut()
+                    .retain(|ctx| ctx.span_id != data.context.span_id)
+            });
+            if let Some(ref tx) = *EXPORTER.lock().expect("Trace exporter lock is poisoned") {
+                tx.send(data).ok();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SpanContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+}
+
+#[derive(Debug)]
+struct SpanData {
+    context: SpanContext,
+    parent_span_id: Option<[u8; 8]>,
+    name: String,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(String, String)>,
+    error: Option<String>,
+}
+
+impl SpanData {
+    fn start(name: String) -> Self {
+        let parent = OPEN_SPANS.with(|open| open.borrow().last().cloned());
+        let span_id = new_span_id();
+        let context = match parent {
+            Some(parent) => SpanContext {
+                trace_id: parent.trace_id,
+                span_id,
+            },
+            None => SpanContext {
+                trace_id: *Uuid::new_v4().as_bytes(),
+                span_id,
+            },
+        };
+        OPEN_SPANS.with(|open| open.borrow_mut().push(context));
+        let now = SystemTime::now();
+        SpanData {
+            context,
+            parent_span_id: parent.map(|p| p.span_id),
+            name,
+            start: now,
+            end: now,
+            attributes: vec![],
+            error: None,
+        }
+    }
+}
+
+fn new_span_id() -> [u8; 8] {
+    let mut id = [0; 8];
+    id.copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+    id
+}
+
+fn export_loop(rx: &mpsc::Receiver<SpanData>, exporter: &TraceExporter) {
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    let interval = Duration::from_secs(EXPORT_INTERVAL_SECS);
+    let mut last_export = Instant::now();
+    loop {
+        let wait = interval
+            .checked_sub(last_export.elapsed())
+            .unwrap_or_default();
+        let disconnected = match rx.recv_timeout(wait) {
+            Ok(span) => {
+                batch.push(span);
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+        if batch.len() >= MAX_BATCH_SIZE || last_export.elapsed() >= interval || disconnected {
+            if !batch.is_empty() {
+                if let Err(err) = exporter.export(&batch) {
+                    warn!("Unable to export {} trace span(s), {}", batch.len(), err);
+                }
+                batch.clear();
+            }
+            last_export = Instant::now();
+        }
+        if disconnected {
+            return;
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs() * 1_000_000_000 + u64::from(since_epoch.subsec_nanos())).to_string()
+}
+
+// The types below mirror the JSON encoding of OTLP's `ExportTraceServiceRequest`.
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportRequest {
+    resource_spans: Vec<ResourceSpans>,
+}
+
+impl ExportRequest {
+    fn new(spans: &[SpanData]) -> Self {
+        ExportRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Resource {
+                    attributes: vec![KeyValue::new("service.name", SERVICE_NAME)],
+                },
+                scope_spans: vec![ScopeSpans {
+                    scope: Scope {
+                        name: "habitat",
+                        version: env!("CARGO_PKG_VERSION"),
+                    },
+                    spans: spans.iter().map(OtlpSpan::from).collect(),
+                }],
+            }],
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceSpans {
+    resource: Resource,
+    scope_spans: Vec<ScopeSpans>,
+}
+
+#[derive(Serialize)]
+struct Resource {
+    attributes: Vec<KeyValue>,
+}
+
+#[derive(Serialize)]
+struct ScopeSpans {
+    scope: Scope,
+    spans: Vec<OtlpSpan>,
+}
+
+#[derive(Serialize)]
+struct Scope {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpan {
+    trace_id: String,
+    span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+    attributes: Vec<KeyValue>,
+    status: Status,
+}
+
+impl<'a> From<&'a SpanData> for OtlpSpan {
+    fn from(data: &'a SpanData) -> Self {
+        OtlpSpan {
+            trace_id: hex(&data.context.trace_id),
+            span_id: hex(&data.context.span_id),
+            parent_span_id: data.parent_span_id.as_ref().map(|id| hex(id)),
+            name: data.name.clone(),
+            kind: SPAN_KIND_INTERNAL,
+            start_time_unix_nano: unix_nanos(data.start),
+            end_time_unix_nano: unix_nanos(data.end),
+            attributes: data
+                .attributes
+                .iter()
+                .map(|&(ref k, ref v)| KeyValue::new(k.as_str(), v.as_str()))
+                .collect(),
+            status: match data.error {
+                Some(ref message) => Status {
+                    code: STATUS_CODE_ERROR,
+                    message: Some(message.clone()),
+                },
+                None => Status {
+                    code: STATUS_CODE_OK,
+                    message: None,
+                },
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct KeyValue {
+    key: String,
+    value: AnyValue,
+}
+
+impl KeyValue {
+    fn new(key: &str, value: &str) -> Self {
+        KeyValue {
+            key: key.to_string(),
+            value: AnyValue {
+                string_value: value.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnyValue {
+    string_value: String,
+}
+
+#[derive(Serialize)]
+struct Status {
+    code: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endpoint_without_path_uses_traces_path() {
+        assert_eq!(
+            TraceExporter::endpoint("http://localhost:4318").unwrap(),
+            TraceExporter::Endpoint(Url::parse("http://localhost:4318/v1/traces").unwrap())
+        );
+        assert_eq!(
+            TraceExporter::endpoint("http://collector:4318/custom").unwrap(),
+            TraceExporter::Endpoint(Url::parse("http://collector:4318/custom").unwrap())
+        );
+    }
+
+    #[test]
+    fn endpoint_must_be_http() {
+        assert!(TraceExporter::endpoint("https://localhost:4318").is_err());
+        assert!(TraceExporter::endpoint("localhost").is_err());
+    }
+
+    #[test]
+    fn nested_spans_share_a_trace() {
+        let outer = SpanData::start("outer".to_string());
+        let inner = SpanData::start("inner".to_string());
+        assert_eq!(inner.context.trace_id, outer.context.trace_id);
+        assert_eq!(inner.parent_span_id, Some(outer.context.span_id));
+        assert_ne!(inner.context.span_id, outer.context.span_id);
+
+        drop(Span(Some(inner)));
+        drop(Span(Some(outer)));
+        let root = SpanData::start("root".to_string());
+        assert_eq!(root.parent_span_id, None);
+        drop(Span(Some(root)));
+    }
+
+    #[test]
+    fn disabled_spans_are_not_recorded() {
+        let mut span = span("noop").attr("service_group", "redis.default");
+        span.set_error("boom");
+        assert!(span.0.is_none());
+    }
+
+    #[test]
+    fn export_request_is_otlp_json() {
+        let mut data = SpanData::start("hook".to_string());
+        data.attributes
+            .push(("service_group".to_string(), "redis.default".to_string()));
+        data.error = Some("exit 1".to_string());
+        let json = serde_json::to_value(ExportRequest::new(&[data])).unwrap();
+        let span = &json["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["name"], "hook");
+        assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(span["spanId"].as_str().unwrap().len(), 16);
+        assert!(span.get("parentSpanId").is_none());
+        assert_eq!(span["attributes"][0]["key"], "service_group");
+        assert_eq!(
+            span["attributes"][0]["value"]["stringValue"],
+            "redis.default"
+        );
+        assert_eq!(span["status"]["code"], STATUS_CODE_ERROR);
+        assert_eq!(span["status"]["message"], "exit 1");
+        OPEN_SPANS.with(|open| open.borrow_mut().clear());
+    }
+}
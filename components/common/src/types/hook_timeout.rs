@@ -0,0 +1,93 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// The names of the hooks the Supervisor runs to completion, which may be given a timeout.
+pub const TIMED_HOOK_NAMES: &[&str] = &[
+    "file-updated",
+    "health-check",
+    "init",
+    "install",
+    "post-run",
+    "post-stop",
+    "post-update",
+    "pre-stop",
+    "pre-update",
+    "reconfigure",
+    "reload",
+    "suitability",
+];
+
+/// The seconds a hook may run for before it is terminated, given as `HOOK=SECONDS`, e.g.
+/// `init=300`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HookTimeout {
+    pub hook: String,
+    pub seconds: u32,
+}
+
+impl FromStr for HookTimeout {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self> {
+        let mut parts = val.splitn(2, '=');
+        let hook = parts.next().unwrap_or_default().trim().to_lowercase();
+        let seconds = parts.next().map(str::trim).unwrap_or_default();
+        if !TIMED_HOOK_NAMES.contains(&hook.as_str()) {
+            return Err(Error::HookTimeout(format!(
+                "'{}' does not name a hook, expected HOOK=SECONDS where HOOK is one of: {}",
+                val,
+                TIMED_HOOK_NAMES.join(", ")
+            )));
+        }
+        let seconds = match seconds.parse() {
+            Ok(seconds) if seconds > 0 => seconds,
+            _ => {
+                return Err(Error::HookTimeout(format!(
+                    "'{}' is not a valid timeout for the {} hook, expected a positive number of \
+                     seconds",
+                    seconds, hook
+                )));
+            }
+        };
+        Ok(HookTimeout { hook, seconds })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_timeout_from_str() {
+        assert_eq!(
+            HookTimeout::from_str("init=300").unwrap(),
+            HookTimeout {
+                hook: String::from("init"),
+                seconds: 300,
+            }
+        );
+        assert_eq!(
+            HookTimeout::from_str("Health-Check = 5").unwrap().hook,
+            "health-check"
+        );
+        assert!(HookTimeout::from_str("init").is_err());
+        assert!(HookTimeout::from_str("init=0").is_err());
+        assert!(HookTimeout::from_str("init=forever").is_err());
+        assert!(HookTimeout::from_str("run=10").is_err());
+    }
+}
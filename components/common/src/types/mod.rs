@@ -13,6 +13,15 @@
 // limitations under the License.
 
 mod env_config;
+mod hook_timeout;
 mod listen_ctl_addr;
+mod resource_limits;
+mod sandbox;
 
-pub use self::{env_config::EnvConfig, listen_ctl_addr::ListenCtlAddr};
+pub use self::{
+    env_config::EnvConfig,
+    hook_timeout::{HookTimeout, TIMED_HOOK_NAMES},
+    listen_ctl_addr::ListenCtlAddr,
+    resource_limits::{MemorySize, Rlimit},
+    sandbox::Capability,
+};
@@ -0,0 +1,151 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fmt, result, str::FromStr};
+
+use crate::{
+    error::{Error, Result},
+    launcher_protocol::{RlimitValue, RLIMIT_NAMES},
+};
+
+/// An amount of memory in bytes, parsed from a number with an optional binary `K`, `M`, `G` or
+/// `T` suffix, e.g. `512M`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemorySize(u64);
+
+impl MemorySize {
+    pub fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for MemorySize {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self> {
+        let val = val.trim();
+        let (digits, shift) = match val.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&val[..val.len() - 1], 10),
+            Some('M') => (&val[..val.len() - 1], 20),
+            Some('G') => (&val[..val.len() - 1], 30),
+            Some('T') => (&val[..val.len() - 1], 40),
+            _ => (val, 0),
+        };
+        digits
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(1 << shift))
+            .map(MemorySize)
+            .ok_or_else(|| {
+                Error::ResourceLimit(format!(
+                    "'{}' is not a size in bytes, optionally suffixed with K, M, G or T",
+                    val
+                ))
+            })
+    }
+}
+
+impl fmt::Display for MemorySize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> result::Result<(), fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A process rlimit given as `NAME=VALUE`, e.g. `nofile=65536`. A value of `unlimited` removes
+/// the limit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rlimit {
+    pub name: String,
+    pub value: RlimitValue,
+}
+
+impl FromStr for Rlimit {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self> {
+        let mut parts = val.splitn(2, '=');
+        let name = parts.next().unwrap_or_default().trim().to_lowercase();
+        let value = parts.next().map(str::trim).unwrap_or_default();
+        if !RLIMIT_NAMES.contains(&name.as_str()) {
+            return Err(Error::ResourceLimit(format!(
+                "'{}' does not name an rlimit, expected NAME=VALUE where NAME is one of: {}",
+                val,
+                RLIMIT_NAMES.join(", ")
+            )));
+        }
+        let value = if value == "unlimited" {
+            RlimitValue::Unlimited
+        } else {
+            // Limits are stored in TOML, whose integers are signed.
+            let limit = value
+                .parse::<i64>()
+                .ok()
+                .filter(|v| *v >= 0)
+                .ok_or_else(|| {
+                    Error::ResourceLimit(format!(
+                    "'{}' is not a valid value for the {} rlimit, expected a number up to {} or \
+                     'unlimited'",
+                    value,
+                    name,
+                    i64::max_value()
+                ))
+                })?;
+            RlimitValue::Limited(limit as u64)
+        };
+        Ok(Rlimit { name, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_size_from_str() {
+        assert_eq!(MemorySize::from_str("1024").unwrap().bytes(), 1024);
+        assert_eq!(MemorySize::from_str("4k").unwrap().bytes(), 4096);
+        assert_eq!(
+            MemorySize::from_str("512M").unwrap().bytes(),
+            512 * 1024 * 1024
+        );
+        assert_eq!(
+            MemorySize::from_str("2G").unwrap().bytes(),
+            2 * 1024 * 1024 * 1024
+        );
+        assert!(MemorySize::from_str("").is_err());
+        assert!(MemorySize::from_str("M").is_err());
+        assert!(MemorySize::from_str("12Q").is_err());
+        assert!(MemorySize::from_str("99999999999T").is_err());
+    }
+
+    #[test]
+    fn rlimit_from_str() {
+        assert_eq!(
+            Rlimit::from_str("nofile=65536").unwrap(),
+            Rlimit {
+                name: String::from("nofile"),
+                value: RlimitValue::Limited(65536),
+            }
+        );
+        assert_eq!(
+            Rlimit::from_str("core=unlimited").unwrap().value,
+            RlimitValue::Unlimited
+        );
+        assert!(Rlimit::from_str("nofile").is_err());
+        assert!(Rlimit::from_str("nofile=-1").is_err());
+        assert!(Rlimit::from_str("nofile=18446744073709551615").is_err());
+        assert!(Rlimit::from_str("nofile=lots").is_err());
+        assert!(Rlimit::from_str("files=10").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fmt, result, str::FromStr};
+
+use crate::error::{Error, Result};
+
+/// The Linux capabilities, without their `CAP_` prefix.
+const CAPABILITY_NAMES: &[&str] = &[
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "DAC_READ_SEARCH",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "SETGID",
+    "SETUID",
+    "SETPCAP",
+    "LINUX_IMMUTABLE",
+    "NET_BIND_SERVICE",
+    "NET_BROADCAST",
+    "NET_ADMIN",
+    "NET_RAW",
+    "IPC_LOCK",
+    "IPC_OWNER",
+    "SYS_MODULE",
+    "SYS_RAWIO",
+    "SYS_CHROOT",
+    "SYS_PTRACE",
+    "SYS_PACCT",
+    "SYS_ADMIN",
+    "SYS_BOOT",
+    "SYS_NICE",
+    "SYS_RESOURCE",
+    "SYS_TIME",
+    "SYS_TTY_CONFIG",
+    "MKNOD",
+    "LEASE",
+    "AUDIT_WRITE",
+    "AUDIT_CONTROL",
+    "SETFCAP",
+    "MAC_OVERRIDE",
+    "MAC_ADMIN",
+    "SYSLOG",
+    "WAKE_ALARM",
+    "BLOCK_SUSPEND",
+    "AUDIT_READ",
+    "PERFMON",
+    "BPF",
+    "CHECKPOINT_RESTORE",
+];
+
+/// A Linux capability, parsed case-insensitively with or without its `CAP_` prefix and always
+/// displayed in its canonical form, e.g. `CAP_NET_BIND_SERVICE`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Capability(String);
+
+impl FromStr for Capability {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self> {
+        let name = val.trim().to_uppercase();
+        let name = name.trim_start_matches("CAP_");
+        if CAPABILITY_NAMES.contains(&name) {
+            Ok(Capability(format!("CAP_{}", name)))
+        } else {
+            Err(Error::UnknownCapability(val.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> result::Result<(), fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capability_from_str() {
+        assert_eq!(
+            Capability::from_str("net_bind_service")
+                .unwrap()
+                .to_string(),
+            "CAP_NET_BIND_SERVICE"
+        );
+        assert_eq!(
+            Capability::from_str("CAP_SYS_NICE").unwrap().to_string(),
+            "CAP_SYS_NICE"
+        );
+        assert!(Capability::from_str("CAP_FLY").is_err());
+        assert!(Capability::from_str("").is_err());
+    }
+}
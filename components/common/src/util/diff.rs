@@ -0,0 +1,188 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line-based unified diffs, in the format produced by `diff -u`.
+
+use std::cmp;
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl<'a> Line<'a> {
+    fn in_old(self) -> bool {
+        match self {
+            Line::Same(_) | Line::Removed(_) => true,
+            Line::Added(_) => false,
+        }
+    }
+
+    fn in_new(self) -> bool {
+        match self {
+            Line::Same(_) | Line::Added(_) => true,
+            Line::Removed(_) => false,
+        }
+    }
+}
+
+/// Returns a unified diff from `old` to `new`, labelling the two sides with the given names, or
+/// `None` if no line differs.
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str) -> Option<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|&(_, line)| match line {
+            Line::Same(_) => false,
+            _ => true,
+        })
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return None;
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut changes = changes.into_iter().peekable();
+    while let Some(first) = changes.next() {
+        // Changes separated by no more than twice the context are shown in a single hunk.
+        let mut last = first;
+        while let Some(&next) = changes.peek() {
+            if next - last > 2 * CONTEXT_LINES + 1 {
+                break;
+            }
+            last = next;
+            changes.next();
+        }
+        let start = first.saturating_sub(CONTEXT_LINES);
+        let end = cmp::min(lines.len(), last + CONTEXT_LINES + 1);
+        let hunk = &lines[start..end];
+        let old_start = lines[..start].iter().filter(|l| l.in_old()).count() + 1;
+        let new_start = lines[..start].iter().filter(|l| l.in_new()).count() + 1;
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, hunk.iter().filter(|l| l.in_old()).count()),
+            range(new_start, hunk.iter().filter(|l| l.in_new()).count())
+        ));
+        for line in hunk {
+            match *line {
+                Line::Same(l) => out.push_str(&format!(" {}\n", l)),
+                Line::Removed(l) => out.push_str(&format!("-{}\n", l)),
+                Line::Added(l) => out.push_str(&format!("+{}\n", l)),
+            }
+        }
+    }
+    Some(out)
+}
+
+/// Formats one side of a hunk header. An empty side is numbered after the line it follows.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start - 1),
+        1 => start.to_string(),
+        _ => format!("{},{}", start, len),
+    }
+}
+
+/// Computes a shortest edit from `old` to `new` using the longest common subsequence of lines.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|l| Line::Removed(l)));
+    lines.extend(new[j..].iter().map(|l| Line::Added(l)));
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identical_content_has_no_diff() {
+        assert_eq!(unified("a", "b", "one\ntwo\n", "one\ntwo\n"), None);
+    }
+
+    #[test]
+    fn changed_line() {
+        let diff = unified(
+            "a/app.conf",
+            "b/app.conf",
+            "port = 80\nhost = x\n",
+            "port = 81\nhost = x\n",
+        )
+        .unwrap();
+        assert_eq!(
+            diff,
+            "--- a/app.conf\n+++ b/app.conf\n@@ -1,2 +1,2 @@\n-port = 80\n+port = 81\n host = x\n"
+        );
+    }
+
+    #[test]
+    fn new_file() {
+        let diff = unified("/dev/null", "b/app.conf", "", "port = 80\n").unwrap();
+        assert_eq!(
+            diff,
+            "--- /dev/null\n+++ b/app.conf\n@@ -0,0 +1 @@\n+port = 80\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_are_separate_hunks() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new: String = (1..=20)
+            .map(|n| match n {
+                2 => "two\n".to_string(),
+                19 => "nineteen\n".to_string(),
+                _ => format!("{}\n", n),
+            })
+            .collect();
+        let diff = unified("a", "b", &old, &new).unwrap();
+        assert_eq!(diff.matches("@@ -").count(), 2, "{}", diff);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n"), "{}", diff);
+        assert!(diff.contains("@@ -16,5 +16,5 @@\n"), "{}", diff);
+    }
+}
@@ -20,8 +20,9 @@ use crate::{
         package::{ident, Identifiable, PackageIdent, PackageTarget},
         service::{HealthCheckInterval, ServiceGroup},
     },
-    protocol,
+    launcher_protocol, protocol,
 };
+use chrono::DateTime;
 use clap::{App, AppSettings, Arg};
 use url::Url;
 
@@ -32,7 +33,8 @@ use crate::{
             GOSSIP_DEFAULT_ADDR, GOSSIP_LISTEN_ADDRESS_ENVVAR, LISTEN_CTL_DEFAULT_ADDR_STRING,
             LISTEN_HTTP_ADDRESS_ENVVAR, LISTEN_HTTP_DEFAULT_ADDR, RING_ENVVAR, RING_KEY_ENVVAR,
         },
-        types::{EnvConfig, ListenCtlAddr},
+        templating::config_source::{self, ExternalPrecedence},
+        types::{Capability, EnvConfig, HookTimeout, ListenCtlAddr, MemorySize, Rlimit},
     },
     feat,
 };
@@ -77,6 +79,9 @@ pub fn get() -> App<'static, 'static> {
             (aliases: &["co", "con", "conf", "confi"])
             (@setting ArgRequiredElseHelp)
             (subcommand: sub_config_apply().aliases(&["ap", "app", "appl"]))
+            (subcommand: sub_config_diff().aliases(&["d", "di", "dif"]))
+            (subcommand: sub_config_history().aliases(&["hi", "his", "hist"]))
+            (subcommand: sub_config_rollback().aliases(&["ro", "rol", "roll"]))
             (@subcommand show =>
                 (about: "Displays the default configuration options for a service")
                 (aliases: &["sh", "sho"])
@@ -84,6 +89,10 @@ pub fn get() -> App<'static, 'static> {
                     "A package identifier (ex: core/redis, core/busybox-static/1.42.2)")
                 (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
                     "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                (@arg FORMAT: --format -f +takes_value possible_value[table json yaml]
+                    default_value("table") "Output format; table prints the TOML unchanged")
+                (@arg PROVENANCE: --provenance
+                    "Show the effective value of every key and the configuration layer which set it")
             )
         )
         (@subcommand file =>
@@ -238,6 +247,15 @@ pub fn get() -> App<'static, 'static> {
                 )
             )
         )
+        (@subcommand launcher =>
+            (about: "Commands relating to the Habitat Launcher")
+            (aliases: &["la", "lau", "laun", "launc", "launch", "launche"])
+            (@setting ArgRequiredElseHelp)
+            (@subcommand ps =>
+                (about: "List the processes the Launcher is running: the Supervisor, and the \
+                    services it has spawned")
+            )
+        )
         (@subcommand origin =>
             (about: "Commands relating to Habitat origin keys")
             (aliases: &["o", "or", "ori", "orig", "origi"])
@@ -440,6 +458,21 @@ pub fn get() -> App<'static, 'static> {
                     (ex: core/busybox-static/1.24.2/20160708162350)")
                 (@arg FULL_PATHS: -p "Show full path to file")
             )
+            (@subcommand render =>
+                (about: "Renders the configuration and hook templates of a package or plan \
+                    without a Supervisor")
+                (aliases: &["ren", "rend", "rende"])
+                (@arg SOURCE: +required +takes_value
+                    "A package identifier of an installed package \
+                    (ex: core/redis, core/busybox-static/1.42.2) or a plan directory")
+                (@arg FIXTURE: -f --fixture +takes_value {file_exists}
+                    "A TOML or JSON file describing the system, configuration, service group \
+                    members and binds to render the templates with")
+                (@arg OUTPUT: -o --output +takes_value default_value("./results/rendered")
+                    "The directory to write the rendered templates to")
+                (@arg STRICT: --strict
+                    "Fail on references to undefined cfg, bind, svc and sys paths")
+            )
             (@subcommand search =>
                 (about: "Search for a package in Builder")
                 (@arg SEARCH_TERM: +required +takes_value "Search term")
@@ -604,6 +637,7 @@ pub fn get() -> App<'static, 'static> {
             (about: "Commands relating to Habitat services")
             (aliases: &["sv", "ser", "serv", "service"])
             (@setting ArgRequiredElseHelp)
+            (subcommand: sub_svc_attach().aliases(&["a", "at", "att", "atta", "attac"]))
             (@subcommand key =>
                 (about: "Commands relating to Habitat service keys")
                 (aliases: &["k", "ke"])
@@ -617,6 +651,7 @@ pub fn get() -> App<'static, 'static> {
                 )
             )
             (subcommand: sub_svc_load().aliases(&["l", "lo", "loa"]))
+            (subcommand: sub_svc_logs().aliases(&["log"]))
             (subcommand: sub_svc_start().aliases(&["star"]))
             (subcommand: sub_svc_status().aliases(&["stat", "statu"]))
             (subcommand: sub_svc_stop().aliases(&["sto"]))
@@ -710,6 +745,7 @@ pub fn sup_commands() -> App<'static, 'static> {
     (usage: "hab sup <SUBCOMMAND>")
     (@setting VersionlessSubcommands)
     (@setting SubcommandRequiredElseHelp)
+    (subcommand: sub_sup_audit().aliases(&["a", "au", "aud", "audi"]))
     (subcommand: sub_sup_bash().aliases(&["b", "ba", "bas"]))
     (subcommand: sub_sup_depart().aliases(&["d", "de", "dep", "depa", "depart"]))
     (subcommand: sub_sup_run().aliases(&["r", "ru"]))
@@ -848,9 +884,65 @@ fn sub_config_apply() -> App<'static, 'static> {
     )
 }
 
+fn sub_config_diff() -> App<'static, 'static> {
+    clap_app!(@subcommand diff =>
+        (about: "Shows how a configuration would change the files rendered for a Service Group, \
+            without applying it")
+        (@arg SERVICE_GROUP: +required {valid_service_group}
+            "Target service group service.group[@organization] (ex: redis.default or foo.default@bazcorp)")
+        (@arg FILE: {file_exists_or_stdin}
+            "Path to local file on disk (ex: /tmp/config.toml, default: <stdin>)")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+            "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+    )
+}
+
+fn sub_config_history() -> App<'static, 'static> {
+    clap_app!(@subcommand history =>
+        (about: "Lists the configurations previously applied to a Service Group")
+        (@arg SERVICE_GROUP: +required {valid_service_group}
+            "Target service group service.group[@organization] (ex: redis.default or foo.default@bazcorp)")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+            "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+        (@arg FORMAT: --format -f +takes_value possible_value[table json yaml]
+            default_value("table") "Output format")
+    )
+}
+
+fn sub_config_rollback() -> App<'static, 'static> {
+    clap_app!(@subcommand rollback =>
+        (about: "Re-applies a configuration from a Service Group's history as a new version, one \
+            greater than the latest version in the Supervisor's history")
+        (@arg SERVICE_GROUP: +required {valid_service_group}
+            "Target service group service.group[@organization] (ex: redis.default or foo.default@bazcorp)")
+        (@arg INCARNATION: +required {valid_numeric::<u64>}
+            "Version of the configuration to roll back to, as listed by `hab config history`")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+            "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+    )
+}
+
 // the following sup related functions are
 // public due to their utilization in `hab-sup`
 // for consistency, all supervisor related clap subcommands are defined in this module
+pub fn sub_sup_audit() -> App<'static, 'static> {
+    clap_app!(@subcommand audit =>
+        (about: "Query the log of operations which changed a Supervisor's services")
+        (@arg TARGET: +takes_value
+            "Only show operations on this package identifier or service group")
+        (@arg MESSAGE_TYPE: --("type") -t +takes_value
+            "Only show operations of this type (ex: SvcSetCfg, ServiceConfig)")
+        (@arg SINCE: --since -s +takes_value {valid_rfc3339_timestamp}
+            "Only show operations recorded at or after this RFC 3339 timestamp")
+        (@arg LIMIT: -l --limit +takes_value {valid_numeric::<u32>}
+            "Only show this many of the most recent operations")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+            "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+        (@arg FORMAT: --format -f +takes_value possible_value[table json yaml]
+            default_value("table") "Output format; json and yaml output follow a stable schema")
+    )
+}
+
 pub fn sub_sup_depart() -> App<'static, 'static> {
     clap_app!(@subcommand depart =>
         (about: "Depart a Supervisor from the gossip ring; kicking and banning the target \
@@ -933,6 +1025,32 @@ pub fn sub_sup_run() -> App<'static, 'static> {
         "Used for enabling TLS for the HTTP gateway. Read server certificates from CERT_FILE. \
          This should contain PEM-format certificates in the right order (the first certificate \
          should certify KEY_FILE, the last should be a root CA).")
+    (@arg CTL_KEY_FILE: --("ctl-server-key") +takes_value {file_exists}
+        requires[CTL_CERT_FILE]
+        "Used for enabling TLS for the ctl gateway. Read private key from CTL_KEY_FILE. \
+         This should be a RSA private key, in PEM format.")
+    (@arg CTL_CERT_FILE: --("ctl-server-certificate") +takes_value {file_exists}
+        requires[CTL_KEY_FILE]
+        "Used for enabling TLS for the ctl gateway. Read server certificates from CTL_CERT_FILE. \
+         This should contain PEM-format certificates in the right order (the first certificate \
+         should certify CTL_KEY_FILE, the last should be a root CA).")
+    (@arg CTL_CLIENT_CA_FILE: --("ctl-client-ca-certificate") +takes_value {file_exists}
+        requires[CTL_KEY_FILE]
+        "Used for enabling mutual TLS for the ctl gateway. Clients must present a certificate \
+         signed by one of the PEM-format CA certificates in CTL_CLIENT_CA_FILE. Clients with a \
+         verified certificate do not need to send the ctl secret.")
+    (@arg TRACE_ENDPOINT: --("trace-endpoint") +takes_value conflicts_with[TRACE_FILE]
+        "Export traces of Supervisor activity to this OpenTelemetry collector OTLP/HTTP endpoint \
+         (ex: http://localhost:4318)")
+    (@arg TRACE_FILE: --("trace-file") +takes_value conflicts_with[TRACE_ENDPOINT]
+        "Export traces of Supervisor activity to this file as OTLP/JSON, one batch of spans per \
+         line")
+    (@arg LOG_SINK: --("log-sink") +takes_value possible_value[syslog journald]
+        "Also forward the output of the Supervisor and its services to syslog or the systemd \
+         journal")
+    (@arg SYSLOG_ADDRESS: --("syslog-address") +takes_value requires[LOG_SINK]
+        "Where syslog messages are sent: the path of a Unix socket, or HOST:PORT to send them \
+         over UDP [default: /dev/log]")
     // === Optional arguments to additionally load an initial service for the Supervisor
     (@arg PKG_IDENT_OR_ARTIFACT: +takes_value "Load the given Habitat package as part of \
         the Supervisor startup specified by a package identifier \
@@ -959,6 +1077,70 @@ pub fn sub_sup_run() -> App<'static, 'static> {
         Implies NO_COLOR")
     (@arg HEALTH_CHECK_INTERVAL: --("health-check-interval") -i +takes_value {valid_health_check_interval}
         "The interval (seconds) on which to run health checks [default: 30]")
+    (@arg CONFIG_SOURCE: --("config-source") +takes_value {valid_config_source}
+        "An external source of configuration for the service, polled for changes: an absolute \
+         path to a directory of .toml and .json files (ex: a mounted ConfigMap) or an http:// URL")
+    (@arg CONFIG_SOURCE_PRECEDENCE: --("config-source-precedence") +takes_value
+        requires[CONFIG_SOURCE] {valid_config_source_precedence}
+        "Where the configuration source sits among the other configuration layers \
+         [default: below-user] [values: below-user, above-user, above-gossip]")
+    (@arg STRICT_TEMPLATES: --("strict-templates")
+        "Fail to render configuration and hook templates which reference undefined values")
+    (@arg SHUTDOWN_SIGNAL: --("shutdown-signal") +takes_value
+        possible_value[HUP INT QUIT TERM USR1 USR2 WINCH]
+        "The signal which asks the service to stop, overriding the package's; ignored on \
+         Windows [default: TERM]")
+    (@arg SHUTDOWN_TIMEOUT: --("shutdown-timeout") +takes_value {valid_shutdown_timeout}
+        "Seconds the service is given to stop before it is killed, overriding the package's \
+         [default: 8]")
+    (@arg MEMORY_MAX: --("memory-max") +takes_value {valid_memory_size}
+        "Bytes of memory the service may use, with an optional K, M, G or T suffix; Linux only, \
+         requires cgroup v2")
+    (@arg CPU_WEIGHT: --("cpu-weight") +takes_value {valid_cpu_weight}
+        "Relative share of CPU time given to the service, from 1 to 10000; Linux only, requires \
+         cgroup v2 [default: 100]")
+    (@arg CPU_QUOTA: --("cpu-quota") +takes_value {valid_cpu_quota}
+        "Percentage of a single CPU the service may use, e.g. 150 for one and a half CPUs; Linux \
+         only, requires cgroup v2")
+    (@arg PIDS_MAX: --("pids-max") +takes_value {valid_numeric::<u32>}
+        "Number of processes and threads the service may run; Linux only, requires cgroup v2")
+    (@arg RLIMIT: --rlimit +takes_value +multiple number_of_values(1) {valid_rlimit}
+        "A process rlimit for the service as NAME=VALUE, e.g. nofile=65536 or core=unlimited; may \
+         be given more than once. Linux only")
+    (@arg DROP_CAPABILITIES: --("drop-capabilities")
+        "Drop every capability from the service's bounding set except those given with \
+         --keep-capability; Linux only")
+    (@arg KEEP_CAPABILITY: --("keep-capability") +takes_value +multiple number_of_values(1)
+        {valid_capability}
+        "A capability kept when dropping capabilities, and granted to a service which \
+         doesn't run as root, e.g. CAP_NET_BIND_SERVICE; may be given more than once. \
+         Implies --drop-capabilities")
+    (@arg NO_NEW_PRIVS: --("no-new-privs")
+        "Prevent the service from gaining privileges, e.g. through setuid binaries; Linux only")
+    (@arg PRIVATE_TMP: --("private-tmp") "Give the service its own empty /tmp; Linux only")
+    (@arg READ_ONLY_PACKAGE: --("read-only-package")
+        "Mount the service's package path read-only; Linux only")
+    (@arg SECCOMP_PROFILE: --("seccomp-profile") +takes_value {file_exists}
+        "Path to a compiled seccomp BPF filter installed before the service starts. Implies \
+         --no-new-privs. Linux only")
+    (@arg LOG_FORMAT: --("log-format") +takes_value possible_value[text json]
+        "Format of the service's log file; json writes one object per line with timestamp, \
+         stream and service group fields [default: text]")
+    (@arg LOG_MAX_SIZE: --("log-max-size") +takes_value {valid_memory_size}
+        "Size at which the service's log file is rotated, e.g. 10M [default: 10M]")
+    (@arg LOG_MAX_AGE: --("log-max-age") +takes_value {valid_numeric::<u64>}
+        "Age in seconds at which the service's log file is rotated")
+    (@arg LOG_RETAIN: --("log-retain") +takes_value {valid_numeric::<u32>}
+        "Number of rotated log files to keep [default: 5]")
+    (@arg CONSOLE: --console +takes_value possible_value[detached stdin pty]
+        "How the service's standard streams are connected: detached, stdin to keep its standard \
+         input open for attached clients, or pty to run it under a pseudo-terminal. Services \
+         which aren't detached can be attached to with `hab svc attach`; Unix only \
+         [default: detached]")
+    (@arg HOOK_TIMEOUT: --("hook-timeout") +takes_value +multiple number_of_values(1)
+        {valid_hook_timeout}
+        "Seconds a hook may run for before it is terminated along with every process it started, \
+         as HOOK=SECONDS, e.g. init=300, overriding the package's; may be given more than once")
     )
 }
 
@@ -992,6 +1174,30 @@ fn sub_svc_start() -> App<'static, 'static> {
     )
 }
 
+fn sub_svc_attach() -> App<'static, 'static> {
+    clap_app!(@subcommand attach =>
+        (about: "Attach the terminal to the console of a Habitat service loaded with --console \
+            stdin or --console pty. Detach with Ctrl-].")
+        (@arg PKG_IDENT: +required +takes_value {valid_ident}
+            "A Habitat package identifier (ex: core/redis)")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+            "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+    )
+}
+
+fn sub_svc_logs() -> App<'static, 'static> {
+    clap_app!(@subcommand logs =>
+        (about: "Show the output a Habitat service has written to its log file.")
+        (@arg PKG_IDENT: +required +takes_value {valid_ident}
+            "A Habitat package identifier (ex: core/redis)")
+        (@arg LINES: --lines -n +takes_value {valid_numeric::<u64>}
+            "Number of lines from the end of the log to show [default: all]")
+        (@arg FOLLOW: --follow -f "Keep showing output as the service writes it")
+        (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+            "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+    )
+}
+
 // `hab svc status` is the canonical location for this command, but we
 // have historically used `hab sup status` as an alias.
 pub fn sub_svc_status() -> App<'static, 'static> {
@@ -1000,6 +1206,8 @@ pub fn sub_svc_status() -> App<'static, 'static> {
         (@arg PKG_IDENT: +takes_value {valid_ident} "A Habitat package identifier (ex: core/redis)")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
         "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+        (@arg FORMAT: --format -f +takes_value possible_value[table json yaml]
+            default_value("table") "Output format; json and yaml output follow a stable schema")
     )
 }
 
@@ -1048,6 +1256,72 @@ fn sub_svc_load() -> App<'static, 'static> {
             "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
         (@arg HEALTH_CHECK_INTERVAL: --("health-check-interval") -i +takes_value {valid_health_check_interval}
             "The interval (seconds) on which to run health checks [default: 30]")
+        (@arg CONFIG_SOURCE: --("config-source") +takes_value {valid_config_source}
+            "An external source of configuration for the service, polled for changes: an \
+             absolute path to a directory of .toml and .json files (ex: a mounted ConfigMap) \
+             or an http:// URL")
+        (@arg CONFIG_SOURCE_PRECEDENCE: --("config-source-precedence") +takes_value
+            requires[CONFIG_SOURCE] {valid_config_source_precedence}
+            "Where the configuration source sits among the other configuration layers \
+             [default: below-user] [values: below-user, above-user, above-gossip]")
+        (@arg STRICT_TEMPLATES: --("strict-templates")
+            "Fail to render configuration and hook templates which reference undefined values")
+        (@arg SHUTDOWN_SIGNAL: --("shutdown-signal") +takes_value
+            possible_value[HUP INT QUIT TERM USR1 USR2 WINCH]
+            "The signal which asks the service to stop, overriding the package's; ignored on \
+             Windows [default: TERM]")
+        (@arg SHUTDOWN_TIMEOUT: --("shutdown-timeout") +takes_value {valid_shutdown_timeout}
+            "Seconds the service is given to stop before it is killed, overriding the \
+             package's [default: 8]")
+        (@arg MEMORY_MAX: --("memory-max") +takes_value {valid_memory_size}
+            "Bytes of memory the service may use, with an optional K, M, G or T suffix; Linux only, \
+             requires cgroup v2")
+        (@arg CPU_WEIGHT: --("cpu-weight") +takes_value {valid_cpu_weight}
+            "Relative share of CPU time given to the service, from 1 to 10000; Linux only, requires \
+             cgroup v2 [default: 100]")
+        (@arg CPU_QUOTA: --("cpu-quota") +takes_value {valid_cpu_quota}
+            "Percentage of a single CPU the service may use, e.g. 150 for one and a half CPUs; Linux \
+             only, requires cgroup v2")
+        (@arg PIDS_MAX: --("pids-max") +takes_value {valid_numeric::<u32>}
+            "Number of processes and threads the service may run; Linux only, requires cgroup v2")
+        (@arg RLIMIT: --rlimit +takes_value +multiple number_of_values(1) {valid_rlimit}
+            "A process rlimit for the service as NAME=VALUE, e.g. nofile=65536 or core=unlimited; may \
+             be given more than once. Linux only")
+        (@arg DROP_CAPABILITIES: --("drop-capabilities")
+            "Drop every capability from the service's bounding set except those given with \
+             --keep-capability; Linux only")
+        (@arg KEEP_CAPABILITY: --("keep-capability") +takes_value +multiple number_of_values(1)
+            {valid_capability}
+            "A capability kept when dropping capabilities, and granted to a service which \
+             doesn't run as root, e.g. CAP_NET_BIND_SERVICE; may be given more than once. \
+             Implies --drop-capabilities")
+        (@arg NO_NEW_PRIVS: --("no-new-privs")
+            "Prevent the service from gaining privileges, e.g. through setuid binaries; Linux only")
+        (@arg PRIVATE_TMP: --("private-tmp") "Give the service its own empty /tmp; Linux only")
+        (@arg READ_ONLY_PACKAGE: --("read-only-package")
+            "Mount the service's package path read-only; Linux only")
+        (@arg SECCOMP_PROFILE: --("seccomp-profile") +takes_value {file_exists}
+            "Path to a compiled seccomp BPF filter installed before the service starts. Implies \
+             --no-new-privs. Linux only")
+        (@arg LOG_FORMAT: --("log-format") +takes_value possible_value[text json]
+            "Format of the service's log file; json writes one object per line with timestamp, \
+             stream and service group fields [default: text]")
+        (@arg LOG_MAX_SIZE: --("log-max-size") +takes_value {valid_memory_size}
+            "Size at which the service's log file is rotated, e.g. 10M [default: 10M]")
+        (@arg LOG_MAX_AGE: --("log-max-age") +takes_value {valid_numeric::<u64>}
+            "Age in seconds at which the service's log file is rotated")
+        (@arg LOG_RETAIN: --("log-retain") +takes_value {valid_numeric::<u32>}
+            "Number of rotated log files to keep [default: 5]")
+        (@arg CONSOLE: --console +takes_value possible_value[detached stdin pty]
+            "How the service's standard streams are connected: detached, stdin to keep its \
+             standard input open for attached clients, or pty to run it under a pseudo-terminal. \
+             Services which aren't detached can be attached to with `hab svc attach`; Unix only \
+             [default: detached]")
+        (@arg HOOK_TIMEOUT: --("hook-timeout") +takes_value +multiple number_of_values(1)
+            {valid_hook_timeout}
+            "Seconds a hook may run for before it is terminated along with every process it \
+             started, as HOOK=SECONDS, e.g. init=300, overriding the package's; may be given more \
+             than once")
     )
 }
 
@@ -1087,6 +1361,72 @@ fn sub_svc_load() -> App<'static, 'static> {
             "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
         (@arg HEALTH_CHECK_INTERVAL: --("health-check-interval") -i +takes_value {valid_health_check_interval}
             "The interval (seconds) on which to run health checks [default: 30]")
+        (@arg CONFIG_SOURCE: --("config-source") +takes_value {valid_config_source}
+            "An external source of configuration for the service, polled for changes: an \
+             absolute path to a directory of .toml and .json files (ex: a mounted ConfigMap) \
+             or an http:// URL")
+        (@arg CONFIG_SOURCE_PRECEDENCE: --("config-source-precedence") +takes_value
+            requires[CONFIG_SOURCE] {valid_config_source_precedence}
+            "Where the configuration source sits among the other configuration layers \
+             [default: below-user] [values: below-user, above-user, above-gossip]")
+        (@arg STRICT_TEMPLATES: --("strict-templates")
+            "Fail to render configuration and hook templates which reference undefined values")
+        (@arg SHUTDOWN_SIGNAL: --("shutdown-signal") +takes_value
+            possible_value[HUP INT QUIT TERM USR1 USR2 WINCH]
+            "The signal which asks the service to stop, overriding the package's; ignored on \
+             Windows [default: TERM]")
+        (@arg SHUTDOWN_TIMEOUT: --("shutdown-timeout") +takes_value {valid_shutdown_timeout}
+            "Seconds the service is given to stop before it is killed, overriding the \
+             package's [default: 8]")
+        (@arg MEMORY_MAX: --("memory-max") +takes_value {valid_memory_size}
+            "Bytes of memory the service may use, with an optional K, M, G or T suffix; Linux only, \
+             requires cgroup v2")
+        (@arg CPU_WEIGHT: --("cpu-weight") +takes_value {valid_cpu_weight}
+            "Relative share of CPU time given to the service, from 1 to 10000; Linux only, requires \
+             cgroup v2 [default: 100]")
+        (@arg CPU_QUOTA: --("cpu-quota") +takes_value {valid_cpu_quota}
+            "Percentage of a single CPU the service may use, e.g. 150 for one and a half CPUs; Linux \
+             only, requires cgroup v2")
+        (@arg PIDS_MAX: --("pids-max") +takes_value {valid_numeric::<u32>}
+            "Number of processes and threads the service may run; Linux only, requires cgroup v2")
+        (@arg RLIMIT: --rlimit +takes_value +multiple number_of_values(1) {valid_rlimit}
+            "A process rlimit for the service as NAME=VALUE, e.g. nofile=65536 or core=unlimited; may \
+             be given more than once. Linux only")
+        (@arg DROP_CAPABILITIES: --("drop-capabilities")
+            "Drop every capability from the service's bounding set except those given with \
+             --keep-capability; Linux only")
+        (@arg KEEP_CAPABILITY: --("keep-capability") +takes_value +multiple number_of_values(1)
+            {valid_capability}
+            "A capability kept when dropping capabilities, and granted to a service which \
+             doesn't run as root, e.g. CAP_NET_BIND_SERVICE; may be given more than once. \
+             Implies --drop-capabilities")
+        (@arg NO_NEW_PRIVS: --("no-new-privs")
+            "Prevent the service from gaining privileges, e.g. through setuid binaries; Linux only")
+        (@arg PRIVATE_TMP: --("private-tmp") "Give the service its own empty /tmp; Linux only")
+        (@arg READ_ONLY_PACKAGE: --("read-only-package")
+            "Mount the service's package path read-only; Linux only")
+        (@arg SECCOMP_PROFILE: --("seccomp-profile") +takes_value {file_exists}
+            "Path to a compiled seccomp BPF filter installed before the service starts. Implies \
+             --no-new-privs. Linux only")
+        (@arg LOG_FORMAT: --("log-format") +takes_value possible_value[text json]
+            "Format of the service's log file; json writes one object per line with timestamp, \
+             stream and service group fields [default: text]")
+        (@arg LOG_MAX_SIZE: --("log-max-size") +takes_value {valid_memory_size}
+            "Size at which the service's log file is rotated, e.g. 10M [default: 10M]")
+        (@arg LOG_MAX_AGE: --("log-max-age") +takes_value {valid_numeric::<u64>}
+            "Age in seconds at which the service's log file is rotated")
+        (@arg LOG_RETAIN: --("log-retain") +takes_value {valid_numeric::<u32>}
+            "Number of rotated log files to keep [default: 5]")
+        (@arg CONSOLE: --console +takes_value possible_value[detached stdin pty]
+            "How the service's standard streams are connected: detached, stdin to keep its \
+             standard input open for attached clients, or pty to run it under a pseudo-terminal. \
+             Services which aren't detached can be attached to with `hab svc attach`; Unix only \
+             [default: detached]")
+        (@arg HOOK_TIMEOUT: --("hook-timeout") +takes_value +multiple number_of_values(1)
+            {valid_hook_timeout}
+            "Seconds a hook may run for before it is terminated along with every process it \
+             started, as HOOK=SECONDS, e.g. init=300, overriding the package's; may be given more \
+             than once")
     )
 }
 
@@ -1160,6 +1500,20 @@ fn valid_numeric<T: FromStr>(val: String) -> result::Result<(), String> {
     }
 }
 
+fn valid_config_source(val: String) -> result::Result<(), String> {
+    match config_source::parse(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn valid_config_source_precedence(val: String) -> result::Result<(), String> {
+    match ExternalPrecedence::from_str(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 fn valid_health_check_interval(val: String) -> result::Result<(), String> {
     match HealthCheckInterval::from_str(&val) {
         Ok(_) => Ok(()),
@@ -1170,6 +1524,79 @@ fn valid_health_check_interval(val: String) -> result::Result<(), String> {
     }
 }
 
+fn valid_shutdown_timeout(val: String) -> result::Result<(), String> {
+    match val.parse::<u32>() {
+        Ok(timeout) if timeout <= launcher_protocol::MAX_SHUTDOWN_TIMEOUT => Ok(()),
+        Ok(_) => Err(format!(
+            "'{}' is not a valid value for shutdown timeout: it may be at most {} seconds",
+            val,
+            launcher_protocol::MAX_SHUTDOWN_TIMEOUT
+        )),
+        Err(e) => Err(format!(
+            "'{}' is not a valid value for shutdown timeout: {}",
+            val, e
+        )),
+    }
+}
+
+fn valid_rfc3339_timestamp(val: String) -> result::Result<(), String> {
+    match DateTime::parse_from_rfc3339(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!(
+            "'{}' is not a valid RFC 3339 timestamp (ex: 2019-02-01T12:00:00Z): {}",
+            val, e
+        )),
+    }
+}
+
+fn valid_memory_size(val: String) -> result::Result<(), String> {
+    match MemorySize::from_str(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn valid_cpu_weight(val: String) -> result::Result<(), String> {
+    match val.parse::<u32>() {
+        Ok(1..=10000) => Ok(()),
+        _ => Err(format!(
+            "'{}' is not a valid value for CPU weight, expected a number from 1 to 10000",
+            val
+        )),
+    }
+}
+
+fn valid_cpu_quota(val: String) -> result::Result<(), String> {
+    match val.parse::<u32>() {
+        Ok(quota) if quota > 0 => Ok(()),
+        _ => Err(format!(
+            "'{}' is not a valid value for CPU quota, expected a percentage greater than 0",
+            val
+        )),
+    }
+}
+
+fn valid_capability(val: String) -> result::Result<(), String> {
+    match Capability::from_str(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn valid_rlimit(val: String) -> result::Result<(), String> {
+    match Rlimit::from_str(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn valid_hook_timeout(val: String) -> result::Result<(), String> {
+    match HookTimeout::from_str(&val) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 fn valid_update_strategy(val: String) -> result::Result<(), String> {
     match protocol::types::UpdateStrategy::from_str(&val) {
         Ok(_) => Ok(()),
@@ -1255,5 +1682,21 @@ mod tests {
             let run_matches = run_matches.expect("Error while getting run matches");
             assert_eq!(run_matches.value_of("PEER"), Some("1.1.1.1"));
         }
+
+        #[test]
+        fn sup_subcommand_audit_with_since() {
+            let r = get().get_matches_from_safe(vec![
+                "hab",
+                "sup",
+                "audit",
+                "--since",
+                "2019-02-01T07:00:00-05:00",
+            ]);
+            assert!(r.is_ok());
+
+            let r =
+                get().get_matches_from_safe(vec!["hab", "sup", "audit", "--since", "yesterday"]);
+            assert_eq!(r.unwrap_err().kind, ErrorKind::ValueValidation);
+        }
     }
 }
@@ -27,6 +27,7 @@ pub mod list;
 pub mod path;
 pub mod promote;
 pub mod provides;
+pub mod render;
 pub mod search;
 pub mod sign;
 pub mod uninstall;
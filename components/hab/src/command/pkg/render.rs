@@ -0,0 +1,111 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Renders the configuration and hook templates of a package or plan without a Supervisor.
+//!
+//! # Examples
+//!
+//! ```bash
+//! $ hab pkg render core/redis --fixture census.toml --output ./rendered
+//! $ hab pkg render ./redis --fixture census.json
+//! ```
+//!
+//! Templates are rendered with the same helpers as in a Supervisor, against a render context
+//! built from the fixture, which describes the system, configuration overrides, the members of
+//! the service group and the members of each bound service group. Without a fixture, templates
+//! are rendered as for a lone Supervisor with no binds.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    common::{
+        templating::{
+            config::{Cfg, CfgRenderer},
+            fixture::RenderFixture,
+            package::Pkg,
+        },
+        ui::{Status, UIWriter, UI},
+    },
+    hcore::package::{PackageIdent, PackageInstall},
+};
+
+use crate::error::Result;
+
+/// Directories of a package or plan holding templates, in rendering order.
+const TEMPLATE_DIRS: &[&str] = &["config", "config_install", "hooks"];
+
+pub fn start(
+    ui: &mut UI,
+    source: &str,
+    fixture: Option<&Path>,
+    output: &Path,
+    strict: bool,
+    version: &str,
+    fs_root_path: &Path,
+) -> Result<()> {
+    let fixture = match fixture {
+        Some(path) => RenderFixture::from_file(path)?,
+        None => RenderFixture::default(),
+    };
+    let pkg = match plan_context(source) {
+        Some(plan_context) => fixture.plan_pkg(&plan_context),
+        None => {
+            let ident = source.parse::<PackageIdent>()?;
+            let package = PackageInstall::load(&ident, Some(fs_root_path))?;
+            Pkg::from_install(&package)?
+        }
+    };
+    ui.begin(format!(
+        "Rendering templates of {} into {}",
+        pkg.ident,
+        output.display()
+    ))?;
+
+    let mut cfg = Cfg::new(&pkg, None)?;
+    fixture.apply_cfg(&mut cfg)?;
+    let ctx = fixture.render_context(&pkg, &cfg, version)?;
+
+    let mut rendered = 0;
+    for dir in TEMPLATE_DIRS {
+        let mut renderer = CfgRenderer::new(pkg.path.join(dir))?;
+        renderer.set_strict_mode(strict || pkg.strict_templates);
+        for (template, content) in renderer.render(&ctx)? {
+            let dest = output.join(dir).join(&template);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&dest, content)?;
+            ui.status(Status::Created, dest.display())?;
+            rendered += 1;
+        }
+    }
+    ui.end(format!("Rendered {} templates of {}", rendered, pkg.ident))?;
+    Ok(())
+}
+
+/// The directory containing `plan.sh` or `plan.ps1` if `source` is a plan directory, either
+/// directly or in its `habitat` subdirectory.
+fn plan_context(source: &str) -> Option<PathBuf> {
+    let path = Path::new(source);
+    if !path.is_dir() {
+        return None;
+    }
+    [path.join("habitat"), path.to_path_buf()]
+        .iter()
+        .find(|dir| dir.join("plan.sh").is_file() || dir.join("plan.ps1").is_file())
+        .cloned()
+}
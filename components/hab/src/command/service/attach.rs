@@ -0,0 +1,94 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Terminal handling for `hab svc attach`.
+//!
+//! While attached, the terminal is put in raw mode so that every key is sent to the service as
+//! it is pressed, including those which would otherwise send a signal to `hab`. Output processing
+//! is left on so that services without a pseudo-terminal, which write bare newlines, display
+//! correctly.
+
+use std::io;
+#[cfg(unix)]
+use std::mem;
+
+/// The key which detaches from the service, Ctrl-].
+pub const DETACH_KEY: u8 = 0x1d;
+
+/// Restores the terminal's original mode when dropped.
+pub struct RawTerminal {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl RawTerminal {
+    /// Put the terminal on standard input into raw mode. Nothing is changed if standard input is
+    /// not a terminal.
+    #[cfg(unix)]
+    pub fn enable() -> io::Result<Self> {
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) != 1 {
+                return Ok(RawTerminal { original: None });
+            }
+            let mut original: libc::termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            raw.c_oflag = original.c_oflag;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(RawTerminal {
+                original: Some(original),
+            })
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn enable() -> io::Result<Self> {
+        Ok(RawTerminal {})
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            if let Some(ref original) = self.original {
+                unsafe {
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+                }
+            }
+        }
+    }
+}
+
+/// The rows and columns of the terminal on standard output, if it is one.
+#[cfg(unix)]
+pub fn terminal_size() -> Option<(u16, u16)> {
+    let mut size: libc::winsize = unsafe { mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ as _, &mut size) } != 0
+        || size.ws_row == 0
+    {
+        return None;
+    }
+    Some((size.ws_row, size.ws_col))
+}
+
+#[cfg(windows)]
+pub fn terminal_size() -> Option<(u16, u16)> {
+    None
+}
@@ -54,6 +54,8 @@ pub enum Error {
     JobGroupPromoteOrDemote(api_client::Error, bool /* promote */),
     JobGroupCancel(api_client::Error),
     JobGroupPromoteOrDemoteUnprocessable(bool /* promote */),
+    LauncherNotRunning,
+    LauncherStatus(String),
     NameLookup,
     NetErr(net::NetErr),
     PackageArchiveMalformed(String),
@@ -147,6 +149,8 @@ impl fmt::Display for Error {
                 e
             ),
             Error::JobGroupCancel(ref e) => format!("Failed to cancel job group: {:?}", e),
+            Error::LauncherNotRunning => "The Habitat Launcher is not running".to_string(),
+            Error::LauncherStatus(ref e) => format!("Unable to read the Launcher's status, {}", e),
             Error::NameLookup => "Error resolving a name or IP address".to_string(),
             Error::NetErr(ref e) => e.to_string(),
             Error::PackageArchiveMalformed(ref e) => format!(
@@ -218,6 +222,8 @@ impl error::Error for Error {
             }
             Error::JobGroupPromoteOrDemote(ref err, _) => err.description(),
             Error::JobGroupCancel(ref err) => err.description(),
+            Error::LauncherNotRunning => "The Habitat Launcher is not running",
+            Error::LauncherStatus(_) => "Unable to read the Launcher's status",
             Error::NetErr(ref err) => err.description(),
             Error::NameLookup => "Error resolving a name or IP address",
             Error::PackageArchiveMalformed(_) => {
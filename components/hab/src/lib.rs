@@ -18,6 +18,7 @@ use habitat_api_client as api_client;
 use habitat_common as common;
 use habitat_core as hcore;
 use habitat_http_client as http_client;
+use habitat_launcher_protocol as launcher_protocol;
 use habitat_sup_client as sup_client;
 use habitat_sup_protocol as protocol;
 
@@ -49,11 +50,16 @@ pub mod command;
 pub mod config;
 pub mod error;
 mod exec;
+pub mod output;
 pub mod scaffolding;
 
 pub const PRODUCT: &str = "hab";
 pub const VERSION: &str = include_str!(concat!(env!("OUT_DIR"), "/VERSION"));
 pub const CTL_SECRET_ENVVAR: &str = "HAB_CTL_SECRET";
+pub const CTL_CA_CERTIFICATE_ENVVAR: &str = "HAB_CTL_CA_CERTIFICATE";
+pub const CTL_CLIENT_CERTIFICATE_ENVVAR: &str = "HAB_CTL_CLIENT_CERTIFICATE";
+pub const CTL_CLIENT_KEY_ENVVAR: &str = "HAB_CTL_CLIENT_KEY";
+pub const CTL_SERVER_NAME_ENVVAR: &str = "HAB_CTL_SERVER_NAME";
 pub const ORIGIN_ENVVAR: &str = "HAB_ORIGIN";
 pub const BLDR_URL_ENVVAR: &str = "HAB_BLDR_URL";
 
@@ -20,6 +20,7 @@ use env_logger;
 
 use habitat_common as common;
 use habitat_core as hcore;
+use habitat_launcher_protocol::{self as launcher_protocol, LauncherMessage, LauncherStatus};
 use habitat_sup_client as sup_client;
 use habitat_sup_protocol as protocol;
 
@@ -28,17 +29,19 @@ extern crate lazy_static;
 #[macro_use]
 extern crate log;
 use pbr;
+use toml;
 
 use std::{
     env,
     ffi::OsString,
-    fs::File,
+    fs::{self, File},
     io::{self, prelude::*, Read},
     net::ToSocketAddrs,
     path::{Path, PathBuf},
     process, result,
     str::FromStr,
     thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 #[cfg(windows)]
@@ -48,7 +51,7 @@ use crate::{
         command::package::install::{
             InstallHookMode, InstallMode, InstallSource, LocalPackageUsage,
         },
-        types::ListenCtlAddr,
+        types::{Capability, HookTimeout, ListenCtlAddr, MemorySize, Rlimit},
         ui::{Coloring, Status, UIWriter, NONINTERACTIVE_ENVVAR, UI},
     },
     hcore::{
@@ -60,8 +63,9 @@ use crate::{
         ChannelIdent,
     },
 };
+use chrono::{DateTime, TimeZone, Utc};
 use clap::{ArgMatches, Shell};
-use futures::prelude::*;
+use futures::{future, prelude::*};
 
 use crate::{
     hcore::{
@@ -69,17 +73,20 @@ use crate::{
         url::{bldr_url_from_env, default_bldr_url},
     },
     protocol::{codec::*, ctl::ServiceBindList, net::ErrCode, types::*},
-    sup_client::{SrvClient, SrvClientError},
+    sup_client::{SrvClient, SrvClientError, SrvClientTls},
 };
 use tabwriter::TabWriter;
 
 use hab::{
     analytics, cli,
-    command::{self, pkg::list::ListingType},
+    command::{self, pkg::list::ListingType, service::attach},
     config::{self, Config},
     error::{Error, Result},
-    feat, scaffolding, AUTH_TOKEN_ENVVAR, BLDR_URL_ENVVAR, CTL_SECRET_ENVVAR, ORIGIN_ENVVAR,
-    PRODUCT, VERSION,
+    feat,
+    output::{self, OutputFormat},
+    scaffolding, AUTH_TOKEN_ENVVAR, BLDR_URL_ENVVAR, CTL_CA_CERTIFICATE_ENVVAR,
+    CTL_CLIENT_CERTIFICATE_ENVVAR, CTL_CLIENT_KEY_ENVVAR, CTL_SECRET_ENVVAR,
+    CTL_SERVER_NAME_ENVVAR, ORIGIN_ENVVAR, PRODUCT, VERSION,
 };
 
 /// Makes the --org CLI param optional when this env var is set
@@ -101,6 +108,30 @@ lazy_static! {
         ]
     };
 
+    static ref PROVENANCE_HEADER: Vec<&'static str> = {
+        vec!["key", "value", "layer", "source", "incarnation"]
+    };
+
+    static ref HISTORY_HEADER: Vec<&'static str> = {
+        vec!["incarnation", "timestamp", "digest", "applied by", "encrypted"]
+    };
+
+    static ref LAUNCHER_PS_HEADER: Vec<&'static str> = {
+        vec!["pid", "service", "uid", "gid", "state", "started", "elapsed (s)", "last exit"]
+    };
+
+    static ref AUDIT_HEADER: Vec<&'static str> = {
+        vec![
+            "timestamp",
+            "source",
+            "type",
+            "target",
+            "origin",
+            "incarnation",
+            "digest",
+        ]
+    };
+
     /// The default filesystem root path to base all commands from. This is lazily generated on
     /// first call and reflects on the presence and value of the environment variable keyed as
     /// `FS_ROOT_ENVVAR`.
@@ -168,6 +199,9 @@ fn start(ui: &mut UI) -> Result<()> {
         },
         ("config", Some(m)) => match m.subcommand() {
             ("apply", Some(m)) => sub_svc_set(m)?,
+            ("diff", Some(m)) => sub_svc_cfg_diff(m)?,
+            ("history", Some(m)) => sub_svc_cfg_history(m)?,
+            ("rollback", Some(m)) => sub_svc_cfg_rollback(m)?,
             ("show", Some(m)) => sub_svc_config(m)?,
             _ => unreachable!(),
         },
@@ -176,6 +210,10 @@ fn start(ui: &mut UI) -> Result<()> {
             _ => unreachable!(),
         },
         ("install", Some(m)) => sub_pkg_install(ui, m)?,
+        ("launcher", Some(matches)) => match matches.subcommand() {
+            ("ps", Some(_)) => sub_launcher_ps()?,
+            _ => unreachable!(),
+        },
         ("origin", Some(matches)) => match matches.subcommand() {
             ("key", Some(m)) => match m.subcommand() {
                 ("download", Some(sc)) => sub_origin_key_download(ui, sc)?,
@@ -225,6 +263,7 @@ fn start(ui: &mut UI) -> Result<()> {
             ("list", Some(m)) => sub_pkg_list(m)?,
             ("path", Some(m)) => sub_pkg_path(m)?,
             ("provides", Some(m)) => sub_pkg_provides(m)?,
+            ("render", Some(m)) => sub_pkg_render(ui, m)?,
             ("search", Some(m)) => sub_pkg_search(m)?,
             ("sign", Some(m)) => sub_pkg_sign(ui, m)?,
             ("uninstall", Some(m)) => sub_pkg_uninstall(ui, m)?,
@@ -250,11 +289,13 @@ fn start(ui: &mut UI) -> Result<()> {
             _ => unreachable!(),
         },
         ("svc", Some(matches)) => match matches.subcommand() {
+            ("attach", Some(m)) => sub_svc_attach(m)?,
             ("key", Some(m)) => match m.subcommand() {
                 ("generate", Some(sc)) => sub_service_key_generate(ui, sc)?,
                 _ => unreachable!(),
             },
             ("load", Some(m)) => sub_svc_load(m)?,
+            ("logs", Some(m)) => sub_svc_logs(m)?,
             ("unload", Some(m)) => sub_svc_unload(m)?,
             ("start", Some(m)) => sub_svc_start(m)?,
             ("stop", Some(m)) => sub_svc_stop(m)?,
@@ -262,6 +303,7 @@ fn start(ui: &mut UI) -> Result<()> {
             _ => unreachable!(),
         },
         ("sup", Some(m)) => match m.subcommand() {
+            ("audit", Some(m)) => sub_sup_audit(m)?,
             ("depart", Some(m)) => sub_sup_depart(m)?,
             ("secret", Some(m)) => match m.subcommand() {
                 ("generate", _) => sub_sup_secret_generate()?,
@@ -732,6 +774,21 @@ fn sub_pkg_provides(m: &ArgMatches<'_>) -> Result<()> {
     command::pkg::provides::start(&filename, &*FS_ROOT, full_releases, full_paths)
 }
 
+fn sub_pkg_render(ui: &mut UI, m: &ArgMatches<'_>) -> Result<()> {
+    let source = m.value_of("SOURCE").unwrap(); // Required via clap
+    let fixture = m.value_of("FIXTURE").map(Path::new);
+    let output = Path::new(m.value_of("OUTPUT").unwrap()); // Defaulted via clap
+    command::pkg::render::start(
+        ui,
+        source,
+        fixture,
+        output,
+        m.is_present("STRICT"),
+        VERSION,
+        &*FS_ROOT,
+    )
+}
+
 fn sub_pkg_search(m: &ArgMatches<'_>) -> Result<()> {
     let url = bldr_url_from_matches(&m)?;
     let search_term = m.value_of("SEARCH_TERM").unwrap(); // Required via clap
@@ -883,7 +940,7 @@ fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
             .unwrap_or_else(|| "UNKNOWN".to_string()),
     ))?;
     ui.status(Status::Creating, "service configuration")?;
-    SrvClient::connect(&listen_ctl_addr, &secret_key)
+    ctl_connect(&listen_ctl_addr, &secret_key)
         .and_then(|conn| {
             conn.call(validate)
                 .for_each(|reply| match reply.message_id() {
@@ -892,8 +949,10 @@ fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
                         let m = reply
                             .parse::<protocol::net::NetErr>()
                             .map_err(SrvClientError::Decode)?;
+                        // Partial replies are warnings, a final error means the configuration
+                        // was rejected.
                         match ErrCode::from_i32(m.code) {
-                            Some(ErrCode::InvalidPayload) => {
+                            Some(ErrCode::InvalidPayload) if !reply.is_complete() => {
                                 ui.warn(m)?;
                                 Ok(())
                             }
@@ -910,7 +969,7 @@ fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     // JW: We should not need to make two connections here. I need a way to return the
     // SrvClient from a for_each iterator so we can chain upon a successful stream but I don't
     // know if it's possible with this version of futures.
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| {
             conn.call(set).for_each(|reply| match reply.message_id() {
                 "NetOk" => Ok(()),
@@ -930,21 +989,173 @@ fn sub_svc_set(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+fn sub_svc_cfg_diff(m: &ArgMatches<'_>) -> Result<()> {
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let service_group = ServiceGroup::from_str(m.value_of("SERVICE_GROUP").unwrap())?;
+    let mut ui = ui();
+    let mut buf = Vec::with_capacity(protocol::butterfly::MAX_SVC_CFG_SIZE);
+    let cfg_len = match m.value_of("FILE") {
+        Some("-") | None => io::stdin().read_to_end(&mut buf)?,
+        Some(f) => {
+            let mut file = File::open(f)?;
+            file.read_to_end(&mut buf)?
+        }
+    };
+    if cfg_len > protocol::butterfly::MAX_SVC_CFG_SIZE {
+        ui.fatal(format!(
+            "Configuration too large. Maximum size allowed is {} bytes.",
+            protocol::butterfly::MAX_SVC_CFG_SIZE
+        ))?;
+        process::exit(1);
+    }
+    let mut msg = protocol::ctl::SvcDiffCfg::default();
+    msg.service_group = Some(service_group.into());
+    msg.cfg = Some(buf);
+    ctl_connect(&listen_ctl_addr, secret_key)
+        .and_then(|conn| {
+            conn.call(msg).for_each(|reply| match reply.message_id() {
+                "ConfigFileDiff" => {
+                    let m = reply
+                        .parse::<protocol::ctl::ConfigFileDiff>()
+                        .map_err(SrvClientError::Decode)?;
+                    print!("{}", m.diff.unwrap_or_default());
+                    Ok(())
+                }
+                "NetOk" => {
+                    println!("No configuration files would change.");
+                    Ok(())
+                }
+                "NetErr" => {
+                    let m = reply
+                        .parse::<protocol::net::NetErr>()
+                        .map_err(SrvClientError::Decode)?;
+                    Err(SrvClientError::from(m))
+                }
+                _ => Err(SrvClientError::from(io::Error::from(
+                    io::ErrorKind::UnexpectedEof,
+                ))),
+            })
+        })
+        .wait()?;
+    Ok(())
+}
+
+fn sub_svc_cfg_history(m: &ArgMatches<'_>) -> Result<()> {
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let service_group = ServiceGroup::from_str(m.value_of("SERVICE_GROUP").unwrap())?;
+    let format = value_t!(m, "FORMAT", OutputFormat).unwrap_or_default();
+    let mut msg = protocol::ctl::SvcGetCfgHistory::default();
+    msg.service_group = Some(service_group.into());
+    let entries = ctl_connect(&listen_ctl_addr, secret_key)
+        .and_then(|conn| {
+            conn.call(msg).fold(vec![], |mut entries, reply| {
+                match reply.message_id() {
+                    "ConfigHistoryEntry" => {
+                        let entry = reply
+                            .parse::<protocol::ctl::ConfigHistoryEntry>()
+                            .map_err(SrvClientError::Decode)?;
+                        entries.push(entry);
+                    }
+                    "NetOk" => (),
+                    "NetErr" => {
+                        let err = reply
+                            .parse::<protocol::net::NetErr>()
+                            .map_err(SrvClientError::Decode)?;
+                        return Err(SrvClientError::from(err));
+                    }
+                    _ => warn!("Unexpected config history message, {:?}", reply),
+                }
+                Ok::<_, SrvClientError>(entries)
+            })
+        })
+        .wait()?;
+    if format != OutputFormat::Table {
+        let entries: Vec<output::ConfigHistoryEntry> = entries
+            .into_iter()
+            .map(output::ConfigHistoryEntry::from)
+            .collect();
+        output::render(io::stdout(), format, &entries)?;
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("No configuration history recorded.");
+        return Ok(());
+    }
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out, "{}", HISTORY_HEADER.join("\t"))?;
+    for entry in entries {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}",
+            entry.incarnation.unwrap_or_default(),
+            entry.timestamp.unwrap_or_default(),
+            entry.digest.unwrap_or_default(),
+            entry.applied_by.unwrap_or_else(|| "<unknown>".to_string()),
+            entry.encrypted.unwrap_or(false),
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn sub_svc_cfg_rollback(m: &ArgMatches<'_>) -> Result<()> {
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let service_group = ServiceGroup::from_str(m.value_of("SERVICE_GROUP").unwrap())?;
+    let incarnation = value_t!(m, "INCARNATION", u64).unwrap();
+    let mut ui = ui();
+    ui.begin(format!(
+        "Rolling back {} to configuration version {}",
+        service_group, incarnation
+    ))?;
+    let mut msg = protocol::ctl::SvcRollbackCfg::default();
+    msg.service_group = Some(service_group.into());
+    msg.incarnation = Some(incarnation);
+    ui.status(Status::Applying, format!("via peer {}", listen_ctl_addr))?;
+    ctl_connect(&listen_ctl_addr, secret_key)
+        .and_then(|conn| {
+            conn.call(msg).for_each(|reply| match reply.message_id() {
+                "NetOk" => Ok(()),
+                "NetErr" => {
+                    let m = reply
+                        .parse::<protocol::net::NetErr>()
+                        .map_err(SrvClientError::Decode)?;
+                    Err(SrvClientError::from(m))
+                }
+                _ => Err(SrvClientError::from(io::Error::from(
+                    io::ErrorKind::UnexpectedEof,
+                ))),
+            })
+        })
+        .wait()?;
+    ui.end("Applied configuration")?;
+    Ok(())
+}
+
 fn sub_svc_config(m: &ArgMatches<'_>) -> Result<()> {
     let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
     let secret_key = ctl_secret_key(&cfg)?;
+    let format = value_t!(m, "FORMAT", OutputFormat).unwrap_or_default();
+    if m.is_present("PROVENANCE") {
+        return sub_svc_config_provenance(&listen_ctl_addr, secret_key, ident, format);
+    }
     let mut msg = protocol::ctl::SvcGetDefaultCfg::default();
     msg.ident = Some(ident.into());
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| {
             conn.call(msg).for_each(|reply| match reply.message_id() {
                 "ServiceCfg" => {
                     let m = reply
                         .parse::<protocol::types::ServiceCfg>()
                         .map_err(SrvClientError::Decode)?;
-                    println!("{}", m.default.unwrap_or_default());
+                    print_svc_config(format, &m.default.unwrap_or_default())?;
                     Ok(())
                 }
                 "NetErr" => {
@@ -962,6 +1173,73 @@ fn sub_svc_config(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+fn sub_svc_config_provenance(
+    listen_ctl_addr: &ListenCtlAddr,
+    secret_key: String,
+    ident: PackageIdent,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut msg = protocol::ctl::SvcGetCfgProvenance::default();
+    msg.ident = Some(ident.into());
+    let keys = ctl_connect(listen_ctl_addr, secret_key)
+        .and_then(|conn| {
+            conn.call(msg).fold(vec![], |mut keys, reply| {
+                match reply.message_id() {
+                    "ConfigKeyProvenance" => {
+                        let key = reply
+                            .parse::<protocol::ctl::ConfigKeyProvenance>()
+                            .map_err(SrvClientError::Decode)?;
+                        keys.push(key);
+                    }
+                    "NetOk" => (),
+                    "NetErr" => {
+                        let err = reply
+                            .parse::<protocol::net::NetErr>()
+                            .map_err(SrvClientError::Decode)?;
+                        return Err(SrvClientError::from(err));
+                    }
+                    _ => warn!("Unexpected config provenance message, {:?}", reply),
+                }
+                Ok::<_, SrvClientError>(keys)
+            })
+        })
+        .wait()?;
+    if format != OutputFormat::Table {
+        let keys: Vec<output::ConfigKeyProvenance> = keys
+            .into_iter()
+            .map(output::ConfigKeyProvenance::from)
+            .collect();
+        output::render(io::stdout(), format, &keys)?;
+        return Ok(());
+    }
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out, "{}", PROVENANCE_HEADER.join("\t"))?;
+    for key in keys {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}",
+            key.key.unwrap_or_default(),
+            key.value.unwrap_or_default(),
+            key.layer.unwrap_or_default(),
+            key.source.unwrap_or_else(|| "<none>".to_string()),
+            key.incarnation
+                .map_or_else(|| "<none>".to_string(), |i| i.to_string()),
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn print_svc_config(format: OutputFormat, config: &str) -> io::Result<()> {
+    if format == OutputFormat::Table {
+        println!("{}", config);
+        return Ok(());
+    }
+    let value = toml::from_str::<toml::Value>(config)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    output::render(io::stdout(), format, &value)
+}
+
 fn sub_svc_load(m: &ArgMatches<'_>) -> Result<()> {
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
@@ -970,7 +1248,7 @@ fn sub_svc_load(m: &ArgMatches<'_>) -> Result<()> {
     update_svc_load_from_input(m, &mut msg)?;
     let ident: PackageIdent = m.value_of("PKG_IDENT").unwrap().parse()?;
     msg.ident = Some(ident.into());
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
         .wait()?;
     Ok(())
@@ -983,7 +1261,7 @@ fn sub_svc_unload(m: &ArgMatches<'_>) -> Result<()> {
     let secret_key = ctl_secret_key(&cfg)?;
     let mut msg = protocol::ctl::SvcUnload::default();
     msg.ident = Some(ident.into());
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
         .wait()?;
     Ok(())
@@ -996,7 +1274,7 @@ fn sub_svc_start(m: &ArgMatches<'_>) -> Result<()> {
     let secret_key = ctl_secret_key(&cfg)?;
     let mut msg = protocol::ctl::SvcStart::default();
     msg.ident = Some(ident.into());
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
         .wait()?;
     Ok(())
@@ -1010,8 +1288,36 @@ fn sub_svc_status(m: &ArgMatches<'_>) -> Result<()> {
     if let Some(pkg) = m.value_of("PKG_IDENT") {
         msg.ident = Some(PackageIdent::from_str(pkg)?.into());
     }
+    let format = value_t!(m, "FORMAT", OutputFormat).unwrap_or_default();
+    if format != OutputFormat::Table {
+        let statuses = ctl_connect(&listen_ctl_addr, secret_key)
+            .and_then(|conn| {
+                conn.call(msg).fold(vec![], |mut statuses, reply| {
+                    match reply.message_id() {
+                        "ServiceStatus" => {
+                            let status = reply
+                                .parse::<protocol::types::ServiceStatus>()
+                                .map_err(SrvClientError::Decode)?;
+                            statuses.push(output::ServiceStatus::from(status));
+                        }
+                        "NetOk" => (),
+                        "NetErr" => {
+                            let err = reply
+                                .parse::<protocol::net::NetErr>()
+                                .map_err(SrvClientError::Decode)?;
+                            return Err(SrvClientError::from(err));
+                        }
+                        _ => warn!("Unexpected status message, {:?}", reply),
+                    }
+                    Ok::<_, SrvClientError>(statuses)
+                })
+            })
+            .wait()?;
+        output::render(io::stdout(), format, &statuses)?;
+        return Ok(());
+    }
 
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| {
             let mut out = TabWriter::new(io::stdout());
             conn.call(msg)
@@ -1043,6 +1349,148 @@ fn sub_svc_status(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+fn sub_svc_attach(m: &ArgMatches<'_>) -> Result<()> {
+    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let size = attach::terminal_size();
+    let mut msg = protocol::ctl::SvcAttach::default();
+    msg.ident = Some(ident.clone().into());
+    msg.rows = size.map(|(rows, _)| rows.into());
+    msg.cols = size.map(|(_, cols)| cols.into());
+    let (sink, replies) = ctl_connect(&listen_ctl_addr, secret_key)
+        .and_then(|conn| conn.call_split(msg))
+        .wait()?;
+    eprintln!("Attached to {}, detach with Ctrl-]", ident);
+    let raw = attach::RawTerminal::enable()?;
+    // Input is sent as `Some` messages until the client detaches, which is sent as `None` so
+    // that detaching doesn't wait for every sender to be dropped.
+    let (tx, rx) = futures::sync::mpsc::unbounded();
+    if let Some(mut last) = size {
+        let tx = tx.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(250));
+            match attach::terminal_size() {
+                Some(size) if size != last => {
+                    let mut input = protocol::ctl::SvcAttachInput::default();
+                    input.rows = Some(size.0.into());
+                    input.cols = Some(size.1.into());
+                    if tx.unbounded_send(Some(SrvMessage::from(input))).is_err() {
+                        break;
+                    }
+                    last = size;
+                }
+                _ => (),
+            }
+        });
+    }
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        let mut buf = [0; 1024];
+        loop {
+            let read = match stdin.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            let detach = buf[..read].iter().position(|&b| b == attach::DETACH_KEY);
+            let data = &buf[..detach.unwrap_or(read)];
+            if !data.is_empty() {
+                let mut input = protocol::ctl::SvcAttachInput::default();
+                input.data = Some(data.to_vec());
+                if tx.unbounded_send(Some(SrvMessage::from(input))).is_err() {
+                    return;
+                }
+            }
+            if detach.is_some() {
+                break;
+            }
+        }
+        tx.unbounded_send(None).ok();
+    });
+    let input = rx
+        .take_while(|input| future::ok(input.is_some()))
+        .filter_map(|input| input)
+        .map_err(|()| SrvClientError::ConnectionClosed)
+        .forward(sink);
+    let output = replies.map_err(SrvClientError::from).for_each(|reply| {
+        match reply.message_id() {
+            "SvcAttachOutput" => {
+                let m = reply
+                    .parse::<protocol::ctl::SvcAttachOutput>()
+                    .map_err(SrvClientError::Decode)?;
+                let stdout = io::stdout();
+                let mut out = stdout.lock();
+                out.write_all(&m.data.unwrap_or_default())?;
+                out.flush()?;
+            }
+            "NetOk" => (),
+            "NetErr" => {
+                let err = reply
+                    .parse::<protocol::net::NetErr>()
+                    .map_err(SrvClientError::Decode)?;
+                return Err(SrvClientError::from(err));
+            }
+            _ => warn!("Unexpected attach message, {:?}", reply),
+        }
+        Ok(())
+    });
+    let result = output
+        .select2(input)
+        .map(|_| ())
+        .map_err(|err| match err {
+            future::Either::A((err, _)) | future::Either::B((err, _)) => err,
+        })
+        .wait();
+    drop(raw);
+    eprintln!("\r\nDetached from {}", ident);
+    result?;
+    Ok(())
+}
+
+fn sub_svc_logs(m: &ArgMatches<'_>) -> Result<()> {
+    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::SvcLogs::default();
+    msg.ident = Some(ident.into());
+    msg.lines = m
+        .value_of("LINES")
+        .map(|l| l.parse().expect("LINES is validated by clap"));
+    msg.follow = Some(m.is_present("FOLLOW"));
+    ctl_connect(&listen_ctl_addr, secret_key)
+        .and_then(|conn| {
+            conn.call(msg).for_each(|reply| {
+                match reply.message_id() {
+                    "SvcLogLines" => {
+                        let m = reply
+                            .parse::<protocol::ctl::SvcLogLines>()
+                            .map_err(SrvClientError::Decode)?;
+                        let stdout = io::stdout();
+                        let mut out = stdout.lock();
+                        for line in m.lines {
+                            writeln!(out, "{}", line)?;
+                        }
+                        out.flush()?;
+                    }
+                    "NetOk" => (),
+                    "NetErr" => {
+                        let err = reply
+                            .parse::<protocol::net::NetErr>()
+                            .map_err(SrvClientError::Decode)?;
+                        return Err(SrvClientError::from(err));
+                    }
+                    _ => warn!("Unexpected logs message, {:?}", reply),
+                }
+                Ok(())
+            })
+        })
+        .wait()?;
+    Ok(())
+}
+
 fn sub_svc_stop(m: &ArgMatches<'_>) -> Result<()> {
     let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
     let cfg = config::load()?;
@@ -1050,7 +1498,7 @@ fn sub_svc_stop(m: &ArgMatches<'_>) -> Result<()> {
     let secret_key = ctl_secret_key(&cfg)?;
     let mut msg = protocol::ctl::SvcStop::default();
     msg.ident = Some(ident.into());
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
         .wait()?;
     Ok(())
@@ -1107,7 +1555,7 @@ fn sub_file_put(m: &ArgMatches<'_>) -> Result<()> {
         }
         _ => msg.content = Some(buf.to_vec()),
     }
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| {
             ui.status(Status::Applying, format!("via peer {}", listen_ctl_addr))
                 .unwrap();
@@ -1135,6 +1583,69 @@ fn sub_file_put(m: &ArgMatches<'_>) -> Result<()> {
     Ok(())
 }
 
+fn sub_sup_audit(m: &ArgMatches<'_>) -> Result<()> {
+    let cfg = config::load()?;
+    let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::SupAudit::default();
+    msg.target = m.value_of("TARGET").map(str::to_string);
+    msg.message_type = m.value_of("MESSAGE_TYPE").map(str::to_string);
+    // Already validated by clap; sent in UTC so the Supervisor sees the same instant regardless of
+    // the offset it was given in.
+    msg.since = m.value_of("SINCE").map(|since| {
+        DateTime::parse_from_rfc3339(since)
+            .unwrap()
+            .with_timezone(&Utc)
+            .to_rfc3339()
+    });
+    if m.is_present("LIMIT") {
+        msg.limit = Some(value_t!(m, "LIMIT", u32).unwrap());
+    }
+    let format = value_t!(m, "FORMAT", OutputFormat).unwrap_or_default();
+    if format != OutputFormat::Table {
+        let entries = ctl_connect(&listen_ctl_addr, secret_key)
+            .and_then(|conn| {
+                conn.call(msg).fold(vec![], |mut entries, reply| {
+                    match reply.message_id() {
+                        "AuditEntry" => {
+                            let entry = reply
+                                .parse::<protocol::ctl::AuditEntry>()
+                                .map_err(SrvClientError::Decode)?;
+                            entries.push(output::AuditEntry::from(entry));
+                        }
+                        "NetOk" => (),
+                        "NetErr" => {
+                            let err = reply
+                                .parse::<protocol::net::NetErr>()
+                                .map_err(SrvClientError::Decode)?;
+                            return Err(SrvClientError::from(err));
+                        }
+                        _ => warn!("Unexpected audit message, {:?}", reply),
+                    }
+                    Ok::<_, SrvClientError>(entries)
+                })
+            })
+            .wait()?;
+        output::render(io::stdout(), format, &entries)?;
+        return Ok(());
+    }
+    ctl_connect(&listen_ctl_addr, secret_key)
+        .and_then(|conn| {
+            let out = TabWriter::new(io::stdout());
+            conn.call(msg)
+                .fold((out, true), |(mut out, print_header), reply| {
+                    print_audit_entry(&mut out, reply, print_header)?;
+                    Ok::<_, SrvClientError>((out, false))
+                })
+                .and_then(|(mut out, _)| {
+                    out.flush()?;
+                    Ok(())
+                })
+        })
+        .wait()?;
+    Ok(())
+}
+
 fn sub_sup_depart(m: &ArgMatches<'_>) -> Result<()> {
     let cfg = config::load()?;
     let listen_ctl_addr = listen_ctl_addr_from_input(m)?;
@@ -1142,7 +1653,7 @@ fn sub_sup_depart(m: &ArgMatches<'_>) -> Result<()> {
     let mut ui = ui();
     let mut msg = protocol::ctl::SupDepart::default();
     msg.member_id = Some(m.value_of("MEMBER_ID").unwrap().to_string());
-    SrvClient::connect(&listen_ctl_addr, secret_key)
+    ctl_connect(&listen_ctl_addr, secret_key)
         .and_then(|conn| {
             ui.begin(format!(
                 "Permanently marking {} as departed",
@@ -1328,11 +1839,52 @@ fn ctl_secret_key(config: &Config) -> Result<String> {
         Ok(v) => Ok(v.to_string()),
         Err(_) => match config.ctl_secret {
             Some(ref v) => Ok(v.to_string()),
-            None => SrvClient::read_secret_key().map_err(Error::from),
+            None => match SrvClient::read_secret_key() {
+                Ok(v) => Ok(v),
+                // A Supervisor which verified our client certificate does not need the secret.
+                Err(_) if henv::var(CTL_CLIENT_CERTIFICATE_ENVVAR).is_ok() => Ok(String::new()),
+                Err(err) => Err(Error::from(err)),
+            },
         },
     }
 }
 
+/// Connect to the CtlGateway of a Supervisor. If the HAB_CTL_CA_CERTIFICATE env var is set, the
+/// connection is made over TLS and the Supervisor's certificate is verified against that CA
+/// bundle.
+fn ctl_connect<S>(
+    listen_ctl_addr: &ListenCtlAddr,
+    secret_key: S,
+) -> Box<dyn Future<Item = SrvClient, Error = SrvClientError>>
+where
+    S: ToString,
+{
+    match ctl_tls() {
+        Ok(Some(tls)) => SrvClient::connect_tls(listen_ctl_addr, secret_key, &tls),
+        Ok(None) => SrvClient::connect(listen_ctl_addr, secret_key),
+        Err(err) => Box::new(future::err(err)),
+    }
+}
+
+/// Read the CtlGateway TLS settings from the environment. The certificate presented by the
+/// Supervisor must be valid for HAB_CTL_SERVER_NAME, or `localhost` if it is not set. A client
+/// certificate is presented if both HAB_CTL_CLIENT_CERTIFICATE and HAB_CTL_CLIENT_KEY are set.
+fn ctl_tls() -> result::Result<Option<SrvClientTls>, SrvClientError> {
+    let ca_path = match henv::var(CTL_CA_CERTIFICATE_ENVVAR) {
+        Ok(v) => v,
+        Err(_) => return Ok(None),
+    };
+    let server_name = henv::var(CTL_SERVER_NAME_ENVVAR).unwrap_or_else(|_| "localhost".to_string());
+    let tls = SrvClientTls::new(ca_path, server_name)?;
+    match (
+        henv::var(CTL_CLIENT_CERTIFICATE_ENVVAR),
+        henv::var(CTL_CLIENT_KEY_ENVVAR),
+    ) {
+        (Ok(cert_path), Ok(key_path)) => tls.client_certificate(cert_path, key_path).map(Some),
+        _ => Ok(Some(tls)),
+    }
+}
+
 /// Check to see if an auth token exists and convert it to a string slice if it does. Unlike
 /// auth_token_param_or_env, it's ok for no auth token to be present here. This is useful for
 /// commands that can optionally take an auth token for operating on private packages.
@@ -1575,9 +2127,139 @@ where
         svc_pid,
         status.service_group,
     )?;
+    // The table's columns are fixed, so a timed out hook is reported alongside it.
+    if let Some(hook) = status.timed_out_hook {
+        eprintln!(
+            "{}: the {} hook timed out and was terminated",
+            status.service_group, hook
+        );
+    }
     return Ok(());
 }
 
+fn print_audit_entry<T>(
+    out: &mut T,
+    reply: SrvMessage,
+    print_header: bool,
+) -> result::Result<(), SrvClientError>
+where
+    T: io::Write,
+{
+    let entry = match reply.message_id() {
+        "AuditEntry" => reply
+            .parse::<protocol::ctl::AuditEntry>()
+            .map_err(SrvClientError::Decode)?,
+        "NetOk" => {
+            println!("No operations recorded.");
+            return Ok(());
+        }
+        "NetErr" => {
+            let err = reply
+                .parse::<protocol::net::NetErr>()
+                .map_err(SrvClientError::Decode)?;
+            return Err(SrvClientError::from(err));
+        }
+        _ => {
+            warn!("Unexpected audit message, {:?}", reply);
+            return Ok(());
+        }
+    };
+    if print_header {
+        writeln!(out, "{}", AUDIT_HEADER.join("\t"))?;
+    }
+    let target = match entry.filename {
+        Some(filename) => format!("{}/{}", entry.target.unwrap_or_default(), filename),
+        None => entry.target.unwrap_or_default(),
+    };
+    let message_type = match entry.denied {
+        Some(true) => format!("{} (denied)", entry.message_type.unwrap_or_default()),
+        _ => entry.message_type.unwrap_or_default(),
+    };
+    let origin = match (entry.credential, entry.remote_addr) {
+        (Some(credential), Some(addr)) => format!("{}@{}", credential, addr),
+        (Some(credential), None) => credential,
+        (None, Some(addr)) => addr,
+        (None, None) => "<none>".to_string(),
+    };
+    writeln!(
+        out,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        entry.timestamp.unwrap_or_default(),
+        entry.source.unwrap_or_default(),
+        message_type,
+        target,
+        origin,
+        entry
+            .incarnation
+            .map_or_else(|| "<none>".to_string(), |i| i.to_string()),
+        entry.config_digest.unwrap_or_else(|| "<none>".to_string()),
+    )?;
+    Ok(())
+}
+
+fn sub_launcher_ps() -> Result<()> {
+    let launcher_root = launcher_root_path(Some(&*FS_ROOT));
+    let status_file = launcher_root.join(launcher_protocol::LAUNCHER_STATUS_FILE);
+    if !launcher_is_running(&*FS_ROOT) || !status_file.is_file() {
+        return Err(Error::LauncherNotRunning);
+    }
+    let bytes = fs::read(&status_file)?;
+    let status =
+        LauncherStatus::from_bytes(&bytes).map_err(|e| Error::LauncherStatus(e.to_string()))?;
+    // A Launcher which didn't exit cleanly leaves its files behind.
+    if !hcore::os::process::is_alive(status.pid as hcore::os::process::Pid) {
+        return Err(Error::LauncherNotRunning);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    println!("Launcher: PID {}", status.pid);
+    println!(
+        "Supervisor: PID {}, {}, restarts: {}",
+        status.supervisor.pid,
+        if status.supervisor.up { "up" } else { "down" },
+        status.supervisor.restarts
+    );
+    if status.processes.is_empty() {
+        return Ok(());
+    }
+    println!();
+    let mut out = TabWriter::new(io::stdout());
+    writeln!(out, "{}", LAUNCHER_PS_HEADER.join("\t"))?;
+    for process in status.processes {
+        let (state, since) = match process.last_exit {
+            Some(ref exit) if !process.running => ("down", exit.exited_at),
+            _ => ("up", process.started_at),
+        };
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            process.pid,
+            process.id,
+            process
+                .uid
+                .map(|uid| uid.to_string())
+                .or(process.user)
+                .unwrap_or_else(|| "-".to_string()),
+            process
+                .gid
+                .map_or_else(|| "-".to_string(), |gid| gid.to_string()),
+            state,
+            Utc.timestamp_opt(process.started_at, 0)
+                .single()
+                .map_or_else(|| "-".to_string(), |started| started.to_rfc3339()),
+            now - since,
+            process
+                .last_exit
+                .map_or_else(|| "-".to_string(), |exit| exit.to_string()),
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
 /// Check if we have a launcher/supervisor running out of this habitat root.
 /// If the launcher PID file exists then the supervisor is up and running
 fn launcher_is_running(fs_root_path: &Path) -> bool {
@@ -1598,7 +2280,7 @@ fn supervisor_services() -> Result<Vec<PackageIdent>> {
     let msg = protocol::ctl::SvcStatus::default();
 
     let mut out: Vec<PackageIdent> = vec![];
-    SrvClient::connect(&listen_ctl_addr, &secret_key)
+    ctl_connect(&listen_ctl_addr, &secret_key)
         .and_then(|conn| {
             conn.call(msg).for_each(|reply| match reply.message_id() {
                 "ServiceStatus" => {
@@ -1780,6 +2462,88 @@ fn update_svc_load_from_input(m: &ArgMatches<'_>, msg: &mut protocol::ctl::SvcLo
     msg.group = get_group_from_input(m);
     msg.svc_encrypted_password = get_password_from_input(m)?;
     msg.health_check_interval = get_health_check_interval_from_input(m);
+    msg.config_source = m.value_of("CONFIG_SOURCE").map(ToString::to_string);
+    msg.config_source_precedence = m
+        .value_of("CONFIG_SOURCE_PRECEDENCE")
+        .map(ToString::to_string);
+    if m.is_present("STRICT_TEMPLATES") {
+        msg.strict_templates = Some(true);
+    }
+    msg.shutdown_signal = m.value_of("SHUTDOWN_SIGNAL").map(ToString::to_string);
+    msg.shutdown_timeout = m
+        .value_of("SHUTDOWN_TIMEOUT")
+        .map(|t| t.parse().expect("SHUTDOWN_TIMEOUT is validated by clap"));
+    msg.memory_max = m.value_of("MEMORY_MAX").map(|s| {
+        MemorySize::from_str(s)
+            .expect("MEMORY_MAX is validated by clap")
+            .bytes()
+    });
+    msg.cpu_weight = m
+        .value_of("CPU_WEIGHT")
+        .map(|w| w.parse().expect("CPU_WEIGHT is validated by clap"));
+    msg.cpu_quota = m
+        .value_of("CPU_QUOTA")
+        .map(|q| q.parse().expect("CPU_QUOTA is validated by clap"));
+    msg.pids_max = m
+        .value_of("PIDS_MAX")
+        .map(|p| p.parse().expect("PIDS_MAX is validated by clap"));
+    if let Some(rlimits) = m.values_of("RLIMIT") {
+        msg.rlimits = rlimits
+            .map(|r| {
+                let rlimit = Rlimit::from_str(r).expect("RLIMIT is validated by clap");
+                (rlimit.name, u64::from(rlimit.value))
+            })
+            .collect();
+    }
+    if m.is_present("DROP_CAPABILITIES") || m.is_present("KEEP_CAPABILITY") {
+        msg.drop_capabilities = Some(true);
+    }
+    if let Some(capabilities) = m.values_of("KEEP_CAPABILITY") {
+        msg.capabilities = capabilities
+            .map(|c| {
+                Capability::from_str(c)
+                    .expect("KEEP_CAPABILITY is validated by clap")
+                    .to_string()
+            })
+            .collect();
+    }
+    if m.is_present("NO_NEW_PRIVS") {
+        msg.no_new_privs = Some(true);
+    }
+    if m.is_present("PRIVATE_TMP") {
+        msg.private_tmp = Some(true);
+    }
+    if m.is_present("READ_ONLY_PACKAGE") {
+        msg.read_only_package = Some(true);
+    }
+    // The profile is read by the Launcher, which may not share our working directory.
+    msg.seccomp_profile = m.value_of("SECCOMP_PROFILE").map(|p| {
+        fs::canonicalize(p)
+            .unwrap_or_else(|_| PathBuf::from(p))
+            .to_string_lossy()
+            .into_owned()
+    });
+    msg.log_format = m.value_of("LOG_FORMAT").map(ToString::to_string);
+    msg.log_max_size = m.value_of("LOG_MAX_SIZE").map(|s| {
+        MemorySize::from_str(s)
+            .expect("LOG_MAX_SIZE is validated by clap")
+            .bytes()
+    });
+    msg.log_max_age = m
+        .value_of("LOG_MAX_AGE")
+        .map(|a| a.parse().expect("LOG_MAX_AGE is validated by clap"));
+    msg.log_retain = m
+        .value_of("LOG_RETAIN")
+        .map(|r| r.parse().expect("LOG_RETAIN is validated by clap"));
+    msg.console = m.value_of("CONSOLE").map(ToString::to_string);
+    if let Some(hook_timeouts) = m.values_of("HOOK_TIMEOUT") {
+        msg.hook_timeouts = hook_timeouts
+            .map(|t| {
+                let timeout = HookTimeout::from_str(t).expect("HOOK_TIMEOUT is validated by clap");
+                (timeout.hook, timeout.seconds)
+            })
+            .collect();
+    }
     msg.binding_mode = get_binding_mode_from_input(m).map(|v| v as i32);
     msg.topology = get_topology_from_input(m).map(|v| v as i32);
     msg.update_strategy = get_strategy_from_input(m).map(|v| v as i32);
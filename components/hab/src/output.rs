@@ -0,0 +1,386 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Machine-readable rendering of replies from a Supervisor's Control Gateway.
+//!
+//! Commands which query a Supervisor accept `--format json|yaml|table`. The `table` format is
+//! meant for people and may change between releases. The `json` and `yaml` formats render the
+//! types in this module, whose fields are only ever added to: existing fields are not renamed,
+//! removed or given a different meaning. Fields a Supervisor did not report are rendered as
+//! `null` rather than omitted.
+
+use std::{io, str::FromStr};
+
+use serde::Serialize;
+use serde_json;
+use serde_yaml;
+use toml;
+
+use crate::protocol::{
+    ctl,
+    types::{self, BindingMode, DesiredState, ProcessState, Topology, UpdateStrategy},
+};
+
+/// Values accepted by `--format`.
+pub const FORMATS: &[&str] = &["table", "json", "yaml"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Table
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => Err(format!(
+                "Invalid output format \"{}\", must be one of: {}",
+                value,
+                FORMATS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Write the given value to `out` in a machine-readable format. Table output is specific to each
+/// command and is not handled here.
+pub fn render<T, W>(mut out: W, format: OutputFormat, value: &T) -> io::Result<()>
+where
+    T: Serialize,
+    W: io::Write,
+{
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, value).map_err(io::Error::from)?;
+            writeln!(out)
+        }
+        OutputFormat::Yaml => {
+            serde_yaml::to_writer(&mut out, value)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            writeln!(out)
+        }
+        OutputFormat::Table => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "table output must be rendered by the command",
+        )),
+    }
+}
+
+/// A service loaded by a Supervisor, as rendered by `hab svc status`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ServiceStatus {
+    /// Fully qualified identifier of the running package.
+    pub package: String,
+    /// Identifier the service was loaded with, which may be less specific than `package`.
+    pub spec_ident: Option<String>,
+    pub service_group: String,
+    /// State the service was asked to be in: `up` or `down`.
+    pub desired_state: Option<String>,
+    /// State of the service's process: `up` or `down`.
+    pub state: String,
+    pub pid: Option<u32>,
+    /// Seconds the process has been in its current state.
+    pub uptime_seconds: Option<i64>,
+    /// Result of the last health check: `OK`, `WARNING`, `CRITICAL` or `UNKNOWN`.
+    pub health: Option<String>,
+    /// `standalone` or `leader`.
+    pub topology: Option<String>,
+    /// `none`, `at-once` or `rolling`.
+    pub update_strategy: Option<String>,
+    /// Where the service is in its update strategy: `polling`, `awaiting-election`,
+    /// `in-election`, `leader-polling`, `leader-waiting`, `follower-waiting` or
+    /// `follower-updating`. `null` if the service has no update strategy.
+    pub update_state: Option<String>,
+    pub channel: Option<String>,
+    pub bldr_url: Option<String>,
+    /// `relaxed` or `strict`.
+    pub binding_mode: Option<String>,
+    /// Binds of the service, each as `<bind name>:<service group>`.
+    pub binds: Vec<String>,
+    pub health_check_interval_seconds: Option<u64>,
+    /// Name of the hook which most recently timed out, e.g. `init`, unless it has since run to
+    /// completion.
+    pub timed_out_hook: Option<String>,
+}
+
+impl From<types::ServiceStatus> for ServiceStatus {
+    fn from(other: types::ServiceStatus) -> Self {
+        let (state, pid, uptime_seconds) = match other.process {
+            Some(process) => (
+                ProcessState::from_i32(process.state).unwrap_or_default(),
+                process.pid,
+                process.elapsed,
+            ),
+            None => (ProcessState::default(), None, None),
+        };
+        ServiceStatus {
+            package: other.ident.to_string(),
+            spec_ident: other.spec_ident.map(|i| i.to_string()),
+            service_group: other.service_group.to_string(),
+            desired_state: other
+                .desired_state
+                .and_then(DesiredState::from_i32)
+                .filter(|d| *d != DesiredState::DesiredNone)
+                .map(|d| d.to_string()),
+            state: state.to_string(),
+            pid,
+            uptime_seconds,
+            health: other.health_check,
+            topology: other
+                .topology
+                .and_then(Topology::from_i32)
+                .map(|t| t.to_string()),
+            update_strategy: other
+                .update_strategy
+                .and_then(UpdateStrategy::from_i32)
+                .map(|u| u.to_string()),
+            update_state: other.update_state,
+            channel: other.channel,
+            bldr_url: other.bldr_url,
+            binding_mode: other
+                .binding_mode
+                .and_then(BindingMode::from_i32)
+                .map(|b| b.to_string()),
+            binds: other
+                .binds
+                .iter()
+                .map(|b| format!("{}:{}", b.name, b.service_group))
+                .collect(),
+            health_check_interval_seconds: other.health_check_interval.map(|h| h.seconds),
+            timed_out_hook: other.timed_out_hook,
+        }
+    }
+}
+
+/// An operation recorded in a Supervisor's audit log, as rendered by `hab sup audit`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AuditEntry {
+    /// RFC 3339 timestamp, in UTC, of when the operation was applied.
+    pub timestamp: Option<String>,
+    /// `ctl` for Control Gateway requests or `gossip` for rumors applied from the ring.
+    pub source: Option<String>,
+    /// Control Gateway message or rumor type, e.g. `SvcSetCfg` or `ServiceConfig`.
+    pub message_type: Option<String>,
+    /// Package identifier or service group the operation applied to.
+    pub target: Option<String>,
+    pub filename: Option<String>,
+    /// Address of the Control Gateway client which made the request.
+    pub remote_addr: Option<String>,
+    /// Name of the credential the Control Gateway client authenticated with.
+    pub credential: Option<String>,
+    pub incarnation: Option<u64>,
+    /// Digest of the applied configuration or file content.
+    pub config_digest: Option<String>,
+    /// Whether the request was refused because the client's credential doesn't permit it.
+    pub denied: bool,
+}
+
+impl From<ctl::AuditEntry> for AuditEntry {
+    fn from(other: ctl::AuditEntry) -> Self {
+        AuditEntry {
+            timestamp: other.timestamp,
+            source: other.source,
+            message_type: other.message_type,
+            target: other.target,
+            filename: other.filename,
+            remote_addr: other.remote_addr,
+            credential: other.credential,
+            incarnation: other.incarnation,
+            config_digest: other.config_digest,
+            denied: other.denied.unwrap_or(false),
+        }
+    }
+}
+
+/// A key of a service's effective configuration, as rendered by `hab config show --provenance`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ConfigKeyProvenance {
+    /// Dotted path of the key, e.g. `server.port`.
+    pub key: Option<String>,
+    /// Effective value of the key. Rendered as a string if the Supervisor's value is not valid
+    /// TOML.
+    pub value: Option<toml::Value>,
+    /// Layer which set the key: `default`, `environment`, `user` or `gossip`.
+    pub layer: Option<String>,
+    /// File or environment variable the layer was loaded from.
+    pub source: Option<String>,
+    /// Incarnation of the service group's configuration, for keys set by gossip.
+    pub incarnation: Option<u64>,
+}
+
+impl From<ctl::ConfigKeyProvenance> for ConfigKeyProvenance {
+    fn from(other: ctl::ConfigKeyProvenance) -> Self {
+        let value = other.value.map(|v| {
+            toml::from_str::<toml::value::Table>(&format!("value = {}", v))
+                .ok()
+                .and_then(|mut t| t.remove("value"))
+                .unwrap_or(toml::Value::String(v))
+        });
+        ConfigKeyProvenance {
+            key: other.key,
+            value,
+            layer: other.layer,
+            source: other.source,
+            incarnation: other.incarnation,
+        }
+    }
+}
+
+/// A configuration previously applied to a service group, as rendered by `hab config history`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ConfigHistoryEntry {
+    pub incarnation: Option<u64>,
+    /// RFC 3339 timestamp, in UTC, of when the Supervisor recorded the configuration.
+    pub timestamp: Option<String>,
+    /// Digest of the configuration as it was gossiped.
+    pub digest: Option<String>,
+    /// Control Gateway client which applied the configuration, as `credential@address`. `null`
+    /// unless the configuration was applied through the queried Supervisor.
+    pub applied_by: Option<String>,
+    pub encrypted: Option<bool>,
+}
+
+impl From<ctl::ConfigHistoryEntry> for ConfigHistoryEntry {
+    fn from(other: ctl::ConfigHistoryEntry) -> Self {
+        ConfigHistoryEntry {
+            incarnation: other.incarnation,
+            timestamp: other.timestamp,
+            digest: other.digest,
+            applied_by: other.applied_by,
+            encrypted: other.encrypted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hcore::package::PackageIdent;
+
+    fn status() -> types::ServiceStatus {
+        let mut status = types::ServiceStatus::default();
+        status.ident = PackageIdent::from_str("core/redis/4.0.10/20180801003001")
+            .unwrap()
+            .into();
+        status.service_group = "redis.default".parse().unwrap();
+        status.desired_state = Some(DesiredState::DesiredUp as i32);
+        status.process = Some(types::ProcessStatus {
+            elapsed: Some(42),
+            pid: Some(1234),
+            state: ProcessState::Up as i32,
+        });
+        status
+    }
+
+    #[test]
+    fn parse_format() {
+        assert_eq!("json".parse::<OutputFormat>(), Ok(OutputFormat::Json));
+        assert_eq!("yaml".parse::<OutputFormat>(), Ok(OutputFormat::Yaml));
+        assert_eq!("table".parse::<OutputFormat>(), Ok(OutputFormat::Table));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn status_from_older_supervisor_has_null_fields() {
+        let status = ServiceStatus::from(status());
+        assert_eq!(status.package, "core/redis/4.0.10/20180801003001");
+        assert_eq!(status.service_group, "redis.default");
+        assert_eq!(status.desired_state, Some("up".to_string()));
+        assert_eq!(status.state, "up");
+        assert_eq!(status.pid, Some(1234));
+        assert_eq!(status.uptime_seconds, Some(42));
+        assert_eq!(status.health, None);
+        assert_eq!(status.topology, None);
+        assert!(status.binds.is_empty());
+        assert_eq!(status.timed_out_hook, None);
+    }
+
+    #[test]
+    fn status_includes_spec_fields() {
+        let mut proto = status();
+        proto.health_check = Some("OK".to_string());
+        proto.topology = Some(Topology::Leader as i32);
+        proto.update_strategy = Some(UpdateStrategy::Rolling as i32);
+        proto.update_state = Some("follower-waiting".to_string());
+        proto.binds = vec!["db:postgres.default".parse().unwrap()];
+        proto.health_check_interval = Some(types::HealthCheckInterval { seconds: 30 });
+        proto.timed_out_hook = Some("init".to_string());
+        let status = ServiceStatus::from(proto);
+        assert_eq!(status.health, Some("OK".to_string()));
+        assert_eq!(status.topology, Some("leader".to_string()));
+        assert_eq!(status.update_strategy, Some("rolling".to_string()));
+        assert_eq!(status.update_state, Some("follower-waiting".to_string()));
+        assert_eq!(status.binds, vec!["db:postgres.default".to_string()]);
+        assert_eq!(status.health_check_interval_seconds, Some(30));
+        assert_eq!(status.timed_out_hook, Some("init".to_string()));
+    }
+
+    #[test]
+    fn provenance_values_are_parsed_as_toml() {
+        let key = ConfigKeyProvenance::from(ctl::ConfigKeyProvenance {
+            key: Some("server.port".to_string()),
+            value: Some("6379".to_string()),
+            layer: Some("user".to_string()),
+            source: Some("/hab/user/redis/config/user.toml".to_string()),
+            incarnation: None,
+        });
+        assert_eq!(key.value, Some(toml::Value::Integer(6379)));
+
+        let key = ConfigKeyProvenance::from(ctl::ConfigKeyProvenance {
+            value: Some("not toml".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(key.value, Some(toml::Value::String("not toml".to_string())));
+    }
+
+    #[test]
+    fn render_json() {
+        let mut out = vec![];
+        render(
+            &mut out,
+            OutputFormat::Json,
+            &vec![ServiceStatus::from(status())],
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["package"], "core/redis/4.0.10/20180801003001");
+        assert_eq!(json[0]["pid"], 1234);
+        assert!(json[0]["health"].is_null());
+    }
+
+    #[test]
+    fn render_yaml() {
+        let mut out = vec![];
+        render(
+            &mut out,
+            OutputFormat::Yaml,
+            &vec![ServiceStatus::from(status())],
+        )
+        .unwrap();
+        let yaml = String::from_utf8(out).unwrap();
+        assert!(yaml.contains("service_group: redis.default"));
+    }
+}
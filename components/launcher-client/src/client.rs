@@ -14,7 +14,10 @@
 
 use crate::error::{Error, Result};
 use habitat_core::os::process::Pid;
-use habitat_launcher_protocol::{self as protocol, Error as ProtocolError};
+use habitat_launcher_protocol::{
+    self as protocol, Console, Error as ProtocolError, ResourceLimits, RunningService, Sandbox,
+    ServiceLog, ShutdownConfig, ShutdownMethod,
+};
 use ipc_channel::ipc::{IpcOneShotServer, IpcReceiver, IpcSender};
 use std::{collections::HashMap, fs, io, path::Path};
 
@@ -115,14 +118,29 @@ impl LauncherCli {
         }
     }
 
-    /// Restart a running process with the same arguments
-    pub fn restart(&self, pid: Pid) -> Result<Pid> {
-        let msg = protocol::Restart { pid: pid.into() };
+    /// Restart a running process with the same arguments, stopping it as `shutdown` describes
+    pub fn restart(&self, pid: Pid, shutdown: &ShutdownConfig) -> Result<Pid> {
+        let msg = protocol::Restart {
+            pid: pid.into(),
+            shutdown_signal: shutdown.signal.clone(),
+            shutdown_timeout: shutdown.timeout,
+        };
         Self::send(&self.tx, &msg)?;
         let reply = Self::recv::<protocol::SpawnOk>(&self.rx)?;
         Ok(reply.pid as Pid)
     }
 
+    /// The services the connected Launcher is running, or `None` if the Launcher is too old to
+    /// report them.
+    pub fn services(&self) -> Result<Option<Vec<RunningService>>> {
+        if crate::env_protocol_version() < 1 {
+            return Ok(None);
+        }
+        Self::send(&self.tx, &protocol::ServiceList::default())?;
+        let reply = Self::recv::<protocol::ServiceListOk>(&self.rx)?;
+        Ok(Some(reply.services))
+    }
+
     /// Send a process spawn command to the connected Launcher
     ///
     /// `user` and `group` are string names, while `user_id` and
@@ -138,6 +156,12 @@ impl LauncherCli {
         group_id: Option<u32>,
         password: Option<P>,
         env: Env,
+        limits: &ResourceLimits,
+        sandbox: &Sandbox,
+        log: Option<ServiceLog>,
+        console: Console,
+        attach_path: &Path,
+        shutdown: &ShutdownConfig,
     ) -> Result<Pid>
     where
         I: ToString,
@@ -161,7 +185,16 @@ impl LauncherCli {
             svc_password: password.map(|p| p.to_string()),
             env,
             id: id.to_string(),
-            ..Default::default()
+            limits: limits.clone(),
+            sandbox: sandbox.clone(),
+            log,
+            console,
+            attach_path: if console == Console::Detached {
+                None
+            } else {
+                Some(attach_path.to_string_lossy().into_owned())
+            },
+            shutdown: shutdown.clone(),
         };
 
         Self::send(&self.tx, &msg)?;
@@ -169,10 +202,15 @@ impl LauncherCli {
         Ok(reply.pid as Pid)
     }
 
-    pub fn terminate(&self, pid: Pid) -> Result<i32> {
-        let msg = protocol::Terminate { pid: pid.into() };
+    /// Terminate a running process as `shutdown` describes, returning how it was shut down
+    pub fn terminate(&self, pid: Pid, shutdown: &ShutdownConfig) -> Result<ShutdownMethod> {
+        let msg = protocol::Terminate {
+            pid: pid.into(),
+            shutdown_signal: shutdown.signal.clone(),
+            shutdown_timeout: shutdown.timeout,
+        };
         Self::send(&self.tx, &msg)?;
         let reply = Self::recv::<protocol::TerminateOk>(&self.rx)?;
-        Ok(reply.exit_code)
+        Ok(reply.shutdown_method)
     }
 }
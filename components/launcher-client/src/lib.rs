@@ -19,7 +19,9 @@ mod client;
 pub mod error;
 
 pub use habitat_launcher_protocol::{
-    ERR_NO_RETRY_EXCODE, LAUNCHER_LOCK_CLEAN_ENV, LAUNCHER_PID_ENV, OK_NO_RETRY_EXCODE,
+    AttachFrame, Console, LogFormat, ResourceLimits, RlimitValue, RunningService, Sandbox,
+    ServiceLog, ShutdownConfig, ShutdownMethod, ERR_NO_RETRY_EXCODE, LAUNCHER_LOCK_CLEAN_ENV,
+    LAUNCHER_PID_ENV, MAX_SHUTDOWN_TIMEOUT, OK_NO_RETRY_EXCODE,
 };
 
 pub use crate::{client::LauncherCli, error::Error};
@@ -27,3 +29,11 @@ pub use crate::{client::LauncherCli, error::Error};
 pub fn env_pipe() -> Option<String> {
     habitat_core::env::var(habitat_launcher_protocol::LAUNCHER_PIPE_ENV).ok()
 }
+
+/// The version of the protocol spoken by the Launcher which spawned us.
+pub fn env_protocol_version() -> u32 {
+    habitat_core::env::var(habitat_launcher_protocol::LAUNCHER_PROTOCOL_VERSION_ENV)
+        .ok()
+        .and_then(|version| version.parse().ok())
+        .unwrap_or(0)
+}
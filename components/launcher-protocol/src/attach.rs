@@ -0,0 +1,151 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Framing of the messages clients send over a service's attach socket.
+//!
+//! A client attached to a service's console writes frames of a one byte kind, a four byte
+//! big-endian length and a payload. The Launcher writes the service's output back to the client
+//! unframed, exactly as the service wrote it.
+
+use std::io::{self, Read, Write};
+
+const FRAME_INPUT: u8 = 0;
+const FRAME_RESIZE: u8 = 1;
+/// Frames larger than this are rejected rather than buffered.
+const MAX_FRAME_LEN: u32 = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttachFrame {
+    /// Bytes written to the service's standard input.
+    Input(Vec<u8>),
+    /// The new size of the client's terminal, applied to the service's pseudo-terminal.
+    Resize { rows: u16, cols: u16 },
+}
+
+impl AttachFrame {
+    /// Read the next frame, or `None` once the client has closed its end of the socket.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let mut header = [0; 5];
+        match reader.read_exact(&mut header) {
+            Ok(()) => (),
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("attach frame of {} bytes is too large", len),
+            ));
+        }
+        let mut payload = vec![0; len as usize];
+        reader.read_exact(&mut payload)?;
+        match header[0] {
+            FRAME_INPUT => Ok(Some(AttachFrame::Input(payload))),
+            FRAME_RESIZE if payload.len() == 4 => Ok(Some(AttachFrame::Resize {
+                rows: u16::from_be_bytes([payload[0], payload[1]]),
+                cols: u16::from_be_bytes([payload[2], payload[3]]),
+            })),
+            kind => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid attach frame of kind {}", kind),
+            )),
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let (kind, payload) = match *self {
+            AttachFrame::Input(ref data) => (FRAME_INPUT, data.clone()),
+            AttachFrame::Resize { rows, cols } => {
+                let mut payload = rows.to_be_bytes().to_vec();
+                payload.extend_from_slice(&cols.to_be_bytes());
+                (FRAME_RESIZE, payload)
+            }
+        };
+        let mut frame = Vec::with_capacity(5 + payload.len());
+        frame.push(kind);
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&payload);
+        writer.write_all(&frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(bytes: &[u8]) -> io::Result<Option<AttachFrame>> {
+        AttachFrame::read_from(&mut &bytes[..])
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let frames = vec![
+            AttachFrame::Input(b"ls -l\n".to_vec()),
+            AttachFrame::Input(vec![]),
+            AttachFrame::Resize {
+                rows: 50,
+                cols: 132,
+            },
+        ];
+        let mut bytes = vec![];
+        for frame in &frames {
+            frame.write_to(&mut bytes).expect("writing frame");
+        }
+        let mut reader = &bytes[..];
+        for frame in frames {
+            assert_eq!(
+                AttachFrame::read_from(&mut reader).expect("reading frame"),
+                Some(frame)
+            );
+        }
+        assert_eq!(
+            AttachFrame::read_from(&mut reader).expect("reading EOF"),
+            None
+        );
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let mut bytes = vec![FRAME_INPUT];
+        bytes.extend_from_slice(&MAX_FRAME_LEN.to_be_bytes());
+        bytes.extend(vec![b'x'; MAX_FRAME_LEN as usize]);
+        assert_eq!(
+            read(&bytes).expect("reading frame"),
+            Some(AttachFrame::Input(vec![b'x'; MAX_FRAME_LEN as usize]))
+        );
+
+        let mut bytes = vec![FRAME_INPUT];
+        bytes.extend_from_slice(&(MAX_FRAME_LEN + 1).to_be_bytes());
+        let err = read(&bytes).expect_err("frame is too large");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn resize_frames_need_a_four_byte_payload() {
+        for payload in &[&[0, 24, 0][..], &[0, 24, 0, 80, 0][..]] {
+            let mut bytes = vec![FRAME_RESIZE];
+            bytes.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(payload);
+            let err = read(&bytes).expect_err("resize payload is invalid");
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn frames_of_unknown_kind_are_rejected() {
+        let err = read(&[7, 0, 0, 0, 0]).expect_err("frame kind is unknown");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
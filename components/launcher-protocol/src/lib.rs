@@ -18,18 +18,34 @@ extern crate prost_derive;
 #[macro_use]
 extern crate serde_derive;
 
+mod attach;
 mod error;
 mod generated;
 mod types;
 
 use crate::error::Result;
-pub use crate::{error::Error, types::*};
+pub use crate::{attach::AttachFrame, error::Error, types::*};
 
 pub const LAUNCHER_PIPE_ENV: &str = "HAB_LAUNCHER_PIPE";
 pub const LAUNCHER_PID_ENV: &str = "HAB_LAUNCHER_PID";
 // Set to instruct the Supervisor to clean the Launcher's process LOCK on startup. This is useful
 // when restarting a Supervisor which terminated normally.
 pub const LAUNCHER_LOCK_CLEAN_ENV: &str = "HAB_LAUNCHER_LOCK_CLEAN";
+/// Name of the file in the Launcher's root which it publishes its `LauncherStatus` to.
+pub const LAUNCHER_STATUS_FILE: &str = "STATUS";
+/// Set by the Launcher for the Supervisor it spawns to the version of this protocol it speaks.
+/// Launchers which predate it speak version 0.
+pub const LAUNCHER_PROTOCOL_VERSION_ENV: &str = "HAB_LAUNCHER_PROTOCOL_VERSION";
+/// The version of this protocol. Version 1 added `ServiceList`.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The most seconds a service is given to stop before it is killed. Longer timeouts are capped.
+pub const MAX_SHUTDOWN_TIMEOUT: u32 = 3600;
+/// The rlimit value which leaves a resource unlimited, as on Linux.
+pub const RLIM_INFINITY: u64 = u64::max_value();
+/// The names of the process rlimits which may be set on a service.
+pub const RLIMIT_NAMES: &[&str] = &[
+    "as", "core", "cpu", "data", "fsize", "memlock", "nofile", "nproc", "stack",
+];
 /// Process exit code from Supervisor which indicates to Launcher that the Supervisor
 /// ran to completion with a successful result. The Launcher should not attempt to restart
 /// the Supervisor and should exit immediately with a successful exit code.
@@ -14,10 +14,17 @@
 
 use crate::{
     error::{Error, Result},
-    generated,
+    generated, RLIM_INFINITY,
 };
 use prost::{self, Message};
-use std::{collections::HashMap, fmt};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, result,
+};
 
 pub trait MessageStatic {
     const MESSAGE_ID: &'static str;
@@ -49,7 +56,264 @@ where
     }
 }
 
-pub use generated::{ErrCode, ShutdownMethod};
+pub use generated::{Console, ErrCode, LogFormat, ShutdownMethod};
+
+/// How a service is stopped: the name of the signal asking it to exit, and the seconds to wait
+/// for it to exit before it is killed. Unset values are left to the Launcher's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShutdownConfig {
+    pub signal: Option<String>,
+    pub timeout: Option<u32>,
+}
+
+/// Limits on the resources a spawned service may use. Unset limits are not enforced.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// Bytes of memory the service may use.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_max: Option<u64>,
+    /// Relative share of CPU time, from 1 to 10000.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_weight: Option<u32>,
+    /// Percentage of a single CPU the service may use.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_quota: Option<u32>,
+    /// Number of processes and threads the service may contain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pids_max: Option<u32>,
+    /// Process rlimits, keyed by name, e.g. "nofile".
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub rlimits: BTreeMap<String, RlimitValue>,
+}
+
+impl ResourceLimits {
+    /// Returns true if none of the limits which require a cgroup are set.
+    pub fn cgroup_unlimited(&self) -> bool {
+        self.memory_max.is_none()
+            && self.cpu_weight.is_none()
+            && self.cpu_quota.is_none()
+            && self.pids_max.is_none()
+    }
+
+    /// Returns true if no limits are set at all.
+    pub fn is_empty(&self) -> bool {
+        self.cgroup_unlimited() && self.rlimits.is_empty()
+    }
+}
+
+impl From<generated::ResourceLimits> for ResourceLimits {
+    fn from(value: generated::ResourceLimits) -> Self {
+        ResourceLimits {
+            memory_max: value.memory_max,
+            cpu_weight: value.cpu_weight,
+            cpu_quota: value.cpu_quota,
+            pids_max: value.pids_max,
+            rlimits: value
+                .rlimits
+                .into_iter()
+                .map(|(name, value)| (name, RlimitValue::from(value)))
+                .collect(),
+        }
+    }
+}
+
+impl From<ResourceLimits> for generated::ResourceLimits {
+    fn from(value: ResourceLimits) -> Self {
+        generated::ResourceLimits {
+            memory_max: value.memory_max,
+            cpu_weight: value.cpu_weight,
+            cpu_quota: value.cpu_quota,
+            pids_max: value.pids_max,
+            rlimits: value
+                .rlimits
+                .into_iter()
+                .map(|(name, value)| (name, u64::from(value)))
+                .collect(),
+        }
+    }
+}
+
+/// The value of a process rlimit. Unlimited rlimits are sent over the wire as `RLIM_INFINITY`
+/// and written to specs as `"unlimited"`, as TOML integers can't hold `RLIM_INFINITY`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RlimitValue {
+    Limited(u64),
+    Unlimited,
+}
+
+impl From<u64> for RlimitValue {
+    fn from(value: u64) -> Self {
+        if value == RLIM_INFINITY {
+            RlimitValue::Unlimited
+        } else {
+            RlimitValue::Limited(value)
+        }
+    }
+}
+
+impl From<RlimitValue> for u64 {
+    fn from(value: RlimitValue) -> Self {
+        match value {
+            RlimitValue::Limited(value) => value,
+            RlimitValue::Unlimited => RLIM_INFINITY,
+        }
+    }
+}
+
+impl fmt::Display for RlimitValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RlimitValue::Limited(value) => write!(f, "{}", value),
+            RlimitValue::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+impl Serialize for RlimitValue {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            RlimitValue::Limited(value) => serializer.serialize_u64(value),
+            RlimitValue::Unlimited => serializer.serialize_str("unlimited"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RlimitValue {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RlimitValueVisitor;
+
+        impl<'de> Visitor<'de> for RlimitValueVisitor {
+            type Value = RlimitValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a non-negative integer or \"unlimited\"")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> result::Result<RlimitValue, E>
+            where
+                E: de::Error,
+            {
+                Ok(RlimitValue::Limited(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> result::Result<RlimitValue, E>
+            where
+                E: de::Error,
+            {
+                if value < 0 {
+                    return Err(E::invalid_value(de::Unexpected::Signed(value), &self));
+                }
+                Ok(RlimitValue::Limited(value as u64))
+            }
+
+            fn visit_str<E>(self, value: &str) -> result::Result<RlimitValue, E>
+            where
+                E: de::Error,
+            {
+                if value == "unlimited" {
+                    Ok(RlimitValue::Unlimited)
+                } else {
+                    Err(E::invalid_value(de::Unexpected::Str(value), &self))
+                }
+            }
+        }
+
+        deserializer.deserialize_any(RlimitValueVisitor)
+    }
+}
+
+/// Opt-in hardening applied to a spawned service before it execs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sandbox {
+    /// Drop every capability from the bounding set except `capabilities`.
+    pub drop_capabilities: bool,
+    pub capabilities: Vec<String>,
+    pub no_new_privs: bool,
+    pub private_tmp: bool,
+    pub read_only_paths: Vec<String>,
+    /// Path to a compiled seccomp BPF filter.
+    pub seccomp_profile: Option<String>,
+}
+
+impl Sandbox {
+    /// Returns true if no hardening was asked for.
+    pub fn is_empty(&self) -> bool {
+        *self == Sandbox::default()
+    }
+}
+
+impl From<generated::Sandbox> for Sandbox {
+    fn from(value: generated::Sandbox) -> Self {
+        Sandbox {
+            drop_capabilities: value.drop_capabilities.unwrap_or(false),
+            capabilities: value.capabilities,
+            no_new_privs: value.no_new_privs.unwrap_or(false),
+            private_tmp: value.private_tmp.unwrap_or(false),
+            read_only_paths: value.read_only_paths,
+            seccomp_profile: value.seccomp_profile,
+        }
+    }
+}
+
+impl From<Sandbox> for generated::Sandbox {
+    fn from(value: Sandbox) -> Self {
+        generated::Sandbox {
+            drop_capabilities: Some(value.drop_capabilities),
+            capabilities: value.capabilities,
+            no_new_privs: Some(value.no_new_privs),
+            private_tmp: Some(value.private_tmp),
+            read_only_paths: value.read_only_paths,
+            seccomp_profile: value.seccomp_profile,
+        }
+    }
+}
+
+/// Where and how the output of a spawned service is written.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServiceLog {
+    pub path: String,
+    pub format: LogFormat,
+    /// Rotate the log once it grows past this many bytes.
+    pub max_size: Option<u64>,
+    /// Rotate the log once it was opened more than this many seconds ago.
+    pub max_age: Option<u64>,
+    /// Number of rotated logs to keep.
+    pub retain: u32,
+}
+
+impl FromProto<generated::ServiceLog> for ServiceLog {
+    fn from_proto(value: generated::ServiceLog) -> Result<Self> {
+        Ok(ServiceLog {
+            path: value.path.ok_or(Error::ProtocolMismatch("path"))?,
+            format: value
+                .format
+                .and_then(LogFormat::from_i32)
+                .unwrap_or_default(),
+            max_size: value.max_size,
+            max_age: value.max_age,
+            retain: value.retain.unwrap_or(0),
+        })
+    }
+}
+
+impl From<ServiceLog> for generated::ServiceLog {
+    fn from(value: ServiceLog) -> Self {
+        generated::ServiceLog {
+            path: Some(value.path),
+            format: Some(value.format as i32),
+            max_size: value.max_size,
+            max_age: value.max_age,
+            retain: Some(value.retain),
+        }
+    }
+}
 
 // Now we're going to define our own set of structs to use internally, as well as conversion
 // facilities to and from the corresponding protobuf types. It'd be rad if there was a way to
@@ -142,6 +406,8 @@ impl From<Register> for generated::Register {
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Restart {
     pub pid: i64,
+    pub shutdown_signal: Option<String>,
+    pub shutdown_timeout: Option<u32>,
 }
 
 impl LauncherMessage for Restart {
@@ -152,6 +418,8 @@ impl LauncherMessage for Restart {
     fn from_proto(proto: generated::Restart) -> Result<Self> {
         Ok(Restart {
             pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+            shutdown_signal: proto.shutdown_signal,
+            shutdown_timeout: proto.shutdown_timeout,
         })
     }
 }
@@ -160,6 +428,8 @@ impl From<Restart> for generated::Restart {
     fn from(value: Restart) -> Self {
         generated::Restart {
             pid: Some(value.pid),
+            shutdown_signal: value.shutdown_signal,
+            shutdown_timeout: value.shutdown_timeout,
         }
     }
 }
@@ -174,6 +444,17 @@ pub struct Spawn {
     pub env: HashMap<String, String>,
     pub svc_user_id: Option<u32>,
     pub svc_group_id: Option<u32>,
+    pub limits: ResourceLimits,
+    pub sandbox: Sandbox,
+    /// The service's log file. Older Supervisors don't send one, in which case the service's
+    /// output is only passed through to the Launcher's own stdout and stderr.
+    pub log: Option<ServiceLog>,
+    pub console: Console,
+    /// The socket clients attach to the service's console through, unless it is detached.
+    pub attach_path: Option<String>,
+    /// How the Launcher stops the service when it shuts down, as opposed to when the Supervisor
+    /// asks it to.
+    pub shutdown: ShutdownConfig,
 }
 
 impl LauncherMessage for Spawn {
@@ -191,6 +472,21 @@ impl LauncherMessage for Spawn {
             env: proto.env,
             svc_user_id: proto.svc_user_id,
             svc_group_id: proto.svc_group_id,
+            limits: proto.limits.map(ResourceLimits::from).unwrap_or_default(),
+            sandbox: proto.sandbox.map(Sandbox::from).unwrap_or_default(),
+            log: match proto.log {
+                Some(log) => Some(ServiceLog::from_proto(log)?),
+                None => None,
+            },
+            console: proto
+                .console
+                .and_then(Console::from_i32)
+                .unwrap_or_default(),
+            attach_path: proto.attach_path,
+            shutdown: ShutdownConfig {
+                signal: proto.shutdown_signal,
+                timeout: proto.shutdown_timeout,
+            },
         })
     }
 }
@@ -206,6 +502,21 @@ impl From<Spawn> for generated::Spawn {
             env: value.env,
             svc_user_id: value.svc_user_id,
             svc_group_id: value.svc_group_id,
+            limits: if value.limits.is_empty() {
+                None
+            } else {
+                Some(value.limits.into())
+            },
+            sandbox: if value.sandbox.is_empty() {
+                None
+            } else {
+                Some(value.sandbox.into())
+            },
+            log: value.log.map(Into::into),
+            console: Some(value.console as i32),
+            attach_path: value.attach_path,
+            shutdown_signal: value.shutdown.signal,
+            shutdown_timeout: value.shutdown.timeout,
         }
     }
 }
@@ -235,9 +546,248 @@ impl From<SpawnOk> for generated::SpawnOk {
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServiceList {}
+
+impl LauncherMessage for ServiceList {
+    type Generated = generated::ServiceList;
+
+    const MESSAGE_ID: &'static str = "ServiceList";
+
+    fn from_proto(_proto: generated::ServiceList) -> Result<Self> {
+        Ok(ServiceList {})
+    }
+}
+
+impl From<ServiceList> for generated::ServiceList {
+    fn from(_value: ServiceList) -> Self {
+        generated::ServiceList {}
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServiceListOk {
+    pub services: Vec<RunningService>,
+}
+
+impl LauncherMessage for ServiceListOk {
+    type Generated = generated::ServiceListOk;
+
+    const MESSAGE_ID: &'static str = "ServiceListOk";
+
+    fn from_proto(proto: generated::ServiceListOk) -> Result<Self> {
+        Ok(ServiceListOk {
+            services: proto
+                .services
+                .into_iter()
+                .map(RunningService::from_proto)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl From<ServiceListOk> for generated::ServiceListOk {
+    fn from(value: ServiceListOk) -> Self {
+        generated::ServiceListOk {
+            services: value.services.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A service the Launcher is running, along with the arguments it was spawned with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunningService {
+    pub pid: i64,
+    pub spawn: Spawn,
+    /// When the service was spawned, in seconds since the Unix epoch.
+    pub started_at: i64,
+}
+
+impl FromProto<generated::RunningService> for RunningService {
+    fn from_proto(proto: generated::RunningService) -> Result<Self> {
+        Ok(RunningService {
+            pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+            spawn: Spawn::from_proto(proto.spawn.ok_or(Error::ProtocolMismatch("spawn"))?)?,
+            started_at: proto
+                .started_at
+                .ok_or(Error::ProtocolMismatch("started_at"))?,
+        })
+    }
+}
+
+impl From<RunningService> for generated::RunningService {
+    fn from(value: RunningService) -> Self {
+        generated::RunningService {
+            pid: Some(value.pid),
+            spawn: Some(value.spawn.into()),
+            started_at: Some(value.started_at),
+        }
+    }
+}
+
+/// The state of a Launcher: its Supervisor, and the processes it has spawned for services.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LauncherStatus {
+    pub pid: i64,
+    pub supervisor: SupervisorStatus,
+    pub processes: Vec<ProcessStatus>,
+}
+
+impl LauncherMessage for LauncherStatus {
+    type Generated = generated::LauncherStatus;
+
+    const MESSAGE_ID: &'static str = "LauncherStatus";
+
+    fn from_proto(proto: generated::LauncherStatus) -> Result<Self> {
+        Ok(LauncherStatus {
+            pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+            supervisor: proto
+                .supervisor
+                .ok_or(Error::ProtocolMismatch("supervisor"))?
+                .into(),
+            processes: proto
+                .processes
+                .into_iter()
+                .map(ProcessStatus::from_proto)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl From<LauncherStatus> for generated::LauncherStatus {
+    fn from(value: LauncherStatus) -> Self {
+        generated::LauncherStatus {
+            pid: Some(value.pid),
+            supervisor: Some(value.supervisor.into()),
+            processes: value.processes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SupervisorStatus {
+    pub pid: i64,
+    pub up: bool,
+    /// Number of times the Launcher has restarted the Supervisor.
+    pub restarts: u32,
+}
+
+impl From<generated::SupervisorStatus> for SupervisorStatus {
+    fn from(value: generated::SupervisorStatus) -> Self {
+        SupervisorStatus {
+            pid: value.pid.unwrap_or_default(),
+            up: value.up.unwrap_or(false),
+            restarts: value.restarts.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<SupervisorStatus> for generated::SupervisorStatus {
+    fn from(value: SupervisorStatus) -> Self {
+        generated::SupervisorStatus {
+            pid: Some(value.pid),
+            up: Some(value.up),
+            restarts: Some(value.restarts),
+        }
+    }
+}
+
+/// A process the Launcher spawned for a service.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessStatus {
+    pub pid: i64,
+    /// The service group the process was spawned for.
+    pub id: String,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub user: Option<String>,
+    /// When the process was spawned, in seconds since the Unix epoch.
+    pub started_at: i64,
+    pub running: bool,
+    /// How the last process reaped for the service exited.
+    pub last_exit: Option<ProcessExit>,
+}
+
+impl FromProto<generated::ProcessStatus> for ProcessStatus {
+    fn from_proto(proto: generated::ProcessStatus) -> Result<Self> {
+        Ok(ProcessStatus {
+            pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+            id: proto.id.ok_or(Error::ProtocolMismatch("id"))?,
+            uid: proto.uid,
+            gid: proto.gid,
+            user: proto.user,
+            started_at: proto.started_at.unwrap_or_default(),
+            running: proto.running.unwrap_or(false),
+            last_exit: match proto.last_exit {
+                Some(exit) => Some(ProcessExit::from_proto(exit)?),
+                None => None,
+            },
+        })
+    }
+}
+
+impl From<ProcessStatus> for generated::ProcessStatus {
+    fn from(value: ProcessStatus) -> Self {
+        generated::ProcessStatus {
+            pid: Some(value.pid),
+            id: Some(value.id),
+            uid: value.uid,
+            gid: value.gid,
+            user: value.user,
+            started_at: Some(value.started_at),
+            running: Some(value.running),
+            last_exit: value.last_exit.map(Into::into),
+        }
+    }
+}
+
+/// How a process exited: with an exit code, or ended by a signal.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessExit {
+    pub pid: i64,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    /// When the process was reaped, in seconds since the Unix epoch.
+    pub exited_at: i64,
+}
+
+impl FromProto<generated::ProcessExit> for ProcessExit {
+    fn from_proto(proto: generated::ProcessExit) -> Result<Self> {
+        Ok(ProcessExit {
+            pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+            code: proto.code,
+            signal: proto.signal,
+            exited_at: proto.exited_at.unwrap_or_default(),
+        })
+    }
+}
+
+impl From<ProcessExit> for generated::ProcessExit {
+    fn from(value: ProcessExit) -> Self {
+        generated::ProcessExit {
+            pid: Some(value.pid),
+            code: value.code,
+            signal: value.signal,
+            exited_at: Some(value.exited_at),
+        }
+    }
+}
+
+impl fmt::Display for ProcessExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (_, Some(signal)) => write!(f, "signal {}", signal),
+            (Some(code), None) => write!(f, "code {}", code),
+            (None, None) => write!(f, "unknown"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Terminate {
     pub pid: i64,
+    pub shutdown_signal: Option<String>,
+    pub shutdown_timeout: Option<u32>,
 }
 
 impl LauncherMessage for Terminate {
@@ -248,6 +798,8 @@ impl LauncherMessage for Terminate {
     fn from_proto(proto: generated::Terminate) -> Result<Self> {
         Ok(Terminate {
             pid: proto.pid.ok_or(Error::ProtocolMismatch("pid"))?,
+            shutdown_signal: proto.shutdown_signal,
+            shutdown_timeout: proto.shutdown_timeout,
         })
     }
 }
@@ -256,6 +808,8 @@ impl From<Terminate> for generated::Terminate {
     fn from(value: Terminate) -> Self {
         generated::Terminate {
             pid: Some(value.pid),
+            shutdown_signal: value.shutdown_signal,
+            shutdown_timeout: value.shutdown_timeout,
         }
     }
 }
@@ -343,3 +897,74 @@ impl From<Shutdown> for generated::Shutdown {
         generated::Shutdown {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exit(code: Option<i32>, signal: Option<i32>) -> ProcessExit {
+        ProcessExit {
+            pid: 4242,
+            code,
+            signal,
+            exited_at: 1_550_000_100,
+        }
+    }
+
+    #[test]
+    fn launcher_status_round_trips() {
+        let running = ProcessStatus {
+            pid: 4243,
+            id: "redis.default".to_string(),
+            uid: Some(42),
+            gid: Some(43),
+            started_at: 1_550_000_000,
+            running: true,
+            last_exit: Some(exit(Some(1), None)),
+            ..Default::default()
+        };
+        let down = ProcessStatus {
+            pid: 4244,
+            id: "nginx.default".to_string(),
+            user: Some("hab".to_string()),
+            started_at: 1_550_000_050,
+            ..Default::default()
+        };
+        let status = LauncherStatus {
+            pid: 1,
+            supervisor: SupervisorStatus {
+                pid: 2,
+                up: true,
+                restarts: 3,
+            },
+            processes: vec![running, down],
+        };
+        let bytes = status.to_bytes().expect("encode launcher status");
+        assert_eq!(
+            LauncherStatus::from_bytes(&bytes).expect("decode launcher status"),
+            status
+        );
+    }
+
+    #[test]
+    fn launcher_status_without_processes_round_trips() {
+        let status = LauncherStatus {
+            pid: 1,
+            ..Default::default()
+        };
+        let bytes = status.to_bytes().expect("encode launcher status");
+        assert_eq!(
+            LauncherStatus::from_bytes(&bytes).expect("decode launcher status"),
+            status
+        );
+    }
+
+    #[test]
+    fn process_exit_displays_how_the_process_ended() {
+        assert_eq!(exit(Some(1), None).to_string(), "code 1");
+        assert_eq!(exit(None, Some(9)).to_string(), "signal 9");
+        // A process killed by a signal has no meaningful exit code.
+        assert_eq!(exit(Some(137), Some(9)).to_string(), "signal 9");
+        assert_eq!(exit(None, None).to_string(), "unknown");
+    }
+}
@@ -16,14 +16,18 @@
 extern crate habitat_core as core;
 use habitat_launcher_protocol as protocol;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
 
 #[cfg(windows)]
 extern crate winapi;
 
 pub mod error;
+pub mod log_sink;
 pub mod server;
 pub mod service;
+mod service_log;
 mod sys;
 
 pub const SUP_CMD: &str = "hab-sup";
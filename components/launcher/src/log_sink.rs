@@ -0,0 +1,345 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Forwarding of the output of the Supervisor and its services to the host's syslog or
+//! systemd-journald. A sink is configured once, from the arguments the Launcher was started with,
+//! and each line read from a process is then sent to it as a single message.
+//!
+//! Sending never blocks the process whose output is being forwarded: the sockets are nonblocking
+//! and a line the receiver has no room for is dropped. If the receiver goes away, for example
+//! because syslog was restarted, a new socket is connected and the line is sent again.
+
+use std::{
+    io,
+    net::UdpSocket,
+    sync::{Arc, Mutex},
+};
+#[cfg(unix)]
+use std::{os::unix::net::UnixDatagram, path::Path};
+
+#[cfg(unix)]
+use libc;
+use time;
+
+use crate::service_log::Stream;
+
+/// Identifier of the messages forwarded from the Supervisor's own output.
+pub const SUPERVISOR_IDENTIFIER: &str = "hab-sup";
+/// Socket syslog messages are sent to if no address is given.
+pub const DEFAULT_SYSLOG_ADDRESS: &str = "/dev/log";
+#[cfg(unix)]
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+/// Messages are logged with the daemon facility.
+const FACILITY_DAEMON: u8 = 3;
+/// The RFC 5424 structured data ID of our fields, under the private enterprise number reserved
+/// for documentation.
+const SD_ID: &str = "habitat@32473";
+
+lazy_static! {
+    static ref SINK: Mutex<Option<Arc<LogSink>>> = Mutex::new(None);
+}
+
+/// Send all forwarded output to `sink` from now on.
+pub fn set(sink: LogSink) {
+    *SINK.lock().expect("LogSink lock is poisoned") = Some(Arc::new(sink));
+}
+
+/// Returns true if output is being forwarded to a sink.
+pub fn is_configured() -> bool {
+    SINK.lock().expect("LogSink lock is poisoned").is_some()
+}
+
+/// Forward a line of output to the configured sink, if there is one.
+pub fn send(record: &Record<'_>) {
+    // Take our own handle to the sink so the lock isn't held while sending.
+    let sink = match *SINK.lock().expect("LogSink lock is poisoned") {
+        Some(ref sink) => Arc::clone(sink),
+        None => return,
+    };
+    match sink.send(record) {
+        Ok(()) => (),
+        // The receiver is not keeping up, drop the line rather than stall the process.
+        Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => (),
+        Err(ref err)
+            if err.kind() == io::ErrorKind::ConnectionRefused
+                || err.kind() == io::ErrorKind::NotConnected =>
+        {
+            reconnect(&sink, record)
+        }
+        Err(err) => debug!("Unable to forward output of {}, {}", record.identifier, err),
+    }
+}
+
+/// Replace `sink`, whose receiver has gone away, with a newly connected one and send `record`
+/// with it.
+fn reconnect(sink: &Arc<LogSink>, record: &Record<'_>) {
+    let new_sink = match sink.reconnect() {
+        Ok(new_sink) => Arc::new(new_sink),
+        Err(err) => {
+            debug!("Unable to reconnect log sink, {}", err);
+            return;
+        }
+    };
+    {
+        let mut current = SINK.lock().expect("LogSink lock is poisoned");
+        // Another thread may have reconnected already; keep its sink if so.
+        if current.as_ref().map_or(false, |c| Arc::ptr_eq(c, sink)) {
+            *current = Some(Arc::clone(&new_sink));
+        }
+    }
+    if let Err(err) = new_sink.send(record) {
+        debug!("Unable to forward output of {}, {}", record.identifier, err);
+    }
+}
+
+/// A line of output read from the Supervisor or one of its services.
+pub struct Record<'a> {
+    /// Name of the program which wrote the line; a service's package name, or `hab-sup`.
+    pub identifier: &'a str,
+    pub pid: u32,
+    /// Service group of the service which wrote the line, if it came from a service.
+    pub service_group: Option<&'a str>,
+    pub stream: Stream,
+    pub message: &'a str,
+}
+
+impl<'a> Record<'a> {
+    /// The syslog severity of the line: error for standard error and informational otherwise.
+    fn severity(&self) -> u8 {
+        match self.stream {
+            Stream::Stdout => 6,
+            Stream::Stderr => 3,
+        }
+    }
+}
+
+pub enum LogSink {
+    Syslog {
+        transport: Transport,
+        /// The address the transport was connected to, kept to reconnect with.
+        address: String,
+        hostname: String,
+    },
+    #[cfg(unix)]
+    Journald(UnixDatagram),
+}
+
+pub enum Transport {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+}
+
+impl LogSink {
+    /// Create the sink named `kind`, either `syslog` or `journald`. The syslog sink sends to
+    /// `address`, which is either the path of a Unix socket or a `HOST:PORT` to send UDP to.
+    pub fn new(kind: &str, address: Option<&str>) -> io::Result<Self> {
+        match kind {
+            "syslog" => Self::syslog(address.unwrap_or(DEFAULT_SYSLOG_ADDRESS)),
+            "journald" => Self::journald(),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown log sink '{}'", kind),
+            )),
+        }
+    }
+
+    fn syslog(address: &str) -> io::Result<Self> {
+        let transport = if address.starts_with('/') {
+            Self::unix_socket(address)?
+        } else {
+            let socket = UdpSocket::bind("0.0.0.0:0")?;
+            socket.connect(address)?;
+            socket.set_nonblocking(true)?;
+            Transport::Udp(socket)
+        };
+        Ok(LogSink::Syslog {
+            transport,
+            address: address.to_string(),
+            hostname: hostname(),
+        })
+    }
+
+    /// Create a sink which sends to the same place as this one with a new socket.
+    fn reconnect(&self) -> io::Result<Self> {
+        match *self {
+            LogSink::Syslog { ref address, .. } => Self::syslog(address),
+            #[cfg(unix)]
+            LogSink::Journald(_) => Self::journald(),
+        }
+    }
+
+    #[cfg(unix)]
+    fn unix_socket(path: &str) -> io::Result<Transport> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(Path::new(path))?;
+        socket.set_nonblocking(true)?;
+        Ok(Transport::Unix(socket))
+    }
+
+    #[cfg(windows)]
+    fn unix_socket(path: &str) -> io::Result<Transport> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "cannot send to {}, only UDP syslog is supported on Windows",
+                path
+            ),
+        ))
+    }
+
+    #[cfg(unix)]
+    fn journald() -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(JOURNALD_SOCKET)?;
+        socket.set_nonblocking(true)?;
+        Ok(LogSink::Journald(socket))
+    }
+
+    #[cfg(windows)]
+    fn journald() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "journald is not supported on Windows",
+        ))
+    }
+
+    fn send(&self, record: &Record<'_>) -> io::Result<()> {
+        match *self {
+            LogSink::Syslog {
+                ref transport,
+                ref hostname,
+                ..
+            } => {
+                let message = syslog_message(record, hostname);
+                match *transport {
+                    #[cfg(unix)]
+                    Transport::Unix(ref socket) => socket.send(message.as_bytes())?,
+                    Transport::Udp(ref socket) => socket.send(message.as_bytes())?,
+                };
+            }
+            #[cfg(unix)]
+            LogSink::Journald(ref socket) => {
+                socket.send(&journald_message(record))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format an RFC 5424 syslog message with the record's service group and stream as structured
+/// data.
+fn syslog_message(record: &Record<'_>, hostname: &str) -> String {
+    let mut data = format!("[{}", SD_ID);
+    if let Some(service_group) = record.service_group {
+        data.push_str(&format!(" service_group=\"{}\"", sd_escape(service_group)));
+    }
+    data.push_str(&format!(" stream=\"{}\"]", record.stream.name()));
+    format!(
+        "<{}>1 {} {} {} {} - {} {}",
+        FACILITY_DAEMON * 8 + record.severity(),
+        time::now_utc().rfc3339(),
+        hostname,
+        record.identifier,
+        record.pid,
+        data,
+        strip_ansi(record.message)
+    )
+}
+
+/// Escape a structured data parameter value.
+fn sd_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '"' || c == '\\' || c == ']' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Serialize a record with journald's native protocol.
+#[cfg(unix)]
+fn journald_message(record: &Record<'_>) -> Vec<u8> {
+    let mut message = Vec::new();
+    journald_field(&mut message, "MESSAGE", &strip_ansi(record.message));
+    journald_field(&mut message, "PRIORITY", &record.severity().to_string());
+    journald_field(
+        &mut message,
+        "SYSLOG_FACILITY",
+        &FACILITY_DAEMON.to_string(),
+    );
+    journald_field(&mut message, "SYSLOG_IDENTIFIER", record.identifier);
+    journald_field(&mut message, "SYSLOG_PID", &record.pid.to_string());
+    if let Some(service_group) = record.service_group {
+        journald_field(&mut message, "HAB_SERVICE_GROUP", service_group);
+    }
+    journald_field(&mut message, "HAB_STREAM", record.stream.name());
+    message
+}
+
+/// Append a field, using the length-prefixed form for values which span lines.
+#[cfg(unix)]
+fn journald_field(message: &mut Vec<u8>, name: &str, value: &str) {
+    message.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        message.push(b'\n');
+        message.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        message.push(b'=');
+    }
+    message.extend_from_slice(value.as_bytes());
+    message.push(b'\n');
+}
+
+/// Remove the ANSI escape sequences used to color the Supervisor's output.
+fn strip_ansi(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence up to and including its final byte.
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if let '@'..='~' = c {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if rc != 0 {
+        return "-".to_string();
+    }
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    if len == 0 {
+        return "-".to_string();
+    }
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(windows)]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "-".to_string())
+}
@@ -20,7 +20,10 @@ extern crate log;
 
 use std::{env, process};
 
-use crate::launcher::server;
+use crate::launcher::{
+    log_sink::{self, LogSink},
+    server,
+};
 
 fn main() {
     env_logger::init();
@@ -65,4 +68,24 @@ fn set_global_logging_options(args: &[String]) {
     if args.contains(&String::from("-v")) {
         core::output::set_verbose(true);
     }
+    if let Some(kind) = arg_value(args, "--log-sink") {
+        match LogSink::new(kind, arg_value(args, "--syslog-address")) {
+            Ok(sink) => log_sink::set(sink),
+            Err(err) => error!("Unable to forward output to {}, {}", kind, err),
+        }
+    }
+}
+
+/// Returns the value of the long option `name`, given either as `--name value` or `--name=value`.
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("{}=", name);
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == name {
+            args.get(i + 1).map(String::as_str)
+        } else if arg.starts_with(&prefix) {
+            Some(&arg[prefix.len()..])
+        } else {
+            None
+        }
+    })
 }
@@ -13,10 +13,16 @@
 // limitations under the License.
 
 mod restart;
+mod service_list;
 mod spawn;
 mod terminate;
 
-pub use self::{restart::*, spawn::*, terminate::*};
+pub use self::{restart::*, service_list::*, spawn::*, terminate::*};
+
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
 
 use crate::protocol;
 
@@ -24,6 +30,9 @@ use super::{Sender, ServiceTable};
 
 type HandleResult<T> = Result<T, protocol::NetErr>;
 
+/// Work a `BackgroundHandler` runs on its own thread, returning the reply to send.
+type Work<T> = Box<dyn FnOnce() -> HandleResult<T> + Send>;
+
 pub trait Handler {
     type Message: protocol::LauncherMessage;
     type Reply: protocol::LauncherMessage;
@@ -53,3 +62,68 @@ pub trait Handler {
         }
     }
 }
+
+/// A handler whose work can take a long time, such as waiting for a service to stop. The work
+/// runs on its own thread, so that the Launcher carries on serving requests and reaping processes
+/// meanwhile, and the reply is sent once it is done.
+pub trait BackgroundHandler {
+    type Message: protocol::LauncherMessage;
+    type Reply: protocol::LauncherMessage + 'static;
+
+    /// Take what the work needs from `services`, or fail at once with the reply to send.
+    fn prepare(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Work<Self::Reply>>;
+
+    fn run_in_background(tx: &Sender, txn: protocol::NetTxn, services: &mut ServiceTable) {
+        let msg = match txn.decode::<Self::Message>() {
+            Ok(msg) => msg,
+            Err(err) => {
+                error!("{}: decoding, {}", txn.message_id(), err);
+                return;
+            }
+        };
+        trace!("{}, {:?}, {:?}", txn.message_id(), msg, services);
+        let message_id = txn.message_id().to_string();
+        let work = match Self::prepare(msg, services) {
+            Ok(work) => Arc::new(Mutex::new(Some(work))),
+            Err(reply) => {
+                reply_with(tx, &message_id, &Err::<Self::Reply, _>(reply));
+                return;
+            }
+        };
+        let (handoff, thread_tx, thread_message_id) =
+            (Arc::clone(&work), tx.clone(), message_id.clone());
+        let spawned = thread::Builder::new()
+            .name(format!("{}-handler", message_id))
+            .spawn(move || {
+                if let Some(work) = handoff
+                    .lock()
+                    .expect("Handler work lock is poisoned")
+                    .take()
+                {
+                    reply_with(&thread_tx, &thread_message_id, &work());
+                }
+            });
+        if let Err(err) = spawned {
+            warn!(
+                "{}: unable to start thread, handling it in place, {}",
+                message_id, err
+            );
+            if let Some(work) = work.lock().expect("Handler work lock is poisoned").take() {
+                reply_with(tx, &message_id, &work());
+            }
+        }
+    }
+}
+
+fn reply_with<T>(tx: &Sender, message_id: &str, result: &HandleResult<T>)
+where
+    T: protocol::LauncherMessage,
+{
+    let sent = match *result {
+        Ok(ref reply) => super::send(tx, reply),
+        Err(ref reply) => super::send(tx, reply),
+    };
+    if let Err(err) = sent {
+        error!("{}: replying, {}", message_id, err);
+    }
+}
@@ -14,15 +14,15 @@
 
 use crate::protocol;
 
-use super::{HandleResult, Handler};
+use super::{BackgroundHandler, HandleResult, Work};
 use crate::{server::ServiceTable, service};
 
 pub struct RestartHandler;
-impl Handler for RestartHandler {
+impl BackgroundHandler for RestartHandler {
     type Message = protocol::Restart;
     type Reply = protocol::SpawnOk;
 
-    fn handle(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Self::Reply> {
+    fn prepare(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Work<Self::Reply>> {
         let mut service = match services.remove(msg.pid as u32) {
             Some(service) => service,
             None => {
@@ -31,22 +31,38 @@ impl Handler for RestartHandler {
                 return Err(reply);
             }
         };
-        service.kill();
-        match service.wait() {
-            Ok(_status) => match service::run(service.take_args()) {
-                Ok(new_service) => {
-                    let mut reply = protocol::SpawnOk::default();
-                    reply.pid = new_service.id().into();
-                    services.insert(new_service);
-                    Ok(reply)
+        let returned = services.returned();
+        Ok(Box::new(move || {
+            let shutdown = protocol::ShutdownConfig {
+                signal: msg.shutdown_signal,
+                timeout: msg.shutdown_timeout,
+            };
+            service.kill(&shutdown);
+            match service.wait() {
+                Ok(_status) => {
+                    // The Supervisor's latest shutdown settings apply when the Launcher stops the
+                    // respawned service itself.
+                    let mut args = service.take_args();
+                    args.shutdown = shutdown;
+                    match service::run(args) {
+                        Ok(new_service) => {
+                            let mut reply = protocol::SpawnOk::default();
+                            reply.pid = new_service.id().into();
+                            returned
+                                .lock()
+                                .expect("Returned services lock is poisoned")
+                                .push(new_service);
+                            Ok(reply)
+                        }
+                        Err(err) => Err(protocol::error(err)),
+                    }
+                }
+                Err(_) => {
+                    let mut reply = protocol::NetErr::default();
+                    reply.code = protocol::ErrCode::ExecWait;
+                    Err(reply)
                 }
-                Err(err) => Err(protocol::error(err)),
-            },
-            Err(_) => {
-                let mut reply = protocol::NetErr::default();
-                reply.code = protocol::ErrCode::ExecWait;
-                Err(reply)
             }
-        }
+        }))
     }
 }
@@ -0,0 +1,75 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::protocol;
+
+use super::{HandleResult, Handler};
+use crate::server::ServiceTable;
+
+pub struct ServiceListHandler;
+impl Handler for ServiceListHandler {
+    type Message = protocol::ServiceList;
+    type Reply = protocol::ServiceListOk;
+
+    fn handle(_msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Self::Reply> {
+        let services = services
+            .iter()
+            .map(|service| protocol::RunningService {
+                pid: service.id().into(),
+                spawn: service.args().clone(),
+                started_at: service.started_at(),
+            })
+            .collect();
+        Ok(protocol::ServiceListOk { services })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_table_lists_no_services() {
+        let mut services = ServiceTable::default();
+        let reply = ServiceListHandler::handle(protocol::ServiceList::default(), &mut services)
+            .expect("listing services");
+        assert!(reply.services.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn every_service_is_listed_with_what_it_was_spawned_with() {
+        let spawn = protocol::Spawn {
+            id: "true.default".to_string(),
+            binary: "/bin/true".to_string(),
+            svc_user_id: Some(unsafe { libc::geteuid() }),
+            svc_group_id: Some(unsafe { libc::getegid() }),
+            ..Default::default()
+        };
+        let mut services = ServiceTable::default();
+        let service = crate::service::run(spawn.clone()).expect("spawning service");
+        let pid = service.id();
+        services.insert(service);
+
+        let reply = ServiceListHandler::handle(protocol::ServiceList::default(), &mut services)
+            .expect("listing services");
+        assert_eq!(reply.services.len(), 1);
+        assert_eq!(reply.services[0].pid, i64::from(pid));
+        assert_eq!(reply.services[0].spawn.id, spawn.id);
+        assert_eq!(reply.services[0].spawn.binary, spawn.binary);
+        assert!(reply.services[0].started_at > 0);
+
+        services.remove(pid).expect("service in table").wait().ok();
+    }
+}
@@ -14,38 +14,51 @@
 
 use crate::protocol;
 
-use super::{HandleResult, Handler};
+use super::{BackgroundHandler, HandleResult, Work};
 use crate::server::ServiceTable;
 
 pub struct TerminateHandler;
-impl Handler for TerminateHandler {
+impl BackgroundHandler for TerminateHandler {
     type Message = protocol::Terminate;
     type Reply = protocol::TerminateOk;
 
-    fn handle(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Self::Reply> {
-        match services.get_mut(msg.pid as u32) {
-            Some(service) => {
-                debug!("Terminating: {}", service.id());
-                let shutdown_method = service.kill();
-                match service.wait() {
-                    Ok(status) => {
-                        let mut reply = protocol::TerminateOk::default();
-                        reply.exit_code = status.code().unwrap_or(0);
-                        reply.shutdown_method = shutdown_method;
-                        Ok(reply)
-                    }
-                    Err(_) => {
-                        let mut reply = protocol::NetErr::default();
-                        reply.code = protocol::ErrCode::ExecWait;
-                        Err(reply)
-                    }
-                }
-            }
+    fn prepare(msg: Self::Message, services: &mut ServiceTable) -> HandleResult<Work<Self::Reply>> {
+        let mut service = match services.remove(msg.pid as u32) {
+            Some(service) => service,
             None => {
                 let mut reply = protocol::NetErr::default();
                 reply.code = protocol::ErrCode::NoPid;
-                Err(reply)
+                return Err(reply);
+            }
+        };
+        // The service is handed back once it has exited, so that its exit is recorded when it's
+        // reaped.
+        let returned = services.returned();
+        Ok(Box::new(move || {
+            debug!("Terminating: {}", service.id());
+            let shutdown = protocol::ShutdownConfig {
+                signal: msg.shutdown_signal,
+                timeout: msg.shutdown_timeout,
+            };
+            let shutdown_method = service.kill(&shutdown);
+            let status = service.wait();
+            returned
+                .lock()
+                .expect("Returned services lock is poisoned")
+                .push(service);
+            match status {
+                Ok(status) => {
+                    let mut reply = protocol::TerminateOk::default();
+                    reply.exit_code = status.code().unwrap_or(0);
+                    reply.shutdown_method = shutdown_method;
+                    Ok(reply)
+                }
+                Err(_) => {
+                    let mut reply = protocol::NetErr::default();
+                    reply.code = protocol::ErrCode::ExecWait;
+                    Err(reply)
+                }
             }
-        }
+        }))
     }
 }
@@ -17,7 +17,7 @@ mod handlers;
 use std::{
     collections::HashMap,
     fs,
-    io::Write,
+    io::{self, BufRead, BufReader, Read, Write},
     path::PathBuf,
     process::{Child, Command, Stdio},
     str::FromStr,
@@ -31,6 +31,9 @@ use std::os::unix::process::ExitStatusExt;
 #[cfg(unix)]
 use std::process::ExitStatus;
 
+#[cfg(windows)]
+use crate::core::os::process::windows_child::ExitStatus;
+
 use crate::{
     core::{
         self,
@@ -42,9 +45,11 @@ use crate::{
         package::{PackageIdent, PackageInstall},
     },
     error::{Error, Result},
+    log_sink::{self, Record, SUPERVISOR_IDENTIFIER},
     protocol::{self, ERR_NO_RETRY_EXCODE, OK_NO_RETRY_EXCODE},
-    server::handlers::Handler,
-    service::Service,
+    server::handlers::{BackgroundHandler, Handler},
+    service::{self, Service},
+    service_log::Stream,
     SUP_CMD, SUP_PACKAGE_IDENT,
 };
 use ipc_channel::ipc::{IpcOneShotServer, IpcReceiver, IpcSender};
@@ -63,6 +68,9 @@ const SUP_VERSION_CHECK_DISABLE: &str = "HAB_LAUNCH_NO_SUP_VERSION_CHECK";
 // always existed such as https://github.com/habitat-sh/habitat/issues/5380
 const SUP_VERSION_REQ: &str = ">= 0.56";
 
+/// How many reads of the Supervisor's output may fail in a row before it is no longer passed on.
+const MAX_FAILED_SUP_OUTPUT_READS: u32 = 10;
+
 type Receiver = IpcReceiver<Vec<u8>>;
 type Sender = IpcSender<Vec<u8>>;
 
@@ -73,17 +81,25 @@ enum TickState {
 
 pub struct Server {
     pid_file_path: PathBuf,
+    status_file_path: PathBuf,
+    /// The status last written to the status file, so that it is only rewritten on change.
+    published_status: Vec<u8>,
     services: ServiceTable,
     tx: Sender,
     rx: Receiver,
     pipe: String,
     supervisor: Child,
+    supervisor_up: bool,
+    supervisor_restarts: u32,
+    /// Whether orphaned processes were left unreaped while handlers were stopping services.
+    orphans_pending: bool,
     args: Vec<String>,
 }
 
 impl Drop for Server {
     fn drop(&mut self) {
         fs::remove_file(&self.pid_file_path).ok();
+        fs::remove_file(&self.status_file_path).ok();
         self.remove_pipe();
     }
 }
@@ -96,16 +112,25 @@ impl Server {
         let mut pid_file = fs::File::create(&pid_file_path)?;
         write!(&mut pid_file, "{}", process::current_pid())?;
 
+        let status_file_path = launcher_root.join(protocol::LAUNCHER_STATUS_FILE);
+
         let ((rx, tx), supervisor, pipe) = Self::init(&args, false)?;
-        Ok(Server {
+        let mut server = Server {
             pid_file_path,
+            status_file_path,
+            published_status: vec![],
             services: ServiceTable::default(),
             tx,
             rx,
             pipe,
             supervisor,
+            supervisor_up: true,
+            supervisor_restarts: 0,
+            orphans_pending: false,
             args,
-        })
+        };
+        server.publish_status();
+        Ok(server)
     }
 
     /// Spawn a Supervisor and setup a bi-directional IPC connection to it.
@@ -132,10 +157,16 @@ impl Server {
     fn reload(&mut self) -> Result<()> {
         self.supervisor.kill();
         self.supervisor.wait();
+        if self.supervisor_up {
+            self.supervisor_up = false;
+            self.publish_status();
+        }
         let ((rx, tx), supervisor, pipe) = Self::init(&self.args, true)?;
         self.tx = tx;
         self.rx = rx;
         self.supervisor = supervisor;
+        self.supervisor_up = true;
+        self.supervisor_restarts += 1;
         // We're connecting to a new supervisor instance, so we need to remove
         // the socket files for the old pipe to avoid https://github.com/habitat-sh/habitat/issues/4673
         self.remove_pipe();
@@ -143,6 +174,42 @@ impl Server {
         Ok(())
     }
 
+    /// Write our status to the status file for `hab launcher ps`, if it changed since it was
+    /// last written.
+    fn publish_status(&mut self) {
+        let status = protocol::LauncherStatus {
+            pid: process::current_pid().into(),
+            supervisor: protocol::SupervisorStatus {
+                pid: self.supervisor.id().into(),
+                up: self.supervisor_up,
+                restarts: self.supervisor_restarts,
+            },
+            processes: self.services.status(),
+        };
+        let bytes = match protocol::LauncherMessage::to_bytes(&status) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Unable to encode Launcher status, {}", err);
+                return;
+            }
+        };
+        if bytes == self.published_status {
+            return;
+        }
+        // Write a new file and rename it over the old one, so readers never see a partial status.
+        let tmp_path = self.status_file_path.with_extension("tmp");
+        match fs::write(&tmp_path, &bytes)
+            .and_then(|_| fs::rename(&tmp_path, &self.status_file_path))
+        {
+            Ok(()) => self.published_status = bytes,
+            Err(err) => warn!(
+                "Unable to write Launcher status to {}, {}",
+                self.status_file_path.display(),
+                err
+            ),
+        }
+    }
+
     fn forward_signal(&self, signal: Signal) {
         if let Err(err) = core::os::process::signal(self.supervisor.id() as Pid, signal) {
             error!(
@@ -257,7 +324,14 @@ impl Server {
             }
             None => (),
         }
-        self.handle_message()
+        if self.orphans_pending && !self.services.handlers_running() {
+            if let Some(result) = self.reap_zombie_orphans() {
+                return result;
+            }
+        }
+        let tick = self.handle_message();
+        self.publish_status();
+        tick
     }
 
     /// When the supervisor runs as the init process (e.g. in a
@@ -281,13 +355,22 @@ impl Server {
         let mut reaped_sup_status: Option<ExitStatus> = None;
         let mut waitpid_status = 0 as libc::c_int;
 
+        // Waiting for any child would take the exit status of a service that a handler is
+        // stopping, so only the Supervisor is reaped until those handlers are done.
+        self.orphans_pending = self.services.handlers_running();
+        let wait_pid = if self.orphans_pending {
+            self.supervisor.id() as libc::pid_t
+        } else {
+            -1
+        };
+
         // We reap as many child processes as need reaping.
         loop {
             // We're not calling waitpid with WUNTRACED or WCONTINUED,
             // so we shouldn't be getting SIGCHLD from STOP or CONT
             // signals sent to a Supervisor; only when the Supervisor
             // process ends somehow.
-            let res = unsafe { libc::waitpid(-1, &mut waitpid_status, libc::WNOHANG) };
+            let res = unsafe { libc::waitpid(wait_pid, &mut waitpid_status, libc::WNOHANG) };
             if res > 0 {
                 // Some child process ended; let's see if it was the Supervisor
                 if res == self.supervisor.id() as libc::pid_t {
@@ -297,6 +380,9 @@ impl Server {
                 } else {
                     debug!("Reaped a non-supervisor child process, PID {}", res);
                 }
+                if wait_pid > 0 {
+                    break;
+                }
             } else if res == 0 {
                 // There are no more children waiting
                 break;
@@ -361,36 +447,118 @@ impl Server {
 }
 
 #[derive(Debug, Default)]
-pub struct ServiceTable(HashMap<u32, Service>);
+pub struct ServiceTable {
+    services: HashMap<u32, Service>,
+    /// The status of the last process reaped for each service, keyed by service group.
+    exited: HashMap<String, protocol::ProcessStatus>,
+    /// Services handed back by handlers running off the dispatch loop, which are added to the
+    /// table again on the next tick. Each running handler holds a reference.
+    returned: Arc<Mutex<Vec<Service>>>,
+}
 
 impl ServiceTable {
     pub fn get(&self, pid: u32) -> Option<&Service> {
-        self.0.get(&pid)
+        self.services.get(&pid)
     }
 
     pub fn get_mut(&mut self, pid: u32) -> Option<&mut Service> {
-        self.0.get_mut(&pid)
+        self.services.get_mut(&pid)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Service> {
+        self.services.values()
     }
 
     pub fn insert(&mut self, service: Service) {
-        self.0.insert(service.id(), service);
+        self.services.insert(service.id(), service);
     }
 
     pub fn remove(&mut self, pid: u32) -> Option<Service> {
-        self.0.remove(&pid)
+        self.services.remove(&pid)
+    }
+
+    /// Where a handler running off the dispatch loop hands back the services it took.
+    pub fn returned(&self) -> Arc<Mutex<Vec<Service>>> {
+        Arc::clone(&self.returned)
     }
 
+    /// Whether any handlers are still running off the dispatch loop.
+    fn handlers_running(&self) -> bool {
+        Arc::strong_count(&self.returned) > 1
+    }
+
+    /// Add the services handed back since the last tick to the table.
+    fn take_returned(&mut self) {
+        let returned: Vec<_> = self
+            .returned
+            .lock()
+            .expect("Returned services lock is poisoned")
+            .drain(..)
+            .collect();
+        for service in returned {
+            self.insert(service);
+        }
+    }
+
+    /// A row for each running service, along with how its previous process exited, and for each
+    /// service whose last process exited without being respawned.
+    pub fn status(&self) -> Vec<protocol::ProcessStatus> {
+        let mut rows: Vec<protocol::ProcessStatus> = self
+            .services
+            .values()
+            .map(|service| {
+                let mut row = service.status();
+                row.last_exit = self
+                    .exited
+                    .get(&row.id)
+                    .and_then(|exited| exited.last_exit.clone());
+                row
+            })
+            .collect();
+        for (id, exited) in &self.exited {
+            if !rows.iter().any(|row| &row.id == id) {
+                rows.push(exited.clone());
+            }
+        }
+        rows.sort_by(|a, b| a.id.cmp(&b.id));
+        rows
+    }
+
+    /// Stops every service as the Supervisor asked when spawning it. Services are stopped in
+    /// parallel, so the Launcher waits no longer than the longest shutdown timeout.
     fn kill_all(&mut self) {
-        for service in self.0.values_mut() {
-            outputln!(preamble service.name(), "Stopping...");
-            let shutdown_method = service.kill();
-            outputln!(preamble service.name(), "Shutdown OK: {}", shutdown_method);
+        // Let handlers which are stopping or restarting services finish first, so that none of
+        // their services are left behind.
+        while self.handlers_running() {
+            thread::sleep(service::SHUTDOWN_POLL_INTERVAL);
+        }
+        self.take_returned();
+        let stopping: Vec<_> = self
+            .services
+            .drain()
+            .filter_map(|(pid, mut service)| {
+                outputln!(preamble service.name(), "Stopping...");
+                thread::Builder::new()
+                    .name(format!("stop-{}", pid))
+                    .spawn(move || {
+                        let shutdown = service.args().shutdown.clone();
+                        let shutdown_method = service.kill(&shutdown);
+                        service.wait().ok();
+                        outputln!(preamble service.name(), "Shutdown OK: {}", shutdown_method);
+                    })
+                    .map_err(|err| warn!("Unable to stop service {}, {}", pid, err))
+                    .ok()
+            })
+            .collect();
+        for handle in stopping {
+            handle.join().ok();
         }
     }
 
     fn reap_services(&mut self) {
-        let mut dead: Vec<u32> = vec![];
-        for service in self.0.values_mut() {
+        self.take_returned();
+        let mut dead: Vec<(u32, protocol::ProcessExit)> = vec![];
+        for service in self.services.values_mut() {
             match service.try_wait() {
                 Ok(None) => (),
                 Ok(Some(code)) => {
@@ -400,20 +568,46 @@ impl ServiceTable {
                         service.id(),
                         code
                     );
-                    dead.push(service.id());
+                    let exit = protocol::ProcessExit {
+                        pid: service.id().into(),
+                        code: code.code(),
+                        signal: exit_signal(&code),
+                        exited_at: service::unix_time(),
+                    };
+                    dead.push((service.id(), exit));
                 }
                 Err(err) => {
                     warn!("Error waiting for child, {}, {}", service.id(), err);
-                    dead.push(service.id());
+                    let exit = protocol::ProcessExit {
+                        pid: service.id().into(),
+                        exited_at: service::unix_time(),
+                        ..Default::default()
+                    };
+                    dead.push((service.id(), exit));
                 }
             }
         }
-        for pid in dead {
-            self.0.remove(&pid);
+        for (pid, exit) in dead {
+            if let Some(service) = self.services.remove(&pid) {
+                let mut row = service.status();
+                row.running = false;
+                row.last_exit = Some(exit);
+                self.exited.insert(row.id.clone(), row);
+            }
         }
     }
 }
 
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    status.signal()
+}
+
+#[cfg(windows)]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
 ////////////////////////
 // Public Func
 //
@@ -459,9 +653,10 @@ fn dispatch(tx: &Sender, bytes: &[u8], services: &mut ServiceTable) {
         }
     };
     let func = match msg.message_id() {
-        "Restart" => handlers::RestartHandler::run,
+        "Restart" => handlers::RestartHandler::run_in_background,
+        "ServiceList" => handlers::ServiceListHandler::run,
         "Spawn" => handlers::SpawnHandler::run,
-        "Terminate" => handlers::TerminateHandler::run,
+        "Terminate" => handlers::TerminateHandler::run_in_background,
         unknown => {
             warn!("Received unknown message from Supervisor, {}", unknown);
             return;
@@ -598,20 +793,89 @@ fn spawn_supervisor(pipe: &str, args: &[String], clean: bool) -> Result<Child> {
         protocol::LAUNCHER_PIPE_ENV,
         pipe
     );
-    let child = command
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
+    // The Supervisor's output only passes through us when it is also forwarded to a log sink.
+    let forward = log_sink::is_configured();
+    let output = || {
+        if forward {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        }
+    };
+    let mut child = command
+        .stdout(output())
+        .stderr(output())
         .env(protocol::LAUNCHER_PIPE_ENV, pipe)
         .env(
             protocol::LAUNCHER_PID_ENV,
             process::current_pid().to_string(),
         )
+        .env(
+            protocol::LAUNCHER_PROTOCOL_VERSION_ENV,
+            protocol::PROTOCOL_VERSION.to_string(),
+        )
         .args(args)
         .spawn()
         .map_err(Error::SupSpawn)?;
+    if forward {
+        forward_supervisor_output(&mut child);
+    }
     Ok(child)
 }
 
+/// Pass the Supervisor's output through to our own, forwarding each line to the log sink.
+fn forward_supervisor_output(supervisor: &mut Child) {
+    let pid = supervisor.id();
+    if let Some(stdout) = supervisor.stdout.take() {
+        thread::Builder::new()
+            .name("sup-out".to_string())
+            .spawn(move || pipe_supervisor(stdout, io::stdout(), pid, Stream::Stdout))
+            .ok();
+    }
+    if let Some(stderr) = supervisor.stderr.take() {
+        thread::Builder::new()
+            .name("sup-err".to_string())
+            .spawn(move || pipe_supervisor(stderr, io::stderr(), pid, Stream::Stderr))
+            .ok();
+    }
+}
+
+fn pipe_supervisor<R, W>(from: R, mut to: W, pid: u32, stream: Stream)
+where
+    R: Read,
+    W: Write,
+{
+    let mut reader = BufReader::new(from);
+    let mut buffer = Vec::new();
+    let mut failed_reads = 0;
+    loop {
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => failed_reads = 0,
+            // Keep draining the pipe, so the Supervisor never blocks writing to it, unless reading
+            // keeps failing.
+            Err(err) => {
+                failed_reads += 1;
+                if failed_reads >= MAX_FAILED_SUP_OUTPUT_READS {
+                    error!("Unable to read Supervisor output, {}", err);
+                    break;
+                }
+                continue;
+            }
+        }
+        to.write_all(&buffer).and_then(|_| to.flush()).ok();
+        let line = String::from_utf8_lossy(&buffer);
+        log_sink::send(&Record {
+            identifier: SUPERVISOR_IDENTIFIER,
+            pid,
+            service_group: None,
+            stream,
+            message: line.trim_end_matches(|c| c == '\n' || c == '\r'),
+        });
+        buffer.clear();
+    }
+}
+
 /// Determines the most viable Supervisor binary to run and returns a `PathBuf` to it.
 ///
 /// Setting a filepath value to the `HAB_SUP_BINARY` env variable will force that binary to be used
@@ -630,3 +894,39 @@ fn supervisor_cmd() -> Result<PathBuf> {
         Err(_) => Err(Error::SupPackageNotFound),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads from `chunks` in turn, failing once where a chunk is `None`.
+    struct FlakyReader(Vec<Option<&'static [u8]>>);
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            match self.0.remove(0) {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    Ok(chunk.len())
+                }
+                None => Err(io::Error::new(io::ErrorKind::Other, "flaky")),
+            }
+        }
+    }
+
+    #[test]
+    fn supervisor_output_is_drained_past_invalid_utf8_and_read_errors() {
+        let from = FlakyReader(vec![
+            Some(b"one\n"),
+            Some(b"\xff\xfe\n"),
+            None,
+            Some(b"two\n"),
+        ]);
+        let mut to = Vec::new();
+        pipe_supervisor(from, &mut to, 1, Stream::Stdout);
+        assert_eq!(to, b"one\n\xff\xfe\ntwo\n".to_vec());
+    }
+}
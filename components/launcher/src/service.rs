@@ -16,45 +16,81 @@
 use std::process::{ChildStderr, ChildStdout, ExitStatus};
 use std::{
     fmt,
-    io::{self, BufRead, BufReader, Read, Write},
+    io::{self, Read, Write},
+    sync::{Arc, Mutex},
     thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use crate::protocol;
+use crate::{
+    log_sink::{self, Record},
+    protocol,
+    service_log::{LogFile, Stream},
+};
 #[cfg(windows)]
 use core::os::process::windows_child::{ChildStderr, ChildStdout, ExitStatus};
 
+use crate::sys::console::Attach;
 pub use crate::sys::service::*;
 
+/// Seconds a service is given to exit after being asked to before it is killed, unless the
+/// Supervisor asks for another timeout.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u32 = 8;
+/// How often a service being stopped is checked for having exited.
+pub const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 pub struct Service {
     args: protocol::Spawn,
     process: Process,
+    started_at: i64,
 }
 
 impl Service {
-    pub fn new(
+    /// Track a spawned service, relaying its output until it closes its output streams. A
+    /// service running under a pseudo-terminal has its output on `stdout` alone. Clients stay
+    /// able to attach to the service until then.
+    pub fn new<O, E>(
         spawn: protocol::Spawn,
         process: Process,
-        stdout: Option<ChildStdout>,
-        stderr: Option<ChildStderr>,
-    ) -> Self {
+        stdout: Option<O>,
+        stderr: Option<E>,
+        attach: Option<Arc<Attach>>,
+    ) -> Self
+    where
+        O: Read + Send + 'static,
+        E: Read + Send + 'static,
+    {
+        let log = spawn
+            .log
+            .clone()
+            .and_then(|config| match LogFile::open(&spawn.id, config) {
+                Ok(log) => Some(Arc::new(Mutex::new(log))),
+                Err(err) => {
+                    warn!("Unable to open log file for {}, {}", spawn.id, err);
+                    None
+                }
+            });
+        let pid = process.id();
         if let Some(stdout) = stdout {
             let id = spawn.id.to_string();
+            let log = log.clone();
+            let attach = attach.clone();
             thread::Builder::new()
                 .name(format!("{}-out", spawn.id))
-                .spawn(move || pipe_stdout(stdout, &id))
+                .spawn(move || pipe_output(stdout, &id, pid, Stream::Stdout, log, attach))
                 .ok();
         }
         if let Some(stderr) = stderr {
             let id = spawn.id.to_string();
             thread::Builder::new()
                 .name(format!("{}-err", spawn.id))
-                .spawn(move || pipe_stderr(stderr, &id))
+                .spawn(move || pipe_output(stderr, &id, pid, Stream::Stderr, log, attach))
                 .ok();
         }
         Service {
             args: spawn,
             process,
+            started_at: unix_time(),
         }
     }
 
@@ -66,16 +102,35 @@ impl Service {
         self.process.id()
     }
 
-    /// Attempt to gracefully terminate a proccess and then forcefully kill it after
-    /// 8 seconds if it has not terminated.
-    pub fn kill(&mut self) -> protocol::ShutdownMethod {
-        self.process.kill()
+    /// Attempt to gracefully terminate a proccess and then forcefully kill it if it has not
+    /// terminated within the timeout of `shutdown`.
+    pub fn kill(&mut self, shutdown: &protocol::ShutdownConfig) -> protocol::ShutdownMethod {
+        self.process.kill(shutdown)
     }
 
     pub fn name(&self) -> &str {
         &self.args.id
     }
 
+    /// When the service was spawned, in seconds since the Unix epoch.
+    pub fn started_at(&self) -> i64 {
+        self.started_at
+    }
+
+    /// The row describing this service's process in the Launcher's status.
+    pub fn status(&self) -> protocol::ProcessStatus {
+        protocol::ProcessStatus {
+            pid: self.id().into(),
+            id: self.args.id.clone(),
+            uid: self.args.svc_user_id,
+            gid: self.args.svc_group_id,
+            user: self.args.svc_user.clone(),
+            started_at: self.started_at,
+            running: true,
+            last_exit: None,
+        }
+    }
+
     pub fn take_args(self) -> protocol::Spawn {
         self.args
     }
@@ -95,30 +150,86 @@ impl fmt::Debug for Service {
     }
 }
 
-/// Consume output from a child process until EOF, then finish
-fn pipe_stdout<T>(out: T, id: &str)
-where
+/// The current time in seconds since the Unix epoch.
+pub fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Consume output from a child process until EOF, then finish. Output is sent to attached
+/// clients as soon as it is read, while it is logged a line at a time.
+fn pipe_output<T>(
+    mut out: T,
+    id: &str,
+    pid: u32,
+    stream: Stream,
+    log: Option<Arc<Mutex<LogFile>>>,
+    attach: Option<Arc<Attach>>,
+) where
     T: Read,
 {
-    let mut reader = BufReader::new(out);
-    let mut buffer = String::new();
-    while reader.read_line(&mut buffer).unwrap() > 0 {
-        let line = output_format!(preamble &id, logkey "O", buffer);
-        writeln!(&mut io::stdout(), "{}", line).expect("unable to write to stdout");
-        buffer.clear();
+    let mut chunk = [0; 4096];
+    let mut buffer = Vec::new();
+    loop {
+        let read = match out.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            // Reading a pseudo-terminal fails, rather than returning EOF, once the service exits.
+            Err(_) => break,
+        };
+        if let Some(ref attach) = attach {
+            attach.send(&chunk[..read]);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let rest = buffer.split_off(end + 1);
+            let line = String::from_utf8_lossy(&buffer);
+            relay_line(log.as_ref(), id, pid, stream, &line);
+            buffer = rest;
+        }
+    }
+    if !buffer.is_empty() {
+        let line = String::from_utf8_lossy(&buffer);
+        relay_line(log.as_ref(), id, pid, stream, &line);
     }
 }
 
-/// Consume standard error from a child process until EOF, then finish
-fn pipe_stderr<T>(err: T, id: &str)
-where
-    T: Read,
-{
-    let mut reader = BufReader::new(err);
-    let mut buffer = String::new();
-    while reader.read_line(&mut buffer).unwrap() > 0 {
-        let line = output_format!(preamble &id, logkey "E", buffer);
-        writeln!(&mut io::stderr(), "{}", line).expect("unable to write to stderr");
-        buffer.clear();
+/// Write a line read from the service to the Launcher's own stdout or stderr, and record it.
+fn relay_line(log: Option<&Arc<Mutex<LogFile>>>, id: &str, pid: u32, stream: Stream, line: &str) {
+    record_line(log, id, pid, stream, line);
+    match stream {
+        Stream::Stdout => {
+            let line = output_format!(preamble &id, logkey "O", line);
+            writeln!(&mut io::stdout(), "{}", line).expect("unable to write to stdout");
+        }
+        Stream::Stderr => {
+            let line = output_format!(preamble &id, logkey "E", line);
+            writeln!(&mut io::stderr(), "{}", line).expect("unable to write to stderr");
+        }
+    }
+}
+
+/// Append a line read from the service to its log file, if it has one, and forward it to the log
+/// sink, if one is configured.
+fn record_line(log: Option<&Arc<Mutex<LogFile>>>, id: &str, pid: u32, stream: Stream, line: &str) {
+    let line = line.trim_end_matches(|c| c == '\n' || c == '\r');
+    if let Some(log) = log {
+        if let Err(err) = log
+            .lock()
+            .expect("LogFile lock is poisoned")
+            .write_line(stream, line)
+        {
+            warn!("Unable to write to log file for {}, {}", id, err);
+        }
     }
+    log_sink::send(&Record {
+        identifier: id.split('.').next().unwrap_or(id),
+        pid,
+        service_group: Some(id),
+        stream,
+        message: line,
+    });
 }
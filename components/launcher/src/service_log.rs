@@ -0,0 +1,239 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-service log files. Each line a service writes to stdout or stderr is appended to its log
+//! file, as well as being passed through to the Launcher's own output.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use time;
+
+use crate::protocol::{LogFormat, ServiceLog};
+
+/// The stream of the service a line was read from.
+#[derive(Clone, Copy, Debug)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+pub struct LogFile {
+    config: ServiceLog,
+    path: PathBuf,
+    service_group: String,
+    file: File,
+    size: u64,
+    opened: Instant,
+}
+
+impl LogFile {
+    /// Open, or create, the log file of the service in `service_group` for appending.
+    pub fn open(service_group: &str, config: ServiceLog) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            config,
+            path,
+            service_group: service_group.to_string(),
+            file,
+            size,
+            opened: Instant::now(),
+        })
+    }
+
+    /// Append a line, without its line ending, rotating the log first if it is due.
+    pub fn write_line(&mut self, stream: Stream, line: &str) -> io::Result<()> {
+        if self.rotation_due() {
+            self.rotate()?;
+        }
+        let entry = match self.config.format {
+            LogFormat::Text => format!("{}\n", line),
+            LogFormat::Json => format!(
+                "{{\"timestamp\":\"{}\",\"stream\":\"{}\",\"service_group\":{},\"message\":{}}}\n",
+                time::now_utc().rfc3339(),
+                stream.name(),
+                json_string(&self.service_group),
+                json_string(line)
+            ),
+        };
+        self.file.write_all(entry.as_bytes())?;
+        self.size += entry.len() as u64;
+        Ok(())
+    }
+
+    fn rotation_due(&self) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_large = self.config.max_size.map_or(false, |max| self.size >= max);
+        let too_old = self.config.max_age.map_or(false, |max| {
+            self.opened.elapsed() >= Duration::from_secs(max)
+        });
+        too_large || too_old
+    }
+
+    /// Shift each rotated log along by one, dropping the oldest, and start a new log.
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..self.config.retain).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        if self.config.retain > 0 {
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        } else {
+            fs::remove_file(&self.path)?;
+        }
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut rotated = path.as_os_str().to_os_string();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+/// Quote and escape `value` as a JSON string.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn log_file(dir: &TempDir, format: LogFormat, max_size: Option<u64>, retain: u32) -> LogFile {
+        let config = ServiceLog {
+            path: dir.path().join("redis.log").to_string_lossy().into_owned(),
+            format,
+            max_size,
+            max_age: None,
+            retain,
+        };
+        LogFile::open("redis.default", config).unwrap()
+    }
+
+    fn content(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rotates_once_max_size_is_reached_and_retains_the_newest_logs() {
+        let dir = TempDir::new().unwrap();
+        let mut log = log_file(&dir, LogFormat::Text, Some(6), 2);
+        for line in &["first", "second", "third", "fourth"] {
+            log.write_line(Stream::Stdout, line).unwrap();
+        }
+
+        let path = dir.path().join("redis.log");
+        assert_eq!(content(&path), "fourth\n");
+        assert_eq!(content(&rotated_path(&path, 1)), "third\n");
+        assert_eq!(content(&rotated_path(&path, 2)), "second\n");
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn rotating_without_retention_starts_a_new_log() {
+        let dir = TempDir::new().unwrap();
+        let mut log = log_file(&dir, LogFormat::Text, Some(1), 0);
+        log.write_line(Stream::Stdout, "first").unwrap();
+        log.write_line(Stream::Stderr, "second").unwrap();
+
+        let path = dir.path().join("redis.log");
+        assert_eq!(content(&path), "second\n");
+        assert!(!rotated_path(&path, 1).exists());
+    }
+
+    #[test]
+    fn appends_to_an_existing_log_without_rotating_it() {
+        let dir = TempDir::new().unwrap();
+        log_file(&dir, LogFormat::Text, Some(100), 1)
+            .write_line(Stream::Stdout, "before")
+            .unwrap();
+        log_file(&dir, LogFormat::Text, Some(100), 1)
+            .write_line(Stream::Stdout, "after")
+            .unwrap();
+
+        let path = dir.path().join("redis.log");
+        assert_eq!(content(&path), "before\nafter\n");
+        assert!(!rotated_path(&path, 1).exists());
+    }
+
+    #[test]
+    fn json_lines_name_the_stream_and_service_group() {
+        let dir = TempDir::new().unwrap();
+        let mut log = log_file(&dir, LogFormat::Json, None, 0);
+        log.write_line(Stream::Stderr, "said \"hi\"").unwrap();
+
+        let line = content(&dir.path().join("redis.log"));
+        assert!(line.starts_with("{\"timestamp\":\""));
+        assert!(line.contains(r#""stream":"stderr","service_group":"redis.default","#));
+        assert!(line.ends_with("\"message\":\"said \\\"hi\\\"\"}\n"));
+    }
+
+    #[test]
+    fn json_string_escapes_quotes_backslashes_and_control_characters() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("a \"b\" c"), "\"a \\\"b\\\" c\"");
+        assert_eq!(json_string("C:\\hab"), "\"C:\\\\hab\"");
+        assert_eq!(json_string("tab\there\r\n"), "\"tab\\there\\r\\n\"");
+        assert_eq!(json_string("bell\u{7}"), "\"bell\\u0007\"");
+        assert_eq!(
+            json_string("caf\u{e9} \u{1f600}"),
+            "\"caf\u{e9} \u{1f600}\""
+        );
+    }
+}
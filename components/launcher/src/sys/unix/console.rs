@@ -0,0 +1,265 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consoles of services which are not detached: their pseudo-terminal, and the socket clients
+//! attach to them through.
+//!
+//! Each client connected to a service's attach socket is sent everything the service writes, and
+//! the input frames it sends are written to the service's standard input, or its pseudo-terminal.
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+        io::{AsRawFd, FromRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use libc;
+
+use crate::protocol::AttachFrame;
+
+/// Clients which don't read the service's output within this long are disconnected, rather than
+/// leaving the service blocked on writing it.
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+type Input = Arc<Mutex<Box<dyn Write + Send>>>;
+
+pub struct Attach {
+    path: PathBuf,
+    clients: Mutex<Vec<UnixStream>>,
+}
+
+impl Attach {
+    /// Listen for clients attaching to the service `id` on the socket at `path`. Their input is
+    /// written to `input`, and resizes of their terminal applied to `terminal`, if the service
+    /// has one. Only the Launcher's user may connect to the socket, and its directory must be
+    /// owned by the Launcher's user so no one else can replace it.
+    pub fn listen(
+        id: &str,
+        path: &Path,
+        input: Box<dyn Write + Send>,
+        terminal: Option<File>,
+    ) -> io::Result<Arc<Self>> {
+        if let Some(dir) = path.parent() {
+            create_private_dir(dir)?;
+        }
+        match fs::remove_file(path) {
+            Ok(()) => (),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+        // The private directory already keeps anyone but the Launcher's user from reaching the
+        // socket. The socket itself is restricted to that user too, before any client is accepted.
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        let attach = Arc::new(Attach {
+            path: path.to_path_buf(),
+            clients: Mutex::new(vec![]),
+        });
+        let weak = Arc::downgrade(&attach);
+        let input = Arc::new(Mutex::new(input));
+        let terminal = Arc::new(terminal);
+        let name = format!("{}-attach", id);
+        thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || accept(&listener, &name, &weak, &input, &terminal))?;
+        Ok(attach)
+    }
+
+    /// Send output of the service to every attached client.
+    pub fn send(&self, data: &[u8]) {
+        self.clients
+            .lock()
+            .expect("Attach clients lock is poisoned")
+            .retain(|mut client| client.write_all(data).is_ok());
+    }
+}
+
+impl Drop for Attach {
+    fn drop(&mut self) {
+        // Wake the thread accepting clients, which notices the service is gone and exits.
+        UnixStream::connect(&self.path).ok();
+        for client in self
+            .clients
+            .lock()
+            .expect("Attach clients lock is poisoned")
+            .iter()
+        {
+            client.shutdown(std::net::Shutdown::Both).ok();
+        }
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Create `dir` accessible only to the Launcher's user, or check that it already is.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != unsafe { libc::geteuid() } {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a directory owned by the Launcher's user",
+                dir.display()
+            ),
+        ));
+    }
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+}
+
+/// Open a pseudo-terminal, returning its master and slave ends. Neither is inherited by the
+/// processes the Launcher spawns, except as the standard streams they are given.
+pub fn open_pty() -> io::Result<(File, File)> {
+    let mut master: libc::c_int = 0;
+    let mut slave: libc::c_int = 0;
+    let mut size = libc::winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut size,
+        )
+    } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    set_cloexec(master.as_raw_fd())?;
+    set_cloexec(slave.as_raw_fd())?;
+    Ok((master, slave))
+}
+
+/// Make the pseudo-terminal on the standard input of the calling process its controlling
+/// terminal, in a new session. Called in the child before it execs the service.
+pub fn set_controlling_terminal() -> io::Result<()> {
+    unsafe {
+        if libc::setsid() < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY as _, 0) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn accept(
+    listener: &UnixListener,
+    name: &str,
+    attach: &Weak<Attach>,
+    input: &Input,
+    terminal: &Arc<Option<File>>,
+) {
+    for stream in listener.incoming() {
+        let attach = match attach.upgrade() {
+            Some(attach) => attach,
+            None => break,
+        };
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Unable to accept client for {}, {}", name, err);
+                continue;
+            }
+        };
+        let output = match stream.try_clone().and_then(|output| {
+            output.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+            Ok(output)
+        }) {
+            Ok(output) => output,
+            Err(err) => {
+                warn!("Unable to attach client to {}, {}", name, err);
+                continue;
+            }
+        };
+        let input = input.clone();
+        let terminal = terminal.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("{}-in", name))
+            .spawn(move || relay_input(stream, &input, &terminal));
+        match spawned {
+            Ok(_) => attach
+                .clients
+                .lock()
+                .expect("Attach clients lock is poisoned")
+                .push(output),
+            Err(err) => warn!("Unable to attach client to {}, {}", name, err),
+        }
+    }
+}
+
+/// Apply the frames sent by an attached client until it detaches.
+fn relay_input(mut stream: UnixStream, input: &Input, terminal: &Option<File>) {
+    loop {
+        match AttachFrame::read_from(&mut stream) {
+            Ok(Some(AttachFrame::Input(data))) => {
+                let mut input = input.lock().expect("Attach input lock is poisoned");
+                if let Err(err) = input.write_all(&data).and_then(|()| input.flush()) {
+                    debug!("Unable to write attached client's input, {}", err);
+                    return;
+                }
+            }
+            Ok(Some(AttachFrame::Resize { rows, cols })) => {
+                if let Some(ref terminal) = *terminal {
+                    if let Err(err) = resize(terminal.as_raw_fd(), rows, cols) {
+                        debug!("Unable to resize terminal, {}", err);
+                    }
+                }
+            }
+            Ok(None) => return,
+            Err(err) => {
+                debug!("Dropping attached client, {}", err);
+                return;
+            }
+        }
+    }
+}
+
+fn resize(fd: RawFd, rows: u16, cols: u16) -> io::Result<()> {
+    let size = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if unsafe { libc::ioctl(fd, libc::TIOCSWINSZ as _, &size) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn set_cloexec(fd: RawFd) -> io::Result<()> {
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,293 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resource limits for spawned services.
+//!
+//! Memory, CPU and process count limits are enforced by placing each service into its own cgroup
+//! in the cgroup v2 hierarchy, beneath `habitat.slice` in the cgroup the Launcher was started in.
+//! Controllers can only be handed down to child cgroups of a cgroup without processes of its own,
+//! so the Launcher first moves every process of its cgroup into a `launcher` leaf. When the
+//! Launcher runs under systemd, its unit needs `Delegate=yes`. Process rlimits are applied with
+//! `setrlimit(2)`. Both are applied by the forked child before it drops privileges and execs the
+//! service, so that the limits are inherited by everything the service goes on to spawn.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use libc;
+
+use crate::{
+    error::{Error, Result},
+    protocol::{ResourceLimits, RLIMIT_NAMES},
+};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const CGROUP_SLICE: &str = "habitat.slice";
+/// The leaf cgroup the Launcher moves its own processes into.
+const CGROUP_LEAF: &str = "launcher";
+/// The period, in microseconds, over which a service's CPU quota is measured.
+const CPU_PERIOD: u64 = 100_000;
+
+lazy_static! {
+    /// The cgroup the Launcher was started in, found the first time a service needs a cgroup.
+    static ref CGROUP_BASE: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+pub struct Limits {
+    /// The service's cgroup, if any cgroup limits were requested.
+    cgroup: Option<PathBuf>,
+    /// The `cgroup.procs` file of the service's cgroup, opened by the Launcher so that the child
+    /// can join the cgroup without needing to resolve any paths after forking.
+    procs: Option<File>,
+    rlimits: Vec<(libc::c_int, u64)>,
+}
+
+impl Limits {
+    /// Prepare the limits for the service identified by `id`, creating and configuring its cgroup
+    /// if any cgroup limits were requested.
+    pub fn new(id: &str, limits: &ResourceLimits) -> Result<Self> {
+        let mut rlimits = vec![];
+        for (name, value) in limits.rlimits.iter() {
+            let resource = resource(name).ok_or_else(|| {
+                Error::ResourceLimits(format!(
+                    "unknown rlimit '{}', expected one of: {}",
+                    name,
+                    RLIMIT_NAMES.join(", ")
+                ))
+            })?;
+            rlimits.push((resource, u64::from(*value)));
+        }
+        if limits.cpu_quota == Some(0) {
+            return Err(Error::ResourceLimits(String::from(
+                "a CPU quota must be at least 1 percent",
+            )));
+        }
+        let (cgroup, procs) = if limits.cgroup_unlimited() {
+            (None, None)
+        } else {
+            let (cgroup, procs) = create_cgroup(id, limits)?;
+            (Some(cgroup), Some(procs))
+        };
+        Ok(Limits {
+            cgroup,
+            procs,
+            rlimits,
+        })
+    }
+
+    /// The service's cgroup, which is left for the Launcher to remove once the service is reaped.
+    pub fn cgroup(&self) -> Option<&Path> {
+        self.cgroup.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Move the calling process into the service's cgroup and apply its rlimits. This is called
+    /// in the child after fork and so must not allocate.
+    pub fn apply(&self) -> io::Result<()> {
+        if let Some(mut procs) = self.procs.as_ref() {
+            procs.write_all(b"0")?;
+        }
+        for (resource, value) in self.rlimits.iter() {
+            setrlimit(*resource, *value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove a service's cgroup. This fails while any process the service started is still running,
+/// in which case the cgroup is left behind and reused if the service is spawned again.
+pub fn remove_cgroup(cgroup: &Path) {
+    if let Err(err) = fs::remove_dir(cgroup) {
+        debug!("Unable to remove cgroup {}, {}", cgroup.display(), err);
+    }
+}
+
+/// Create the service's cgroup, write its limits, and return it along with its opened
+/// `cgroup.procs` file.
+fn create_cgroup(id: &str, limits: &ResourceLimits) -> Result<(PathBuf, File)> {
+    let base = cgroup_base()?;
+    let slice = base.join(CGROUP_SLICE);
+    let cgroup = slice.join(id);
+    fs::create_dir_all(&cgroup).map_err(|e| cgroup_error(&cgroup, &e))?;
+
+    let mut controllers = vec![];
+    if limits.memory_max.is_some() {
+        controllers.push("memory");
+    }
+    if limits.cpu_weight.is_some() || limits.cpu_quota.is_some() {
+        controllers.push("cpu");
+    }
+    if limits.pids_max.is_some() {
+        controllers.push("pids");
+    }
+    for controller in controllers {
+        enable_controller(&base, controller)?;
+        enable_controller(&slice, controller)?;
+    }
+
+    // Limits which were not requested are reset, as the cgroup may be left over from a previous
+    // run of the service with different limits.
+    write_control(
+        &cgroup,
+        "memory.max",
+        limits.memory_max.map(|v| v.to_string()),
+        "max",
+    )?;
+    write_control(
+        &cgroup,
+        "cpu.weight",
+        limits.cpu_weight.map(|v| v.to_string()),
+        "100",
+    )?;
+    write_control(
+        &cgroup,
+        "cpu.max",
+        limits
+            .cpu_quota
+            .map(|v| format!("{} {}", u64::from(v) * CPU_PERIOD / 100, CPU_PERIOD)),
+        "max",
+    )?;
+    write_control(
+        &cgroup,
+        "pids.max",
+        limits.pids_max.map(|v| v.to_string()),
+        "max",
+    )?;
+
+    let procs = cgroup.join("cgroup.procs");
+    let file = OpenOptions::new()
+        .write(true)
+        .open(&procs)
+        .map_err(|e| cgroup_error(&procs, &e))?;
+    Ok((cgroup, file))
+}
+
+/// The cgroup the Launcher was started in, which services' cgroups are created beneath. Unless
+/// it is the root cgroup, the Launcher's processes are moved out of it into a leaf the first
+/// time it is needed.
+fn cgroup_base() -> Result<PathBuf> {
+    let mut base = CGROUP_BASE.lock().expect("CGROUP_BASE lock is poisoned");
+    if let Some(ref base) = *base {
+        return Ok(base.clone());
+    }
+    let root = Path::new(CGROUP_ROOT);
+    if !root.join("cgroup.controllers").is_file() {
+        return Err(Error::ResourceLimits(format!(
+            "memory, cpu and pids limits require a cgroup v2 hierarchy mounted at {}",
+            CGROUP_ROOT
+        )));
+    }
+    let own = own_cgroup()?;
+    let path = root.join(own.trim_start_matches('/'));
+    if path != root {
+        let leaf = path.join(CGROUP_LEAF);
+        fs::create_dir_all(&leaf).map_err(|e| cgroup_error(&leaf, &e))?;
+        let procs = path.join("cgroup.procs");
+        let pids = fs::read_to_string(&procs).map_err(|e| cgroup_error(&procs, &e))?;
+        let leaf_procs = leaf.join("cgroup.procs");
+        for pid in pids.split_whitespace() {
+            // A process may exit before it is moved.
+            if let Err(err) = fs::write(&leaf_procs, pid) {
+                debug!("Unable to move {} to {}, {}", pid, leaf.display(), err);
+            }
+        }
+    }
+    *base = Some(path.clone());
+    Ok(path)
+}
+
+/// The path of the calling process's cgroup in the cgroup v2 hierarchy, from `/proc/self/cgroup`.
+fn own_cgroup() -> Result<String> {
+    let cgroups = fs::read_to_string("/proc/self/cgroup")
+        .map_err(|e| cgroup_error(Path::new("/proc/self/cgroup"), &e))?;
+    cgroups
+        .lines()
+        .find(|line| line.starts_with("0::"))
+        .map(|line| line["0::".len()..].to_string())
+        .ok_or_else(|| {
+            Error::ResourceLimits(String::from("the Launcher is not in a cgroup v2 hierarchy"))
+        })
+}
+
+/// Make `controller` available to the children of the cgroup at `path`.
+fn enable_controller(path: &Path, controller: &str) -> Result<()> {
+    let available = path.join("cgroup.controllers");
+    let available = fs::read_to_string(&available).map_err(|e| cgroup_error(&available, &e))?;
+    if !available.split_whitespace().any(|c| c == controller) {
+        return Err(Error::ResourceLimits(format!(
+            "the {} controller is not delegated to cgroup {}",
+            controller,
+            path.display()
+        )));
+    }
+    let control = path.join("cgroup.subtree_control");
+    let enabled = fs::read_to_string(&control).map_err(|e| cgroup_error(&control, &e))?;
+    if enabled.split_whitespace().any(|c| c == controller) {
+        return Ok(());
+    }
+    fs::write(&control, format!("+{}", controller)).map_err(|e| cgroup_error(&control, &e))
+}
+
+/// Write `value` to the control file `name` of the cgroup at `path`. When no value is given the
+/// control is reset to `default`, if the file exists.
+fn write_control(path: &Path, name: &str, value: Option<String>, default: &str) -> Result<()> {
+    let control = path.join(name);
+    let value = match value {
+        Some(value) => value,
+        None if control.exists() => default.to_string(),
+        None => return Ok(()),
+    };
+    fs::write(&control, value).map_err(|e| cgroup_error(&control, &e))
+}
+
+fn cgroup_error(path: &Path, err: &io::Error) -> Error {
+    Error::ResourceLimits(format!(
+        "unable to configure cgroup {}, {}",
+        path.display(),
+        err
+    ))
+}
+
+/// The resource of the rlimit called `name`, one of `RLIMIT_NAMES`.
+fn resource(name: &str) -> Option<libc::c_int> {
+    let resource = match name {
+        "as" => libc::RLIMIT_AS,
+        "core" => libc::RLIMIT_CORE,
+        "cpu" => libc::RLIMIT_CPU,
+        "data" => libc::RLIMIT_DATA,
+        "fsize" => libc::RLIMIT_FSIZE,
+        "memlock" => libc::RLIMIT_MEMLOCK,
+        "nofile" => libc::RLIMIT_NOFILE,
+        "nproc" => libc::RLIMIT_NPROC,
+        "stack" => libc::RLIMIT_STACK,
+        _ => return None,
+    };
+    Some(resource as libc::c_int)
+}
+
+/// Set both the soft and hard limit of `resource`. A value of `RLIM_INFINITY` leaves the resource
+/// unlimited.
+fn setrlimit(resource: libc::c_int, value: u64) -> io::Result<()> {
+    let rlim = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource as _, &rlim) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -12,4 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod console;
+pub mod limits;
+pub mod sandbox;
 pub mod service;
@@ -0,0 +1,374 @@
+// Copyright (c) 2019 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Opt-in hardening for spawned services.
+//!
+//! Mounts and the capability bounding set need the Launcher's privileges and so are applied by
+//! `Sandbox::enter` before the child switches to the service's user. `no_new_privs` and the
+//! seccomp filter are applied by `Sandbox::restrict` afterwards, so that the filter does not have
+//! to allow the calls made while dropping privileges.
+//!
+//! When dropping capabilities, the inheritable and ambient sets are cleared of everything but the
+//! kept capabilities as well, as they could otherwise pass on capabilities outside the bounding
+//! set. A service running as root gets the kept capabilities back from the bounding set when it
+//! execs. A service running as another user would get none, so the kept capabilities are carried
+//! across the switch to its user and raised in its ambient set, which it keeps across exec.
+
+use std::{ffi::CString, fs, io, ptr};
+
+use libc;
+
+use crate::{
+    error::{Error, Result},
+    protocol,
+};
+
+/// The capabilities known to the Launcher, indexed by their number.
+const CAPABILITIES: &[&str] = &[
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "DAC_READ_SEARCH",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "SETGID",
+    "SETUID",
+    "SETPCAP",
+    "LINUX_IMMUTABLE",
+    "NET_BIND_SERVICE",
+    "NET_BROADCAST",
+    "NET_ADMIN",
+    "NET_RAW",
+    "IPC_LOCK",
+    "IPC_OWNER",
+    "SYS_MODULE",
+    "SYS_RAWIO",
+    "SYS_CHROOT",
+    "SYS_PTRACE",
+    "SYS_PACCT",
+    "SYS_ADMIN",
+    "SYS_BOOT",
+    "SYS_NICE",
+    "SYS_RESOURCE",
+    "SYS_TIME",
+    "SYS_TTY_CONFIG",
+    "MKNOD",
+    "LEASE",
+    "AUDIT_WRITE",
+    "AUDIT_CONTROL",
+    "SETFCAP",
+    "MAC_OVERRIDE",
+    "MAC_ADMIN",
+    "SYSLOG",
+    "WAKE_ALARM",
+    "BLOCK_SUSPEND",
+    "AUDIT_READ",
+    "PERFMON",
+    "BPF",
+    "CHECKPOINT_RESTORE",
+];
+/// `prctl` options for the ambient capability set, which the `libc` crate doesn't define.
+const PR_CAP_AMBIENT: libc::c_int = 47;
+const PR_CAP_AMBIENT_RAISE: libc::c_ulong = 2;
+const PR_CAP_AMBIENT_CLEAR_ALL: libc::c_ulong = 4;
+/// The version of the `capget(2)` and `capset(2)` interface with 64 bit capability sets.
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+/// The largest number of instructions the kernel accepts in a BPF program.
+const BPF_MAXINSNS: usize = 4096;
+/// The size of a single BPF instruction, a `struct sock_filter`.
+const BPF_INSN_SIZE: usize = 8;
+
+/// A `struct sock_fprog`. The filter is left as bytes; the kernel copies the instructions out of
+/// our memory itself, so they need no particular alignment here.
+#[repr(C)]
+struct SockFprog {
+    len: libc::c_ushort,
+    filter: *const u8,
+}
+
+/// A `struct __user_cap_header_struct`.
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+/// A `struct __user_cap_data_struct`, which holds 32 capabilities of each set.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// The capability sets of the calling thread, as masks indexed by capability number.
+#[derive(Clone, Copy, Default)]
+struct Capabilities {
+    effective: u64,
+    permitted: u64,
+    inheritable: u64,
+}
+
+impl Capabilities {
+    fn get() -> io::Result<Self> {
+        let mut header = CapUserHeader {
+            version: LINUX_CAPABILITY_VERSION_3,
+            pid: 0,
+        };
+        let mut data = [CapUserData::default(); 2];
+        if unsafe { libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let join = |low: u32, high: u32| u64::from(low) | u64::from(high) << 32;
+        Ok(Capabilities {
+            effective: join(data[0].effective, data[1].effective),
+            permitted: join(data[0].permitted, data[1].permitted),
+            inheritable: join(data[0].inheritable, data[1].inheritable),
+        })
+    }
+
+    fn set(&self) -> io::Result<()> {
+        let mut header = CapUserHeader {
+            version: LINUX_CAPABILITY_VERSION_3,
+            pid: 0,
+        };
+        let data = [
+            CapUserData {
+                effective: self.effective as u32,
+                permitted: self.permitted as u32,
+                inheritable: self.inheritable as u32,
+            },
+            CapUserData {
+                effective: (self.effective >> 32) as u32,
+                permitted: (self.permitted >> 32) as u32,
+                inheritable: (self.inheritable >> 32) as u32,
+            },
+        ];
+        if unsafe { libc::syscall(libc::SYS_capset, &mut header, data.as_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+pub struct Sandbox {
+    /// A mask of the capabilities kept in the bounding set, when dropping capabilities.
+    keep_capabilities: Option<u64>,
+    no_new_privs: bool,
+    private_tmp: bool,
+    read_only_paths: Vec<CString>,
+    seccomp_filter: Option<Vec<u8>>,
+}
+
+impl Sandbox {
+    /// Validate the requested hardening and prepare everything the child will need, so that it
+    /// has nothing left to allocate or look up after forking.
+    pub fn new(sandbox: &protocol::Sandbox) -> Result<Self> {
+        let keep_capabilities = if sandbox.drop_capabilities {
+            let mut mask = 0u64;
+            for name in sandbox.capabilities.iter() {
+                let cap = capability_number(name)
+                    .ok_or_else(|| Error::Sandbox(format!("unknown capability '{}'", name)))?;
+                mask |= 1 << cap;
+            }
+            Some(mask)
+        } else {
+            None
+        };
+        let read_only_paths = sandbox
+            .read_only_paths
+            .iter()
+            .map(|path| {
+                CString::new(path.as_str()).map_err(|_| {
+                    Error::Sandbox(format!("read-only path '{}' contains a NUL byte", path))
+                })
+            })
+            .collect::<Result<_>>()?;
+        let seccomp_filter = match sandbox.seccomp_profile {
+            Some(ref path) => Some(read_seccomp_filter(path)?),
+            None => None,
+        };
+        Ok(Sandbox {
+            keep_capabilities,
+            no_new_privs: sandbox.no_new_privs,
+            private_tmp: sandbox.private_tmp,
+            read_only_paths,
+            seccomp_filter,
+        })
+    }
+
+    /// Set up the service's mount namespace and drop capabilities from its bounding, inheritable
+    /// and ambient sets. Called in the child after fork, while it still has the Launcher's
+    /// privileges.
+    pub fn enter(&self) -> io::Result<()> {
+        if self.private_tmp || !self.read_only_paths.is_empty() {
+            unsafe {
+                cvt(libc::unshare(libc::CLONE_NEWNS))?;
+                // Keep the service's mounts from propagating back to the host's namespace.
+                cvt(libc::mount(
+                    ptr::null(),
+                    c_str(b"/\0"),
+                    ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    ptr::null(),
+                ))?;
+                if self.private_tmp {
+                    cvt(libc::mount(
+                        c_str(b"tmpfs\0"),
+                        c_str(b"/tmp\0"),
+                        c_str(b"tmpfs\0"),
+                        libc::MS_NOSUID | libc::MS_NODEV,
+                        c_str(b"mode=1777\0") as *const libc::c_void,
+                    ))?;
+                }
+                for path in self.read_only_paths.iter() {
+                    cvt(libc::mount(
+                        path.as_ptr(),
+                        path.as_ptr(),
+                        ptr::null(),
+                        libc::MS_BIND | libc::MS_REC,
+                        ptr::null(),
+                    ))?;
+                    cvt(libc::mount(
+                        ptr::null(),
+                        path.as_ptr(),
+                        ptr::null(),
+                        libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY,
+                        ptr::null(),
+                    ))?;
+                }
+            }
+        }
+        if let Some(keep) = self.keep_capabilities {
+            let zero: libc::c_ulong = 0;
+            let last: libc::c_ulong = 63;
+            for cap in 0..=last {
+                if keep & (1 << cap) != 0 {
+                    continue;
+                }
+                if unsafe { libc::prctl(libc::PR_CAPBSET_DROP, cap, zero, zero, zero) } != 0 {
+                    let err = io::Error::last_os_error();
+                    // The kernel rejects capabilities beyond the last one it knows about.
+                    if err.raw_os_error() == Some(libc::EINVAL) {
+                        break;
+                    }
+                    return Err(err);
+                }
+            }
+            let zero: libc::c_ulong = 0;
+            if unsafe { libc::prctl(PR_CAP_AMBIENT, PR_CAP_AMBIENT_CLEAR_ALL, zero, zero, zero) }
+                != 0
+            {
+                let err = io::Error::last_os_error();
+                // Kernels older than 4.3 have no ambient set to clear.
+                if err.raw_os_error() != Some(libc::EINVAL) {
+                    return Err(err);
+                }
+            }
+            let mut caps = Capabilities::get()?;
+            caps.inheritable = caps.permitted & keep;
+            caps.set()?;
+            // Keep the permitted set when switching to the service's user, so that `restrict`
+            // can hand the kept capabilities to a service which doesn't run as root.
+            let on: libc::c_ulong = 1;
+            cvt(unsafe { libc::prctl(libc::PR_SET_KEEPCAPS, on, zero, zero, zero) })?;
+        }
+        Ok(())
+    }
+
+    /// Grant the kept capabilities to a service which doesn't run as root, then set
+    /// `no_new_privs` and install the seccomp filter. Called in the child after it has switched
+    /// to the service's user, immediately before exec.
+    pub fn restrict(&self) -> io::Result<()> {
+        if let Some(keep) = self.keep_capabilities {
+            if unsafe { libc::getuid() } != 0 {
+                let kept = Capabilities::get()?.permitted & keep;
+                Capabilities {
+                    effective: kept,
+                    permitted: kept,
+                    inheritable: kept,
+                }
+                .set()?;
+                let zero: libc::c_ulong = 0;
+                for cap in 0..64 {
+                    if kept & (1 << cap) == 0 {
+                        continue;
+                    }
+                    cvt(unsafe {
+                        libc::prctl(PR_CAP_AMBIENT, PR_CAP_AMBIENT_RAISE, cap, zero, zero)
+                    })?;
+                }
+            }
+        }
+        // The kernel refuses a seccomp filter from an unprivileged process without no_new_privs.
+        if self.no_new_privs || self.seccomp_filter.is_some() {
+            let on: libc::c_ulong = 1;
+            let zero: libc::c_ulong = 0;
+            cvt(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, on, zero, zero, zero) })?;
+        }
+        if let Some(ref filter) = self.seccomp_filter {
+            let prog = SockFprog {
+                len: (filter.len() / BPF_INSN_SIZE) as libc::c_ushort,
+                filter: filter.as_ptr(),
+            };
+            let zero: libc::c_ulong = 0;
+            cvt(unsafe {
+                libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    libc::c_ulong::from(libc::SECCOMP_MODE_FILTER),
+                    &prog as *const SockFprog,
+                    zero,
+                    zero,
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// The number of a capability given by name, with or without the `CAP_` prefix.
+fn capability_number(name: &str) -> Option<usize> {
+    let name = name.to_uppercase();
+    let name = name.trim_start_matches("CAP_");
+    CAPABILITIES.iter().position(|cap| *cap == name)
+}
+
+/// Read a compiled seccomp BPF filter, as written by `seccomp_export_bpf(3)`.
+fn read_seccomp_filter(path: &str) -> Result<Vec<u8>> {
+    let filter = fs::read(path)
+        .map_err(|e| Error::Sandbox(format!("unable to read seccomp profile {}, {}", path, e)))?;
+    if filter.is_empty()
+        || filter.len() % BPF_INSN_SIZE != 0
+        || filter.len() / BPF_INSN_SIZE > BPF_MAXINSNS
+    {
+        return Err(Error::Sandbox(format!(
+            "seccomp profile {} is not a compiled BPF program of at most {} instructions",
+            path, BPF_MAXINSNS
+        )));
+    }
+    Ok(filter)
+}
+
+fn c_str(bytes: &'static [u8]) -> *const libc::c_char {
+    bytes.as_ptr() as *const libc::c_char
+}
+
+fn cvt(rc: libc::c_int) -> io::Result<()> {
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
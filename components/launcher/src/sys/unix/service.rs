@@ -13,11 +13,15 @@
 // limitations under the License.
 
 use std::{
-    io,
+    fs::File,
+    io::{self, Write},
     ops::Neg,
     os::unix::process::CommandExt,
+    path::{Path, PathBuf},
     process::{Child, Command, ExitStatus, Stdio},
-    result,
+    ptr, result,
+    sync::Arc,
+    thread,
 };
 
 use crate::{
@@ -25,27 +29,36 @@ use crate::{
         self,
         process::{signal, Signal},
     },
-    protocol::{self, ShutdownMethod},
+    protocol::{self, Console, ShutdownConfig, ShutdownMethod},
 };
 use libc;
 use time::{Duration, SteadyTime};
 
+use super::{
+    console::{self, Attach},
+    limits::{self, Limits},
+    sandbox::Sandbox,
+};
 use crate::{
     error::{Error, Result},
-    service::Service,
+    service::{Service, DEFAULT_SHUTDOWN_TIMEOUT, SHUTDOWN_POLL_INTERVAL},
 };
 
-pub struct Process(Child);
+pub struct Process {
+    child: Child,
+    /// The service's cgroup, removed along with the process once it has been reaped.
+    cgroup: Option<PathBuf>,
+}
 
 impl Process {
     pub fn id(&self) -> u32 {
-        self.0.id()
+        self.child.id()
     }
 
-    /// Attempt to gracefully terminate a process and then forcefully kill it after
-    /// 8 seconds if it has not terminated.
-    pub fn kill(&mut self) -> ShutdownMethod {
-        let mut pid_to_kill = self.0.id() as i32;
+    /// Attempt to gracefully terminate a process with the configured signal, SIGTERM by default,
+    /// and then forcefully kill it if it has not terminated after the configured timeout.
+    pub fn kill(&mut self, shutdown: &ShutdownConfig) -> ShutdownMethod {
+        let mut pid_to_kill = self.child.id() as i32;
         // check the group of the process being killed
         // if it is the root process of the process group
         // we send our signals to the entire process group
@@ -63,15 +76,31 @@ impl Process {
 
         // JW TODO: Determine if the error represents a case where the process was already
         // exited before we return out and assume so.
-        if signal(pid_to_kill, Signal::TERM).is_err() {
+        let signum = shutdown
+            .signal
+            .as_ref()
+            .and_then(|name| {
+                let code = signal_code(name);
+                if code.is_none() {
+                    warn!("Unknown shutdown signal {}, sending TERM instead", name);
+                }
+                code
+            })
+            .unwrap_or(libc::SIGTERM);
+        if unsafe { libc::kill(pid_to_kill, signum) } != 0 {
             return ShutdownMethod::AlreadyExited;
         }
-        let stop_time = SteadyTime::now() + Duration::seconds(8);
+        let timeout = shutdown
+            .timeout
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT)
+            .min(protocol::MAX_SHUTDOWN_TIMEOUT);
+        let stop_time = SteadyTime::now() + Duration::seconds(timeout.into());
         loop {
             if let Ok(Some(_status)) = self.try_wait() {
                 return ShutdownMethod::GracefulTermination;
             }
             if SteadyTime::now() < stop_time {
+                thread::sleep(SHUTDOWN_POLL_INTERVAL);
                 continue;
             }
             // JW TODO: Determine if the error represents a case where the process was already
@@ -84,11 +113,19 @@ impl Process {
     }
 
     pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
-        self.0.try_wait()
+        self.child.try_wait()
     }
 
     pub fn wait(&mut self) -> io::Result<ExitStatus> {
-        self.0.wait()
+        self.child.wait()
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        if let Some(ref cgroup) = self.cgroup {
+            limits::remove_cgroup(cgroup);
+        }
     }
 }
 
@@ -114,20 +151,133 @@ pub fn run(msg: protocol::Spawn) -> Result<Service> {
         return Err(Error::GroupNotFound(String::from("")));
     };
 
-    cmd.before_exec(owned_pgid);
-    cmd.stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .uid(uid)
-        .gid(gid);
+    // Joining the service's cgroup, raising rlimits, mounting and changing the capability
+    // bounding set all require privileges, so the child applies them before switching to the
+    // service's user and group itself, rather than leaving that to `Command::uid` and
+    // `Command::gid`.
+    let limits = Limits::new(&msg.id, &msg.limits)?;
+    let cgroup = limits.cgroup().map(Path::to_path_buf);
+    let sandbox = Sandbox::new(&msg.sandbox)?;
+    let pty = msg.console == Console::Pty;
+    cmd.before_exec(move || {
+        if pty {
+            console::set_controlling_terminal()?;
+        } else {
+            owned_pgid()?;
+        }
+        limits.apply()?;
+        sandbox.enter()?;
+        drop_privileges(uid, gid)?;
+        sandbox.restrict()
+    });
+    let mut terminal = None;
+    match msg.console {
+        Console::Detached => {
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+        }
+        Console::Stdin => {
+            cmd.stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+        }
+        Console::Pty => {
+            let (master, slave) = console::open_pty().map_err(Error::Spawn)?;
+            cmd.stdin(Stdio::from(slave.try_clone()?))
+                .stdout(Stdio::from(slave.try_clone()?))
+                .stderr(Stdio::from(slave));
+            terminal = Some(master);
+        }
+    }
     for (key, val) in msg.env.iter() {
         cmd.env(key, val);
     }
     let mut child = cmd.spawn().map_err(Error::Spawn)?;
+    // Close our copies of the pseudo-terminal's slave, so that reading the master notices when
+    // the service exits.
+    drop(cmd);
+    let stdin = child.stdin.take();
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
-    let process = Process(child);
-    Ok(Service::new(msg, process, stdout, stderr))
+    let process = Process { child, cgroup };
+    match terminal {
+        Some(terminal) => {
+            let output = terminal.try_clone()?;
+            let input = terminal.try_clone()?;
+            let attach = attach(&msg, Box::new(input), Some(terminal));
+            Ok(Service::new(msg, process, Some(output), stderr, attach))
+        }
+        None => {
+            let attach = match stdin {
+                Some(stdin) => attach(&msg, Box::new(stdin), None),
+                None => None,
+            };
+            Ok(Service::new(msg, process, stdout, stderr, attach))
+        }
+    }
+}
+
+/// Listen for clients attaching to the console of a service. A service is still run if its
+/// socket can't be bound, as it is only missing a way to interact with it.
+fn attach(
+    msg: &protocol::Spawn,
+    input: Box<dyn Write + Send>,
+    terminal: Option<File>,
+) -> Option<Arc<Attach>> {
+    let path = match msg.attach_path {
+        Some(ref path) => Path::new(path),
+        None => {
+            warn!("No attach socket was given for {}", msg.id);
+            return None;
+        }
+    };
+    match Attach::listen(&msg.id, path, input, terminal) {
+        Ok(attach) => Some(attach),
+        Err(err) => {
+            warn!(
+                "Unable to listen for clients attaching to {} on {}, {}",
+                msg.id,
+                path.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+/// The number of a signal given by name, with or without the `SIG` prefix.
+fn signal_code(name: &str) -> Option<libc::c_int> {
+    let name = name.to_uppercase();
+    let code = match name.trim_start_matches("SIG") {
+        "HUP" => libc::SIGHUP,
+        "INT" => libc::SIGINT,
+        "QUIT" => libc::SIGQUIT,
+        "TERM" => libc::SIGTERM,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "WINCH" => libc::SIGWINCH,
+        "KILL" => libc::SIGKILL,
+        _ => return None,
+    };
+    Some(code)
+}
+
+// Mirrors what `Command` does for `uid` and `gid`: set the group, clear supplementary groups
+// when running as root, and finally set the user.
+fn drop_privileges(uid: libc::uid_t, gid: libc::gid_t) -> result::Result<(), io::Error> {
+    unsafe {
+        if libc::setgid(gid) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::getuid() == 0 && libc::setgroups(0, ptr::null()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::setuid(uid) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
 }
 
 // we want the command to spawn processes in their own process group
@@ -12,9 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, io, mem};
+use std::{collections::HashMap, io, mem, thread};
 
-use crate::protocol::{self, ShutdownMethod};
+use crate::protocol::{self, ShutdownConfig, ShutdownMethod};
 use core::os::process::{
     handle_from_pid,
     windows_child::{Child, ExitStatus, Handle},
@@ -36,7 +36,7 @@ use winapi::{
 
 use crate::{
     error::{Error, Result},
-    service::Service,
+    service::{Service, DEFAULT_SHUTDOWN_TIMEOUT, SHUTDOWN_POLL_INTERVAL},
 };
 
 const PROCESS_ACTIVE: u32 = 259;
@@ -59,9 +59,10 @@ impl Process {
         unsafe { processthreadsapi::GetProcessId(self.handle.raw()) as u32 }
     }
 
-    /// Attempt to gracefully terminate a process and then forcefully kill it after
-    /// 8 seconds if it has not terminated.
-    pub fn kill(&mut self) -> ShutdownMethod {
+    /// Attempt to gracefully terminate a process with a Ctrl-Break event and then forcefully
+    /// kill it if it has not terminated after the configured timeout. The configured signal is
+    /// ignored.
+    pub fn kill(&mut self, shutdown: &ShutdownConfig) -> ShutdownMethod {
         if self.status().is_some() {
             return ShutdownMethod::AlreadyExited;
         }
@@ -74,7 +75,11 @@ impl Process {
             );
         }
 
-        let stop_time = SteadyTime::now() + Duration::seconds(8);
+        let timeout = shutdown
+            .timeout
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT)
+            .min(protocol::MAX_SHUTDOWN_TIMEOUT);
+        let stop_time = SteadyTime::now() + Duration::seconds(timeout.into());
         loop {
             if ret == 0 || SteadyTime::now() > stop_time {
                 let proc_table = build_proc_table();
@@ -85,6 +90,7 @@ impl Process {
             if self.status().is_some() {
                 return ShutdownMethod::GracefulTermination;
             }
+            thread::sleep(SHUTDOWN_POLL_INTERVAL);
         }
     }
 
@@ -135,6 +141,24 @@ impl Process {
 }
 
 pub fn run(msg: protocol::Spawn) -> Result<Service> {
+    if !msg.limits.is_empty() {
+        warn!(
+            "Resource limits are not supported on Windows, ignoring them for {}",
+            msg.id
+        );
+    }
+    if !msg.sandbox.is_empty() {
+        warn!(
+            "Sandboxing is not supported on Windows, ignoring it for {}",
+            msg.id
+        );
+    }
+    if msg.console != protocol::Console::Detached {
+        warn!(
+            "Attaching to services is not supported on Windows, running {} detached",
+            msg.id
+        );
+    }
     // Supervisors prior to version 0.53.0 pulled in beta versions of
     // powershell. The official 6.0.0 version of powershell changed
     // the name of the powershell binary to pwsh.exe. Here we will
@@ -174,7 +198,7 @@ fn spawn_pwsh(ps_binary_name: &str, msg: protocol::Spawn) -> Result<Service> {
     ) {
         Ok(child) => {
             let process = Process::new(child.handle);
-            Ok(Service::new(msg, process, child.stdout, child.stderr))
+            Ok(Service::new(msg, process, child.stdout, child.stderr, None))
         }
         Err(_) => Err(Error::Spawn(io::Error::last_os_error())),
     }
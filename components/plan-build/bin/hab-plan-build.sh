@@ -230,6 +230,55 @@
 # pkg_interpreters=(bin/bash bin/sh)
 # ```
 #
+# ### pkg_config_paths
+# An associative array of configuration templates which are rendered somewhere other than the
+# service's config directory. The keys are template paths relative to the `config` directory and
+# the values are the destination path, relative to the service directory unless absolute,
+# optionally followed by an octal file mode and an `owner[:group]`. Relative destinations may not
+# contain `..`, and only the permission bits of the mode are used. Written to the metadata
+# CONFIG_PATHS file.
+# ```
+# pkg_config_paths=(
+#   [nginx.conf]="/etc/nginx/nginx.conf 0644 root:root"
+#   [cache.conf]="data/cache.conf"
+# )
+# ```
+#
+# ### pkg_hook_timeouts
+# An associative array of the number of seconds a hook may run for before the Supervisor
+# terminates it along with any processes it started. The keys are hook names: `file-updated`,
+# `health-check`, `init`, `install`, `post-run`, `post-stop`, `post-update`, `pre-stop`,
+# `pre-update`, `reconfigure`, `reload` or `suitability`. Hooks without a timeout run until they
+# exit. Written to the metadata HOOK_TIMEOUTS file.
+# ```
+# pkg_hook_timeouts=(
+#   [init]=300
+#   [health-check]=10
+# )
+# ```
+#
+# ### pkg_shutdown_signal
+# The name of the signal which asks the service to stop, one of `HUP`, `INT`, `QUIT`, `TERM`,
+# `USR1`, `USR2` or `WINCH`. Defaults to `TERM`. Written to the metadata SHUTDOWN_SIGNAL file.
+# ```
+# pkg_shutdown_signal=QUIT
+# ```
+#
+# ### pkg_shutdown_timeout_sec
+# The number of seconds the service is given to stop after being signaled before it is killed.
+# Defaults to 8 seconds. Written to the metadata SHUTDOWN_TIMEOUT file.
+# ```
+# pkg_shutdown_timeout_sec=120
+# ```
+#
+# ### pkg_strict_templates
+# When set to `true`, the Supervisor fails to render a configuration or hook template which
+# references a `cfg`, `bind`, `svc` or `sys` value that is not defined, instead of rendering it as
+# an empty string. Written to the metadata STRICT_TEMPLATES file.
+# ```
+# pkg_strict_templates=true
+# ```
+#
 # ## Plan variables
 #
 # `hab-plan-build` sets a few useful variables for you, in addition to the ones
@@ -408,10 +457,18 @@ pkg_exposes=()
 declare -A pkg_exports
 declare -A pkg_binds
 declare -A pkg_binds_optional
+declare -A pkg_config_paths
+declare -A pkg_hook_timeouts
 # The user to run the service as
 pkg_svc_user=hab
 # The group to run the service as
 pkg_svc_group=$pkg_svc_user
+# The signal which asks the service to stop, the Launcher's default if empty
+pkg_shutdown_signal=''
+# Seconds the service is given to stop before it is killed, the Launcher's default if empty
+pkg_shutdown_timeout_sec=''
+# Whether configuration templates must only reference defined values
+pkg_strict_templates=false
 
 # Initially set $pkg_svc_* variables. This happens before the Plan is sourced,
 # meaning that `$pkg_name` is not yet set. However, `$pkg_svc_run` wants
@@ -1899,10 +1956,15 @@ do_default_install() {
 # * `$pkg_prefix/EXPOSES` - An array of `pkg_exports` for which ports that this package exposes
 # * `$pkg_prefix/BINDS` - A list of services you connect to and keys that you expect to be exported
 # * `$pkg_prefix/BINDS_OPTIONAL` - Same as `BINDS` but not required for the service to start
+# * `$pkg_prefix/CONFIG_PATHS` - Destinations of configuration templates outside the config directory
 # * `$pkg_prefix/FILES` - blake2b checksums of all files in the package
+# * `$pkg_prefix/HOOK_TIMEOUTS` - Seconds each hook may run for before it is terminated
 # * `$pkg_prefix/LDFLAGS` - Any LDFLAGS for things that link against us
 # * `$pkg_prefix/LD_RUN_PATH` - The LD_RUN_PATH for things that link against us
 # * `$pkg_prefix/PATH` - Any PATH entries for things that link against us
+# * `$pkg_prefix/SHUTDOWN_SIGNAL` - The signal which asks the service to stop
+# * `$pkg_prefix/SHUTDOWN_TIMEOUT` - Seconds the service is given to stop before it is killed
+# * `$pkg_prefix/STRICT_TEMPLATES` - Present if templates are rendered in strict mode
 _build_metadata() {
   build_line "Building package metadata"
 
@@ -1919,7 +1981,9 @@ _build_metadata() {
   _render_metadata_EXPORTS
   _render_metadata_BINDS
   _render_metadata_BINDS_OPTIONAL
+  _render_metadata_CONFIG_PATHS
   _render_metadata_EXPOSES
+  _render_metadata_HOOK_TIMEOUTS
   _render_metadata_INTERPRETERS
   _render_metadata_BUILD_DEPS
   _render_metadata_BUILD_TDEPS
@@ -1930,6 +1994,9 @@ _build_metadata() {
   _render_metadata_RUNTIME_PATH
   _render_metadata_RUNTIME_ENVIRONMENT
   _render_metadata_RUNTIME_ENVIRONMENT_PROVENANCE
+  _render_metadata_SHUTDOWN_SIGNAL
+  _render_metadata_SHUTDOWN_TIMEOUT
+  _render_metadata_STRICT_TEMPLATES
 
   # Only generate `SVC_USER` & `SVC_GROUP` files if this package is a service.
   # We determine this by checking if there is a `hooks/run` script and/or
@@ -1943,7 +2010,7 @@ _build_metadata() {
 }
 
 # Copy the `./config` directory, relative to the Plan, to `$pkg_prefix/config`.
-# Do the same with `default.toml`. Delegates most of the implementation to the
+# Do the same with `default.toml` and `config_schema.json`. Delegates most of the implementation to the
 # `do_default_build_config()` function.
 do_build_config() {
   do_default_build_config
@@ -1964,6 +2031,9 @@ do_default_build_config() {
   if [[ -f "$PLAN_CONTEXT/default.toml" ]]; then
     cp "$PLAN_CONTEXT/default.toml" "$pkg_prefix"
   fi
+  if [[ -f "$PLAN_CONTEXT/config_schema.json" ]]; then
+    cp "$PLAN_CONTEXT/config_schema.json" "$pkg_prefix"
+  fi
   return 0
 }
 
@@ -27,6 +27,11 @@ _render_metadata_CFLAGS() {
     _render_c_includes_metadata_file "${pkg_prefix}" CFLAGS pkg_include_dirs
 }
 
+_render_metadata_CONFIG_PATHS() {
+    # shellcheck disable=2154
+    _render_associative_array_file "${pkg_prefix}" CONFIG_PATHS pkg_config_paths
+}
+
 _render_metadata_CPPFLAGS() {
     _render_c_includes_metadata_file "${pkg_prefix}" CPPFLAGS pkg_include_dirs
 }
@@ -98,6 +103,22 @@ _render_metadata_FILES() {
   popd > /dev/null
 }
 
+_render_metadata_HOOK_TIMEOUTS() {
+  # shellcheck disable=2154
+  for hook in "${!pkg_hook_timeouts[@]}"; do
+    case "${hook}" in
+      file-updated|health-check|init|install|post-run|post-stop|post-update|pre-stop|pre-update|reconfigure|reload|suitability) ;;
+      *)
+        exit_with "Bad key in pkg_hook_timeouts; expected one of file-updated, health-check, init, install, post-run, post-stop, post-update, pre-stop, pre-update, reconfigure, reload or suitability: ${hook}"
+        ;;
+    esac
+    if [[ ! "${pkg_hook_timeouts[${hook}]}" =~ ^[1-9][0-9]*$ ]]; then
+      exit_with "Bad value in pkg_hook_timeouts for ${hook}; expected a number of seconds: ${pkg_hook_timeouts[${hook}]}"
+    fi
+  done
+  _render_associative_array_file "${pkg_prefix}" HOOK_TIMEOUTS pkg_hook_timeouts
+}
+
 _render_metadata_IDENT() {
   debug "Rendering IDENT metadata file"
   # shellcheck disable=2154
@@ -232,6 +253,39 @@ _render_metadata_RUNTIME_PATH(){
   fi
 }
 
+_render_metadata_SHUTDOWN_SIGNAL() {
+  # shellcheck disable=2154
+  if [[ -n "${pkg_shutdown_signal}" ]]; then
+    case "${pkg_shutdown_signal}" in
+      HUP|INT|QUIT|TERM|USR1|USR2|WINCH) ;;
+      *)
+        exit_with "Bad value in pkg_shutdown_signal; expected one of HUP, INT, QUIT, TERM, USR1, USR2 or WINCH: ${pkg_shutdown_signal}"
+        ;;
+    esac
+    debug "Rendering SHUTDOWN_SIGNAL metadata file"
+    echo "${pkg_shutdown_signal}" > "$pkg_prefix"/SHUTDOWN_SIGNAL
+  fi
+}
+
+_render_metadata_SHUTDOWN_TIMEOUT() {
+  # shellcheck disable=2154
+  if [[ -n "${pkg_shutdown_timeout_sec}" ]]; then
+    if [[ ! "${pkg_shutdown_timeout_sec}" =~ ^[0-9]+$ ]]; then
+      exit_with "Bad value in pkg_shutdown_timeout_sec; expected a number of seconds: ${pkg_shutdown_timeout_sec}"
+    fi
+    debug "Rendering SHUTDOWN_TIMEOUT metadata file"
+    echo "${pkg_shutdown_timeout_sec}" > "$pkg_prefix"/SHUTDOWN_TIMEOUT
+  fi
+}
+
+_render_metadata_STRICT_TEMPLATES() {
+  # shellcheck disable=2154
+  if [[ "${pkg_strict_templates}" == "true" ]]; then
+    debug "Rendering STRICT_TEMPLATES metadata file"
+    echo "true" > "$pkg_prefix"/STRICT_TEMPLATES
+  fi
+}
+
 _render_metadata_SVC_GROUP() {
   debug "Rendering SVC_GROUP metadata file"
   # shellcheck disable=2154
@@ -37,6 +37,13 @@
 //!     Ok(())
 //! })
 //! ```
+//!
+//! # TLS
+//!
+//! A Supervisor started with a CtlGateway certificate and key only accepts TLS connections. Use
+//! [`SrvClient::connect_tls`] with a [`SrvClientTls`] which verifies the Supervisor's certificate
+//! against a CA bundle. If the Supervisor requires client certificates, a client certificate and
+//! key can be added to the `SrvClientTls` and the secret key may be left empty.
 
 #[macro_use]
 extern crate futures;
@@ -45,16 +52,29 @@ use habitat_sup_protocol as protocol;
 extern crate log;
 use habitat_common as common;
 
-use std::{error, fmt, io, path::PathBuf};
+use std::{
+    error, fmt,
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::protocol::{codec::*, net::NetErr};
-use futures::{prelude::*, sink};
+use futures::{prelude::*, sink, stream};
+use rustls::{internal::pemfile, ClientConfig};
 use tokio::net::TcpStream;
 use tokio_codec::Framed;
+use tokio_rustls::TlsConnector;
+use webpki::DNSNameRef;
 
 use crate::common::types::ListenCtlAddr;
 
 pub type SrvSend = sink::Send<SrvStream>;
+/// Sending half of a connection split by [`SrvClient::call_split`].
+pub type SrvSink = stream::SplitSink<SrvStream>;
+/// Replies to a request sent by [`SrvClient::call_split`].
+pub type SrvReplies = stream::SplitStream<SrvStream>;
 
 /// Error types returned by a [`SrvClient`].
 #[derive(Debug)]
@@ -65,6 +85,14 @@ pub enum SrvClientError {
     CtlSecretNotFound(PathBuf),
     /// Decoding a message from the remote failed.
     Decode(prost::DecodeError),
+    /// No certificates could be read from the given CA bundle.
+    InvalidCaFile(PathBuf),
+    /// No certificates could be read from the given client certificate file.
+    InvalidCertFile(PathBuf),
+    /// No private key could be read from the given client key file.
+    InvalidKeyFile(PathBuf),
+    /// The given name cannot be used to verify the server's certificate.
+    InvalidServerName(String),
     /// An Os level IO error occurred.
     Io(io::Error),
     /// An RPC call to the remote was received but failed.
@@ -77,6 +105,10 @@ impl error::Error for SrvClientError {
             SrvClientError::ConnectionClosed => "Connection closed",
             SrvClientError::CtlSecretNotFound(_) => "Ctl secret key not found",
             SrvClientError::Decode(ref err) => err.description(),
+            SrvClientError::InvalidCaFile(_) => "Invalid CA certificate file",
+            SrvClientError::InvalidCertFile(_) => "Invalid client certificate file",
+            SrvClientError::InvalidKeyFile(_) => "Invalid client key file",
+            SrvClientError::InvalidServerName(_) => "Invalid TLS server name",
             SrvClientError::Io(ref err) => err.description(),
             SrvClientError::NetErr(ref err) => err.description(),
         }
@@ -94,6 +126,26 @@ impl fmt::Display for SrvClientError {
                 path.display()
             ),
             SrvClientError::Decode(ref err) => format!("{}", err),
+            SrvClientError::InvalidCaFile(ref path) => format!(
+                "Unable to read any CA certificates from {}. This should contain PEM-format \
+                 certificates.",
+                path.display()
+            ),
+            SrvClientError::InvalidCertFile(ref path) => format!(
+                "Unable to read any client certificates from {}. This should contain PEM-format \
+                 certificates.",
+                path.display()
+            ),
+            SrvClientError::InvalidKeyFile(ref path) => format!(
+                "Unable to read a client private key from {}. This should be a RSA private key in \
+                 PEM format.",
+                path.display()
+            ),
+            SrvClientError::InvalidServerName(ref name) => format!(
+                "'{}' cannot be used to verify the Supervisor's certificate. This must be a DNS \
+                 name.",
+                name
+            ),
             SrvClientError::Io(ref err) => format!(
                 "Unable to contact the Supervisor.\n\nIf the Supervisor you are contacting is \
                  local, this probably means it is not running. You can run a Supervisor in the \
@@ -125,6 +177,63 @@ impl From<prost::DecodeError> for SrvClientError {
     }
 }
 
+/// TLS settings for connecting to a server listener which speaks SrvProtocol over TLS.
+#[derive(Clone)]
+pub struct SrvClientTls {
+    config: ClientConfig,
+    server_name: String,
+}
+
+impl SrvClientTls {
+    /// Create TLS settings which verify the server's certificate against the certificates in the
+    /// given CA bundle. The certificate must be valid for `server_name`.
+    pub fn new<P, S>(ca_path: P, server_name: S) -> Result<Self, SrvClientError>
+    where
+        P: AsRef<Path>,
+        S: ToString,
+    {
+        let server_name = server_name.to_string();
+        DNSNameRef::try_from_ascii_str(&server_name)
+            .map_err(|_| SrvClientError::InvalidServerName(server_name.clone()))?;
+        let mut config = ClientConfig::new();
+        let ca_file = &mut BufReader::new(File::open(&ca_path)?);
+        match config.root_store.add_pem_file(ca_file) {
+            Ok((valid, _)) if valid > 0 => (),
+            _ => {
+                return Err(SrvClientError::InvalidCaFile(
+                    ca_path.as_ref().to_path_buf(),
+                ));
+            }
+        }
+        Ok(SrvClientTls {
+            config,
+            server_name,
+        })
+    }
+
+    /// Present the given certificate and key to servers which require client certificates.
+    pub fn client_certificate<C, K>(
+        mut self,
+        cert_path: C,
+        key_path: K,
+    ) -> Result<Self, SrvClientError>
+    where
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        let cert_file = &mut BufReader::new(File::open(&cert_path)?);
+        let key_file = &mut BufReader::new(File::open(&key_path)?);
+        let cert_chain = pemfile::certs(cert_file)
+            .and_then(|c| if c.is_empty() { Err(()) } else { Ok(c) })
+            .map_err(|_| SrvClientError::InvalidCertFile(cert_path.as_ref().to_path_buf()))?;
+        let key = pemfile::rsa_private_keys(key_file)
+            .and_then(|mut k| k.pop().ok_or(()))
+            .map_err(|_| SrvClientError::InvalidKeyFile(key_path.as_ref().to_path_buf()))?;
+        self.config.set_single_client_cert(cert_chain, key);
+        Ok(self)
+    }
+}
+
 /// Client for connecting and communicating with a server listener which speaks SrvProtocol.
 ///
 /// See module doc for usage.
@@ -151,25 +260,30 @@ impl SrvClient {
         let secret_key = secret_key.to_string();
         let conn = TcpStream::connect(addr.as_ref())
             .map_err(SrvClientError::from)
+            .and_then(move |socket| Self::handshake(SrvSocket::from(socket), secret_key));
+        Box::new(conn)
+    }
+
+    /// Connect to the given remote server over TLS and authenticate with the given secret_key.
+    pub fn connect_tls<S>(
+        addr: &ListenCtlAddr,
+        secret_key: S,
+        tls: &SrvClientTls,
+    ) -> Box<dyn Future<Item = SrvClient, Error = SrvClientError> + 'static>
+    where
+        S: ToString,
+    {
+        let secret_key = secret_key.to_string();
+        let connector = TlsConnector::from(Arc::new(tls.config.clone()));
+        let server_name = tls.server_name.clone();
+        let conn = TcpStream::connect(addr.as_ref())
             .and_then(move |socket| {
-                let client = Self::new(socket, None);
-                let mut request = protocol::ctl::Handshake::default();
-                request.secret_key = Some(secret_key);
-                client
-                    .call(request)
-                    .into_future()
-                    .map_err(|(err, _)| err)
-                    .and_then(move |(m, io)| {
-                        m.map_or_else(
-                            || Err(SrvClientError::ConnectionClosed),
-                            move |m| {
-                                m.try_ok()
-                                    .map_err(SrvClientError::from)
-                                    .and_then(|()| Ok(io.into_inner()))
-                            },
-                        )
-                    })
-            });
+                // The name was validated when the `SrvClientTls` was created.
+                let domain = DNSNameRef::try_from_ascii_str(&server_name).unwrap();
+                connector.connect(domain, socket)
+            })
+            .map_err(SrvClientError::from)
+            .and_then(move |socket| Self::handshake(SrvSocket::from(socket), secret_key));
         Box::new(conn)
     }
 
@@ -180,7 +294,32 @@ impl SrvClient {
         Ok(buf)
     }
 
-    fn new(socket: TcpStream, current_txn: Option<SrvTxn>) -> Self {
+    /// Authenticate with the remote server over the given connected socket.
+    fn handshake(
+        socket: SrvSocket,
+        secret_key: String,
+    ) -> Box<dyn Future<Item = SrvClient, Error = SrvClientError> + 'static> {
+        let client = Self::new(socket, None);
+        let mut request = protocol::ctl::Handshake::default();
+        request.secret_key = Some(secret_key);
+        let handshake = client
+            .call(request)
+            .into_future()
+            .map_err(|(err, _)| err)
+            .and_then(move |(m, io)| {
+                m.map_or_else(
+                    || Err(SrvClientError::ConnectionClosed),
+                    move |m| {
+                        m.try_ok()
+                            .map_err(SrvClientError::from)
+                            .and_then(|()| Ok(io.into_inner()))
+                    },
+                )
+            });
+        Box::new(handshake)
+    }
+
+    fn new(socket: SrvSocket, current_txn: Option<SrvTxn>) -> Self {
         SrvClient {
             socket: Framed::new(socket, SrvCodec::new()),
             current_txn: current_txn.unwrap_or_default(),
@@ -200,6 +339,29 @@ impl SrvClient {
         SrvReply::new(self.socket.send(msg), self.current_txn)
     }
 
+    /// Send a transactional request to the connected server and split the connection, for
+    /// requests which go on to send the server further non-transactional messages while their
+    /// replies are received, such as the input of a terminal attached to a service. The returned
+    /// sink sends those messages and the returned stream yields the replies.
+    pub fn call_split<T>(
+        mut self,
+        request: T,
+    ) -> Box<dyn Future<Item = (SrvSink, SrvReplies), Error = SrvClientError> + 'static>
+    where
+        T: Into<SrvMessage> + fmt::Debug,
+    {
+        self.current_txn.increment();
+        let mut msg: SrvMessage = request.into();
+        msg.set_transaction(self.current_txn);
+        trace!("Sending SrvMessage -> {:?}", msg);
+        Box::new(
+            self.socket
+                .send(msg)
+                .map_err(SrvClientError::from)
+                .map(Stream::split),
+        )
+    }
+
     /// Send a non-transactional request to the connected server.
     pub fn cast<T>(self, request: T) -> SrvSend
     where
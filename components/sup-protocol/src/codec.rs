@@ -56,15 +56,20 @@
 
 use std::{
     fmt,
-    io::{self, Cursor},
+    io::{self, Cursor, Read, Write},
     str,
 };
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use futures;
+use futures::{self, Poll};
 use prost::{self, Message};
-use tokio::net::TcpStream;
+use rustls::{ClientSession, ServerSession};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
 use tokio_codec::{Decoder, Encoder, Framed};
+use tokio_rustls::TlsStream;
 
 use crate::{
     message::MessageStatic,
@@ -84,13 +89,91 @@ const RESPONSE_MASK: u32 = 0x1;
 const COMPLETE_OFFSET: u32 = 30;
 const COMPLETE_MASK: u32 = 0x1;
 
-/// A `TcpStream` framed with `SrvCodec`. This is the base socket connection that the CtlGateway
+/// A `SrvSocket` framed with `SrvCodec`. This is the base socket connection that the CtlGateway
 /// client and server speak.
-pub type SrvStream = Framed<TcpStream, SrvCodec>;
+pub type SrvStream = Framed<SrvSocket, SrvCodec>;
 
 /// Sending half of `SrvStream`.
 pub type SrvSink = futures::stream::SplitSink<SrvStream>;
 
+/// A connection to or from a CtlGateway. The connection is either a plain `TcpStream` or a TLS
+/// session running over a `TcpStream`.
+pub enum SrvSocket {
+    Tcp(TcpStream),
+    TlsClient(TlsStream<TcpStream, ClientSession>),
+    TlsServer(TlsStream<TcpStream, ServerSession>),
+}
+
+impl SrvSocket {
+    /// Check if the connection is encrypted with TLS.
+    pub fn is_tls(&self) -> bool {
+        match *self {
+            SrvSocket::Tcp(_) => false,
+            SrvSocket::TlsClient(_) | SrvSocket::TlsServer(_) => true,
+        }
+    }
+}
+
+impl From<TcpStream> for SrvSocket {
+    fn from(socket: TcpStream) -> Self {
+        SrvSocket::Tcp(socket)
+    }
+}
+
+impl From<TlsStream<TcpStream, ClientSession>> for SrvSocket {
+    fn from(socket: TlsStream<TcpStream, ClientSession>) -> Self {
+        SrvSocket::TlsClient(socket)
+    }
+}
+
+impl From<TlsStream<TcpStream, ServerSession>> for SrvSocket {
+    fn from(socket: TlsStream<TcpStream, ServerSession>) -> Self {
+        SrvSocket::TlsServer(socket)
+    }
+}
+
+impl Read for SrvSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            SrvSocket::Tcp(ref mut socket) => socket.read(buf),
+            SrvSocket::TlsClient(ref mut socket) => socket.read(buf),
+            SrvSocket::TlsServer(ref mut socket) => socket.read(buf),
+        }
+    }
+}
+
+impl Write for SrvSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            SrvSocket::Tcp(ref mut socket) => socket.write(buf),
+            SrvSocket::TlsClient(ref mut socket) => socket.write(buf),
+            SrvSocket::TlsServer(ref mut socket) => socket.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            SrvSocket::Tcp(ref mut socket) => socket.flush(),
+            SrvSocket::TlsClient(ref mut socket) => socket.flush(),
+            SrvSocket::TlsServer(ref mut socket) => socket.flush(),
+        }
+    }
+}
+
+impl AsyncRead for SrvSocket {}
+
+impl AsyncWrite for SrvSocket {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        // `TcpStream` has an inherent `shutdown` method which would shadow the trait's, so the
+        // trait method is called explicitly.
+        match *self {
+            SrvSocket::Tcp(ref mut socket) => AsyncWrite::shutdown(socket),
+            SrvSocket::TlsClient(ref mut socket) => AsyncWrite::shutdown(socket),
+            SrvSocket::TlsServer(ref mut socket) => AsyncWrite::shutdown(socket),
+        }
+    }
+}
+
 /// An unsigned 32-bit integer packed with transaction information which is present if a request
 /// should receive a response from the destination.
 #[derive(Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
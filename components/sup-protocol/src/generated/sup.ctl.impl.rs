@@ -9,6 +9,9 @@ impl message::MessageStatic for Handshake {
 impl message::MessageStatic for ServiceBindList {
     const MESSAGE_ID: &'static str = "ServiceBindList";
 }
+impl message::MessageStatic for SupAudit {
+    const MESSAGE_ID: &'static str = "SupAudit";
+}
 impl message::MessageStatic for SupDepart {
     const MESSAGE_ID: &'static str = "SupDepart";
 }
@@ -18,12 +21,24 @@ impl message::MessageStatic for SvcFilePut {
 impl message::MessageStatic for SvcGetDefaultCfg {
     const MESSAGE_ID: &'static str = "SvcGetDefaultCfg";
 }
+impl message::MessageStatic for SvcGetCfgProvenance {
+    const MESSAGE_ID: &'static str = "SvcGetCfgProvenance";
+}
 impl message::MessageStatic for SvcValidateCfg {
     const MESSAGE_ID: &'static str = "SvcValidateCfg";
 }
+impl message::MessageStatic for SvcDiffCfg {
+    const MESSAGE_ID: &'static str = "SvcDiffCfg";
+}
 impl message::MessageStatic for SvcSetCfg {
     const MESSAGE_ID: &'static str = "SvcSetCfg";
 }
+impl message::MessageStatic for SvcGetCfgHistory {
+    const MESSAGE_ID: &'static str = "SvcGetCfgHistory";
+}
+impl message::MessageStatic for SvcRollbackCfg {
+    const MESSAGE_ID: &'static str = "SvcRollbackCfg";
+}
 impl message::MessageStatic for SvcLoad {
     const MESSAGE_ID: &'static str = "SvcLoad";
 }
@@ -39,6 +54,33 @@ impl message::MessageStatic for SvcStop {
 impl message::MessageStatic for SvcStatus {
     const MESSAGE_ID: &'static str = "SvcStatus";
 }
+impl message::MessageStatic for SvcLogs {
+    const MESSAGE_ID: &'static str = "SvcLogs";
+}
+impl message::MessageStatic for SvcLogLines {
+    const MESSAGE_ID: &'static str = "SvcLogLines";
+}
+impl message::MessageStatic for SvcAttach {
+    const MESSAGE_ID: &'static str = "SvcAttach";
+}
+impl message::MessageStatic for SvcAttachInput {
+    const MESSAGE_ID: &'static str = "SvcAttachInput";
+}
+impl message::MessageStatic for SvcAttachOutput {
+    const MESSAGE_ID: &'static str = "SvcAttachOutput";
+}
 impl message::MessageStatic for ConsoleLine {
     const MESSAGE_ID: &'static str = "ConsoleLine";
 }
+impl message::MessageStatic for AuditEntry {
+    const MESSAGE_ID: &'static str = "AuditEntry";
+}
+impl message::MessageStatic for ConfigFileDiff {
+    const MESSAGE_ID: &'static str = "ConfigFileDiff";
+}
+impl message::MessageStatic for ConfigKeyProvenance {
+    const MESSAGE_ID: &'static str = "ConfigKeyProvenance";
+}
+impl message::MessageStatic for ConfigHistoryEntry {
+    const MESSAGE_ID: &'static str = "ConfigHistoryEntry";
+}
@@ -31,6 +31,24 @@ pub struct ServiceBindList {
     #[prost(message, repeated, tag="1")]
     pub binds: ::std::vec::Vec<super::types::ServiceBind>,
 }
+/// Request to query the Supervisor's audit log. Matching entries are returned oldest first.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SupAudit {
+    /// Only return entries targeting this service group, package identifier, or member.
+    #[prost(string, optional, tag="1")]
+    pub target: ::std::option::Option<String>,
+    /// Only return entries for this request or rumor type, such as `SvcLoad` or `ServiceConfig`.
+    #[prost(string, optional, tag="2")]
+    pub message_type: ::std::option::Option<String>,
+    /// Only return entries recorded at or after this RFC 3339 timestamp.
+    #[prost(string, optional, tag="3")]
+    pub since: ::std::option::Option<String>,
+    /// Return at most this many of the most recent matching entries.
+    #[prost(uint32, optional, tag="4")]
+    pub limit: ::std::option::Option<u32>,
+}
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -63,6 +81,16 @@ pub struct SvcGetDefaultCfg {
     #[prost(message, optional, tag="1")]
     pub ident: ::std::option::Option<super::types::PackageIdent>,
 }
+/// Request for retrieving every key of a running service's effective configuration along with the
+/// configuration layer which set it.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcGetCfgProvenance {
+    /// Package identifier to target running service.
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+}
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -77,6 +105,22 @@ pub struct SvcValidateCfg {
     #[prost(bytes, optional, tag="3")]
     pub cfg: ::std::option::Option<Vec<u8>>,
 }
+/// Request to render a running service's configuration files with a candidate configuration
+/// and compare them to the files currently rendered, without changing anything.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcDiffCfg {
+    /// Service group of a running service to render the candidate configuration for.
+    #[prost(message, optional, tag="1")]
+    pub service_group: ::std::option::Option<super::types::ServiceGroup>,
+    /// Structured and self-describing string format contained in the configuration string.
+    #[prost(enumeration="super::types::service_cfg::Format", optional, tag="2", default="Toml")]
+    pub format: ::std::option::Option<i32>,
+    /// Unencrypted candidate configuration, as it would be applied with `SvcSetCfg`.
+    #[prost(bytes, optional, tag="3")]
+    pub cfg: ::std::option::Option<Vec<u8>>,
+}
 /// Request to set a running service's configuration to the given values.
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
@@ -97,6 +141,27 @@ pub struct SvcSetCfg {
     #[prost(bool, optional, tag="4", default="false")]
     pub is_encrypted: ::std::option::Option<bool>,
 }
+/// Request to list the configurations previously applied to a service group through gossip.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcGetCfgHistory {
+    /// Service group to list the configuration history of.
+    #[prost(message, optional, tag="1")]
+    pub service_group: ::std::option::Option<super::types::ServiceGroup>,
+}
+/// Request to re-apply a configuration from a service group's history as a new incarnation.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcRollbackCfg {
+    /// Service group to roll back the configuration of.
+    #[prost(message, optional, tag="1")]
+    pub service_group: ::std::option::Option<super::types::ServiceGroup>,
+    /// Incarnation of the configuration in the service group's history to re-apply.
+    #[prost(uint64, optional, tag="2")]
+    pub incarnation: ::std::option::Option<u64>,
+}
 /// Request to load a new service.
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
@@ -148,6 +213,122 @@ pub struct SvcLoad {
     /// Health Check interval for the service
     #[prost(message, optional, tag="15")]
     pub health_check_interval: ::std::option::Option<super::types::HealthCheckInterval>,
+    /// An external source of configuration for the service: an absolute directory path or an
+    /// http:// URL.
+    #[prost(string, optional, tag="16")]
+    pub config_source: ::std::option::Option<String>,
+    /// Where the configuration from `config_source` sits among the other layers: `below-user`,
+    /// `above-user` or `above-gossip`.
+    #[prost(string, optional, tag="17")]
+    pub config_source_precedence: ::std::option::Option<String>,
+    /// If set to true, configuration templates referencing undefined values fail to render.
+    #[prost(bool, optional, tag="18")]
+    pub strict_templates: ::std::option::Option<bool>,
+    /// Name of the signal which asks the service to stop, e.g. `TERM` or `QUIT`.
+    #[prost(string, optional, tag="19")]
+    pub shutdown_signal: ::std::option::Option<String>,
+    /// Seconds the service is given to stop before it is killed.
+    #[prost(uint32, optional, tag="20")]
+    pub shutdown_timeout: ::std::option::Option<u32>,
+    /// Bytes of memory the service may use.
+    #[prost(uint64, optional, tag="21")]
+    pub memory_max: ::std::option::Option<u64>,
+    /// Relative share of CPU time given to the service, from 1 to 10000.
+    #[prost(uint32, optional, tag="22")]
+    pub cpu_weight: ::std::option::Option<u32>,
+    /// Percentage of a single CPU the service may use.
+    #[prost(uint32, optional, tag="23")]
+    pub cpu_quota: ::std::option::Option<u32>,
+    /// Number of processes and threads the service may contain.
+    #[prost(uint32, optional, tag="24")]
+    pub pids_max: ::std::option::Option<u32>,
+    /// Process rlimits of the service, keyed by name, e.g. `nofile`.
+    #[prost(map="string, uint64", tag="25")]
+    pub rlimits: ::std::collections::HashMap<String, u64>,
+    /// Drop every capability from the service's bounding set except `capabilities`.
+    #[prost(bool, optional, tag="26")]
+    pub drop_capabilities: ::std::option::Option<bool>,
+    /// Capabilities kept when dropping capabilities, e.g. `CAP_NET_BIND_SERVICE`. A service which
+    /// doesn't run as root is granted them.
+    #[prost(string, repeated, tag="27")]
+    pub capabilities: ::std::vec::Vec<String>,
+    /// Prevent the service from gaining privileges, e.g. through setuid binaries.
+    #[prost(bool, optional, tag="28")]
+    pub no_new_privs: ::std::option::Option<bool>,
+    /// Give the service its own empty /tmp.
+    #[prost(bool, optional, tag="29")]
+    pub private_tmp: ::std::option::Option<bool>,
+    /// Mount the service's package path read-only.
+    #[prost(bool, optional, tag="30")]
+    pub read_only_package: ::std::option::Option<bool>,
+    /// Path to a compiled seccomp BPF filter installed before the service starts.
+    #[prost(string, optional, tag="31")]
+    pub seccomp_profile: ::std::option::Option<String>,
+    /// Format of the service's log file, `text` or `json`.
+    #[prost(string, optional, tag="32")]
+    pub log_format: ::std::option::Option<String>,
+    /// Rotate the service's log file once it grows past this many bytes.
+    #[prost(uint64, optional, tag="33")]
+    pub log_max_size: ::std::option::Option<u64>,
+    /// Rotate the service's log file once it was opened more than this many seconds ago.
+    #[prost(uint64, optional, tag="34")]
+    pub log_max_age: ::std::option::Option<u64>,
+    /// Number of the service's rotated log files to keep.
+    #[prost(uint32, optional, tag="35")]
+    pub log_retain: ::std::option::Option<u32>,
+    /// How the service's standard streams are connected, `detached`, `stdin` or `pty`.
+    #[prost(string, optional, tag="36")]
+    pub console: ::std::option::Option<String>,
+    /// Seconds a hook may run for before it is terminated, keyed by hook name, e.g. `init`.
+    #[prost(map="string, uint32", tag="37")]
+    pub hook_timeouts: ::std::collections::HashMap<String, u32>,
+}
+/// Request to read the log file of a loaded service.
+#[derive(Clone, PartialEq, Message)]
+pub struct SvcLogs {
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Number of lines from the end of the log to send. All lines are sent if unset.
+    #[prost(uint64, optional, tag="2")]
+    pub lines: ::std::option::Option<u64>,
+    /// Keep sending lines as they are written to the log, until the client disconnects.
+    #[prost(bool, optional, tag="3")]
+    pub follow: ::std::option::Option<bool>,
+}
+/// Lines read from a service's log file.
+#[derive(Clone, PartialEq, Message)]
+pub struct SvcLogLines {
+    #[prost(string, repeated, tag="1")]
+    pub lines: ::std::vec::Vec<String>,
+}
+/// Request to attach to the console of a loaded service. While attached, the client sends its
+/// input as `SvcAttachInput` messages on the same connection and is sent the service's output as
+/// `SvcAttachOutput` replies. The client detaches by closing the connection.
+#[derive(Clone, PartialEq, Message)]
+pub struct SvcAttach {
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Size of the client's terminal.
+    #[prost(uint32, optional, tag="2")]
+    pub rows: ::std::option::Option<u32>,
+    #[prost(uint32, optional, tag="3")]
+    pub cols: ::std::option::Option<u32>,
+}
+/// Input from a client attached to a service, or a new size of its terminal.
+#[derive(Clone, PartialEq, Message)]
+pub struct SvcAttachInput {
+    #[prost(bytes, optional, tag="1")]
+    pub data: ::std::option::Option<Vec<u8>>,
+    #[prost(uint32, optional, tag="2")]
+    pub rows: ::std::option::Option<u32>,
+    #[prost(uint32, optional, tag="3")]
+    pub cols: ::std::option::Option<u32>,
+}
+/// Output of a service sent to a client attached to it.
+#[derive(Clone, PartialEq, Message)]
+pub struct SvcAttachOutput {
+    #[prost(bytes, optional, tag="1")]
+    pub data: ::std::option::Option<Vec<u8>>,
 }
 /// Request to unload a loaded service.
 #[derive(Clone, PartialEq, Message)]
@@ -191,3 +372,96 @@ pub struct ConsoleLine {
     #[prost(string, required, tag="1")]
     pub line: String,
 }
+/// An operation recorded in the Supervisor's audit log.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AuditEntry {
+    /// Time the operation was applied as an RFC 3339 timestamp.
+    #[prost(string, optional, tag="1")]
+    pub timestamp: ::std::option::Option<String>,
+    /// Where the operation originated, either `ctl` or `gossip`.
+    #[prost(string, optional, tag="2")]
+    pub source: ::std::option::Option<String>,
+    /// Address of the client which sent the request.
+    #[prost(string, optional, tag="3")]
+    pub remote_addr: ::std::option::Option<String>,
+    /// Name of the credential the client authenticated with.
+    #[prost(string, optional, tag="4")]
+    pub credential: ::std::option::Option<String>,
+    /// Request or rumor type, such as `SvcLoad` or `ServiceConfig`.
+    #[prost(string, optional, tag="5")]
+    pub message_type: ::std::option::Option<String>,
+    /// Service group, package identifier, or member the operation targeted.
+    #[prost(string, optional, tag="6")]
+    pub target: ::std::option::Option<String>,
+    /// Name of the file for service file operations.
+    #[prost(string, optional, tag="7")]
+    pub filename: ::std::option::Option<String>,
+    /// Hash of the applied configuration or file content.
+    #[prost(string, optional, tag="8")]
+    pub config_digest: ::std::option::Option<String>,
+    /// Incarnation of the applied configuration or file.
+    #[prost(uint64, optional, tag="9")]
+    pub incarnation: ::std::option::Option<u64>,
+    /// Set for requests refused because the client's credential doesn't permit them.
+    #[prost(bool, optional, tag="10")]
+    pub denied: ::std::option::Option<bool>,
+}
+/// A configuration file which would change if a candidate configuration were applied.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfigFileDiff {
+    /// Path of the file relative to the service's configuration directory.
+    #[prost(string, optional, tag="1")]
+    pub filename: ::std::option::Option<String>,
+    /// Unified diff from the currently rendered file to the candidate rendering.
+    #[prost(string, optional, tag="2")]
+    pub diff: ::std::option::Option<String>,
+}
+/// A key of a service's effective configuration and where its value came from.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfigKeyProvenance {
+    /// Dotted path of the key, e.g. `server.port`.
+    #[prost(string, optional, tag="1")]
+    pub key: ::std::option::Option<String>,
+    /// Effective value of the key, as an inline TOML value.
+    #[prost(string, optional, tag="2")]
+    pub value: ::std::option::Option<String>,
+    /// Configuration layer which set the value: `default`, `environment`, `user`,
+    /// `gossip` or `external`.
+    #[prost(string, optional, tag="3")]
+    pub layer: ::std::option::Option<String>,
+    /// File, environment variable or external source the layer was loaded from. Unset for
+    /// gossip.
+    #[prost(string, optional, tag="4")]
+    pub source: ::std::option::Option<String>,
+    /// Incarnation of the service group's configuration, for values set by gossip.
+    #[prost(uint64, optional, tag="5")]
+    pub incarnation: ::std::option::Option<u64>,
+}
+/// A configuration previously applied to a service group through gossip.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfigHistoryEntry {
+    /// Incarnation the configuration was applied with.
+    #[prost(uint64, optional, tag="1")]
+    pub incarnation: ::std::option::Option<u64>,
+    /// RFC 3339 timestamp, in UTC, of when the Supervisor recorded the configuration.
+    #[prost(string, optional, tag="2")]
+    pub timestamp: ::std::option::Option<String>,
+    /// Digest of the configuration as it was gossiped.
+    #[prost(string, optional, tag="3")]
+    pub digest: ::std::option::Option<String>,
+    /// Control Gateway client which applied the configuration, as `credential@address`. Only known
+    /// when the configuration was applied through this Supervisor.
+    #[prost(string, optional, tag="4")]
+    pub applied_by: ::std::option::Option<String>,
+    /// If the configuration was encrypted for the service group.
+    #[prost(bool, optional, tag="5")]
+    pub encrypted: ::std::option::Option<bool>,
+}
@@ -85,6 +85,32 @@ pub struct ServiceStatus {
     pub service_group: ServiceGroup,
     #[prost(enumeration="DesiredState", optional, tag="5")]
     pub desired_state: ::std::option::Option<i32>,
+    /// Result of the most recent health check; one of OK, WARNING, CRITICAL or UNKNOWN.
+    #[prost(string, optional, tag="6")]
+    pub health_check: ::std::option::Option<String>,
+    #[prost(enumeration="Topology", optional, tag="7")]
+    pub topology: ::std::option::Option<i32>,
+    #[prost(enumeration="UpdateStrategy", optional, tag="8")]
+    pub update_strategy: ::std::option::Option<i32>,
+    /// Where the service is in its update strategy, if it has one. See `ServiceUpdater`.
+    #[prost(string, optional, tag="9")]
+    pub update_state: ::std::option::Option<String>,
+    #[prost(string, optional, tag="10")]
+    pub channel: ::std::option::Option<String>,
+    #[prost(string, optional, tag="11")]
+    pub bldr_url: ::std::option::Option<String>,
+    #[prost(message, repeated, tag="12")]
+    pub binds: ::std::vec::Vec<ServiceBind>,
+    #[prost(enumeration="BindingMode", optional, tag="13")]
+    pub binding_mode: ::std::option::Option<i32>,
+    #[prost(message, optional, tag="14")]
+    pub health_check_interval: ::std::option::Option<HealthCheckInterval>,
+    /// The package identifier the service was loaded with, which may be less specific than `ident`.
+    #[prost(message, optional, tag="15")]
+    pub spec_ident: ::std::option::Option<PackageIdent>,
+    /// Name of the hook which most recently timed out, unless it has since run to completion.
+    #[prost(string, optional, tag="16")]
+    pub timed_out_hook: ::std::option::Option<String>,
 }
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
@@ -324,6 +324,11 @@ pub struct ServiceFile {
 pub struct ServiceConfig {
     pub incarnation: u64,
     pub value: toml::value::Table,
+    /// The configuration exactly as it was gossiped, which is encrypted if `encrypted` is set.
+    #[serde(skip)]
+    pub raw: Vec<u8>,
+    #[serde(skip)]
+    pub encrypted: bool,
 }
 
 #[derive(Debug)]
@@ -492,6 +497,8 @@ impl CensusGroup {
                     self.service_config = Some(ServiceConfig {
                         incarnation: service_config.incarnation,
                         value: config,
+                        raw: service_config.config.clone(),
+                        encrypted: service_config.encrypted,
                     });
                 }
             }
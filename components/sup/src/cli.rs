@@ -57,6 +57,11 @@ mod test {
                         "BIND" => ["service.group1", "service.group2"],
                         "PKG_IDENT_OR_ARTIFACT" => "core/redis");
 
+        assert_cli_cmd!(should_handle_log_sink_with_syslog_address,
+                        "hab-sup run --log-sink syslog --syslog-address 127.0.0.1:514",
+                        "LOG_SINK" => "syslog",
+                        "SYSLOG_ADDRESS" => "127.0.0.1:514");
+
     }
 
 }
@@ -0,0 +1,366 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Access control for clients of the CtlGateway.
+//!
+//! A client presenting the Supervisor's `CTL_SECRET` in its [`protocol.ctl.Handshake`] is
+//! granted every message type, as it always has been. Operators may additionally define named
+//! credentials in a `CTL_ACL.toml` file in the Supervisor root. Each credential has its own secret
+//! and is scoped to a list of message types and, optionally, to a list of service groups:
+//!
+//! ```toml
+//! [[credential]]
+//! name = "monitoring"
+//! secret = "..."
+//! allow = ["SvcStatus"]
+//!
+//! [[credential]]
+//! name = "deployer"
+//! secret = "..."
+//! allow = ["SvcSetCfg", "SvcFilePut"]
+//! service_groups = ["redis.default", "redis.prod@acme"]
+//! ```
+//!
+//! A credential restricted to service groups may only send requests which name one of those
+//! service groups; requests targeting a package identifier are denied for it.
+//!
+//! When the CtlGateway requires TLS client certificates, a client which presented a verified
+//! certificate and an empty secret is granted every message type.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    error::{Error, Result},
+    hcore::{crypto, service::ServiceGroup},
+};
+use toml;
+
+static LOGKEY: &'static str = "AC";
+
+/// Name of the file, relative to the Supervisor root, containing additional CtlGateway
+/// credentials.
+pub const CTL_ACL_FILENAME: &str = "CTL_ACL.toml";
+
+/// Name given to the credential backed by the Supervisor's `CTL_SECRET`.
+pub const CTL_SECRET_CREDENTIAL: &str = "ctl-secret";
+
+/// Name given to the credential of clients authenticated by a TLS client certificate.
+pub const CLIENT_CERTIFICATE_CREDENTIAL: &str = "client-certificate";
+
+/// Value of an `allow` entry granting every message type.
+const ALLOW_ALL: &str = "*";
+
+/// Every request message the CtlGateway dispatches.
+const MESSAGE_IDS: &[&str] = &[
+    "SupAudit",
+    "SupDepart",
+    "SvcAttach",
+    "SvcDiffCfg",
+    "SvcFilePut",
+    "SvcGetCfgHistory",
+    "SvcGetCfgProvenance",
+    "SvcGetDefaultCfg",
+    "SvcLoad",
+    "SvcLogs",
+    "SvcRollbackCfg",
+    "SvcSetCfg",
+    "SvcStart",
+    "SvcStatus",
+    "SvcStop",
+    "SvcUnload",
+    "SvcValidateCfg",
+];
+
+/// Request messages which only read state from the Supervisor.
+const READ_ONLY_MESSAGE_IDS: &[&str] = &[
+    "SupAudit",
+    "SvcDiffCfg",
+    "SvcGetCfgHistory",
+    "SvcGetCfgProvenance",
+    "SvcGetDefaultCfg",
+    "SvcLogs",
+    "SvcStatus",
+    "SvcValidateCfg",
+];
+
+/// Returns true if the given request message changes the state of the Supervisor or its
+/// services.
+pub fn is_mutation(message_id: &str) -> bool {
+    !READ_ONLY_MESSAGE_IDS.contains(&message_id)
+}
+
+/// Returns the location of the CtlGateway access control file for the given Supervisor root.
+pub fn acl_path<T>(sup_root: T) -> PathBuf
+where
+    T: AsRef<Path>,
+{
+    sup_root.as_ref().join(CTL_ACL_FILENAME)
+}
+
+/// The set of credentials a CtlGateway client may authenticate with.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct CtlAcl {
+    #[serde(default, rename = "credential")]
+    credentials: Vec<Credential>,
+}
+
+impl CtlAcl {
+    /// Load the access control file from the given Supervisor root. A missing file yields an
+    /// empty list of credentials.
+    pub fn load<T>(sup_root: T) -> Result<Self>
+    where
+        T: AsRef<Path>,
+    {
+        let path = acl_path(sup_root);
+        if !path.exists() {
+            return Ok(CtlAcl::default());
+        }
+        let file =
+            File::open(&path).map_err(|err| sup_error!(Error::CtlAclIo(path.clone(), err)))?;
+        let mut buf = String::new();
+        BufReader::new(file)
+            .read_to_string(&mut buf)
+            .map_err(|err| sup_error!(Error::CtlAclIo(path.clone(), err)))?;
+        let acl = Self::from_str(&buf)?;
+        debug!(
+            "Loaded {} CtlGateway credential(s) from {}",
+            acl.credentials.len(),
+            path.display()
+        );
+        Ok(acl)
+    }
+
+    /// Find the credential matching the secret presented by a client. Secrets are compared in
+    /// constant time.
+    pub fn authenticate(&self, secret: &str) -> Option<&Credential> {
+        self.credentials
+            .iter()
+            .find(|c| crypto::secure_eq(secret, &c.secret))
+    }
+}
+
+impl FromStr for CtlAcl {
+    type Err = crate::error::SupError;
+
+    fn from_str(toml: &str) -> Result<Self> {
+        let acl: CtlAcl = toml::from_str(toml).map_err(|e| sup_error!(Error::CtlAclParse(e)))?;
+        for credential in &acl.credentials {
+            credential.validate()?;
+        }
+        Ok(acl)
+    }
+}
+
+/// A named secret and the requests it is permitted to make.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Credential {
+    pub name: String,
+    secret: String,
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    service_groups: Vec<String>,
+}
+
+impl Credential {
+    /// The credential for clients presenting the Supervisor's `CTL_SECRET`. It is allowed every
+    /// message type for every service group.
+    pub fn ctl_secret<T>(secret: T) -> Self
+    where
+        T: ToString,
+    {
+        Self::unrestricted(CTL_SECRET_CREDENTIAL, secret.to_string())
+    }
+
+    /// The credential for clients which presented a verified TLS client certificate. It is
+    /// allowed every message type for every service group.
+    pub fn client_certificate() -> Self {
+        Self::unrestricted(CLIENT_CERTIFICATE_CREDENTIAL, String::new())
+    }
+
+    fn unrestricted(name: &str, secret: String) -> Self {
+        Credential {
+            name: name.to_string(),
+            secret,
+            allow: vec![ALLOW_ALL.to_string()],
+            service_groups: vec![],
+        }
+    }
+
+    /// Returns true if this credential may send the given message, which optionally targets a
+    /// service group.
+    pub fn permits(&self, message_id: &str, service_group: Option<&str>) -> bool {
+        if !self.allow.iter().any(|a| a == ALLOW_ALL || a == message_id) {
+            return false;
+        }
+        if self.service_groups.is_empty() {
+            return true;
+        }
+        match service_group {
+            Some(sg) => self.service_groups.iter().any(|s| s == sg),
+            None => false,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(sup_error!(Error::CtlAclInvalid(
+                "credential name must not be empty".to_string()
+            )));
+        }
+        if self.secret.is_empty() {
+            return Err(sup_error!(Error::CtlAclInvalid(format!(
+                "credential '{}' has an empty secret",
+                self.name
+            ))));
+        }
+        for allowed in &self.allow {
+            if allowed != ALLOW_ALL && !MESSAGE_IDS.contains(&allowed.as_str()) {
+                return Err(sup_error!(Error::CtlAclInvalid(format!(
+                    "credential '{}' allows unknown message type '{}'",
+                    self.name, allowed
+                ))));
+            }
+        }
+        for service_group in &self.service_groups {
+            ServiceGroup::validate(service_group).map_err(|e| {
+                sup_error!(Error::CtlAclInvalid(format!(
+                    "credential '{}' has an invalid service group '{}', {}",
+                    self.name, service_group, e
+                )))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{fs::File, io::Write};
+    use tempfile::TempDir;
+
+    const ACL: &str = r#"
+        [[credential]]
+        name = "monitoring"
+        secret = "monitoring-secret"
+        allow = ["SvcStatus"]
+
+        [[credential]]
+        name = "deployer"
+        secret = "deployer-secret"
+        allow = ["SvcSetCfg", "SvcFilePut"]
+        service_groups = ["redis.default"]
+        "#;
+
+    #[test]
+    fn missing_file_has_no_credentials() {
+        let tmpdir = TempDir::new().unwrap();
+        let acl = CtlAcl::load(tmpdir.path()).unwrap();
+        assert!(acl.authenticate("anything").is_none());
+    }
+
+    #[test]
+    fn load_from_sup_root() {
+        let tmpdir = TempDir::new().unwrap();
+        let mut file = File::create(acl_path(tmpdir.path())).unwrap();
+        file.write_all(ACL.as_bytes()).unwrap();
+        let acl = CtlAcl::load(tmpdir.path()).unwrap();
+        assert_eq!(
+            acl.authenticate("deployer-secret").unwrap().name,
+            "deployer"
+        );
+    }
+
+    #[test]
+    fn authenticate_matches_secret() {
+        let acl = CtlAcl::from_str(ACL).unwrap();
+        assert_eq!(
+            acl.authenticate("monitoring-secret").unwrap().name,
+            "monitoring"
+        );
+        assert!(acl.authenticate("monitoring").is_none());
+    }
+
+    #[test]
+    fn permits_only_allowed_messages() {
+        let acl = CtlAcl::from_str(ACL).unwrap();
+        let monitoring = acl.authenticate("monitoring-secret").unwrap();
+        assert!(monitoring.permits("SvcStatus", None));
+        assert!(!monitoring.permits("SvcStop", None));
+        assert!(!monitoring.permits("SvcSetCfg", Some("redis.default")));
+    }
+
+    #[test]
+    fn permits_only_allowed_service_groups() {
+        let acl = CtlAcl::from_str(ACL).unwrap();
+        let deployer = acl.authenticate("deployer-secret").unwrap();
+        assert!(deployer.permits("SvcSetCfg", Some("redis.default")));
+        assert!(!deployer.permits("SvcSetCfg", Some("redis.prod")));
+        assert!(!deployer.permits("SvcSetCfg", None));
+        assert!(!deployer.permits("SvcLoad", None));
+    }
+
+    #[test]
+    fn ctl_secret_permits_everything() {
+        let credential = Credential::ctl_secret("shh");
+        assert_eq!(credential.name, CTL_SECRET_CREDENTIAL);
+        assert!(credential.permits("SvcLoad", None));
+        assert!(credential.permits("SvcSetCfg", Some("redis.default")));
+    }
+
+    #[test]
+    fn unknown_message_type_is_invalid() {
+        let toml = r#"
+            [[credential]]
+            name = "typo"
+            secret = "s3cret"
+            allow = ["SvcStats"]
+            "#;
+        assert!(CtlAcl::from_str(toml).is_err());
+    }
+
+    #[test]
+    fn invalid_service_group_is_invalid() {
+        let toml = r#"
+            [[credential]]
+            name = "typo"
+            secret = "s3cret"
+            allow = ["SvcSetCfg"]
+            service_groups = ["redis"]
+            "#;
+        assert!(CtlAcl::from_str(toml).is_err());
+    }
+
+    #[test]
+    fn mutations() {
+        assert!(is_mutation("SvcLoad"));
+        assert!(is_mutation("SvcSetCfg"));
+        assert!(is_mutation("SupDepart"));
+        assert!(is_mutation("SvcRollbackCfg"));
+        assert!(is_mutation("SvcAttach"));
+        assert!(!is_mutation("SvcStatus"));
+        assert!(!is_mutation("SvcGetDefaultCfg"));
+        assert!(!is_mutation("SupAudit"));
+        assert!(!is_mutation("SvcDiffCfg"));
+        assert!(!is_mutation("SvcGetCfgProvenance"));
+        assert!(!is_mutation("SvcGetCfgHistory"));
+        assert!(!is_mutation("SvcLogs"));
+    }
+}
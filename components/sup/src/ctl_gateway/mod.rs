@@ -20,6 +20,7 @@
 //! The [`ctl_gateway.client`] and [`ctl_gateway.server`] speak a streaming, multiplexed, binary
 //! protocol defined in [`protocol.codec`].
 
+pub mod acl;
 pub mod server;
 
 use std::{
@@ -27,6 +28,7 @@ use std::{
     fmt,
     fs::{self, File},
     io::{self, Write},
+    net::SocketAddr,
     path::Path,
 };
 
@@ -74,6 +76,10 @@ pub struct CtlRequest {
     tx: Option<server::CtlSender>,
     /// Transaction for the given request.
     transaction: Option<protocol::codec::SrvTxn>,
+    /// Address of the client which sent the request.
+    peer: Option<SocketAddr>,
+    /// Name of the credential the client authenticated with.
+    credential: Option<String>,
 }
 
 impl CtlRequest {
@@ -83,7 +89,31 @@ impl CtlRequest {
         tx: Option<server::CtlSender>,
         transaction: Option<protocol::codec::SrvTxn>,
     ) -> Self {
-        CtlRequest { tx, transaction }
+        CtlRequest {
+            tx,
+            transaction,
+            peer: None,
+            credential: None,
+        }
+    }
+
+    /// Record the client which sent this request and the credential it authenticated with.
+    pub fn set_origin<T>(&mut self, peer: SocketAddr, credential: T)
+    where
+        T: ToString,
+    {
+        self.peer = Some(peer);
+        self.credential = Some(credential.to_string());
+    }
+
+    /// Address of the client which sent this request, if it came from a client.
+    pub fn peer(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+
+    /// Name of the credential the client authenticated with, if it came from a client.
+    pub fn credential(&self) -> Option<&str> {
+        self.credential.as_ref().map(String::as_str)
     }
 
     /// Reply to the transaction with the given message but indicate to the receiver that this is
@@ -95,6 +125,16 @@ impl CtlRequest {
         self.send_msg(msg, false);
     }
 
+    /// Like `reply_partial` but returns false if the reply could not be delivered because the
+    /// client has gone away. Commands which stream replies for an unbounded amount of time use
+    /// this to notice when they should stop.
+    pub fn try_reply_partial<T>(&mut self, msg: T) -> bool
+    where
+        T: Into<protocol::codec::SrvMessage> + fmt::Debug,
+    {
+        self.send_msg(msg, false)
+    }
+
     /// Reply to the transaction with the given message and indicate to the receiver that this is
     /// the final message for the transaction.
     pub fn reply_complete<T>(&mut self, msg: T)
@@ -109,7 +149,7 @@ impl CtlRequest {
         self.transaction.is_some() && self.tx.is_some()
     }
 
-    fn send_msg<T>(&mut self, msg: T, complete: bool)
+    fn send_msg<T>(&mut self, msg: T, complete: bool) -> bool
     where
         T: Into<protocol::codec::SrvMessage> + fmt::Debug,
     {
@@ -118,11 +158,11 @@ impl CtlRequest {
                 "Attempted to reply to a non-transactional message with {:?}",
                 msg
             );
-            return;
+            return false;
         }
         let mut wire: protocol::codec::SrvMessage = msg.into();
         wire.reply_for(self.transaction.unwrap(), complete);
-        self.tx.as_ref().unwrap().start_send(wire).ok(); // ignore Err return
+        self.tx.as_ref().unwrap().start_send(wire).is_ok()
     }
 }
 
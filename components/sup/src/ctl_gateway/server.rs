@@ -22,9 +22,19 @@
 //! mpsc channel, [`CtlSender`], to [`CtlReceiver`]. A new mpsc pair is created for each
 //! transactional request where the sending half is given to a [`ctl_gateway.CtlRequest`].
 
-use std::{cell::RefCell, error, fmt, io, net::SocketAddr, rc::Rc, thread, time::Duration};
+use std::{
+    cell::RefCell,
+    error, fmt, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{mpsc as std_mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
 
 use crate::{
+    common::trace,
     hcore::crypto,
     protocol::{
         self,
@@ -39,12 +49,22 @@ use futures::{
 };
 use prometheus::{HistogramTimer, HistogramVec, IntCounterVec};
 use prost;
-use tokio::net::TcpListener;
+use rustls::{ServerConfig, Session};
+use tokio::net::{TcpListener, TcpStream};
 use tokio_codec::Framed;
 use tokio_core::reactor;
+use tokio_rustls::TlsAcceptor;
+
+use super::{
+    acl::{self, Credential, CtlAcl},
+    CtlRequest, REQ_TIMEOUT,
+};
+use crate::manager::{
+    audit::{self, AuditEntry},
+    commands, ManagerState,
+};
 
-use super::{CtlRequest, REQ_TIMEOUT};
-use crate::manager::{commands, ManagerState};
+static LOGKEY: &'static str = "AG";
 
 lazy_static! {
     static ref RPC_CALLS: IntCounterVec = register_int_counter_vec!(
@@ -132,6 +152,10 @@ impl From<mpsc::SendError<CtlCommand>> for HandlerError {
 /// A wrapper around a [`ctl_gateway.CtlRequest`] and a closure for the main thread to execute.
 pub struct CtlCommand {
     pub req: CtlRequest,
+    /// Message type of the request, recorded on its trace span.
+    message_id: String,
+    /// Service group targeted by the request, if any, recorded on its trace span.
+    service_group: Option<String>,
     // JW: This needs to be an `FnOnce<Box>` and not an `Fn<Box>` but right now there is no support
     // for boxing an FnOnce in stable Rust. There is a new type called `FnBox` which exists only on
     // nightly right now which accomplishes this but it won't stabilize because the Rust core team
@@ -151,12 +175,28 @@ impl CtlCommand {
         CtlCommand {
             fun: Box::new(fun),
             req: CtlRequest::new(tx, txn),
+            message_id: String::new(),
+            service_group: None,
         }
     }
 
+    /// Record the message type of the request and the service group it targets.
+    pub fn set_target(&mut self, message_id: &str, service_group: Option<String>) {
+        self.message_id = message_id.to_string();
+        self.service_group = service_group;
+    }
+
     /// Run the contained closure with the given [`manager.ManagerState`].
     pub fn run(&mut self, state: &ManagerState) -> NetResult<()> {
-        (self.fun)(state, &mut self.req)
+        let mut span = trace::span("ctl.request").attr("message_type", &self.message_id);
+        if let Some(ref service_group) = self.service_group {
+            span.set_attr("service_group", service_group);
+        }
+        let result = (self.fun)(state, &mut self.req);
+        if let Err(ref err) = result {
+            span.set_error(err);
+        }
+        result
     }
 }
 
@@ -164,25 +204,54 @@ impl CtlCommand {
 struct Client {
     handle: reactor::Handle,
     state: Rc<RefCell<SrvState>>,
+    addr: SocketAddr,
 }
 
 impl Client {
-    /// Serve the client from the given framed socket stream.
-    pub fn serve(self, socket: SrvStream) -> Box<dyn Future<Item = (), Error = HandlerError>> {
+    /// Serve the client from the given socket. If the server has a TLS configuration then a TLS
+    /// session is negotiated before the socket is framed.
+    pub fn serve(self, socket: TcpStream) -> Box<dyn Future<Item = (), Error = HandlerError>> {
         let mgr_tx = self.state.borrow().mgr_tx.clone();
+        let sup_root = self.state.borrow().sup_root.clone();
+        let addr = self.addr;
+        let accept: Box<dyn Future<Item = (SrvSocket, bool), Error = HandlerError>> =
+            match self.state.borrow().tls_config {
+                Some(ref tls_config) => Box::new(
+                    TlsAcceptor::from(tls_config.clone())
+                        .accept(socket)
+                        .map(|socket| {
+                            let verified = socket.get_ref().1.get_peer_certificates().is_some();
+                            (SrvSocket::from(socket), verified)
+                        })
+                        .map_err(HandlerError::from),
+                ),
+                None => Box::new(future::ok((SrvSocket::from(socket), false))),
+            };
         Box::new(
-            self.handshake(socket)
-                .and_then(|socket| SrvHandler::new(socket, mgr_tx)),
+            accept
+                .and_then(move |(socket, verified)| {
+                    self.handshake(Framed::new(socket, SrvCodec::new()), verified)
+                })
+                .and_then(move |(socket, credential)| {
+                    SrvHandler::new(socket, mgr_tx, credential, addr, sup_root)
+                }),
         )
     }
 
     /// Initiate a handshake with the connected client before allowing future requests. A failed
     /// handshake will close the connection.
+    ///
+    /// The secret key presented by the client is matched against the Supervisor's secret key and
+    /// then against the credentials of the [`ctl_gateway.acl.CtlAcl`]. A client which presented a
+    /// verified TLS client certificate may instead send an empty secret key. The matching
+    /// credential governs which requests the client may make for the rest of the connection.
     fn handshake(
         &self,
         socket: SrvStream,
-    ) -> Box<dyn Future<Item = SrvStream, Error = HandlerError>> {
+        verified: bool,
+    ) -> Box<dyn Future<Item = (SrvStream, Credential), Error = HandlerError>> {
         let secret_key = self.state.borrow().secret_key.to_string();
+        let acl = self.state.borrow().acl.clone();
         let handshake = socket
             .into_future()
             .map_err(|(err, _)| HandlerError::from(err))
@@ -209,7 +278,14 @@ impl Client {
                             Ok(decoded) => {
                                 trace!("Received handshake, {:?}", decoded);
                                 let decoded_key = decoded.secret_key.unwrap_or_default();
-                                Ok((m, crypto::secure_eq(decoded_key, secret_key), io))
+                                let credential = if crypto::secure_eq(&decoded_key, &secret_key) {
+                                    Some(Credential::ctl_secret(secret_key))
+                                } else if verified && decoded_key.is_empty() {
+                                    Some(Credential::client_certificate())
+                                } else {
+                                    acl.authenticate(&decoded_key).cloned()
+                                };
+                                Ok((m, credential, io))
                             }
                             Err(err) => {
                                 warn!("Handshake error, {:?}", err);
@@ -221,8 +297,8 @@ impl Client {
                     },
                 )
             })
-            .and_then(|(msg, success, socket)| {
-                let mut reply = if success {
+            .and_then(|(msg, credential, socket)| {
+                let mut reply = if credential.is_some() {
                     SrvMessage::from(net::ok())
                 } else {
                     SrvMessage::from(net::err(ErrCode::Unauthorized, "secret key mismatch"))
@@ -231,14 +307,14 @@ impl Client {
                 socket
                     .send(reply)
                     .map_err(HandlerError::from)
-                    .and_then(move |io| Ok((io, success)))
+                    .and_then(move |io| Ok((io, credential)))
             });
         Box::new(
             handshake
                 .select2(self.timeout(REQ_TIMEOUT))
                 .then(|res| match res {
-                    Ok(Either::A(((io, true), _to))) => future::ok(io),
-                    Ok(Either::A(((_, false), _to))) => future::err(HandlerError::from(
+                    Ok(Either::A(((io, Some(credential)), _to))) => future::ok((io, credential)),
+                    Ok(Either::A(((_, None), _to))) => future::err(HandlerError::from(
                         io::Error::new(io::ErrorKind::ConnectionAborted, "handshake failed"),
                     )),
                     Ok(Either::B((_to, _hs))) => future::err(HandlerError::from(io::Error::new(
@@ -267,10 +343,22 @@ struct SrvHandler {
     rx: CtlReceiver,
     tx: CtlSender,
     timer: Option<HistogramTimer>,
+    credential: Credential,
+    addr: SocketAddr,
+    /// Root of the Supervisor, whose audit log denied requests are recorded in.
+    sup_root: PathBuf,
+    /// Input sent by a client attached to a service, for the duration of the attachment.
+    input: Option<std_mpsc::Sender<protocol::ctl::SvcAttachInput>>,
 }
 
 impl SrvHandler {
-    fn new(io: SrvStream, mgr_tx: MgrSender) -> Self {
+    fn new(
+        io: SrvStream,
+        mgr_tx: MgrSender,
+        credential: Credential,
+        addr: SocketAddr,
+        sup_root: PathBuf,
+    ) -> Self {
         let (tx, rx) = mpsc::unbounded();
         SrvHandler {
             io,
@@ -279,8 +367,93 @@ impl SrvHandler {
             rx,
             tx,
             timer: None,
+            credential,
+            addr,
+            sup_root,
+            input: None,
+        }
+    }
+
+    /// Pass the input a client attached to a service has sent on to the attachment. Once the
+    /// client disconnects the input channel is closed, which detaches it.
+    fn poll_input(&mut self) -> Result<(), HandlerError> {
+        while let Some(ref input) = self.input {
+            match self.io.poll()? {
+                Async::Ready(Some(msg)) => {
+                    if msg.message_id() != "SvcAttachInput" {
+                        warn!("Unexpected message while attached, {}", msg.message_id());
+                        continue;
+                    }
+                    let m = msg.parse::<protocol::ctl::SvcAttachInput>()?;
+                    if input.send(m).is_err() {
+                        self.input = None;
+                    }
+                }
+                Async::Ready(None) => self.input = None,
+                Async::NotReady => break,
+            }
         }
+        Ok(())
     }
+
+    fn authorize(&self, msg: &SrvMessage) -> Result<bool, HandlerError> {
+        authorize(&self.sup_root, &self.credential, self.addr, msg)
+    }
+}
+
+/// Check the given request from `addr` against the client's credential. Denied requests are logged
+/// and recorded in the audit log in `sup_root`. Permitted requests which mutate the Supervisor are
+/// logged.
+fn authorize(
+    sup_root: &Path,
+    credential: &Credential,
+    addr: SocketAddr,
+    msg: &SrvMessage,
+) -> Result<bool, HandlerError> {
+    let message_id = msg.message_id();
+    let service_group = target_service_group(msg)?;
+    let target = service_group.as_ref().map(String::as_str).unwrap_or("-");
+    if !credential.permits(message_id, service_group.as_ref().map(String::as_str)) {
+        outputln!(
+            "Denied {} for {} from {} using credential '{}'",
+            message_id,
+            target,
+            addr,
+            credential.name
+        );
+        audit::record(
+            sup_root,
+            &AuditEntry::denied(addr, &credential.name, message_id, target),
+        );
+        return Ok(false);
+    }
+    if acl::is_mutation(message_id) {
+        outputln!(
+            "Accepted {} for {} from {} using credential '{}'",
+            message_id,
+            target,
+            addr,
+            credential.name
+        );
+    }
+    Ok(true)
+}
+
+/// Returns the service group targeted by the given request, if the request targets one.
+fn target_service_group(msg: &SrvMessage) -> Result<Option<String>, HandlerError> {
+    let service_group = match msg.message_id() {
+        "SvcFilePut" => msg.parse::<protocol::ctl::SvcFilePut>()?.service_group,
+        "SvcSetCfg" => msg.parse::<protocol::ctl::SvcSetCfg>()?.service_group,
+        "SvcValidateCfg" => msg.parse::<protocol::ctl::SvcValidateCfg>()?.service_group,
+        "SvcDiffCfg" => msg.parse::<protocol::ctl::SvcDiffCfg>()?.service_group,
+        "SvcGetCfgHistory" => {
+            msg.parse::<protocol::ctl::SvcGetCfgHistory>()?
+                .service_group
+        }
+        "SvcRollbackCfg" => msg.parse::<protocol::ctl::SvcRollbackCfg>()?.service_group,
+        _ => None,
+    };
+    Ok(service_group.map(|sg| sg.to_string()))
 }
 
 impl Future for SrvHandler {
@@ -300,7 +473,28 @@ impl Future for SrvHandler {
                         self.timer = Some(timer);
 
                         trace!("OnMessage, {}", msg.message_id());
-                        let cmd = match msg.message_id() {
+                        if !self.authorize(&msg)? {
+                            let txn = match msg.transaction() {
+                                Some(txn) => txn,
+                                None => break,
+                            };
+                            let mut reply = SrvMessage::from(net::err(
+                                ErrCode::Unauthorized,
+                                format!(
+                                    "credential '{}' is not permitted to send {}",
+                                    self.credential.name,
+                                    msg.message_id()
+                                ),
+                            ));
+                            reply.reply_for(txn, true);
+                            if let Err(err) = self.tx.unbounded_send(reply) {
+                                warn!("CtlSender err, {:?}", err);
+                                break;
+                            }
+                            self.state = SrvHandlerState::Sending;
+                            continue;
+                        }
+                        let mut cmd = match msg.message_id() {
                             "SvcGetDefaultCfg" => {
                                 let m = msg
                                     .parse::<protocol::ctl::SvcGetDefaultCfg>()
@@ -311,6 +505,18 @@ impl Future for SrvHandler {
                                     move |state, req| commands::service_cfg(state, req, m.clone()),
                                 )
                             }
+                            "SvcGetCfgProvenance" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcGetCfgProvenance>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        commands::service_cfg_provenance(state, req, m.clone())
+                                    },
+                                )
+                            }
                             "SvcFilePut" => {
                                 let m = msg
                                     .parse::<protocol::ctl::SvcFilePut>()
@@ -347,6 +553,42 @@ impl Future for SrvHandler {
                                     },
                                 )
                             }
+                            "SvcDiffCfg" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcDiffCfg>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        commands::service_cfg_diff(state, req, m.clone())
+                                    },
+                                )
+                            }
+                            "SvcGetCfgHistory" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcGetCfgHistory>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        commands::service_cfg_history(state, req, m.clone())
+                                    },
+                                )
+                            }
+                            "SvcRollbackCfg" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcRollbackCfg>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        commands::service_cfg_rollback(state, req, m.clone())
+                                    },
+                                )
+                            }
                             "SvcLoad" => {
                                 let m = msg
                                     .parse::<protocol::ctl::SvcLoad>()
@@ -403,6 +645,39 @@ impl Future for SrvHandler {
                                     },
                                 )
                             }
+                            "SvcLogs" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcLogs>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| commands::service_logs(state, req, m.clone()),
+                                )
+                            }
+                            "SvcAttach" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcAttach>()
+                                    .map_err(HandlerError::from)?;
+                                let (input_tx, input_rx) = std_mpsc::channel();
+                                self.input = Some(input_tx);
+                                // The command's closure may only be called once, but must be `Fn`.
+                                let input_rx = Mutex::new(Some(input_rx));
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        let input = input_rx
+                                            .lock()
+                                            .expect("Attach input lock is poisoned")
+                                            .take()
+                                            .ok_or_else(|| {
+                                                net::err(ErrCode::Internal, "Already attached")
+                                            })?;
+                                        commands::service_attach(state, req, m.clone(), input)
+                                    },
+                                )
+                            }
                             "SupDepart" => {
                                 let m = msg
                                     .parse::<protocol::ctl::SupDepart>()
@@ -415,11 +690,25 @@ impl Future for SrvHandler {
                                     },
                                 )
                             }
+                            "SupAudit" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SupAudit>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        commands::supervisor_audit(state, req, m.clone())
+                                    },
+                                )
+                            }
                             _ => {
                                 warn!("Unhandled message, {}", msg.message_id());
                                 break;
                             }
                         };
+                        cmd.req.set_origin(self.addr, &self.credential.name);
+                        cmd.set_target(msg.message_id(), target_service_group(&msg)?);
                         match self.mgr_tx.start_send(cmd) {
                             Ok(AsyncSink::Ready) => {
                                 self.state = SrvHandlerState::Sending;
@@ -434,20 +723,23 @@ impl Future for SrvHandler {
                     }
                     None => break,
                 },
-                SrvHandlerState::Sending => match self.rx.poll() {
-                    Ok(Async::Ready(Some(msg))) => {
-                        trace!("MgrSender -> SrvHandler, {:?}", msg);
-                        if msg.is_complete() {
-                            self.state = SrvHandlerState::Sent;
+                SrvHandlerState::Sending => {
+                    self.poll_input()?;
+                    match self.rx.poll() {
+                        Ok(Async::Ready(Some(msg))) => {
+                            trace!("MgrSender -> SrvHandler, {:?}", msg);
+                            if msg.is_complete() {
+                                self.state = SrvHandlerState::Sent;
+                            }
+                            try_nb!(self.io.start_send(msg));
+                            try_ready!(self.io.poll_complete());
+                            continue;
                         }
-                        try_nb!(self.io.start_send(msg));
-                        try_ready!(self.io.poll_complete());
-                        continue;
+                        Ok(Async::Ready(None)) => self.state = SrvHandlerState::Sent,
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Err(()) => break,
                     }
-                    Ok(Async::Ready(None)) => self.state = SrvHandlerState::Sent,
-                    Ok(Async::NotReady) => return Ok(Async::NotReady),
-                    Err(()) => break,
-                },
+                }
                 SrvHandlerState::Sent => {
                     if let Some(timer) = self.timer.take() {
                         timer.observe_duration();
@@ -472,32 +764,50 @@ enum SrvHandlerState {
 }
 
 struct SrvState {
+    sup_root: PathBuf,
     secret_key: String,
+    acl: CtlAcl,
+    tls_config: Option<Arc<ServerConfig>>,
     mgr_tx: MgrSender,
 }
 
 /// Start a new thread which will run the CtlGateway server.
 ///
-/// New connections will be authenticated using `secret_key`. Messages from the main thread
-/// will be sent over the channel `mgr_tx`.
-pub fn run(listen_addr: SocketAddr, secret_key: String, mgr_tx: MgrSender) {
+/// New connections will be authenticated using `secret_key` or one of the credentials in `acl`.
+/// If `tls_config` is given then every connection must first negotiate a TLS session. Messages
+/// from the main thread will be sent over the channel `mgr_tx`. Denied requests are recorded in the
+/// audit log in `sup_root`.
+pub fn run(
+    sup_root: PathBuf,
+    listen_addr: SocketAddr,
+    secret_key: String,
+    acl: CtlAcl,
+    tls_config: Option<Arc<ServerConfig>>,
+    mgr_tx: MgrSender,
+) {
     thread::Builder::new()
         .name("ctl-gateway".to_string())
         .spawn(move || {
             let mut core = reactor::Core::new().unwrap();
             let handle = core.handle();
             let listener = TcpListener::bind(&listen_addr).unwrap();
-            let state = SrvState { secret_key, mgr_tx };
+            let state = SrvState {
+                sup_root,
+                secret_key,
+                acl,
+                tls_config,
+                mgr_tx,
+            };
             let state = Rc::new(RefCell::new(state));
             let clients = listener.incoming().map(|socket| {
                 let addr = socket.peer_addr().unwrap();
-                let io = Framed::new(socket, SrvCodec::new());
                 (
                     Client {
                         handle: handle.clone(),
                         state: state.clone(),
+                        addr,
                     }
-                    .serve(io),
+                    .serve(socket),
                     addr,
                 )
             });
@@ -512,3 +822,38 @@ pub fn run(listen_addr: SocketAddr, secret_key: String, mgr_tx: MgrSender) {
         })
         .expect("ctl-gateway thread start failure");
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+    use tempfile::TempDir;
+
+    #[test]
+    fn denied_requests_are_audited() {
+        let tmpdir = TempDir::new().unwrap();
+        let acl = CtlAcl::from_str(
+            r#"
+            [[credential]]
+            name = "monitoring"
+            secret = "monitoring-secret"
+            allow = ["SvcStatus"]
+            "#,
+        )
+        .unwrap();
+        let credential = acl.authenticate("monitoring-secret").unwrap();
+        let addr = "10.0.0.5:51234".parse().unwrap();
+
+        let status = SrvMessage::from(protocol::ctl::SvcStatus::default());
+        assert!(authorize(tmpdir.path(), credential, addr, &status).unwrap());
+        let stop = SrvMessage::from(protocol::ctl::SvcStop::default());
+        assert!(!authorize(tmpdir.path(), credential, addr, &stop).unwrap());
+
+        let entries = audit::query(tmpdir.path(), &protocol::ctl::SupAudit::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message_type, "SvcStop");
+        assert_eq!(entries[0].credential, Some("monitoring".to_string()));
+        assert_eq!(entries[0].remote_addr, Some("10.0.0.5:51234".to_string()));
+        assert!(entries[0].denied);
+    }
+}
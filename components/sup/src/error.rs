@@ -48,6 +48,7 @@ use crate::{
     },
     launcher_client, protocol,
 };
+use chrono;
 use glob;
 use notify;
 use rustls;
@@ -96,11 +97,16 @@ impl SupError {
 #[derive(Debug)]
 pub enum Error {
     Departed,
+    AuditLogIo(PathBuf, io::Error),
+    AuditLogJson(serde_json::Error),
+    AuditTimestamp(String, chrono::ParseError),
     BadAddress(String),
+    BadConsole(String),
     BadDataFile(PathBuf, io::Error),
     BadDataPath(PathBuf, io::Error),
     BadDesiredState(String),
     BadElectionStatus(String),
+    BadLogFormat(String),
     BadPackage(PackageInstall, hcore::error::Error),
     BadSpecsPath(PathBuf, io::Error),
     BadStartStyle(String),
@@ -108,6 +114,11 @@ pub enum Error {
     LockPoisoned,
     TestBootFail,
     ButterflyError(butterfly::error::Error),
+    ConfigHistoryIo(PathBuf, io::Error),
+    ConfigHistoryJson(PathBuf, serde_json::Error),
+    CtlAclInvalid(String),
+    CtlAclIo(PathBuf, io::Error),
+    CtlAclParse(toml::de::Error),
     CtlSecretIo(PathBuf, io::Error),
     APIClient(api_client::Error),
     EnvJoinPathsError(env::JoinPathsError),
@@ -122,6 +133,7 @@ pub enum Error {
     InvalidKeyFile(PathBuf),
     InvalidKeyParameter(String),
     InvalidPidFile,
+    InvalidShutdownTimeout(u32),
     InvalidTokioThreadCount,
     InvalidTopology(String),
     InvalidUpdateStrategy(String),
@@ -173,6 +185,17 @@ impl fmt::Display for SupError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let content = match self.err {
             Error::APIClient(ref err) => err.to_string(),
+            Error::AuditLogIo(ref path, ref err) => format!(
+                "IoError while reading or writing audit log, {}, {}",
+                path.display(),
+                err
+            ),
+            Error::AuditLogJson(ref err) => format!("Unable to serialize audit log entry, {}", err),
+            Error::AuditTimestamp(ref timestamp, ref err) => format!(
+                "Invalid timestamp '{}', {}. Expected an RFC 3339 timestamp such as \
+                 2019-02-01T12:00:00Z",
+                timestamp, err
+            ),
             Error::BadAddress(ref err) => format!("Unable to bind to address {}.", err),
             Error::Departed => {
                 "This Supervisor has been manually departed.\n\nFor the safety of the system, this \
@@ -197,7 +220,9 @@ impl fmt::Display for SupError {
             Error::BadDesiredState(ref state) => {
                 format!("Unknown service desired state style '{}'", state)
             }
+            Error::BadConsole(ref console) => format!("Unknown service console '{}'", console),
             Error::BadElectionStatus(ref status) => format!("Unknown election status '{}'", status),
+            Error::BadLogFormat(ref format) => format!("Unknown service log format '{}'", format),
             Error::BadPackage(ref pkg, ref err) => format!("Bad package, {}, {}", pkg, err),
             Error::BadSpecsPath(ref path, ref err) => format!(
                 "Unable to create the specs directory '{}' ({})",
@@ -209,6 +234,25 @@ impl fmt::Display for SupError {
             Error::LockPoisoned => "A mutex or read/write lock has failed.".to_string(),
             Error::TestBootFail => "Simulated boot failure".to_string(),
             Error::ButterflyError(ref err) => format!("Butterfly error: {}", err),
+            Error::ConfigHistoryIo(ref path, ref err) => format!(
+                "IoError while reading or writing configuration history, {}, {}",
+                path.display(),
+                err
+            ),
+            Error::ConfigHistoryJson(ref path, ref err) => format!(
+                "Unable to read or write configuration history, {}, {}",
+                path.display(),
+                err
+            ),
+            Error::CtlAclInvalid(ref err) => format!("Invalid ctl access control list, {}", err),
+            Error::CtlAclIo(ref path, ref err) => format!(
+                "IoError while reading ctl access control list, {}, {}",
+                path.display(),
+                err
+            ),
+            Error::CtlAclParse(ref err) => {
+                format!("Unable to parse ctl access control list, {}", err)
+            }
             Error::CtlSecretIo(ref path, ref err) => format!(
                 "IoError while reading or writing ctl secret, {}, {}",
                 path.display(),
@@ -231,6 +275,11 @@ impl fmt::Display for SupError {
                 format!("Invalid parameter for key generation: {:?}", e)
             }
             Error::InvalidPidFile => "Invalid child process PID file".to_string(),
+            Error::InvalidShutdownTimeout(timeout) => format!(
+                "Invalid shutdown timeout of {} seconds, it may be at most {}",
+                timeout,
+                launcher_client::MAX_SHUTDOWN_TIMEOUT
+            ),
             Error::InvalidTokioThreadCount => {
                 "Tokio thread count should be a positive integer".to_string()
             }
@@ -332,12 +381,17 @@ impl error::Error for SupError {
     fn description(&self) -> &str {
         match self.err {
             Error::APIClient(ref err) => err.description(),
+            Error::AuditLogIo(..) => "IoError while reading or writing audit log",
+            Error::AuditLogJson(_) => "Unable to serialize audit log entry",
+            Error::AuditTimestamp(..) => "Invalid audit log timestamp",
             Error::BadAddress(_) => "Unable to bind to address",
             Error::Departed => "Supervisor has been manually departed",
+            Error::BadConsole(_) => "Unknown service console",
             Error::BadDataFile(..) => "Unable to read or write to a data file",
             Error::BadDataPath(..) => "Unable to read or write to data directory",
             Error::BadElectionStatus(_) => "Unknown election status",
             Error::BadDesiredState(_) => "Unknown desired state in service spec",
+            Error::BadLogFormat(_) => "Unknown service log format",
             Error::BadPackage(..) => "Package was malformed or contained malformed contents",
             Error::BadSpecsPath(..) => "Unable to create the specs directory",
             Error::BadStartStyle(_) => "Unknown start style in service spec",
@@ -345,6 +399,11 @@ impl error::Error for SupError {
             Error::LockPoisoned => "A mutex or read/write lock has failed",
             Error::TestBootFail => "Simulated boot failure",
             Error::ButterflyError(ref err) => err.description(),
+            Error::ConfigHistoryIo(..) => "IoError while reading or writing configuration history",
+            Error::ConfigHistoryJson(..) => "Unable to read or write configuration history",
+            Error::CtlAclInvalid(_) => "Invalid ctl access control list",
+            Error::CtlAclIo(..) => "IoError while reading ctl access control list",
+            Error::CtlAclParse(_) => "Ctl access control list could not be parsed successfully",
             Error::CtlSecretIo(..) => "IoError while reading ctl secret",
             Error::ExecCommandNotFound(_) => "Exec command was not found on filesystem or in PATH",
             Error::GroupNotFound(_) => "No matching GID for group found",
@@ -360,6 +419,7 @@ impl error::Error for SupError {
             Error::InvalidKeyFile(_) => "Invalid key file",
             Error::InvalidKeyParameter(_) => "Key parameter error",
             Error::InvalidPidFile => "Invalid child process PID file",
+            Error::InvalidShutdownTimeout(_) => "Invalid shutdown timeout",
             Error::InvalidTokioThreadCount => "Invalid Tokio thread count",
             Error::InvalidTopology(_) => "Invalid topology",
             Error::InvalidUpdateStrategy(_) => "Invalid update strategy",
@@ -422,8 +422,15 @@ fn config(
         Err(_) => return HttpResponse::BadRequest().finish(),
     };
 
+    // With `?provenance=true`, each key is returned along with the configuration layer which
+    // set it rather than the merged configuration.
+    let field = match req.query().get("provenance").map(String::as_str) {
+        Some("true") => "cfg_provenance",
+        _ => "cfg",
+    };
+
     match service_from_services(&service_group, &data) {
-        Some(mut s) => HttpResponse::Ok().json(s["cfg"].take()),
+        Some(mut s) => HttpResponse::Ok().json(s[field].take()),
         None => HttpResponse::NotFound().finish(),
     }
 }
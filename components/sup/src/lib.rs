@@ -44,6 +44,7 @@ extern crate bitflags;
 
 #[cfg(target_os = "linux")]
 extern crate caps;
+extern crate chrono;
 extern crate clap;
 extern crate cpu_time;
 #[cfg(windows)]
@@ -40,7 +40,7 @@ extern crate tokio_core;
 extern crate url;
 
 use std::{
-    env,
+    env, fs,
     io::{self, Write},
     net::{SocketAddr, ToSocketAddrs},
     path::PathBuf,
@@ -52,6 +52,8 @@ use crate::{
     common::{
         cli_defaults::GOSSIP_DEFAULT_PORT,
         command::package::install::InstallSource,
+        trace::TraceExporter,
+        types::{Capability, HookTimeout, MemorySize, Rlimit},
         ui::{Coloring, NONINTERACTIVE_ENVVAR, UI},
     },
     hcore::{
@@ -281,6 +283,16 @@ fn mgrcfg_from_sup_run_matches(m: &ArgMatches) -> Result<ManagerConfig> {
                 ),
             ))
         }),
+        ctl_tls_files: m.value_of("CTL_KEY_FILE").and_then(|kf| {
+            Some((
+                PathBuf::from(kf),
+                PathBuf::from(m.value_of("CTL_CERT_FILE").expect(
+                    "CTL_CERT_FILE should always have a value if CTL_KEY_FILE has a value.",
+                )),
+            ))
+        }),
+        ctl_client_ca_file: m.value_of("CTL_CLIENT_CA_FILE").map(PathBuf::from),
+        trace_exporter: get_trace_exporter(m)?,
         // default is only included here for the custom_state_path field which will ideally
         // eventually be removed, it only exists to manipulate test data.
         ..Default::default()
@@ -338,6 +350,15 @@ fn get_ring_key(m: &ArgMatches) -> Result<Option<SymKey>> {
     }
 }
 
+fn get_trace_exporter(m: &ArgMatches) -> Result<Option<TraceExporter>> {
+    match m.value_of("TRACE_ENDPOINT") {
+        Some(url) => Ok(Some(TraceExporter::endpoint(url)?)),
+        None => Ok(m
+            .value_of("TRACE_FILE")
+            .map(|path| TraceExporter::File(PathBuf::from(path)))),
+    }
+}
+
 /// Resolve a Builder URL. Taken from CLI args, the environment, or
 /// (failing those) a default value.
 fn bldr_url(m: &ArgMatches) -> String {
@@ -518,6 +539,88 @@ fn update_svc_load_from_input(m: &ArgMatches, msg: &mut protocol::ctl::SvcLoad)
     msg.application_environment = get_app_env_from_input(m)?;
     msg.binds = get_binds_from_input(m)?;
     msg.config_from = get_config_from_input(m);
+    msg.config_source = m.value_of("CONFIG_SOURCE").map(ToString::to_string);
+    msg.config_source_precedence = m
+        .value_of("CONFIG_SOURCE_PRECEDENCE")
+        .map(ToString::to_string);
+    if m.is_present("STRICT_TEMPLATES") {
+        msg.strict_templates = Some(true);
+    }
+    msg.shutdown_signal = m.value_of("SHUTDOWN_SIGNAL").map(ToString::to_string);
+    msg.shutdown_timeout = m
+        .value_of("SHUTDOWN_TIMEOUT")
+        .map(|t| t.parse().expect("SHUTDOWN_TIMEOUT is validated by clap"));
+    msg.memory_max = m.value_of("MEMORY_MAX").map(|s| {
+        MemorySize::from_str(s)
+            .expect("MEMORY_MAX is validated by clap")
+            .bytes()
+    });
+    msg.cpu_weight = m
+        .value_of("CPU_WEIGHT")
+        .map(|w| w.parse().expect("CPU_WEIGHT is validated by clap"));
+    msg.cpu_quota = m
+        .value_of("CPU_QUOTA")
+        .map(|q| q.parse().expect("CPU_QUOTA is validated by clap"));
+    msg.pids_max = m
+        .value_of("PIDS_MAX")
+        .map(|p| p.parse().expect("PIDS_MAX is validated by clap"));
+    if let Some(rlimits) = m.values_of("RLIMIT") {
+        msg.rlimits = rlimits
+            .map(|r| {
+                let rlimit = Rlimit::from_str(r).expect("RLIMIT is validated by clap");
+                (rlimit.name, u64::from(rlimit.value))
+            })
+            .collect();
+    }
+    if m.is_present("DROP_CAPABILITIES") || m.is_present("KEEP_CAPABILITY") {
+        msg.drop_capabilities = Some(true);
+    }
+    if let Some(capabilities) = m.values_of("KEEP_CAPABILITY") {
+        msg.capabilities = capabilities
+            .map(|c| {
+                Capability::from_str(c)
+                    .expect("KEEP_CAPABILITY is validated by clap")
+                    .to_string()
+            })
+            .collect();
+    }
+    if m.is_present("NO_NEW_PRIVS") {
+        msg.no_new_privs = Some(true);
+    }
+    if m.is_present("PRIVATE_TMP") {
+        msg.private_tmp = Some(true);
+    }
+    if m.is_present("READ_ONLY_PACKAGE") {
+        msg.read_only_package = Some(true);
+    }
+    // The profile is read by the Launcher, which may not share our working directory.
+    msg.seccomp_profile = m.value_of("SECCOMP_PROFILE").map(|p| {
+        fs::canonicalize(p)
+            .unwrap_or_else(|_| PathBuf::from(p))
+            .to_string_lossy()
+            .into_owned()
+    });
+    msg.log_format = m.value_of("LOG_FORMAT").map(ToString::to_string);
+    msg.log_max_size = m.value_of("LOG_MAX_SIZE").map(|s| {
+        MemorySize::from_str(s)
+            .expect("LOG_MAX_SIZE is validated by clap")
+            .bytes()
+    });
+    msg.log_max_age = m
+        .value_of("LOG_MAX_AGE")
+        .map(|a| a.parse().expect("LOG_MAX_AGE is validated by clap"));
+    msg.log_retain = m
+        .value_of("LOG_RETAIN")
+        .map(|r| r.parse().expect("LOG_RETAIN is validated by clap"));
+    msg.console = m.value_of("CONSOLE").map(ToString::to_string);
+    if let Some(hook_timeouts) = m.values_of("HOOK_TIMEOUT") {
+        msg.hook_timeouts = hook_timeouts
+            .map(|t| {
+                let timeout = HookTimeout::from_str(t).expect("HOOK_TIMEOUT is validated by clap");
+                (timeout.hook, timeout.seconds)
+            })
+            .collect();
+    }
     if m.is_present("FORCE") {
         msg.force = Some(true);
     }
@@ -540,7 +643,7 @@ mod test {
     mod manager_config {
 
         use super::*;
-        use std::iter::FromIterator;
+        use std::{fs::File, iter::FromIterator};
 
         locked_env_var!(HAB_CACHE_KEY_PATH, lock_var);
 
@@ -746,5 +849,53 @@ RCFaO84j41GmrzWddxMdsXpGdn3iuIy7Mw3xYrjPLsE="#,
             );
         }
 
+        #[test]
+        fn ctl_tls_files_should_be_set() {
+            let tmpdir = TempDir::new().expect("Could not create tempdir");
+            let key = tmpdir.path().join("ctl.key");
+            let cert = tmpdir.path().join("ctl.crt");
+            let ca = tmpdir.path().join("ca.crt");
+            for path in &[&key, &cert, &ca] {
+                File::create(path).expect("Could not create file");
+            }
+            let cmd = format!(
+                "hab-sup run --ctl-server-key {} --ctl-server-certificate {} \
+                 --ctl-client-ca-certificate {}",
+                key.display(),
+                cert.display(),
+                ca.display()
+            );
+            let config = config_from_cmd_str(&cmd);
+            assert_eq!(config.ctl_tls_files, Some((key, cert)));
+            assert_eq!(config.ctl_client_ca_file, Some(ca));
+        }
+
+        #[test]
+        fn ctl_tls_files_are_not_set_by_default() {
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.ctl_tls_files, None);
+            assert_eq!(config.ctl_client_ca_file, None);
+        }
+
+        #[test]
+        fn trace_endpoint_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --trace-endpoint http://localhost:4318");
+            assert_eq!(
+                config.trace_exporter,
+                Some(TraceExporter::endpoint("http://localhost:4318/v1/traces").unwrap())
+            );
+        }
+
+        #[test]
+        fn trace_file_should_be_set() {
+            let config = config_from_cmd_str("hab-sup run --trace-file /tmp/traces.json");
+            assert_eq!(
+                config.trace_exporter,
+                Some(TraceExporter::File(PathBuf::from("/tmp/traces.json")))
+            );
+            let config = config_from_cmd_str("hab-sup run");
+            assert_eq!(config.trace_exporter, None);
+        }
+
     }
 }
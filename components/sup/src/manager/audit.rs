@@ -0,0 +1,404 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Durable record of operations which changed the Supervisor's services.
+//!
+//! Every mutating CtlGateway request and every service configuration or service file applied from
+//! gossip is appended to `AUDIT.log` in the Supervisor root, as is every CtlGateway request denied
+//! to the client's credential. Each line of the log is a JSON object describing a single operation.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    net::SocketAddr,
+    ops::Not,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ctl_gateway::CtlRequest,
+    error::{Error, Result},
+    hcore::crypto::hash,
+    protocol,
+};
+use chrono::{DateTime, Utc};
+use serde_json;
+use time;
+
+static LOGKEY: &'static str = "AU";
+
+/// Name of the file, relative to the Supervisor root, containing the audit log.
+pub const AUDIT_LOG_FILENAME: &str = "AUDIT.log";
+
+/// Returns the location of the audit log for the given Supervisor root.
+pub fn audit_log_path<T>(sup_root: T) -> PathBuf
+where
+    T: AsRef<Path>,
+{
+    sup_root.as_ref().join(AUDIT_LOG_FILENAME)
+}
+
+/// Where an audited operation originated.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    /// A request made by a client of the CtlGateway.
+    Ctl,
+    /// A rumor received from the gossip ring.
+    Gossip,
+}
+
+impl AuditSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            AuditSource::Ctl => "ctl",
+            AuditSource::Gossip => "gossip",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub source: AuditSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_addr: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+    pub message_type: String,
+    pub target: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incarnation: Option<u64>,
+    /// Set for requests which were refused because the client's credential doesn't permit them.
+    #[serde(default, skip_serializing_if = "Not::not")]
+    pub denied: bool,
+}
+
+impl AuditEntry {
+    /// Create an entry for a request made by a CtlGateway client.
+    pub fn ctl<T>(req: &CtlRequest, message_type: &str, target: T) -> Self
+    where
+        T: ToString,
+    {
+        let mut entry = Self::new(AuditSource::Ctl, message_type, target);
+        entry.remote_addr = req.peer().map(|addr| addr.to_string());
+        entry.credential = req.credential().map(str::to_string);
+        entry
+    }
+
+    /// Create an entry for a request from `addr` which `credential` isn't permitted to make.
+    pub fn denied<T>(addr: SocketAddr, credential: &str, message_type: &str, target: T) -> Self
+    where
+        T: ToString,
+    {
+        let mut entry = Self::new(AuditSource::Ctl, message_type, target);
+        entry.remote_addr = Some(addr.to_string());
+        entry.credential = Some(credential.to_string());
+        entry.denied = true;
+        entry
+    }
+
+    /// Create an entry for a rumor applied from gossip.
+    pub fn gossip<T>(message_type: &str, target: T, incarnation: u64) -> Self
+    where
+        T: ToString,
+    {
+        Self::new(AuditSource::Gossip, message_type, target).incarnation(incarnation)
+    }
+
+    fn new<T>(source: AuditSource, message_type: &str, target: T) -> Self
+    where
+        T: ToString,
+    {
+        AuditEntry {
+            timestamp: time::now_utc().rfc3339().to_string(),
+            source,
+            remote_addr: None,
+            credential: None,
+            message_type: message_type.to_string(),
+            target: target.to_string(),
+            filename: None,
+            config_digest: None,
+            incarnation: None,
+            denied: false,
+        }
+    }
+
+    /// Record a digest of the applied configuration or file content.
+    pub fn content(mut self, content: &[u8]) -> Self {
+        self.config_digest = Some(hash::hash_bytes(content));
+        self
+    }
+
+    pub fn filename<T>(mut self, filename: T) -> Self
+    where
+        T: ToString,
+    {
+        self.filename = Some(filename.to_string());
+        self
+    }
+
+    pub fn incarnation(mut self, incarnation: u64) -> Self {
+        self.incarnation = Some(incarnation);
+        self
+    }
+
+    fn matches(&self, query: &protocol::ctl::SupAudit, since: Option<&DateTime<Utc>>) -> bool {
+        if let Some(ref target) = query.target {
+            if &self.target != target {
+                return false;
+            }
+        }
+        if let Some(ref message_type) = query.message_type {
+            if &self.message_type != message_type {
+                return false;
+            }
+        }
+        if let Some(since) = since {
+            match to_utc(&self.timestamp) {
+                Ok(ref timestamp) if timestamp >= since => (),
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl From<AuditEntry> for protocol::ctl::AuditEntry {
+    fn from(other: AuditEntry) -> Self {
+        protocol::ctl::AuditEntry {
+            timestamp: Some(other.timestamp),
+            source: Some(other.source.as_str().to_string()),
+            remote_addr: other.remote_addr,
+            credential: other.credential,
+            message_type: Some(other.message_type),
+            target: Some(other.target),
+            filename: other.filename,
+            config_digest: other.config_digest,
+            incarnation: other.incarnation,
+            denied: Some(other.denied),
+        }
+    }
+}
+
+/// Append an entry to the audit log in the given Supervisor root. Failing to write the audit log
+/// does not fail the operation being audited, so errors are only logged.
+pub fn record<T>(sup_root: T, entry: &AuditEntry)
+where
+    T: AsRef<Path>,
+{
+    if let Err(err) = append(sup_root, entry) {
+        outputln!("Unable to write audit log entry, {}", err);
+    }
+}
+
+/// Parse an RFC 3339 timestamp in any offset, such as the `since` of an audit log query, into
+/// UTC.
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>> {
+    to_utc(timestamp).map_err(|err| sup_error!(Error::AuditTimestamp(timestamp.to_string(), err)))
+}
+
+/// Read the entries of the audit log in the given Supervisor root which match the query, oldest
+/// first. Lines which cannot be parsed are skipped.
+pub fn query<T>(sup_root: T, query: &protocol::ctl::SupAudit) -> Result<Vec<AuditEntry>>
+where
+    T: AsRef<Path>,
+{
+    let since = match query.since {
+        Some(ref since) => Some(parse_timestamp(since)?),
+        None => None,
+    };
+    let path = audit_log_path(sup_root);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let file = File::open(&path).map_err(|err| sup_error!(Error::AuditLogIo(path.clone(), err)))?;
+    let mut entries = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| sup_error!(Error::AuditLogIo(path.clone(), err)))?;
+        match serde_json::from_str::<AuditEntry>(&line) {
+            Ok(entry) => {
+                if entry.matches(query, since.as_ref()) {
+                    entries.push(entry);
+                }
+            }
+            Err(err) => warn!("Skipping malformed audit log entry, {}", err),
+        }
+    }
+    if let Some(limit) = query.limit {
+        let limit = limit as usize;
+        if entries.len() > limit {
+            entries.drain(..entries.len() - limit);
+        }
+    }
+    Ok(entries)
+}
+
+fn to_utc(timestamp: &str) -> chrono::ParseResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp).map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+fn append<T>(sup_root: T, entry: &AuditEntry) -> Result<()>
+where
+    T: AsRef<Path>,
+{
+    let path = audit_log_path(sup_root);
+    let mut line =
+        serde_json::to_string(entry).map_err(|err| sup_error!(Error::AuditLogJson(err)))?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|err| sup_error!(Error::AuditLogIo(path.clone(), err)))?;
+    file.write_all(line.as_bytes())
+        .map_err(|err| sup_error!(Error::AuditLogIo(path.clone(), err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn query(
+        target: Option<&str>,
+        message_type: Option<&str>,
+        limit: Option<u32>,
+    ) -> protocol::ctl::SupAudit {
+        protocol::ctl::SupAudit {
+            target: target.map(str::to_string),
+            message_type: message_type.map(str::to_string),
+            since: None,
+            limit,
+        }
+    }
+
+    #[test]
+    fn missing_log_is_empty() {
+        let tmpdir = TempDir::new().unwrap();
+        let entries = super::query(tmpdir.path(), &query(None, None, None)).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn record_and_query() {
+        let tmpdir = TempDir::new().unwrap();
+        let req = CtlRequest::default();
+        let load = AuditEntry::ctl(&req, "SvcLoad", "core/redis");
+        let cfg = AuditEntry::gossip("ServiceConfig", "redis.default", 3).content(b"port = 6379");
+        record(tmpdir.path(), &load);
+        record(tmpdir.path(), &cfg);
+
+        let entries = super::query(tmpdir.path(), &query(None, None, None)).unwrap();
+        assert_eq!(entries, vec![load.clone(), cfg.clone()]);
+
+        let entries =
+            super::query(tmpdir.path(), &query(Some("redis.default"), None, None)).unwrap();
+        assert_eq!(entries, vec![cfg.clone()]);
+
+        let entries = super::query(tmpdir.path(), &query(None, Some("SvcLoad"), None)).unwrap();
+        assert_eq!(entries, vec![load]);
+
+        let entries = super::query(tmpdir.path(), &query(None, None, Some(1))).unwrap();
+        assert_eq!(entries, vec![cfg]);
+    }
+
+    #[test]
+    fn gossip_entries_carry_incarnation_and_digest() {
+        let entry = AuditEntry::gossip("ServiceFile", "redis.default", 7)
+            .filename("redis.conf")
+            .content(b"bind 0.0.0.0");
+        assert_eq!(entry.source, AuditSource::Gossip);
+        assert_eq!(entry.incarnation, Some(7));
+        assert_eq!(entry.filename, Some("redis.conf".to_string()));
+        assert_eq!(entry.config_digest, Some(hash::hash_bytes(b"bind 0.0.0.0")));
+    }
+
+    #[test]
+    fn ctl_entries_omit_unset_fields() {
+        let entry = AuditEntry::ctl(&CtlRequest::default(), "SvcStop", "core/redis");
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(!json.contains("remote_addr"));
+        assert!(!json.contains("incarnation"));
+        assert!(json.contains(r#""source":"ctl""#));
+        assert!(!json.contains("denied"));
+    }
+
+    #[test]
+    fn denied_entries_carry_origin() {
+        let addr = "10.0.0.5:51234".parse().unwrap();
+        let entry = AuditEntry::denied(addr, "monitoring", "SvcStop", "-");
+        assert_eq!(entry.source, AuditSource::Ctl);
+        assert_eq!(entry.remote_addr, Some("10.0.0.5:51234".to_string()));
+        assert_eq!(entry.credential, Some("monitoring".to_string()));
+        assert!(entry.denied);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#""denied":true"#));
+    }
+
+    fn entry_at(timestamp: &str) -> AuditEntry {
+        let mut entry = AuditEntry::gossip("ServiceConfig", "redis.default", 1);
+        entry.timestamp = timestamp.to_string();
+        entry
+    }
+
+    #[test]
+    fn since_is_compared_in_utc() {
+        let tmpdir = TempDir::new().unwrap();
+        let before = entry_at("2019-02-01T11:59:59Z");
+        let after = entry_at("2019-02-01T12:00:00Z");
+        record(tmpdir.path(), &before);
+        record(tmpdir.path(), &after);
+
+        // Noon UTC, which would sort before both entries if compared as a string.
+        let mut opts = query(None, None, None);
+        opts.since = Some("2019-02-01T07:00:00-05:00".to_string());
+        let entries = super::query(tmpdir.path(), &opts).unwrap();
+        assert_eq!(entries, vec![after.clone()]);
+
+        opts.since = Some("2019-02-01T11:59:59.5Z".to_string());
+        let entries = super::query(tmpdir.path(), &opts).unwrap();
+        assert_eq!(entries, vec![after]);
+
+        opts.since = Some("2019-02-01T13:59:59+02:00".to_string());
+        let entries = super::query(tmpdir.path(), &opts).unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[test]
+    fn invalid_since_is_rejected() {
+        let tmpdir = TempDir::new().unwrap();
+        let mut opts = query(None, None, None);
+        opts.since = Some("2019-02-01 12:00".to_string());
+        match super::query(tmpdir.path(), &opts) {
+            Err(e) => match e.err {
+                Error::AuditTimestamp(ref timestamp, _) => {
+                    assert_eq!(timestamp, "2019-02-01 12:00")
+                }
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("An invalid since timestamp should be rejected"),
+        }
+    }
+
+    #[test]
+    fn timestamps_are_normalized_to_utc() {
+        let timestamp = parse_timestamp("2019-02-01T07:00:00-05:00").unwrap();
+        assert_eq!(timestamp.to_rfc3339(), "2019-02-01T12:00:00+00:00");
+    }
+}
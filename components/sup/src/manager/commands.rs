@@ -21,13 +21,18 @@ use crate::{
     error::{Error, Result},
     hcore::{
         package::{Identifiable, PackageIdent, PackageTarget},
-        service::ServiceGroup,
+        service::{HealthCheckInterval, ServiceBind, ServiceGroup},
         ChannelIdent,
     },
+    launcher_client::AttachFrame,
     manager::{
+        audit::{self, AuditEntry},
+        config_history::{self, ConfigHistoryEntry},
         service::{
-            spec::{IntoServiceSpec, ServiceSpec},
-            DesiredState, Pkg, ProcessState,
+            service_attach_path, service_log_path,
+            spec::{Console, IntoServiceSpec, ServiceSpec},
+            BindingMode, Cfg, DesiredState, HealthCheck, Pkg, ProcessState, Topology,
+            UpdateStrategy,
         },
         ManagerConfig, ManagerState,
     },
@@ -38,12 +43,31 @@ use crate::{
     util,
 };
 use serde_json;
-use std::{fmt, fs, path::PathBuf, result};
+use std::{
+    cmp,
+    collections::VecDeque,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    result,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{net::Shutdown, os::unix::net::UnixStream};
 use time::{self, Duration as TimeDuration, Timespec};
 use toml;
 
 static LOGKEY: &'static str = "CMD";
 
+/// How often a followed service log is checked for new output.
+const LOG_FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+/// How long a followed service log may stay quiet before an empty reply is sent, so that a client
+/// which went away is noticed.
+const LOG_FOLLOW_HEARTBEAT: Duration = Duration::from_secs(5);
+
 pub fn service_cfg(
     mgr: &ManagerState,
     req: &mut CtlRequest,
@@ -75,8 +99,44 @@ pub fn service_cfg(
     ))
 }
 
+pub fn service_cfg_provenance(
+    mgr: &ManagerState,
+    req: &mut CtlRequest,
+    opts: protocol::ctl::SvcGetCfgProvenance,
+) -> NetResult<()> {
+    let ident: PackageIdent = opts.ident.ok_or_else(err_update_client)?.into();
+    let provenance = mgr
+        .services
+        .read()
+        .expect("Services lock is poisoned")
+        .values()
+        .find(|service| service.pkg.ident.satisfies(&ident))
+        .map(|service| service.cfg.provenance())
+        .ok_or_else(|| net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)))?;
+    if provenance.is_empty() {
+        req.reply_complete(net::ok());
+        return Ok(());
+    }
+    let mut list = provenance.into_iter().peekable();
+    while let Some(key) = list.next() {
+        let msg = protocol::ctl::ConfigKeyProvenance {
+            key: Some(key.key),
+            value: Some(key.value.to_string()),
+            layer: Some(key.layer.to_string()),
+            source: key.source,
+            incarnation: key.incarnation,
+        };
+        if list.peek().is_some() {
+            req.reply_partial(msg);
+        } else {
+            req.reply_complete(msg);
+        }
+    }
+    Ok(())
+}
+
 pub fn service_cfg_validate(
-    _mgr: &ManagerState,
+    mgr: &ManagerState,
     req: &mut CtlRequest,
     opts: protocol::ctl::SvcValidateCfg,
 ) -> NetResult<()> {
@@ -97,44 +157,91 @@ pub fn service_cfg_validate(
             format!("Configuration format {} not available.", format),
         ));
     }
-    let _new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
+    let new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
         net::err(
             ErrCode::BadPayload,
             format!("Unable to decode configuration as {}, {}", format, e),
         )
     })?;
+    // Services which aren't loaded in this Supervisor are only known through rumor propagation,
+    // which carries neither their configuration interface nor their schema, so they can't be
+    // validated here.
+    if let Some(service_group) = opts.service_group {
+        for warning in validate_cfg_for(mgr, &service_group.into(), &new_cfg)? {
+            req.reply_partial(net::err(ErrCode::InvalidPayload, warning));
+        }
+    }
     req.reply_complete(net::ok());
     Ok(())
-    // JW TODO: Hold off on validation until we can validate services which aren't currently
-    // loaded in the Supervisor but are known through rumor propagation.
-    // let service_group: ServiceGroup = opts.service_group.into();
-    // for service in mgr.services.read().unwrap().iter() {
-    //     if service.service_group != service_group {
-    //         continue;
-    //     }
-    //     if let Some(interface) = service.cfg.interface() {
-    //         match Cfg::validate(interface, &new_cfg) {
-    //             None => req.reply_complete(net::ok()),
-    //             Some(errors) => {
-    //                 for error in errors {
-    //                     req.reply_partial(net::err(ErrCode::InvalidPayload, error));
-    //                 }
-    //                 req.reply_complete(net::ok());
-    //             }
-    //         }
-    //         return Ok(());
-    //     } else {
-    //         // No interface, this service can't be configured.
-    //         return Err(net::err(
-    //             ErrCode::NotFound,
-    //             "Service has no configurable attributes.",
-    //         ));
-    //     }
-    // }
-    // Err(net::err(
-    //     ErrCode::NotFound,
-    //     format!("Service not loaded, {}", service_group),
-    // ))
+}
+
+pub fn service_cfg_diff(
+    mgr: &ManagerState,
+    req: &mut CtlRequest,
+    opts: protocol::ctl::SvcDiffCfg,
+) -> NetResult<()> {
+    let cfg = opts.cfg.ok_or_else(err_update_client)?;
+    let format = opts
+        .format
+        .and_then(protocol::types::service_cfg::Format::from_i32)
+        .unwrap_or_default();
+    let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    if cfg.len() > protocol::butterfly::MAX_SVC_CFG_SIZE {
+        return Err(net::err(
+            ErrCode::EntityTooLarge,
+            "Configuration too large.",
+        ));
+    }
+    if format != protocol::types::service_cfg::Format::Toml {
+        return Err(net::err(
+            ErrCode::NotSupported,
+            format!("Configuration format {} not available.", format),
+        ));
+    }
+    let new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
+        net::err(
+            ErrCode::BadPayload,
+            format!("Unable to decode configuration as {}, {}", format, e),
+        )
+    })?;
+    let diffs = {
+        let services = mgr.services.read().expect("Services lock is poisoned");
+        let service = services
+            .values()
+            .find(|service| service.service_group == service_group)
+            .ok_or_else(|| {
+                net::err(
+                    ErrCode::NotFound,
+                    format!("Service not loaded, {}", service_group),
+                )
+            })?;
+        service
+            .diff_gossip_cfg(new_cfg)
+            .ok_or_else(|| {
+                net::err(
+                    ErrCode::NotFound,
+                    format!("Configuration not rendered yet, {}", service_group),
+                )
+            })?
+            .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?
+    };
+    let mut diffs = diffs.into_iter().peekable();
+    if diffs.peek().is_none() {
+        req.reply_complete(net::ok());
+        return Ok(());
+    }
+    while let Some((filename, diff)) = diffs.next() {
+        let msg = protocol::ctl::ConfigFileDiff {
+            filename: Some(filename),
+            diff: Some(diff),
+        };
+        if diffs.peek().is_some() {
+            req.reply_partial(msg);
+        } else {
+            req.reply_complete(msg);
+        }
+    }
+    Ok(())
 }
 
 pub fn service_cfg_set(
@@ -152,6 +259,15 @@ pub fn service_cfg_set(
             "Configuration too large.",
         ));
     }
+    if !is_encrypted {
+        let new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
+            net::err(
+                ErrCode::BadPayload,
+                format!("Unable to decode configuration as toml, {}", e),
+            )
+        })?;
+        validate_cfg_for(mgr, &service_group, &new_cfg)?;
+    }
     outputln!(
         "Setting new configuration version {} for {}",
         version,
@@ -167,8 +283,14 @@ pub fn service_cfg_set(
             return Err(net::err(ErrCode::Internal, err.to_string()));
         }
     };
-    match client.send_service_config(service_group, version, &cfg, is_encrypted) {
+    let entry = AuditEntry::ctl(req, "SvcSetCfg", &service_group)
+        .content(&cfg)
+        .incarnation(version);
+    let history = ConfigHistoryEntry::new(version, &cfg, is_encrypted).applied_by(req);
+    match client.send_service_config(service_group.clone(), version, &cfg, is_encrypted) {
         Ok(()) => {
+            audit::record(mgr.cfg.sup_root(), &entry);
+            config_history::record(mgr.cfg.sup_root(), &service_group, history);
             req.reply_complete(net::ok());
             return Ok(());
         }
@@ -176,6 +298,101 @@ pub fn service_cfg_set(
     }
 }
 
+pub fn service_cfg_history(
+    mgr: &ManagerState,
+    req: &mut CtlRequest,
+    opts: protocol::ctl::SvcGetCfgHistory,
+) -> NetResult<()> {
+    let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    let entries = config_history::load(mgr.cfg.sup_root(), &service_group)
+        .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    if entries.is_empty() {
+        req.reply_complete(net::ok());
+    } else {
+        let mut list = entries.into_iter().peekable();
+        while let Some(entry) = list.next() {
+            let msg: protocol::ctl::ConfigHistoryEntry = entry.into();
+            if list.peek().is_some() {
+                req.reply_partial(msg);
+            } else {
+                req.reply_complete(msg);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn service_cfg_rollback(
+    mgr: &ManagerState,
+    req: &mut CtlRequest,
+    opts: protocol::ctl::SvcRollbackCfg,
+) -> NetResult<()> {
+    let incarnation = opts.incarnation.ok_or_else(err_update_client)?;
+    let service_group: ServiceGroup = opts.service_group.ok_or_else(err_update_client)?.into();
+    let entries = config_history::load(mgr.cfg.sup_root(), &service_group)
+        .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    let latest = entries.last().map_or(0, |e| e.incarnation);
+    let target = entries
+        .into_iter()
+        .find(|e| e.incarnation == incarnation)
+        .ok_or_else(|| {
+            net::err(
+                ErrCode::NotFound,
+                format!(
+                    "No configuration with incarnation {} in the history of {}",
+                    incarnation, service_group
+                ),
+            )
+        })?;
+    let cfg = target.config.into_bytes();
+    if !target.encrypted {
+        let old_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
+            net::err(
+                ErrCode::BadPayload,
+                format!("Unable to decode configuration as toml, {}", e),
+            )
+        })?;
+        validate_cfg_for(mgr, &service_group, &old_cfg)?;
+    }
+    // Another member may have gossiped a newer configuration than any in our history, and a
+    // rollback with a lower incarnation would be ignored.
+    let mut gossiped = 0;
+    mgr.service_config_store
+        .with_rumor(&service_group.to_string(), "service_config", |rumor| {
+            gossiped = rumor.incarnation
+        });
+    let version = cmp::max(latest, gossiped) + 1;
+    outputln!(
+        "Rolling back {} to configuration version {} as version {}",
+        service_group,
+        incarnation,
+        version,
+    );
+    let mut client = match butterfly::client::Client::new(
+        mgr.cfg.gossip_listen.local_addr(),
+        mgr.cfg.ring_key.clone(),
+    ) {
+        Ok(client) => client,
+        Err(err) => {
+            outputln!("Failed to connect to own gossip server, {}", err);
+            return Err(net::err(ErrCode::Internal, err.to_string()));
+        }
+    };
+    let entry = AuditEntry::ctl(req, "SvcRollbackCfg", &service_group)
+        .content(&cfg)
+        .incarnation(version);
+    let history = ConfigHistoryEntry::new(version, &cfg, target.encrypted).applied_by(req);
+    match client.send_service_config(service_group.clone(), version, &cfg, target.encrypted) {
+        Ok(()) => {
+            audit::record(mgr.cfg.sup_root(), &entry);
+            config_history::record(mgr.cfg.sup_root(), &service_group, history);
+            req.reply_complete(net::ok());
+            Ok(())
+        }
+        Err(e) => Err(net::err(ErrCode::Internal, e.to_string())),
+    }
+}
+
 pub fn service_file_put(
     mgr: &ManagerState,
     req: &mut CtlRequest,
@@ -205,8 +422,13 @@ pub fn service_file_put(
             return Err(net::err(ErrCode::Internal, err.to_string()));
         }
     };
+    let entry = AuditEntry::ctl(req, "SvcFilePut", &service_group)
+        .filename(&filename)
+        .content(&content)
+        .incarnation(version);
     match client.send_service_file(service_group, filename, version, &content, is_encrypted) {
         Ok(()) => {
+            audit::record(mgr.cfg.sup_root(), &entry);
             req.reply_complete(net::ok());
             return Ok(());
         }
@@ -282,6 +504,7 @@ pub fn service_load(
             ))?;
         }
     }
+    audit::record(mgr.cfg.sup_root(), &AuditEntry::ctl(req, "SvcLoad", &ident));
     req.reply_complete(net::ok());
     Ok(())
 }
@@ -301,6 +524,10 @@ pub fn service_unload(
                 format!("{}", sup_error!(Error::ServiceSpecFileIO(file, err))),
             ));
         };
+        audit::record(
+            mgr.cfg.sup_root(),
+            &AuditEntry::ctl(req, "SvcUnload", &ident),
+        );
         // JW TODO: Change this to unloaded from unloading when the Supervisor waits for
         // the work to complete.
         req.info(format!("Unloading {}", ident))?;
@@ -320,6 +547,10 @@ pub fn service_start(
             if spec.desired_state == DesiredState::Down {
                 spec.desired_state = DesiredState::Up;
                 save_spec_for(&mgr.cfg, &spec)?;
+                audit::record(
+                    mgr.cfg.sup_root(),
+                    &AuditEntry::ctl(req, "SvcStart", &ident),
+                );
 
                 // JW TODO: Change the language of the message below to "started" when we actually
                 // synchronously control services from the ctl gateway.
@@ -351,6 +582,7 @@ pub fn service_stop(
             if spec.desired_state == DesiredState::Up {
                 spec.desired_state = DesiredState::Down;
                 save_spec_for(&mgr.cfg, &spec)?;
+                audit::record(mgr.cfg.sup_root(), &AuditEntry::ctl(req, "SvcStop", &ident));
 
                 // JW TODO: Change the langauge of the message below to "stopped" when we actually
                 // synchronously control services from the ctl gateway.
@@ -389,8 +621,10 @@ pub fn supervisor_depart(
         }
     };
     outputln!("Attempting to depart member: {}", member_id);
+    let entry = AuditEntry::ctl(req, "SupDepart", &member_id);
     match client.send_departure(member_id) {
         Ok(()) => {
+            audit::record(mgr.cfg.sup_root(), &entry);
             req.reply_complete(net::ok());
             Ok(())
         }
@@ -398,6 +632,31 @@ pub fn supervisor_depart(
     }
 }
 
+pub fn supervisor_audit(
+    mgr: &ManagerState,
+    req: &mut CtlRequest,
+    opts: protocol::ctl::SupAudit,
+) -> NetResult<()> {
+    let entries = audit::query(mgr.cfg.sup_root(), &opts).map_err(|e| match e.err {
+        Error::AuditTimestamp(..) => net::err(ErrCode::InvalidPayload, e.to_string()),
+        _ => net::err(ErrCode::Internal, e.to_string()),
+    })?;
+    if entries.is_empty() {
+        req.reply_complete(net::ok());
+    } else {
+        let mut list = entries.into_iter().peekable();
+        while let Some(entry) = list.next() {
+            let msg: protocol::ctl::AuditEntry = entry.into();
+            if list.peek().is_some() {
+                req.reply_partial(msg);
+            } else {
+                req.reply_complete(msg);
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn service_status(
     mgr: &ManagerState,
     req: &mut CtlRequest,
@@ -442,8 +701,309 @@ pub fn service_status(
     Ok(())
 }
 
+pub fn service_logs(
+    mgr: &ManagerState,
+    req: &mut CtlRequest,
+    opts: protocol::ctl::SvcLogs,
+) -> NetResult<()> {
+    let ident: PackageIdent = opts.ident.ok_or_else(err_update_client)?.into();
+    if spec_for_ident(&mgr.cfg, &ident).is_none() {
+        return Err(net::err(
+            ErrCode::NotFound,
+            format!("Service not loaded, {}", ident),
+        ));
+    }
+    let path = service_log_path(&ident.name);
+    let lines = opts.lines.map(|lines| lines as usize);
+    let follow = opts.follow.unwrap_or(false);
+
+    // Commands run on the Supervisor's main loop, so reading the log, and following it for as long
+    // as the client stays connected, happens on a thread of its own.
+    let mut req = req.clone();
+    thread::Builder::new()
+        .name(format!("svc-logs-{}", ident))
+        .spawn(move || {
+            let (tail, offset) = match read_log_tail(&path, lines) {
+                Ok(tail) => tail,
+                Err(err) => {
+                    if follow || err.kind() == io::ErrorKind::NotFound {
+                        (vec![], 0)
+                    } else {
+                        req.reply_complete(net::err(
+                            ErrCode::Internal,
+                            format!("Unable to read {}, {}", path.display(), err),
+                        ));
+                        return;
+                    }
+                }
+            };
+            if !follow {
+                if tail.is_empty() {
+                    req.reply_complete(net::ok());
+                } else {
+                    req.reply_complete(protocol::ctl::SvcLogLines { lines: tail });
+                }
+                return;
+            }
+            if !tail.is_empty()
+                && !req.try_reply_partial(protocol::ctl::SvcLogLines { lines: tail })
+            {
+                return;
+            }
+            follow_log(&mut req, &path, offset);
+        })
+        .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    Ok(())
+}
+
+pub fn service_attach(
+    mgr: &ManagerState,
+    req: &mut CtlRequest,
+    opts: protocol::ctl::SvcAttach,
+    input: mpsc::Receiver<protocol::ctl::SvcAttachInput>,
+) -> NetResult<()> {
+    let ident: PackageIdent = opts.ident.ok_or_else(err_update_client)?.into();
+    let spec = spec_for_ident(&mgr.cfg, &ident)
+        .ok_or_else(|| net::err(ErrCode::NotFound, format!("Service not loaded, {}", ident)))?;
+    if spec.console == Console::Detached {
+        return Err(net::err(
+            ErrCode::InvalidPayload,
+            format!(
+                "Service {} is detached, load it with --console stdin or --console pty to attach \
+                 to it",
+                ident
+            ),
+        ));
+    }
+    let size = match (opts.rows, opts.cols) {
+        (Some(rows), Some(cols)) => Some(attach_resize(rows, cols)),
+        _ => None,
+    };
+    audit::record(
+        mgr.cfg.sup_root(),
+        &AuditEntry::ctl(req, "SvcAttach", &ident),
+    );
+    attach_console(
+        req,
+        &ident,
+        &service_attach_path(&mgr.cfg.sup_root(), &ident.name),
+        size,
+        input,
+    )
+}
+
 ////////////////////////////////////////////////////////////////////////
 // Private helper functions
+/// Validates a new gossip layer for a service group against the service loaded in this
+/// Supervisor, if any. A configuration which does not satisfy the package's configuration schema
+/// is rejected. Packages without a schema only have keys missing from their configuration
+/// interface reported, as warnings.
+fn validate_cfg_for(
+    mgr: &ManagerState,
+    service_group: &ServiceGroup,
+    new_cfg: &toml::value::Table,
+) -> NetResult<Vec<String>> {
+    let services = mgr.services.read().expect("Services lock is poisoned");
+    let service = match services
+        .values()
+        .find(|service| &service.service_group == service_group)
+    {
+        Some(service) => service,
+        None => return Ok(vec![]),
+    };
+    service
+        .cfg
+        .validate_gossip(new_cfg)
+        .map_err(|e| net::err(ErrCode::InvalidPayload, e.to_string()))?;
+    if service.cfg.schema.is_some() {
+        return Ok(vec![]);
+    }
+    Ok(service
+        .cfg
+        .interface()
+        .and_then(|interface| Cfg::validate(interface, new_cfg))
+        .unwrap_or_default())
+}
+
+/// Reads the last `count` lines of a service's log file, or all of them, returning them along with
+/// the offset the file was read up to.
+fn read_log_tail(path: &Path, count: Option<usize>) -> io::Result<(Vec<String>, u64)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut tail = VecDeque::new();
+    let mut offset = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line)?;
+        if read == 0 || !line.ends_with('\n') {
+            break;
+        }
+        offset += read as u64;
+        if count == Some(0) {
+            continue;
+        }
+        if Some(tail.len()) == count {
+            tail.pop_front();
+        }
+        tail.push_back(
+            line.trim_end_matches(|c| c == '\n' || c == '\r')
+                .to_string(),
+        );
+    }
+    Ok((tail.into_iter().collect(), offset))
+}
+
+/// Streams lines appended to a service's log file after `offset` to the client until it
+/// disconnects. The file is reopened from the start whenever it is rotated or truncated.
+fn follow_log(req: &mut CtlRequest, path: &Path, mut offset: u64) {
+    let mut partial = String::new();
+    let mut last_reply = Instant::now();
+    loop {
+        thread::sleep(LOG_FOLLOW_INTERVAL);
+        let lines = read_appended_lines(path, &mut offset, &mut partial);
+        if lines.is_empty() && last_reply.elapsed() < LOG_FOLLOW_HEARTBEAT {
+            continue;
+        }
+        // Replying is the only way to find out that the client went away, which an empty reply
+        // does without the client printing anything.
+        if !req.try_reply_partial(protocol::ctl::SvcLogLines { lines }) {
+            debug!("Client following {} disconnected", path.display());
+            return;
+        }
+        last_reply = Instant::now();
+    }
+}
+
+/// Reads the complete lines appended to a log since `offset`, keeping an incomplete last line in
+/// `partial` until the rest of it is written. A log which shrank was rotated, and is read from its
+/// start.
+fn read_appended_lines(path: &Path, offset: &mut u64, partial: &mut String) -> Vec<String> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return vec![],
+    };
+    let len = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return vec![],
+    };
+    if len < *offset {
+        *offset = 0;
+        partial.clear();
+    }
+    if len == *offset {
+        return vec![];
+    }
+    let mut appended = String::new();
+    if file.seek(SeekFrom::Start(*offset)).is_err()
+        || file
+            .take(len - *offset)
+            .read_to_string(&mut appended)
+            .is_err()
+    {
+        return vec![];
+    }
+    *offset = len;
+    partial.push_str(&appended);
+    match partial.rfind('\n') {
+        Some(end) => {
+            let complete: String = partial.drain(..=end).collect();
+            complete
+                .lines()
+                .map(|line| line.trim_end_matches('\r').to_string())
+                .collect()
+        }
+        None => vec![],
+    }
+}
+
+fn attach_resize(rows: u32, cols: u32) -> AttachFrame {
+    AttachFrame::Resize {
+        rows: rows.min(u32::from(u16::max_value())) as u16,
+        cols: cols.min(u32::from(u16::max_value())) as u16,
+    }
+}
+
+/// Connects a client to the socket the Launcher serves a service's console on. The service's
+/// output is streamed to the client until either the service closes its output or the client
+/// detaches, while the client's input is relayed to the service from a thread of its own.
+#[cfg(unix)]
+fn attach_console(
+    req: &CtlRequest,
+    ident: &PackageIdent,
+    path: &Path,
+    size: Option<AttachFrame>,
+    input: mpsc::Receiver<protocol::ctl::SvcAttachInput>,
+) -> NetResult<()> {
+    let attach_err = |err: io::Error| {
+        net::err(
+            ErrCode::Internal,
+            format!("Unable to attach to {}, {}", ident, err),
+        )
+    };
+    let mut socket = UnixStream::connect(path).map_err(attach_err)?;
+    if let Some(size) = size {
+        size.write_to(&mut socket).map_err(attach_err)?;
+    }
+    let mut output = socket.try_clone().map_err(attach_err)?;
+    let mut req = req.clone();
+    thread::Builder::new()
+        .name(format!("svc-attach-{}-out", ident))
+        .spawn(move || {
+            let mut chunk = [0; 4096];
+            loop {
+                let read = match output.read(&mut chunk) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+                let msg = protocol::ctl::SvcAttachOutput {
+                    data: Some(chunk[..read].to_vec()),
+                };
+                if !req.try_reply_partial(msg) {
+                    break;
+                }
+            }
+            req.reply_complete(net::ok());
+            output.shutdown(Shutdown::Both).ok();
+        })
+        .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    thread::Builder::new()
+        .name(format!("svc-attach-{}", ident))
+        .spawn(move || {
+            for msg in input.iter() {
+                let mut frames = vec![];
+                if let (Some(rows), Some(cols)) = (msg.rows, msg.cols) {
+                    frames.push(attach_resize(rows, cols));
+                }
+                if let Some(data) = msg.data {
+                    frames.push(AttachFrame::Input(data));
+                }
+                if frames
+                    .iter()
+                    .any(|frame| frame.write_to(&mut socket).is_err())
+                {
+                    break;
+                }
+            }
+            socket.shutdown(Shutdown::Both).ok();
+        })
+        .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn attach_console(
+    _req: &CtlRequest,
+    _ident: &PackageIdent,
+    _path: &Path,
+    _size: Option<AttachFrame>,
+    _input: mpsc::Receiver<protocol::ctl::SvcAttachInput>,
+) -> NetResult<()> {
+    Err(net::err(
+        ErrCode::NotSupported,
+        "Attaching to services is not supported on Windows",
+    ))
+}
+
 fn err_update_client() -> net::NetErr {
     net::err(ErrCode::UpdateClient, "client out of date")
 }
@@ -472,6 +1032,28 @@ struct ServiceStatus {
     process: ProcessStatus,
     service_group: ServiceGroup,
     desired_state: DesiredState,
+    #[serde(default)]
+    health_check: Option<HealthCheck>,
+    #[serde(default)]
+    topology: Option<Topology>,
+    #[serde(default)]
+    update_strategy: Option<UpdateStrategy>,
+    #[serde(default)]
+    update_state: Option<String>,
+    #[serde(default)]
+    channel: Option<ChannelIdent>,
+    #[serde(default)]
+    bldr_url: Option<String>,
+    #[serde(default)]
+    binds: Vec<ServiceBind>,
+    #[serde(default)]
+    binding_mode: Option<BindingMode>,
+    #[serde(default)]
+    health_check_interval: Option<HealthCheckInterval>,
+    #[serde(default)]
+    spec_ident: Option<PackageIdent>,
+    #[serde(default)]
+    timed_out_hook: Option<String>,
 }
 
 impl fmt::Display for ServiceStatus {
@@ -491,6 +1073,17 @@ impl From<ServiceStatus> for protocol::types::ServiceStatus {
         proto.process = Some(other.process.into());
         proto.service_group = other.service_group.into();
         proto.desired_state = Some(other.desired_state.into());
+        proto.health_check = other.health_check.map(|h| h.to_string());
+        proto.topology = other.topology.map(|t| t as i32);
+        proto.update_strategy = other.update_strategy.map(|u| u as i32);
+        proto.update_state = other.update_state;
+        proto.channel = other.channel.map(|c| c.to_string());
+        proto.bldr_url = other.bldr_url;
+        proto.binds = other.binds.into_iter().map(Into::into).collect();
+        proto.binding_mode = other.binding_mode.map(|b| b as i32);
+        proto.health_check_interval = other.health_check_interval.map(Into::into);
+        proto.spec_ident = other.spec_ident.map(Into::into);
+        proto.timed_out_hook = other.timed_out_hook;
         proto
     }
 }
@@ -0,0 +1,243 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bounded history of the configurations applied to each service group through gossip.
+//!
+//! Applying a new configuration replaces a service group's `ServiceConfig` rumor, so the ring
+//! itself never remembers an earlier configuration. The Supervisor keeps the most recent
+//! configurations it has published or applied for each service group in
+//! `config_history/<service_group>.json` in the Supervisor root, so that one of them can be
+//! re-published as a new incarnation.
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ctl_gateway::CtlRequest,
+    error::{Error, Result},
+    hcore::{crypto::hash, service::ServiceGroup},
+    protocol,
+};
+use serde_json;
+use time;
+
+static LOGKEY: &'static str = "CH";
+
+/// Name of the directory, relative to the Supervisor root, containing configuration history.
+pub const CONFIG_HISTORY_DIR: &str = "config_history";
+
+/// Number of configurations kept for each service group.
+pub const CONFIG_HISTORY_LIMIT: usize = 20;
+
+/// Returns the location of the configuration history of a service group for the given Supervisor
+/// root.
+pub fn history_path<T>(sup_root: T, service_group: &ServiceGroup) -> PathBuf
+where
+    T: AsRef<Path>,
+{
+    sup_root
+        .as_ref()
+        .join(CONFIG_HISTORY_DIR)
+        .join(format!("{}.json", service_group))
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConfigHistoryEntry {
+    pub incarnation: u64,
+    pub timestamp: String,
+    pub digest: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applied_by: Option<String>,
+    #[serde(default)]
+    pub encrypted: bool,
+    /// The configuration exactly as it was gossiped. Encrypted configurations are kept encrypted.
+    pub config: String,
+}
+
+impl ConfigHistoryEntry {
+    pub fn new(incarnation: u64, config: &[u8], encrypted: bool) -> Self {
+        ConfigHistoryEntry {
+            incarnation,
+            timestamp: time::now_utc().rfc3339().to_string(),
+            digest: hash::hash_bytes(config),
+            applied_by: None,
+            encrypted,
+            config: String::from_utf8_lossy(config).into_owned(),
+        }
+    }
+
+    /// Record the CtlGateway client which applied the configuration.
+    pub fn applied_by(mut self, req: &CtlRequest) -> Self {
+        self.applied_by = match (req.credential(), req.peer()) {
+            (Some(credential), Some(addr)) => Some(format!("{}@{}", credential, addr)),
+            (Some(credential), None) => Some(credential.to_string()),
+            (None, Some(addr)) => Some(addr.to_string()),
+            (None, None) => None,
+        };
+        self
+    }
+}
+
+impl From<ConfigHistoryEntry> for protocol::ctl::ConfigHistoryEntry {
+    fn from(other: ConfigHistoryEntry) -> Self {
+        protocol::ctl::ConfigHistoryEntry {
+            incarnation: Some(other.incarnation),
+            timestamp: Some(other.timestamp),
+            digest: Some(other.digest),
+            applied_by: other.applied_by,
+            encrypted: Some(other.encrypted),
+        }
+    }
+}
+
+/// Add an entry to the configuration history of a service group. An entry for an incarnation
+/// already in the history replaces it, keeping who applied it if the new entry doesn't know.
+/// Failing to write the history does not fail applying the configuration, so errors are only
+/// logged.
+pub fn record<T>(sup_root: T, service_group: &ServiceGroup, entry: ConfigHistoryEntry)
+where
+    T: AsRef<Path>,
+{
+    if let Err(err) = append(sup_root, service_group, entry) {
+        outputln!("Unable to write configuration history, {}", err);
+    }
+}
+
+/// Read the configuration history of a service group, oldest incarnation first.
+pub fn load<T>(sup_root: T, service_group: &ServiceGroup) -> Result<Vec<ConfigHistoryEntry>>
+where
+    T: AsRef<Path>,
+{
+    let path = history_path(sup_root, service_group);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let file =
+        File::open(&path).map_err(|err| sup_error!(Error::ConfigHistoryIo(path.clone(), err)))?;
+    serde_json::from_reader(file).map_err(|err| sup_error!(Error::ConfigHistoryJson(path, err)))
+}
+
+fn append<T>(sup_root: T, service_group: &ServiceGroup, mut entry: ConfigHistoryEntry) -> Result<()>
+where
+    T: AsRef<Path>,
+{
+    let mut entries = load(&sup_root, service_group)?;
+    if let Some(pos) = entries
+        .iter()
+        .position(|e| e.incarnation == entry.incarnation)
+    {
+        let previous = entries.remove(pos);
+        if entry.applied_by.is_none() {
+            entry.applied_by = previous.applied_by;
+        }
+    }
+    entries.push(entry);
+    entries.sort_by_key(|e| e.incarnation);
+    if entries.len() > CONFIG_HISTORY_LIMIT {
+        entries.drain(..entries.len() - CONFIG_HISTORY_LIMIT);
+    }
+    save(history_path(sup_root, service_group), &entries)
+}
+
+fn save(path: PathBuf, entries: &[ConfigHistoryEntry]) -> Result<()> {
+    let json = serde_json::to_string_pretty(entries)
+        .map_err(|err| sup_error!(Error::ConfigHistoryJson(path.clone(), err)))?;
+    let dir = path
+        .parent()
+        .expect("Cannot determine parent directory for configuration history");
+    fs::create_dir_all(dir)
+        .map_err(|err| sup_error!(Error::ConfigHistoryIo(dir.to_path_buf(), err)))?;
+    // Write to a temporary file first so the history is never left half-written.
+    let tmpfile = path.with_extension("json.tmp");
+    {
+        let mut file = File::create(&tmpfile)
+            .map_err(|err| sup_error!(Error::ConfigHistoryIo(tmpfile.clone(), err)))?;
+        file.write_all(json.as_bytes())
+            .map_err(|err| sup_error!(Error::ConfigHistoryIo(tmpfile.clone(), err)))?;
+    }
+    fs::rename(&tmpfile, &path).map_err(|err| sup_error!(Error::ConfigHistoryIo(path, err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn service_group() -> ServiceGroup {
+        "redis.default".parse().unwrap()
+    }
+
+    #[test]
+    fn missing_history_is_empty() {
+        let tmpdir = TempDir::new().unwrap();
+        assert!(load(tmpdir.path(), &service_group()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn record_and_load_sorted_by_incarnation() {
+        let tmpdir = TempDir::new().unwrap();
+        let second = ConfigHistoryEntry::new(2, b"port = 6380", false);
+        let first = ConfigHistoryEntry::new(1, b"port = 6379", false);
+        record(tmpdir.path(), &service_group(), second.clone());
+        record(tmpdir.path(), &service_group(), first.clone());
+        assert_eq!(
+            load(tmpdir.path(), &service_group()).unwrap(),
+            vec![first, second]
+        );
+        assert!(load(tmpdir.path(), &"other.default".parse().unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rerecording_an_incarnation_keeps_who_applied_it() {
+        let tmpdir = TempDir::new().unwrap();
+        let mut published = ConfigHistoryEntry::new(3, b"port = 6379", false);
+        published.applied_by = Some("admin@127.0.0.1:50000".to_string());
+        record(tmpdir.path(), &service_group(), published);
+        record(
+            tmpdir.path(),
+            &service_group(),
+            ConfigHistoryEntry::new(3, b"port = 6379", false),
+        );
+        let entries = load(tmpdir.path(), &service_group()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].applied_by,
+            Some("admin@127.0.0.1:50000".to_string())
+        );
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let tmpdir = TempDir::new().unwrap();
+        for incarnation in 1..=(CONFIG_HISTORY_LIMIT as u64 + 5) {
+            record(
+                tmpdir.path(),
+                &service_group(),
+                ConfigHistoryEntry::new(incarnation, b"port = 6379", false),
+            );
+        }
+        let entries = load(tmpdir.path(), &service_group()).unwrap();
+        assert_eq!(entries.len(), CONFIG_HISTORY_LIMIT);
+        assert_eq!(entries[0].incarnation, 6);
+        assert_eq!(
+            entries.last().unwrap().incarnation,
+            CONFIG_HISTORY_LIMIT as u64 + 5
+        );
+    }
+}
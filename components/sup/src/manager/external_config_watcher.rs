@@ -0,0 +1,286 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Polls the external configuration source of each service which has one.
+//!
+//! Reading a source may mean a request to a remote endpoint, so it is never done on the main
+//! loop. Each source is read by a worker thread which hands the configuration over whenever it
+//! differs from what was last read.
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, Builder as ThreadBuilder},
+    time::Duration,
+};
+
+use toml;
+
+use crate::common::templating::config_source::ConfigSource;
+
+static LOGKEY: &'static str = "ECW";
+
+/// How often each configuration source is read.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The configuration a source holds; `None` if it holds none.
+pub type ExternalConfig = Option<toml::value::Table>;
+
+// WorkerState contains what the watcher shares with a worker.
+struct WorkerState {
+    // The configuration most recently read by the worker which the Manager hasn't taken yet. Only
+    // the latest configuration matters, so a newer one replaces any not yet taken.
+    update: Arc<Mutex<Option<ExternalConfig>>>,
+    // Set once the worker has first tried to read the source, whether or not it succeeded.
+    read: Arc<AtomicBool>,
+    // This sender is used by the watcher to notify a worker to stop running.
+    stop_running: Sender<()>,
+}
+
+type ServiceName = String;
+pub struct ExternalConfigWatcher {
+    states: HashMap<ServiceName, WorkerState>,
+    poll_interval: Duration,
+}
+
+impl ExternalConfigWatcher {
+    pub fn new() -> Self {
+        Self::with_poll_interval(DEFAULT_POLL_INTERVAL)
+    }
+
+    pub fn with_poll_interval(poll_interval: Duration) -> Self {
+        Self {
+            states: HashMap::new(),
+            poll_interval,
+        }
+    }
+
+    /// Adds a service to the External Config Watcher, thereby starting a worker thread which
+    /// reads `source`. `current` is the configuration the service was loaded with, so that only
+    /// later changes are reported.
+    pub fn add(
+        &mut self,
+        name: &str,
+        source: Arc<dyn ConfigSource>,
+        current: ExternalConfig,
+    ) -> io::Result<()> {
+        if self.states.contains_key(name) {
+            return Ok(());
+        }
+        let update = Arc::new(Mutex::new(None));
+        let read = Arc::new(AtomicBool::new(false));
+        let (running_tx, running_rx) = channel();
+        Worker::run(
+            source,
+            current,
+            self.poll_interval,
+            update.clone(),
+            read.clone(),
+            running_rx,
+        )?;
+        self.states.insert(
+            name.to_string(),
+            WorkerState {
+                update,
+                read,
+                stop_running: running_tx,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes a service from the External Config Watcher, and tells its worker thread to stop
+    /// running.
+    pub fn remove(&mut self, name: &str) {
+        if let Some(state) = self.states.remove(name) {
+            if state.stop_running.send(()).is_err() {
+                debug!("ExternalConfigWatcher worker for {} has already died", name);
+            }
+        }
+    }
+
+    /// Whether a service's source has been read at least once, successfully or not. Any
+    /// configuration that read found is available from `take_update_for` by then.
+    pub fn has_read(&self, name: &str) -> bool {
+        self.states
+            .get(name)
+            .map_or(false, |state| state.read.load(Ordering::SeqCst))
+    }
+
+    /// Returns the configuration a service's source now holds, if it changed since last asked.
+    ///
+    /// This also consumes the change.
+    pub fn take_update_for(&self, name: &str) -> Option<ExternalConfig> {
+        self.states.get(name).and_then(|state| {
+            state
+                .update
+                .lock()
+                .expect("external config update lock was poisoned")
+                .take()
+        })
+    }
+}
+
+struct Worker;
+
+impl Worker {
+    // starts a new thread polling the service's configuration source
+    fn run(
+        source: Arc<dyn ConfigSource>,
+        mut last: ExternalConfig,
+        poll_interval: Duration,
+        update: Arc<Mutex<Option<ExternalConfig>>>,
+        read: Arc<AtomicBool>,
+        stop_running: Receiver<()>,
+    ) -> io::Result<()> {
+        ThreadBuilder::new()
+            .name(format!("external-config-watcher-{}", source))
+            .spawn(move || {
+                debug!("ExternalConfigWatcher({}) worker thread starting", source);
+                let mut last_error = None;
+                loop {
+                    match stop_running.try_recv() {
+                        Err(TryRecvError::Empty) => (),
+                        Ok(_) => break,
+                        Err(TryRecvError::Disconnected) => {
+                            debug!(
+                                "ExternalConfigWatcher({}) worker thread failed to receive on \
+                                 channel",
+                                source
+                            );
+                            break;
+                        }
+                    }
+
+                    match source.load() {
+                        Ok(config) => {
+                            last_error = None;
+                            if config != last {
+                                *update
+                                    .lock()
+                                    .expect("external config update lock was poisoned") =
+                                    Some(config.clone());
+                                last = config;
+                            }
+                        }
+                        // A source which can't be read keeps its last configuration. The error
+                        // is only reported when it first occurs, not on every poll.
+                        Err(err) => {
+                            let err = err.to_string();
+                            if last_error.as_ref() != Some(&err) {
+                                outputln!("Unable to read configuration source, {}", err);
+                                last_error = Some(err);
+                            }
+                        }
+                    }
+                    read.store(true, Ordering::SeqCst);
+
+                    thread::sleep(poll_interval);
+                }
+            })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        fs::File,
+        io::Write,
+        time::{Duration, Instant},
+    };
+
+    use crate::common::templating::config_source::DirSource;
+
+    use tempfile::TempDir;
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    fn watch(tmp: &TempDir) -> ExternalConfigWatcher {
+        let mut watcher = ExternalConfigWatcher::with_poll_interval(POLL_INTERVAL);
+        let source = Arc::new(DirSource(tmp.path().to_path_buf()));
+        watcher.add("foo", source, None).expect("adding service");
+        watcher
+    }
+
+    fn wait_for_update(watcher: &ExternalConfigWatcher) -> Option<ExternalConfig> {
+        let start = Instant::now();
+        while start.elapsed() < POLL_INTERVAL * 50 {
+            if let Some(update) = watcher.take_update_for("foo") {
+                return Some(update);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        None
+    }
+
+    #[test]
+    fn source_is_read_even_when_unchanged() {
+        let tmp = TempDir::new().expect("creating temp dir");
+        let watcher = watch(&tmp);
+
+        let start = Instant::now();
+        while !watcher.has_read("foo") && start.elapsed() < POLL_INTERVAL * 50 {
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert!(watcher.has_read("foo"));
+        assert!(!watcher.has_read("bar"));
+        assert_eq!(watcher.take_update_for("foo"), None);
+    }
+
+    #[test]
+    fn no_update_while_source_is_unchanged() {
+        let tmp = TempDir::new().expect("creating temp dir");
+        let watcher = watch(&tmp);
+
+        thread::sleep(POLL_INTERVAL * 4);
+        assert_eq!(watcher.take_update_for("foo"), None);
+    }
+
+    #[test]
+    fn update_present_after_changing_source() {
+        let tmp = TempDir::new().expect("creating temp dir");
+        let watcher = watch(&tmp);
+
+        File::create(tmp.path().join("config.toml"))
+            .expect("creating file")
+            .write_all(b"port = 6379")
+            .expect("writing file");
+
+        let config = wait_for_update(&watcher).expect("update for changed source");
+        assert_eq!(
+            config.expect("source holds config")["port"].as_integer(),
+            Some(6379)
+        );
+        assert_eq!(watcher.take_update_for("foo"), None);
+    }
+
+    #[test]
+    fn no_update_for_removed_service() {
+        let tmp = TempDir::new().expect("creating temp dir");
+        let mut watcher = watch(&tmp);
+        watcher.remove("foo");
+
+        File::create(tmp.path().join("config.toml")).expect("creating file");
+        assert_eq!(wait_for_update(&watcher), None);
+    }
+}
@@ -15,7 +15,10 @@
 pub mod service;
 #[macro_use]
 mod debug;
+pub mod audit;
 pub mod commands;
+pub mod config_history;
+mod external_config_watcher;
 mod file_watcher;
 mod peer_watcher;
 mod periodic;
@@ -49,11 +52,13 @@ use crate::{
     butterfly::{
         self,
         member::Member,
+        rumor::{service_config::ServiceConfig as ServiceConfigRumor, RumorStore},
         server::{timing::Timing, ServerProxy, Suitability},
         trace::Trace,
     },
     common::{
         self,
+        trace::{self, TraceExporter},
         types::{EnvConfig, ListenCtlAddr},
     },
     hcore::{
@@ -69,7 +74,7 @@ use crate::{
         util::ToI64,
         ChannelIdent,
     },
-    launcher_client::{LauncherCli, LAUNCHER_LOCK_CLEAN_ENV, LAUNCHER_PID_ENV},
+    launcher_client::{LauncherCli, RunningService, LAUNCHER_LOCK_CLEAN_ENV, LAUNCHER_PID_ENV},
     protocol,
 };
 use cpu_time::ProcessTime;
@@ -77,7 +82,9 @@ use futures::{prelude::*, sync::mpsc};
 #[cfg(unix)]
 use proc_self;
 use prometheus::{HistogramVec, IntGauge, IntGaugeVec};
-use rustls::{internal::pemfile, NoClientAuth, ServerConfig};
+use rustls::{
+    internal::pemfile, AllowAnyAuthenticatedClient, NoClientAuth, RootCertStore, ServerConfig,
+};
 use serde_json;
 use time::{self, Duration as TimeDuration, SteadyTime, Timespec};
 use tokio::{executor, runtime};
@@ -97,7 +104,9 @@ use self::{
 };
 
 pub use self::sys::Sys;
-use self::user_config_watcher::UserConfigWatcher;
+use self::{
+    external_config_watcher::ExternalConfigWatcher, user_config_watcher::UserConfigWatcher,
+};
 use super::feat;
 use crate::{
     census::{CensusRing, CensusRingProxy},
@@ -193,6 +202,9 @@ pub struct ManagerConfig {
     pub organization: Option<String>,
     pub watch_peer_file: Option<String>,
     pub tls_files: Option<(PathBuf, PathBuf)>,
+    pub ctl_tls_files: Option<(PathBuf, PathBuf)>,
+    pub ctl_client_ca_file: Option<PathBuf>,
+    pub trace_exporter: Option<TraceExporter>,
 }
 
 impl ManagerConfig {
@@ -218,6 +230,9 @@ impl Default for ManagerConfig {
             organization: None,
             watch_peer_file: None,
             tls_files: None,
+            ctl_tls_files: None,
+            ctl_client_ca_file: None,
+            trace_exporter: None,
         }
     }
 }
@@ -249,6 +264,8 @@ pub struct ManagerState {
     pub cfg: ManagerConfig,
     pub services: Arc<RwLock<HashMap<PackageIdent, Service>>>,
     pub gateway_state: Arc<RwLock<GatewayState>>,
+    /// The gossiped configuration of every service group, shared with the gossip server.
+    pub service_config_store: RumorStore<ServiceConfigRumor>,
 }
 
 #[derive(Debug, Default)]
@@ -266,10 +283,15 @@ pub struct Manager {
     census_ring: CensusRing,
     fs_cfg: Arc<FsCfg>,
     launcher: LauncherCli,
+    /// The services the Launcher was running when we started, keyed by service group, which are
+    /// taken over as their specs are loaded. `None` if the Launcher is too old to report them, in
+    /// which case services are reattached to through their pidfiles.
+    launcher_services: Option<HashMap<String, RunningService>>,
     updater: ServiceUpdater,
     peer_watcher: Option<PeerWatcher>,
     spec_watcher: SpecWatcher,
     user_config_watcher: UserConfigWatcher,
+    external_config_watcher: ExternalConfigWatcher,
     spec_dir: SpecDir,
     organization: Option<String>,
     self_updater: Option<SelfUpdater>,
@@ -369,20 +391,36 @@ impl Manager {
 
         let spec_watcher = SpecWatcher::run(&spec_dir)?;
 
+        let launcher_services = match launcher.services() {
+            Ok(services) => services.map(|services| {
+                services
+                    .into_iter()
+                    .map(|service| (service.spawn.id.clone(), service))
+                    .collect()
+            }),
+            Err(err) => {
+                warn!("Unable to list the Launcher's services, {}", err);
+                None
+            }
+        };
+
         Ok(Manager {
             state: Arc::new(ManagerState {
                 cfg: cfg_static,
                 services,
                 gateway_state: Arc::new(RwLock::new(gateway_state)),
+                service_config_store: server.service_config_store.clone(),
             }),
             self_updater,
             updater: ServiceUpdater::new(server.clone()),
             census_ring: CensusRing::new(sys.member_id.clone()),
             butterfly: server,
             launcher,
+            launcher_services,
             peer_watcher,
             spec_watcher,
             user_config_watcher: UserConfigWatcher::new(),
+            external_config_watcher: ExternalConfigWatcher::new(),
             spec_dir,
             fs_cfg: Arc::new(fs_cfg),
             organization: cfg.organization,
@@ -476,7 +514,7 @@ impl Manager {
         // back to us. Since we consume and deconstruct the spec in `Service::new()` which
         // `Service::load()` eventually delegates to we just can't have that. We should clean
         // this up in the future.
-        let service = match Service::load(
+        let mut service = match Service::load(
             self.sys.clone(),
             spec.clone(),
             self.fs_cfg.clone(),
@@ -492,6 +530,12 @@ impl Manager {
                 return;
             }
         };
+        if let Some(ref mut running) = self.launcher_services {
+            service.adopt(
+                running.remove(&service.service_group.to_string()),
+                &self.launcher,
+            );
+        }
 
         if feat::is_enabled(feat::InstallHook) {
             if let Ok(package) =
@@ -537,6 +581,21 @@ impl Manager {
             return;
         }
 
+        if let Some(source) = service.config_source() {
+            let current = service.cfg.external.clone();
+            if let Err(e) = self
+                .external_config_watcher
+                .add(&service.pkg.name, source, current)
+            {
+                outputln!(
+                    "Unable to start ExternalConfigWatcher for {}: {}",
+                    service.spec_ident,
+                    e
+                );
+                return;
+            }
+        }
+
         self.updater.add(&service);
         self.state
             .services
@@ -551,6 +610,11 @@ impl Manager {
         let mut next_cpu_measurement = SteadyTime::now();
         let mut cpu_start = ProcessTime::now();
 
+        if let Some(ref exporter) = self.state.cfg.trace_exporter {
+            outputln!("Exporting traces to {}", exporter);
+            trace::init(exporter.clone())?;
+        }
+
         let mut runtime = runtime::Builder::new()
             .name_prefix("tokio-")
             .core_threads(TokioThreadCount::configured_value().into())
@@ -570,6 +634,7 @@ impl Manager {
         }
         // This serves to start up any services that need starting
         self.take_action_on_services()?;
+        self.terminate_unclaimed_launcher_services();
 
         outputln!(
             "Starting gossip-listener on {}",
@@ -581,8 +646,28 @@ impl Manager {
         let http_listen_addr = self.sys.http_listen();
         let ctl_listen_addr = self.sys.ctl_listen();
         let ctl_secret_key = ctl_gateway::readgen_secret_key(&self.fs_cfg.sup_root)?;
-        outputln!("Starting ctl-gateway on {}", &ctl_listen_addr);
-        ctl_gateway::server::run(ctl_listen_addr, ctl_secret_key, ctl_tx);
+        let ctl_acl = ctl_gateway::acl::CtlAcl::load(&self.fs_cfg.sup_root)?;
+        let ctl_tls_server_config = match self.state.cfg.ctl_tls_files {
+            Some((ref key_path, ref cert_path)) => Some(Arc::new(ctl_tls_config(
+                key_path,
+                cert_path,
+                self.state.cfg.ctl_client_ca_file.as_ref(),
+            )?)),
+            None => None,
+        };
+        if ctl_tls_server_config.is_some() {
+            outputln!("Starting ctl-gateway on {} with TLS", &ctl_listen_addr);
+        } else {
+            outputln!("Starting ctl-gateway on {}", &ctl_listen_addr);
+        }
+        ctl_gateway::server::run(
+            self.fs_cfg.sup_root.clone(),
+            ctl_listen_addr,
+            ctl_secret_key,
+            ctl_acl,
+            ctl_tls_server_config,
+            ctl_tx,
+        );
         debug!("ctl-gateway started");
 
         if self.http_disable {
@@ -667,6 +752,7 @@ impl Manager {
             // this var goes out of scope
             #[allow(unused_variables)]
             let main_timer = main_hist.start_timer();
+            let _span = trace::span("run_loop");
 
             match get_fd_count() {
                 Ok(f) => FILE_DESCRIPTORS.set(f.to_i64()),
@@ -722,6 +808,7 @@ impl Manager {
 
             self.update_peers_from_watch_file()?;
             self.update_running_services_from_user_config_watcher();
+            self.update_running_services_from_external_config_watcher();
             self.check_for_updated_packages();
             self.restart_elections();
             self.census_ring.update_from_rumors(
@@ -752,6 +839,8 @@ impl Manager {
                 // this var goes out of scope
                 #[allow(unused_variables)]
                 let service_timer = service_hist.start_timer();
+                let _span =
+                    trace::span("service.tick").attr("service_group", &service.service_group);
                 if service.tick(&self.census_ring, &self.launcher) {
                     self.gossip_latest_service_rumor(&service);
                 }
@@ -935,7 +1024,10 @@ impl Manager {
             .collect();
         let mut services_to_render: Vec<ServiceProxy<'_>> = services
             .values()
-            .map(|s| ServiceProxy::new(s, config_rendering))
+            .map(|s| {
+                ServiceProxy::new(s, config_rendering)
+                    .update_state(self.updater.update_state(&s.service_group))
+            })
             .collect();
 
         services_to_render.extend(watched_service_proxies);
@@ -972,6 +1064,7 @@ impl Manager {
             );
         }
 
+        self.external_config_watcher.remove(&service.pkg.name);
         self.updater.remove(service);
     }
 
@@ -1044,6 +1137,27 @@ impl Manager {
         self.butterfly.persist_data();
     }
 
+    /// Terminate the services the Launcher was running when we started which no loaded spec
+    /// took over, such as those unloaded while no Supervisor was running. Services loaded from
+    /// now on are started afresh.
+    fn terminate_unclaimed_launcher_services(&mut self) {
+        if let Some(ref mut running) = self.launcher_services {
+            for (service_group, service) in running.drain() {
+                outputln!(
+                    "Stopping {} with PID {}, it is no longer loaded",
+                    service_group,
+                    service.pid
+                );
+                if let Err(err) = self
+                    .launcher
+                    .terminate(service.pid as Pid, &service.spawn.shutdown)
+                {
+                    outputln!("Unable to stop {}, {}", service_group, err);
+                }
+            }
+        }
+    }
+
     /// Start, stop, or restart services to bring what's running in
     /// line with what our spec files say.
     fn take_action_on_services(&mut self) -> Result<()> {
@@ -1218,6 +1332,33 @@ impl Manager {
         }
     }
 
+    fn update_running_services_from_external_config_watcher(&mut self) {
+        let mut services = self
+            .state
+            .services
+            .write()
+            .expect("Services lock is poisoned");
+        for service in services.values_mut() {
+            // Whether the source was read is checked first, so that the configuration read is
+            // taken along with it.
+            let read = service.awaiting_external_config
+                && self.external_config_watcher.has_read(&service.pkg.name);
+            if let Some(external) = self
+                .external_config_watcher
+                .take_update_for(&service.pkg.name)
+            {
+                outputln!(
+                    "Configuration source changes detected for {}",
+                    &service.spec_ident
+                );
+                service.external_config_update = Some(external);
+            }
+            if read {
+                service.awaiting_external_config = false;
+            }
+        }
+    }
+
     fn remove_service_for_spec(&mut self, spec: &ServiceSpec) {
         let svc = self
             .state
@@ -1245,6 +1386,43 @@ where
     B: AsRef<Path>,
 {
     let mut config = ServerConfig::new(NoClientAuth::new());
+    set_server_cert(&mut config, key_path, cert_path)?;
+    Ok(config)
+}
+
+/// TLS configuration for the CtlGateway. If a client CA certificate is given then clients must
+/// present a certificate signed by it before they may connect.
+fn ctl_tls_config<A, B>(
+    key_path: A,
+    cert_path: B,
+    client_ca_path: Option<&PathBuf>,
+) -> Result<ServerConfig>
+where
+    A: AsRef<Path>,
+    B: AsRef<Path>,
+{
+    let client_auth = match client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            let ca_file = &mut BufReader::new(File::open(ca_path)?);
+            match roots.add_pem_file(ca_file) {
+                Ok((valid, _)) if valid > 0 => (),
+                _ => return Err(sup_error!(Error::InvalidCertFile(ca_path.clone()))),
+            }
+            AllowAnyAuthenticatedClient::new(roots)
+        }
+        None => NoClientAuth::new(),
+    };
+    let mut config = ServerConfig::new(client_auth);
+    set_server_cert(&mut config, key_path, cert_path)?;
+    Ok(config)
+}
+
+fn set_server_cert<A, B>(config: &mut ServerConfig, key_path: A, cert_path: B) -> Result<()>
+where
+    A: AsRef<Path>,
+    B: AsRef<Path>,
+{
     let key_file = &mut BufReader::new(File::open(&key_path)?);
     let cert_file = &mut BufReader::new(File::open(&cert_path)?);
 
@@ -1261,7 +1439,7 @@ where
 
     config.set_single_cert(cert_chain, key)?;
     config.ignore_client_order = true;
-    Ok(config)
+    Ok(())
 }
 
 /// Represents how many threads to start for our main Tokio runtime
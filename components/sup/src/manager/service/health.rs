@@ -14,7 +14,7 @@
 
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HealthCheck {
     Ok,
     Warning,
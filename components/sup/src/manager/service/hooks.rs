@@ -578,6 +578,203 @@ impl Hook for PostStopHook {
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct PreStopHook {
+    render_pair: RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PreStopHook {
+    type ExitValue = bool;
+
+    fn file_name() -> &'static str {
+        "pre-stop"
+    }
+
+    fn new(package_name: &str, pair: RenderPair) -> Self {
+        PreStopHook {
+            render_pair: pair,
+            stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+            stderr_log_path: hooks::stderr_log_path::<Self>(package_name),
+        }
+    }
+
+    fn handle_exit<'a>(
+        &self,
+        pkg: &Pkg,
+        _: &'a HookOutput,
+        status: &ExitStatus,
+    ) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Pre stop failed! '{}' exited with \
+                    status code {}", Self::file_name(), code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.render_pair.path
+    }
+
+    fn renderer(&self) -> &TemplateRenderer {
+        &self.render_pair.renderer
+    }
+
+    fn stdout_log_path(&self) -> &Path {
+        &self.stdout_log_path
+    }
+
+    fn stderr_log_path(&self) -> &Path {
+        &self.stderr_log_path
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreUpdateHook {
+    render_pair: RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PreUpdateHook {
+    type ExitValue = bool;
+
+    fn file_name() -> &'static str {
+        "pre-update"
+    }
+
+    fn new(package_name: &str, pair: RenderPair) -> Self {
+        PreUpdateHook {
+            render_pair: pair,
+            stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+            stderr_log_path: hooks::stderr_log_path::<Self>(package_name),
+        }
+    }
+
+    fn handle_exit<'a>(
+        &self,
+        pkg: &Pkg,
+        _: &'a HookOutput,
+        status: &ExitStatus,
+    ) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Pre update failed! '{}' exited with \
+                    status code {}", Self::file_name(), code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.render_pair.path
+    }
+
+    fn renderer(&self) -> &TemplateRenderer {
+        &self.render_pair.renderer
+    }
+
+    fn stdout_log_path(&self) -> &Path {
+        &self.stdout_log_path
+    }
+
+    fn stderr_log_path(&self) -> &Path {
+        &self.stderr_log_path
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostUpdateHook {
+    render_pair: RenderPair,
+    stdout_log_path: PathBuf,
+    stderr_log_path: PathBuf,
+}
+
+impl Hook for PostUpdateHook {
+    type ExitValue = bool;
+
+    fn file_name() -> &'static str {
+        "post-update"
+    }
+
+    fn new(package_name: &str, pair: RenderPair) -> Self {
+        PostUpdateHook {
+            render_pair: pair,
+            stdout_log_path: hooks::stdout_log_path::<Self>(package_name),
+            stderr_log_path: hooks::stderr_log_path::<Self>(package_name),
+        }
+    }
+
+    fn handle_exit<'a>(
+        &self,
+        pkg: &Pkg,
+        _: &'a HookOutput,
+        status: &ExitStatus,
+    ) -> Self::ExitValue {
+        let pkg_name = &pkg.name;
+        match status.code() {
+            Some(0) => true,
+            Some(code) => {
+                outputln!(preamble pkg_name, "Post update failed! '{}' exited with \
+                    status code {}", Self::file_name(), code);
+                false
+            }
+            None => {
+                Self::output_termination_message(pkg_name, status);
+                false
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.render_pair.path
+    }
+
+    fn renderer(&self) -> &TemplateRenderer {
+        &self.render_pair.renderer
+    }
+
+    fn stdout_log_path(&self) -> &Path {
+        &self.stdout_log_path
+    }
+
+    fn stderr_log_path(&self) -> &Path {
+        &self.stderr_log_path
+    }
+}
+
+// Lets tests run hooks without creating the service's logs directory, by logging their output
+// to `dir` instead.
+#[cfg(test)]
+macro_rules! log_to_impl {
+    ($($t:ty)*) => ($(
+        impl $t {
+            pub fn log_to(&mut self, dir: &Path) {
+                self.stdout_log_path = dir.join(format!("{}.stdout.log", Self::file_name()));
+                self.stderr_log_path = dir.join(format!("{}.stderr.log", Self::file_name()));
+            }
+        }
+    )*)
+}
+
+#[cfg(test)]
+log_to_impl!(PreStopHook PreUpdateHook);
+
 #[derive(Debug, Default, Serialize)]
 pub struct HookTable {
     pub health_check: Option<HealthCheckHook>,
@@ -589,6 +786,9 @@ pub struct HookTable {
     pub run: Option<RunHook>,
     pub post_run: Option<PostRunHook>,
     pub post_stop: Option<PostStopHook>,
+    pub pre_stop: Option<PreStopHook>,
+    pub pre_update: Option<PreUpdateHook>,
+    pub post_update: Option<PostUpdateHook>,
 }
 
 impl HookTable {
@@ -610,6 +810,9 @@ impl HookTable {
                 table.run = RunHook::load(package_name, &hooks_path, &templates);
                 table.post_run = PostRunHook::load(package_name, &hooks_path, &templates);
                 table.post_stop = PostStopHook::load(package_name, &hooks_path, &templates);
+                table.pre_stop = PreStopHook::load(package_name, &hooks_path, &templates);
+                table.pre_update = PreUpdateHook::load(package_name, &hooks_path, &templates);
+                table.post_update = PostUpdateHook::load(package_name, &hooks_path, &templates);
             }
         }
         debug!(
@@ -621,6 +824,27 @@ impl HookTable {
         table
     }
 
+    /// Render every loaded hook in strict mode, failing on references to undefined paths.
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        let pairs = vec![
+            self.health_check.as_mut().map(|h| &mut h.render_pair),
+            self.init.as_mut().map(|h| &mut h.render_pair),
+            self.file_updated.as_mut().map(|h| &mut h.render_pair),
+            self.reload.as_mut().map(|h| &mut h.render_pair),
+            self.reconfigure.as_mut().map(|h| &mut h.render_pair),
+            self.suitability.as_mut().map(|h| &mut h.render_pair),
+            self.run.as_mut().map(|h| &mut h.render_pair),
+            self.post_run.as_mut().map(|h| &mut h.render_pair),
+            self.post_stop.as_mut().map(|h| &mut h.render_pair),
+            self.pre_stop.as_mut().map(|h| &mut h.render_pair),
+            self.pre_update.as_mut().map(|h| &mut h.render_pair),
+            self.post_update.as_mut().map(|h| &mut h.render_pair),
+        ];
+        for pair in pairs.into_iter().flatten() {
+            pair.renderer.set_strict_mode(strict);
+        }
+    }
+
     /// Compile all loaded hooks from the table into their destination service directory.
     ///
     /// Returns `true` if compiling any of the hooks resulted in new
@@ -658,6 +882,15 @@ impl HookTable {
         if let Some(ref hook) = self.post_stop {
             changed = self.compile_one(hook, service_group, ctx) || changed;
         }
+        if let Some(ref hook) = self.pre_stop {
+            changed = self.compile_one(hook, service_group, ctx) || changed;
+        }
+        if let Some(ref hook) = self.pre_update {
+            changed = self.compile_one(hook, service_group, ctx) || changed;
+        }
+        if let Some(ref hook) = self.post_update {
+            changed = self.compile_one(hook, service_group, ctx) || changed;
+        }
         changed
     }
 
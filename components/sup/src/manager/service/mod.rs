@@ -20,28 +20,35 @@ mod supervisor;
 
 use std::{
     self,
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     fmt,
     fs::File,
     io::prelude::*,
     path::{Path, PathBuf},
     result,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
     time::{Duration, Instant},
 };
 
 use crate::{
     butterfly::rumor::service::Service as ServiceRumor,
-    common::templating::{config::CfgRenderer, hooks::Hook},
+    common::{
+        templating::{
+            config::CfgRenderer,
+            config_source::{self, ConfigSource},
+            hooks::{Hook, HookRun},
+        },
+        trace,
+    },
     hcore::{
         self,
         crypto::hash,
-        fs::{svc_hooks_path, SvcDir, FS_ROOT_PATH},
+        fs::{svc_hooks_path, svc_logs_path, SvcDir, FS_ROOT_PATH},
         package::{metadata::Bind, PackageIdent, PackageInstall},
         service::{HealthCheckInterval, ServiceBind, ServiceGroup},
         ChannelIdent,
     },
-    launcher_client::LauncherCli,
+    launcher_client::{LauncherCli, ResourceLimits, RunningService, ShutdownConfig},
 };
 pub use crate::{
     common::templating::{
@@ -50,24 +57,37 @@ pub use crate::{
     },
     protocol::types::{BindingMode, ProcessState, Topology, UpdateStrategy},
 };
-use prometheus::{HistogramTimer, HistogramVec};
+use prometheus::HistogramVec;
 use serde::{ser::SerializeStruct, Serialize, Serializer};
+use serde_json;
 use time::Timespec;
+use toml;
 
 use self::{context::RenderContext, hooks::HookTable, supervisor::Supervisor};
 pub use self::{
     health::HealthCheck,
-    spec::{DesiredState, IntoServiceSpec, ServiceSpec},
+    spec::{Console, DesiredState, IntoServiceSpec, LogConfig, Sandbox, ServiceSpec},
 };
 use super::{ShutdownReason, Sys};
 use crate::{
     census::{CensusGroup, CensusRing, ElectionStatus, ServiceFile},
     error::{Error, Result, SupError},
-    manager,
+    manager::{
+        self,
+        audit::{self, AuditEntry},
+        config_history::{self, ConfigHistoryEntry},
+    },
 };
 
 static LOGKEY: &'static str = "SR";
 
+/// Name of the file under a service's logs directory that its output is captured to.
+pub const SERVICE_LOG_FILE: &str = "service.log";
+/// Name of the directory under the Supervisor's root holding the sockets clients attach to
+/// services' consoles through. Unlike a service's own directory, it is not writable by the
+/// service's user.
+pub const SERVICE_ATTACH_DIR: &str = "attach";
+
 #[cfg(not(windows))]
 pub const GOSSIP_FILE_PERMISSIONS: u32 = 0o640;
 
@@ -75,7 +95,7 @@ lazy_static! {
     static ref HOOK_DURATION: HistogramVec = register_histogram_vec!(
         "hab_sup_hook_duration_seconds",
         "The time it takes for a hook to run",
-        &["hook"]
+        &["hook", "outcome"]
     )
     .unwrap();
 }
@@ -117,6 +137,13 @@ pub struct Service {
     pub sys: Arc<Sys>,
     pub initialized: bool,
     pub user_config_updated: bool,
+    /// Configuration read from the service's configuration source which hasn't been applied yet.
+    #[serde(skip_serializing)]
+    pub external_config_update: Option<Option<toml::value::Table>>,
+    /// Whether the service waits to be initialized and started until the Manager's external
+    /// config watcher first reads its remote configuration source.
+    #[serde(skip_serializing)]
+    pub awaiting_external_config: bool,
 
     #[serde(skip_serializing)]
     config_renderer: CfgRenderer,
@@ -151,6 +178,40 @@ pub struct Service {
     unsatisfied_binds: HashSet<ServiceBind>,
     hooks: HookTable,
     config_from: Option<PathBuf>,
+    /// External source of the service's configuration, polled by the Manager.
+    #[serde(skip_serializing)]
+    config_source: Option<Arc<dyn ConfigSource>>,
+    /// Whether the service was loaded with strict templates. Packages may also ask for them.
+    #[serde(skip_serializing)]
+    strict_templates: bool,
+    /// Overrides of the package's shutdown signal and timeout from the service's spec.
+    #[serde(skip_serializing)]
+    shutdown_signal: Option<String>,
+    #[serde(skip_serializing)]
+    shutdown_timeout: Option<u32>,
+    /// Resource limits the Launcher applies to the service's process.
+    #[serde(skip_serializing)]
+    limits: ResourceLimits,
+    /// Hardening the Launcher applies to the service's process.
+    #[serde(skip_serializing)]
+    sandbox: Sandbox,
+    /// Format and rotation of the log file the Launcher writes the service's output to.
+    #[serde(skip_serializing)]
+    log: LogConfig,
+    /// How the Launcher connects the service's standard streams.
+    #[serde(skip_serializing)]
+    console: Console,
+    /// Overrides of the package's hook timeouts from the service's spec.
+    #[serde(skip_serializing)]
+    hook_timeouts: BTreeMap<String, u32>,
+    /// Name of the hook which most recently timed out, unless it has since run to completion.
+    /// Hooks run from `&self`, hence the lock.
+    #[serde(skip_serializing)]
+    timed_out_hook: Mutex<Option<&'static str>>,
+    /// The package the service was updated from, until its new package's post-update hook has
+    /// run.
+    #[serde(skip_serializing)]
+    updated_from: Option<PackageIdent>,
     #[serde(skip_serializing)]
     scheduled_health_check: Option<Instant>,
     manager_fs_cfg: Arc<manager::FsCfg>,
@@ -165,6 +226,11 @@ pub struct Service {
     defaults_updated: bool,
     #[serde(skip_serializing)]
     gateway_state: Arc<RwLock<manager::GatewayState>>,
+    /// The render context the configuration was last compiled with, kept so that candidate
+    /// configurations can be rendered outside of the main loop, where the census isn't
+    /// available.
+    #[serde(skip_serializing)]
+    last_render_context: Option<serde_json::Value>,
 }
 
 impl Service {
@@ -188,24 +254,49 @@ impl Service {
         )?;
         let config_root = Self::config_root(&pkg, spec.config_from.as_ref());
         let hooks_root = Self::hooks_root(&pkg, spec.config_from.as_ref());
+        let mut cfg = Cfg::new(&pkg, spec.config_from.as_ref())?;
+        let mut awaiting_external_config = false;
+        let config_source = match spec.config_source {
+            Some(ref source) => {
+                let source: Arc<dyn ConfigSource> = Arc::from(config_source::parse(source)?);
+                if source.is_remote() {
+                    // Reading a remote source is left to the Manager's external config watcher,
+                    // off the main loop. The service isn't started until the watcher first reads
+                    // the source, so that it never runs without its external configuration.
+                    cfg.set_external_source(&*source, spec.config_source_precedence);
+                    awaiting_external_config = true;
+                } else {
+                    cfg.load_external(&*source, spec.config_source_precedence)?;
+                }
+                Some(source)
+            }
+            None => None,
+        };
+        let mut config_renderer = CfgRenderer::new(&config_root)?;
+        config_renderer.set_strict_mode(spec.strict_templates || pkg.strict_templates);
+        let mut hooks = HookTable::load(
+            &pkg.name,
+            &hooks_root,
+            svc_hooks_path(&service_group.service()),
+        );
+        hooks.set_strict_mode(spec.strict_templates || pkg.strict_templates);
+        config_renderer.set_config_paths(pkg.config_paths.clone());
         Ok(Service {
             sys,
-            cfg: Cfg::new(&pkg, spec.config_from.as_ref())?,
-            config_renderer: CfgRenderer::new(&config_root)?,
+            cfg,
+            config_renderer,
             bldr_url: spec.bldr_url,
             channel: spec.channel,
             desired_state: spec.desired_state,
             health_check: HealthCheck::default(),
-            hooks: HookTable::load(
-                &pkg.name,
-                &hooks_root,
-                svc_hooks_path(&service_group.service()),
-            ),
+            hooks,
             initialized: false,
             last_election_status: ElectionStatus::None,
             needs_reload: false,
             needs_reconfiguration: false,
             user_config_updated: false,
+            external_config_update: None,
+            awaiting_external_config,
             manager_fs_cfg,
             supervisor: Supervisor::new(&service_group),
             pkg,
@@ -219,11 +310,23 @@ impl Service {
             topology: spec.topology,
             update_strategy: spec.update_strategy,
             config_from: spec.config_from,
+            config_source,
+            strict_templates: spec.strict_templates,
+            shutdown_signal: spec.shutdown_signal,
+            shutdown_timeout: spec.shutdown_timeout,
+            limits: spec.limits,
+            sandbox: spec.sandbox,
+            log: spec.log,
+            console: spec.console,
+            hook_timeouts: spec.hook_timeouts,
+            timed_out_hook: Mutex::new(None),
+            updated_from: None,
             scheduled_health_check: Some(Instant::now()),
             svc_encrypted_password: spec.svc_encrypted_password,
             health_check_interval: spec.health_check_interval,
             defaults_updated: false,
             gateway_state,
+            last_render_context: None,
         })
     }
 
@@ -263,6 +366,18 @@ impl Service {
         )?)
     }
 
+    /// Take over the process the Launcher reported running for this service when the Supervisor
+    /// started. If it reported none, the service is started afresh rather than reattached to
+    /// whatever process its pidfile names, and if the process runs another release of the
+    /// package, it's terminated and the service is started afresh.
+    pub fn adopt(&mut self, running: Option<RunningService>, launcher: &LauncherCli) {
+        if !self.supervisor.adopt(running.as_ref(), &self.pkg) {
+            if let Some(ref running) = running {
+                self.supervisor.terminate_unadopted(running, launcher);
+            }
+        }
+    }
+
     /// Create the service path for this package.
     pub fn create_svc_path(&self) -> Result<()> {
         debug!("{}, Creating svc paths", self.service_group);
@@ -271,6 +386,8 @@ impl Service {
     }
 
     fn start(&mut self, launcher: &LauncherCli) {
+        let mut span = trace::span("service.start").attr("service_group", &self.service_group);
+        let shutdown = self.shutdown_config();
         if let Some(err) = self
             .supervisor
             .start(
@@ -278,10 +395,17 @@ impl Service {
                 &self.service_group,
                 launcher,
                 self.svc_encrypted_password.as_ref(),
+                &self.limits,
+                &self.sandbox.for_package(&self.pkg.path),
+                &self.log.for_path(&service_log_path(&self.pkg.name)),
+                self.console,
+                &service_attach_path(&self.manager_fs_cfg.sup_root, &self.pkg.name),
+                &shutdown,
             )
             .err()
         {
             outputln!(preamble self.service_group, "Service start failed: {}", err);
+            span.set_error(err);
         } else {
             self.needs_reload = false;
             self.needs_reconfiguration = false;
@@ -289,7 +413,9 @@ impl Service {
     }
 
     pub fn stop(&mut self, launcher: &LauncherCli, cause: ShutdownReason) {
-        match self.supervisor.stop(launcher, cause) {
+        self.pre_stop();
+        let shutdown = self.shutdown_config();
+        match self.supervisor.stop(launcher, cause, &shutdown) {
             Ok(_) => self.post_stop(),
             Err(err) => outputln!(preamble self.service_group, "Service stop failed: {}", err),
         }
@@ -297,9 +423,11 @@ impl Service {
 
     /// Runs the reconfigure hook if present, otherwise restarts the service.
     fn reload(&mut self, launcher: &LauncherCli) {
-        let _timer = hook_timer("reload");
         self.needs_reload = false;
         if self.process_down() || self.hooks.reload.is_none() {
+            let mut span =
+                trace::span("service.restart").attr("service_group", &self.service_group);
+            let shutdown = self.shutdown_config();
             if let Some(err) = self
                 .supervisor
                 .restart(
@@ -307,18 +435,21 @@ impl Service {
                     &self.service_group,
                     launcher,
                     self.svc_encrypted_password.as_ref(),
+                    &self.limits,
+                    &self.sandbox.for_package(&self.pkg.path),
+                    &self.log.for_path(&service_log_path(&self.pkg.name)),
+                    self.console,
+                    &service_attach_path(&self.manager_fs_cfg.sup_root, &self.pkg.name),
+                    &shutdown,
                 )
                 .err()
             {
                 outputln!(preamble self.service_group, "Service restart failed: {}", err);
+                span.set_error(err);
             }
         } else {
             let hook = self.hooks.reload.as_ref().unwrap();
-            hook.run(
-                &self.service_group,
-                &self.pkg,
-                self.svc_encrypted_password.as_ref(),
-            );
+            self.run_hook(hook, "reload");
         }
     }
 
@@ -330,6 +461,11 @@ impl Service {
     ///
     /// Returns `true` if the service was updated.
     pub fn tick(&mut self, census_ring: &CensusRing, launcher: &LauncherCli) -> bool {
+        if !self.initialized && self.awaiting_external_config {
+            outputln!(preamble self.service_group, "Waiting for configuration source...");
+            return false;
+        }
+
         // We may need to block the service from starting until all
         // its binds are satisfied
         if !self.initialized {
@@ -429,9 +565,77 @@ impl Service {
             spec.svc_encrypted_password = Some(password.clone())
         }
         spec.health_check_interval = self.health_check_interval;
+        spec.config_source = self.config_source.as_ref().map(ToString::to_string);
+        spec.config_source_precedence = self.cfg.external_precedence;
+        spec.strict_templates = self.strict_templates;
+        spec.shutdown_signal = self.shutdown_signal.clone();
+        spec.shutdown_timeout = self.shutdown_timeout;
+        spec.limits = self.limits.clone();
+        spec.sandbox = self.sandbox.clone();
+        spec.log = self.log.clone();
+        spec.console = self.console;
+        spec.hook_timeouts = self.hook_timeouts.clone();
         spec
     }
 
+    /// How the Launcher stops the service: the spec's signal and timeout, falling back to the
+    /// package's.
+    fn shutdown_config(&self) -> ShutdownConfig {
+        ShutdownConfig {
+            signal: self
+                .shutdown_signal
+                .clone()
+                .or_else(|| self.pkg.shutdown_signal.clone()),
+            timeout: self.shutdown_timeout.or(self.pkg.shutdown_timeout),
+        }
+    }
+
+    /// Runs a hook, terminating it once the spec's or else the package's timeout for it passes.
+    /// How long it ran is recorded under `metric` along with whether it timed out.
+    fn run_hook<H: Hook>(&self, hook: &H, metric: &str) -> H::ExitValue {
+        self.run_hook_for(hook, &self.pkg, metric)
+    }
+
+    /// Runs a hook like `run_hook`, but with `pkg`'s environment.
+    fn run_hook_for<H: Hook>(&self, hook: &H, pkg: &Pkg, metric: &str) -> H::ExitValue {
+        let timeout = self
+            .hook_timeouts
+            .get(H::file_name())
+            .or_else(|| self.pkg.hook_timeouts.get(H::file_name()))
+            .map(|seconds| Duration::from_secs(u64::from(*seconds)));
+        let start = Instant::now();
+        let run = hook.run_with_timeout(
+            &self.service_group,
+            pkg,
+            self.svc_encrypted_password.as_ref(),
+            timeout,
+        );
+        observe_hook_duration(metric, &run, start.elapsed());
+        let mut timed_out_hook = self
+            .timed_out_hook
+            .lock()
+            .expect("Timed out hook lock is poisoned");
+        if run.timed_out() {
+            *timed_out_hook = Some(H::file_name());
+        } else if *timed_out_hook == Some(H::file_name()) {
+            *timed_out_hook = None;
+        }
+        run.into_value()
+    }
+
+    /// Name of the hook which most recently timed out, unless it has since run to completion.
+    pub fn timed_out_hook(&self) -> Option<&'static str> {
+        *self
+            .timed_out_hook
+            .lock()
+            .expect("Timed out hook lock is poisoned")
+    }
+
+    /// The external source of the service's configuration, if it has one.
+    pub fn config_source(&self) -> Option<Arc<dyn ConfigSource>> {
+        self.config_source.clone()
+    }
+
     /// Iterate through all the service binds, marking any that are
     /// unsatisfied in `self.unsatisfied_binds`.
     ///
@@ -590,8 +794,28 @@ impl Service {
                 if config.incarnation <= self.cfg.gossip_incarnation {
                     return false;
                 }
-                self.cfg
-                    .set_gossip(config.incarnation, config.value.clone());
+                let _span = trace::span("gossip.service_config")
+                    .attr("service_group", &self.service_group)
+                    .attr("incarnation", config.incarnation);
+                if let Err(err) = self
+                    .cfg
+                    .set_gossip(config.incarnation, config.value.clone())
+                {
+                    outputln!(preamble self.service_group,
+                              "Rejected configuration version {}: {}", config.incarnation, err);
+                    return false;
+                }
+                let content = toml::to_string(&config.value).unwrap_or_default();
+                audit::record(
+                    &self.manager_fs_cfg.sup_root,
+                    &AuditEntry::gossip("ServiceConfig", &self.service_group, config.incarnation)
+                        .content(content.as_bytes()),
+                );
+                config_history::record(
+                    &self.manager_fs_cfg.sup_root,
+                    &self.service_group,
+                    ConfigHistoryEntry::new(config.incarnation, &config.raw, config.encrypted),
+                );
                 true
             }
             None => false,
@@ -607,8 +831,10 @@ impl Service {
             .census_group_for(&self.service_group)
             .expect("Service update failed; unable to find own service group");
         let cfg_updated_from_rumors = self.update_gossip(census_group);
-        let cfg_changed =
-            self.defaults_updated || cfg_updated_from_rumors || self.user_config_updated;
+        let cfg_changed = self.defaults_updated
+            || cfg_updated_from_rumors
+            || self.user_config_updated
+            || self.external_config_update.is_some();
 
         if self.user_config_updated {
             if let Err(e) = self.cfg.reload_user() {
@@ -618,10 +844,16 @@ impl Service {
             self.user_config_updated = false;
         }
 
+        if let Some(external) = self.external_config_update.take() {
+            if let Err(e) = self.cfg.set_external(external) {
+                outputln!(preamble self.service_group, "Reloading config source failed: {}", e);
+            }
+        }
+
         self.defaults_updated = false;
 
         if cfg_changed || census_ring.changed() {
-            let (reload, reconfigure) = {
+            let (reload, reconfigure, last_render_context) = {
                 let ctx = self.render_context(census_ring);
 
                 // If any hooks have changed, execute the `reload` hook (if present) or restart the
@@ -633,63 +865,161 @@ impl Service {
                 // config has (e.g. when only a comment has been added to the latter)
                 let reconfigure = self.compile_configuration(&ctx);
 
-                (reload, reconfigure)
+                (reload, reconfigure, serde_json::to_value(&ctx).ok())
             };
 
             self.schedule_special_health_check();
 
             self.needs_reload = reload;
             self.needs_reconfiguration = reconfigure;
+            self.last_render_context = last_render_context;
         }
 
         cfg_changed
     }
 
-    /// Replace the package of the running service and restart its system process.
-    pub fn update_package(&mut self, package: PackageInstall, launcher: &LauncherCli) {
+    /// Render the service's configuration files as they would be if its gossip layer were
+    /// replaced with the given table, returning a unified diff against the files currently
+    /// rendered for every file which would change. Nothing is written to disk.
+    ///
+    /// Returns `None` if the configuration has not been rendered yet.
+    pub fn diff_gossip_cfg(
+        &self,
+        gossip: toml::value::Table,
+    ) -> Option<Result<Vec<(String, String)>>> {
+        let mut ctx = self.last_render_context.clone()?;
+        let mut cfg = self.cfg.clone();
+        Some(
+            cfg.set_gossip(self.cfg.gossip_incarnation, gossip)
+                .map_err(Into::into)
+                .and_then(|_| {
+                    serde_json::to_value(&cfg)
+                        .map_err(|e| sup_error!(Error::ServiceSerializationError(e)))
+                })
+                .and_then(|cfg| {
+                    ctx["cfg"] = cfg;
+                    self.config_renderer
+                        .diff(&self.pkg.svc_config_path, &ctx)
+                        .map_err(Into::into)
+                }),
+        )
+    }
+
+    /// Replace the package of the running service and restart its system process. Returns
+    /// whether the service was updated.
+    ///
+    /// The current package's pre-update hook may veto the update by exiting non-zero, in which
+    /// case the service keeps running its current package.
+    pub fn update_package(&mut self, package: PackageInstall, launcher: &LauncherCli) -> bool {
+        let from = self.pkg.ident.clone();
+        if !self.replace_package(package) {
+            return false;
+        }
+        let shutdown = self.shutdown_config();
+        if let Err(err) = self
+            .supervisor
+            .stop(launcher, ShutdownReason::PkgUpdating, &shutdown)
+        {
+            outputln!(preamble self.service_group,
+                      "Error stopping process while updating package: {}", err);
+        }
+
+        match self.cfg.update_defaults_from_package(&self.pkg) {
+            Ok(maybe_updated) => {
+                self.defaults_updated = maybe_updated;
+            }
+            Err(err) => {
+                outputln!(preamble self.service_group,
+                          "Unexpected error while checking for updated package defaults: {}", err);
+            }
+        }
+
+        self.updated_from = Some(from);
+        self.initialized = false;
+        self.schedule_health_check_at_next_tick();
+        true
+    }
+
+    /// Swap the service's package, along with its configuration templates and hooks, for
+    /// `package`, unless the current package's pre-update hook vetoes it. Returns whether the
+    /// package was swapped.
+    fn replace_package(&mut self, package: PackageInstall) -> bool {
         match Pkg::from_install(&package) {
             Ok(pkg) => {
+                if !self.pre_update(&pkg.ident) {
+                    outputln!(preamble self.service_group,
+                              "Update to {} vetoed by the pre-update hook", pkg.ident);
+                    return false;
+                }
                 outputln!(preamble self.service_group,
                             "Updating service {} to {}", self.pkg.ident, pkg.ident);
 
                 match CfgRenderer::new(&Self::config_root(&pkg, self.config_from.as_ref())) {
-                    Ok(renderer) => self.config_renderer = renderer,
+                    Ok(mut renderer) => {
+                        renderer.set_strict_mode(self.strict_templates || pkg.strict_templates);
+                        renderer.set_config_paths(pkg.config_paths.clone());
+                        self.config_renderer = renderer;
+                    }
                     Err(e) => {
                         outputln!(preamble self.service_group,
                                   "Failed to load config templates after updating package, {}", e);
-                        return;
+                        return false;
                     }
                 }
+                // The pre-stop hook is the current package's, so run it before its hooks are
+                // replaced by the new package's.
+                self.pre_stop();
                 self.hooks = HookTable::load(
                     &pkg.name,
                     &Self::hooks_root(&pkg, self.config_from.as_ref()),
                     svc_hooks_path(self.service_group.service()),
                 );
+                self.hooks
+                    .set_strict_mode(self.strict_templates || pkg.strict_templates);
                 self.pkg = pkg;
+                true
             }
             Err(err) => {
                 outputln!(preamble self.service_group,
                           "Unexpected error while updating package, {}", err);
-                return;
+                false
             }
         }
-        if let Err(err) = self.supervisor.stop(launcher, ShutdownReason::PkgUpdating) {
-            outputln!(preamble self.service_group,
-                      "Error stopping process while updating package: {}", err);
+    }
+
+    /// The service's package, with the idents of the package being updated from and to added to
+    /// its hooks' environment.
+    fn update_pkg(&self, from: &PackageIdent, to: &PackageIdent) -> Pkg {
+        let mut env = (*self.pkg.env).clone();
+        env.insert("HAB_UPDATE_FROM_IDENT".to_string(), from.to_string());
+        env.insert("HAB_UPDATE_TO_IDENT".to_string(), to.to_string());
+        Pkg {
+            env: Env::from(env),
+            ..self.pkg.clone()
         }
+    }
 
-        match self.cfg.update_defaults_from_package(&self.pkg) {
-            Ok(maybe_updated) => {
-                self.defaults_updated = maybe_updated;
-            }
-            Err(err) => {
-                outputln!(preamble self.service_group,
-                          "Unexpected error while checking for updated package defaults: {}", err);
+    /// Run the current package's pre-update hook if present. Returns `false` if it vetoed the
+    /// update to `to`.
+    fn pre_update(&self, to: &PackageIdent) -> bool {
+        match self.hooks.pre_update {
+            Some(ref hook) => {
+                let pkg = self.update_pkg(&self.pkg.ident, to);
+                self.run_hook_for(hook, &pkg, "pre-update")
             }
+            None => true,
         }
+    }
 
-        self.initialized = false;
-        self.schedule_health_check_at_next_tick();
+    /// Run the new package's post-update hook if present, once the service has restarted after
+    /// an update.
+    fn post_update(&mut self) {
+        if let Some(from) = self.updated_from.take() {
+            if let Some(ref hook) = self.hooks.post_update {
+                let pkg = self.update_pkg(&from, &self.pkg.ident);
+                self.run_hook_for(hook, &pkg, "post-update");
+            }
+        }
     }
 
     pub fn to_rumor(&self, incarnation: u64) -> ServiceRumor {
@@ -715,59 +1045,41 @@ impl Service {
 
     /// Run initialization hook if present.
     fn initialize(&mut self) {
-        let timer = hook_timer("initialize");
-
         if self.initialized {
-            timer.observe_duration();
             return;
         }
 
         outputln!(preamble self.service_group, "Initializing");
         self.initialized = true;
         if let Some(ref hook) = self.hooks.init {
-            self.initialized = hook.run(
-                &self.service_group,
-                &self.pkg,
-                self.svc_encrypted_password.as_ref(),
-            )
+            self.initialized = self.run_hook(hook, "initialize");
         }
     }
 
     /// Run reconfigure hook if present.
     fn reconfigure(&mut self) {
-        let _timer = hook_timer("reconfigure");
-
         self.needs_reconfiguration = false;
         if let Some(ref hook) = self.hooks.reconfigure {
-            hook.run(
-                &self.service_group,
-                &self.pkg,
-                self.svc_encrypted_password.as_ref(),
-            );
+            self.run_hook(hook, "reconfigure");
         }
     }
 
     fn post_run(&mut self) {
-        let _timer = hook_timer("post-run");
-
         if let Some(ref hook) = self.hooks.post_run {
-            hook.run(
-                &self.service_group,
-                &self.pkg,
-                self.svc_encrypted_password.as_ref(),
-            );
+            self.run_hook(hook, "post-run");
         }
     }
 
-    fn post_stop(&mut self) {
-        let _timer = hook_timer("post-stop");
+    /// Run pre-stop hook if present, before the Launcher is asked to stop the service.
+    fn pre_stop(&self) {
+        if let Some(ref hook) = self.hooks.pre_stop {
+            self.run_hook(hook, "pre-stop");
+        }
+    }
 
+    fn post_stop(&mut self) {
         if let Some(ref hook) = self.hooks.post_stop {
-            hook.run(
-                &self.service_group,
-                &self.pkg,
-                self.svc_encrypted_password.as_ref(),
-            );
+            self.run_hook(hook, "post-stop");
         }
 
         self.gateway_state
@@ -778,19 +1090,14 @@ impl Service {
     }
 
     pub fn suitability(&self) -> Option<u64> {
-        let _timer = hook_timer("suitability");
-
         if !self.initialized {
             return None;
         }
 
-        self.hooks.suitability.as_ref().and_then(|hook| {
-            hook.run(
-                &self.service_group,
-                &self.pkg,
-                self.svc_encrypted_password.as_ref(),
-            )
-        })
+        self.hooks
+            .suitability
+            .as_ref()
+            .and_then(|hook| self.run_hook(hook, "suitability"))
     }
 
     fn cache_health_check(&self, check_result: HealthCheck) {
@@ -809,6 +1116,9 @@ impl Service {
     ///
     /// Returns `true` if the configuration has changed.
     fn compile_configuration(&self, ctx: &RenderContext) -> bool {
+        let mut span = trace::span("template.compile")
+            .attr("service_group", &self.service_group)
+            .attr("templates", "config");
         match self.config_renderer.compile(
             &ctx.service_group_name(),
             &self.pkg,
@@ -821,6 +1131,7 @@ impl Service {
                 outputln!(preamble self.service_group,
                           "Failed to compile configuration: {}",
                           e);
+                span.set_error(e);
                 false
             }
         }
@@ -832,6 +1143,9 @@ impl Service {
     ///
     /// Returns `true` if any hooks have changed.
     fn compile_hooks(&self, ctx: &RenderContext<'_>) -> bool {
+        let _span = trace::span("template.compile")
+            .attr("service_group", &self.service_group)
+            .attr("templates", "hooks");
         let changed = self.hooks.compile(&self.service_group, ctx);
         if let Some(err) = self.copy_run().err() {
             outputln!(preamble self.service_group, "Failed to copy run hook: {}", err);
@@ -891,6 +1205,7 @@ impl Service {
             if self.initialized {
                 self.start(launcher);
                 self.post_run();
+                self.post_update();
             }
         } else {
             self.check_process();
@@ -921,15 +1236,9 @@ impl Service {
 
     /// Run file-updated hook if present.
     fn file_updated(&self) -> bool {
-        let _timer = hook_timer("file-updated");
-
         if self.initialized {
             if let Some(ref hook) = self.hooks.file_updated {
-                return hook.run(
-                    &self.service_group,
-                    &self.pkg,
-                    self.svc_encrypted_password.as_ref(),
-                );
+                return self.run_hook(hook, "file-updated");
             }
         }
 
@@ -948,8 +1257,22 @@ impl Service {
         let mut updated = false;
         for service_file in census_group.changed_service_files() {
             if self.cache_service_file(&service_file) {
+                let _span = trace::span("gossip.service_file")
+                    .attr("service_group", &self.service_group)
+                    .attr("filename", &service_file.filename)
+                    .attr("incarnation", service_file.incarnation);
                 outputln!(preamble self.service_group, "Service file updated, {}",
                     service_file.filename);
+                audit::record(
+                    &self.manager_fs_cfg.sup_root,
+                    &AuditEntry::gossip(
+                        "ServiceFile",
+                        &self.service_group,
+                        service_file.incarnation,
+                    )
+                    .filename(&service_file.filename)
+                    .content(&service_file.body),
+                );
                 updated = true;
             }
         }
@@ -974,14 +1297,9 @@ impl Service {
     }
 
     fn run_health_check_hook(&mut self) {
-        let _timer = hook_timer("health-check");
         debug!("Running Health Check hook for ({})", self.spec_ident);
         let check_result = if let Some(ref hook) = self.hooks.health_check {
-            hook.run(
-                &self.service_group,
-                &self.pkg,
-                self.svc_encrypted_password.as_ref(),
-            )
+            self.run_hook(hook, "health-check")
         } else {
             match self.supervisor.status() {
                 (true, _) => HealthCheck::Ok,
@@ -1138,10 +1456,25 @@ impl fmt::Display for Service {
     }
 }
 
-// This returns a HistogramTimer that we can use to track how long hooks take to execute. Note that
-// times will get tracked automatically when the HistogramTimer goes out of scope.
-fn hook_timer(name: &str) -> HistogramTimer {
-    HOOK_DURATION.with_label_values(&[name]).start_timer()
+// Records how long a hook took to execute, labeled with whether it completed or timed out.
+fn observe_hook_duration<T: Default>(name: &str, run: &HookRun<T>, elapsed: Duration) {
+    let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+    HOOK_DURATION
+        .with_label_values(&[name, run.outcome()])
+        .observe(seconds);
+}
+
+/// Returns the path of the file the Launcher captures the named service's output to.
+pub fn service_log_path(service_name: &str) -> PathBuf {
+    svc_logs_path(service_name).join(SERVICE_LOG_FILE)
+}
+
+/// Returns the path of the socket the Launcher listens on for clients attaching to the named
+/// service's console.
+pub fn service_attach_path(sup_root: &Path, service_name: &str) -> PathBuf {
+    sup_root
+        .join(SERVICE_ATTACH_DIR)
+        .join(format!("{}.sock", service_name))
 }
 
 /// This enum represents whether or not we want to render config information when we serialize this
@@ -1166,6 +1499,7 @@ pub enum ConfigRendering {
 pub struct ServiceProxy<'a> {
     service: &'a Service,
     config_rendering: ConfigRendering,
+    update_state: Option<&'static str>,
 }
 
 impl<'a> ServiceProxy<'a> {
@@ -1173,8 +1507,16 @@ impl<'a> ServiceProxy<'a> {
         ServiceProxy {
             service: &s,
             config_rendering: c,
+            update_state: None,
         }
     }
+
+    /// Include where the service is in its update strategy, as reported by the
+    /// `ServiceUpdater`.
+    pub fn update_state(mut self, update_state: Option<&'static str>) -> Self {
+        self.update_state = update_state;
+        self
+    }
 }
 
 impl<'a> Serialize for ServiceProxy<'a> {
@@ -1183,9 +1525,9 @@ impl<'a> Serialize for ServiceProxy<'a> {
         S: Serializer,
     {
         let num_fields: usize = if self.config_rendering == ConfigRendering::Full {
-            27
+            30
         } else {
-            26
+            28
         };
 
         let s = &self.service;
@@ -1197,6 +1539,7 @@ impl<'a> Serialize for ServiceProxy<'a> {
 
         if self.config_rendering == ConfigRendering::Full {
             strukt.serialize_field("cfg", &s.cfg)?;
+            strukt.serialize_field("cfg_provenance", &s.cfg.provenance())?;
         }
 
         strukt.serialize_field("channel", &s.channel)?;
@@ -1221,8 +1564,10 @@ impl<'a> Serialize for ServiceProxy<'a> {
         strukt.serialize_field("svc_encrypted_password", &s.svc_encrypted_password)?;
         strukt.serialize_field("health_check_interval", &s.health_check_interval)?;
         strukt.serialize_field("sys", &s.sys)?;
+        strukt.serialize_field("timed_out_hook", &s.timed_out_hook())?;
         strukt.serialize_field("topology", &s.topology)?;
         strukt.serialize_field("update_strategy", &s.update_strategy)?;
+        strukt.serialize_field("update_state", &self.update_state)?;
         strukt.serialize_field("user_config_updated", &s.user_config_updated)?;
         strukt.end()
     }
@@ -1246,6 +1591,13 @@ mod tests {
     };
 
     fn initialize_test_service() -> Service {
+        initialize_test_service_with(|_| ())
+    }
+
+    fn initialize_test_service_with<F>(customize_spec: F) -> Service
+    where
+        F: FnOnce(&mut ServiceSpec),
+    {
         let listen_ctl_addr =
             ListenCtlAddr::from_str("127.0.0.1:1234").expect("Can't parse IP into SocketAddr");
         let http_addr = http_gateway::ListenAddr::default();
@@ -1264,7 +1616,8 @@ mod tests {
             panic!("This is being run on a platform that's not currently supported");
         };
 
-        let spec = ServiceSpec::default_for(ident);
+        let mut spec = ServiceSpec::default_for(ident);
+        customize_spec(&mut spec);
 
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("tests")
@@ -1295,6 +1648,135 @@ mod tests {
         );
     }
 
+    #[test]
+    fn local_config_source_is_read_at_creation() {
+        let dir = tempfile::TempDir::new().expect("create temp dir");
+        std::fs::write(dir.path().join("config.toml"), "port = 6379").expect("write config");
+        let service = initialize_test_service_with(|spec| {
+            spec.config_source = Some(dir.path().to_string_lossy().into_owned());
+        });
+
+        assert!(!service.awaiting_external_config);
+        let external = service.cfg.external.expect("external config read");
+        assert_eq!(external["port"].as_integer(), Some(6379));
+    }
+
+    #[test]
+    fn remote_config_source_holds_back_the_service() {
+        let service = initialize_test_service_with(|spec| {
+            spec.config_source = Some("http://127.0.0.1:9631/config".to_string());
+        });
+
+        assert!(service.awaiting_external_config);
+        assert_eq!(service.cfg.external, None);
+    }
+
+    #[test]
+    fn update_hooks_see_both_idents() {
+        let service = initialize_test_service();
+        let from = service.pkg.ident.clone();
+        let to = PackageIdent::from_str("core/tree/1.8.0/20190101000000").unwrap();
+
+        let pkg = service.update_pkg(&from, &to);
+        assert_eq!(
+            pkg.env.get("HAB_UPDATE_FROM_IDENT"),
+            Some(&from.to_string())
+        );
+        assert_eq!(pkg.env.get("HAB_UPDATE_TO_IDENT"), Some(&to.to_string()));
+        assert!(!service.pkg.env.contains_key("HAB_UPDATE_TO_IDENT"));
+    }
+
+    /// Give `service` pre-stop and pre-update hooks running the given scripts as the current
+    /// user, rendered and logging their output under `dir`.
+    #[cfg(not(windows))]
+    fn set_update_hooks(service: &mut Service, dir: &Path, pre_stop: &str, pre_update: &str) {
+        use super::hooks::{PreStopHook, PreUpdateHook};
+        use crate::hcore::os::users;
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let templates = dir.join("templates");
+        let hooks = dir.join("hooks");
+        fs::create_dir_all(&templates).expect("couldn't create templates dir");
+        fs::create_dir_all(&hooks).expect("couldn't create hooks dir");
+        for &(name, content) in &[("pre-stop", pre_stop), ("pre-update", pre_update)] {
+            fs::write(templates.join(name), content).expect("couldn't write hook template");
+            fs::write(hooks.join(name), content).expect("couldn't write hook");
+            fs::set_permissions(hooks.join(name), fs::Permissions::from_mode(0o755))
+                .expect("couldn't make hook executable");
+        }
+
+        let mut hook = PreStopHook::load(&service.pkg.name, &hooks, &templates)
+            .expect("couldn't load pre-stop hook");
+        hook.log_to(dir);
+        service.hooks.pre_stop = Some(hook);
+        let mut hook = PreUpdateHook::load(&service.pkg.name, &hooks, &templates)
+            .expect("couldn't load pre-update hook");
+        hook.log_to(dir);
+        service.hooks.pre_update = Some(hook);
+
+        service.pkg.svc_user = users::get_current_username().expect("Can get current username");
+        service.pkg.svc_group = users::get_current_groupname().expect("Can get current groupname");
+    }
+
+    /// A release of the test service's package without any hooks, installed under `dir`.
+    #[cfg(not(windows))]
+    fn newer_package(dir: &Path) -> PackageInstall {
+        let path = dir.join("newer");
+        std::fs::create_dir_all(&path).expect("couldn't create package dir");
+        PackageInstall::new_from_parts(
+            PackageIdent::from_str("core/tree/1.8.0/20190101000000").unwrap(),
+            path.clone(),
+            path.clone(),
+            path,
+        )
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn an_update_vetoed_by_the_pre_update_hook_leaves_the_package_unchanged() {
+        let dir = tempfile::TempDir::new().expect("create temp dir");
+        let stopped = dir.path().join("stopped");
+        let mut service = initialize_test_service();
+        set_update_hooks(
+            &mut service,
+            dir.path(),
+            &format!("#!/bin/sh\ntouch {}\n", stopped.display()),
+            "#!/bin/sh\nexit 1\n",
+        );
+        let ident = service.pkg.ident.clone();
+
+        assert!(!service.replace_package(newer_package(dir.path())));
+        assert_eq!(service.pkg.ident, ident);
+        assert!(service.hooks.pre_update.is_some());
+        assert!(!stopped.exists(), "pre-stop ran for a vetoed update");
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn pre_stop_runs_before_the_hooks_are_replaced() {
+        let dir = tempfile::TempDir::new().expect("create temp dir");
+        let stopped = dir.path().join("stopped");
+        let mut service = initialize_test_service();
+        set_update_hooks(
+            &mut service,
+            dir.path(),
+            &format!("#!/bin/sh\ntouch {}\n", stopped.display()),
+            "#!/bin/sh\nexit 0\n",
+        );
+
+        assert!(service.replace_package(newer_package(dir.path())));
+        assert_eq!(
+            service.pkg.ident,
+            PackageIdent::from_str("core/tree/1.8.0/20190101000000").unwrap()
+        );
+        // The new release has no hooks, so only the old release's pre-stop hook could have run.
+        assert!(service.hooks.pre_stop.is_none());
+        assert!(
+            stopped.exists(),
+            "the old release's pre-stop hook didn't run"
+        );
+    }
+
     #[test]
     fn service_proxy_conforms_to_the_schema() {
         let service = initialize_test_service();
@@ -14,6 +14,7 @@
 
 use super::{BindingMode, Topology, UpdateStrategy};
 use crate::{
+    common::templating::config_source::ExternalPrecedence,
     error::{Error, Result, SupError},
     hcore::{
         package::{PackageIdent, PackageInstall},
@@ -22,12 +23,13 @@ use crate::{
         util::{deserialize_using_from_str, serialize_using_to_string},
         ChannelIdent,
     },
+    launcher_client::{self, ResourceLimits, RlimitValue},
     protocol,
 };
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{self, Deserialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     fmt,
     fs::{self, File},
     io::{BufReader, Read, Write},
@@ -86,6 +88,184 @@ impl From<DesiredState> for i32 {
     }
 }
 
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> LogFormat {
+        LogFormat::Text
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match *self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = SupError;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_ref() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(sup_error!(Error::BadLogFormat(value.to_string()))),
+        }
+    }
+}
+
+impl From<LogFormat> for launcher_client::LogFormat {
+    fn from(other: LogFormat) -> Self {
+        match other {
+            LogFormat::Text => launcher_client::LogFormat::Text,
+            LogFormat::Json => launcher_client::LogFormat::Json,
+        }
+    }
+}
+
+/// How the Launcher connects the service's standard streams. Services which aren't detached can
+/// be attached to with `hab svc attach`.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Console {
+    /// Standard input is /dev/null.
+    Detached,
+    /// Standard input is a pipe, written to by attached clients.
+    Stdin,
+    /// The service runs under a pseudo-terminal.
+    Pty,
+}
+
+impl Default for Console {
+    fn default() -> Console {
+        Console::Detached
+    }
+}
+
+impl fmt::Display for Console {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match *self {
+            Console::Detached => "detached",
+            Console::Stdin => "stdin",
+            Console::Pty => "pty",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+impl FromStr for Console {
+    type Err = SupError;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_ref() {
+            "detached" => Ok(Console::Detached),
+            "stdin" => Ok(Console::Stdin),
+            "pty" => Ok(Console::Pty),
+            _ => Err(sup_error!(Error::BadConsole(value.to_string()))),
+        }
+    }
+}
+
+impl From<Console> for launcher_client::Console {
+    fn from(other: Console) -> Self {
+        match other {
+            Console::Detached => launcher_client::Console::Detached,
+            Console::Stdin => launcher_client::Console::Stdin,
+            Console::Pty => launcher_client::Console::Pty,
+        }
+    }
+}
+
+/// How the Launcher writes the service's output to its log file, `logs/service.log` in the
+/// service's directory.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Rotate the log once it grows past this many bytes.
+    pub max_size: u64,
+    /// Rotate the log once it was opened more than this many seconds ago.
+    pub max_age: Option<u64>,
+    /// Number of rotated logs to keep.
+    pub retain: u32,
+}
+
+impl LogConfig {
+    pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+    pub const DEFAULT_RETAIN: u32 = 5;
+
+    /// The log settings the Launcher uses for the log file at `path`.
+    pub fn for_path(&self, path: &Path) -> launcher_client::ServiceLog {
+        launcher_client::ServiceLog {
+            path: path.to_string_lossy().into_owned(),
+            format: self.format.into(),
+            max_size: Some(self.max_size),
+            max_age: self.max_age,
+            retain: self.retain,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            format: LogFormat::default(),
+            max_size: Self::DEFAULT_MAX_SIZE,
+            max_age: None,
+            retain: Self::DEFAULT_RETAIN,
+        }
+    }
+}
+
+/// Opt-in hardening the Launcher applies to the service's process on Linux.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Sandbox {
+    /// Drop every capability from the bounding set except `capabilities`.
+    pub drop_capabilities: bool,
+    pub capabilities: Vec<String>,
+    pub no_new_privs: bool,
+    pub private_tmp: bool,
+    /// Mount the package's path read-only.
+    pub read_only_package: bool,
+    /// A compiled seccomp BPF filter.
+    pub seccomp_profile: Option<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn is_empty(&self) -> bool {
+        *self == Sandbox::default()
+    }
+
+    /// The hardening the Launcher applies when spawning the package installed at `pkg_path`.
+    pub fn for_package(&self, pkg_path: &Path) -> launcher_client::Sandbox {
+        let mut read_only_paths = vec![];
+        if self.read_only_package {
+            read_only_paths.push(pkg_path.to_string_lossy().into_owned());
+        }
+        launcher_client::Sandbox {
+            drop_capabilities: self.drop_capabilities,
+            capabilities: self.capabilities.clone(),
+            no_new_privs: self.no_new_privs,
+            private_tmp: self.private_tmp,
+            read_only_paths,
+            seccomp_profile: self
+                .seccomp_profile
+                .as_ref()
+                .map(|p| p.to_string_lossy().into_owned()),
+        }
+    }
+}
+
 pub fn deserialize_application_environment<'de, D>(
     d: D,
 ) -> result::Result<Option<ApplicationEnvironment>, D::Error>
@@ -149,6 +329,80 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
         if let Some(ref interval) = self.health_check_interval {
             spec.health_check_interval = Duration::from_secs(interval.seconds).into()
         }
+        if let Some(ref config_source) = self.config_source {
+            spec.config_source = Some(config_source.to_string());
+        }
+        if let Some(ref precedence) = self.config_source_precedence {
+            spec.config_source_precedence = precedence.parse().unwrap_or_default();
+        }
+        if let Some(strict_templates) = self.strict_templates {
+            spec.strict_templates = strict_templates;
+        }
+        if let Some(ref shutdown_signal) = self.shutdown_signal {
+            spec.shutdown_signal = Some(shutdown_signal.to_string());
+        }
+        if let Some(shutdown_timeout) = self.shutdown_timeout {
+            spec.shutdown_timeout = Some(shutdown_timeout);
+        }
+        if let Some(memory_max) = self.memory_max {
+            spec.limits.memory_max = Some(memory_max);
+        }
+        if let Some(cpu_weight) = self.cpu_weight {
+            spec.limits.cpu_weight = Some(cpu_weight);
+        }
+        if let Some(cpu_quota) = self.cpu_quota {
+            spec.limits.cpu_quota = Some(cpu_quota);
+        }
+        if let Some(pids_max) = self.pids_max {
+            spec.limits.pids_max = Some(pids_max);
+        }
+        if !self.rlimits.is_empty() {
+            spec.limits.rlimits = self
+                .rlimits
+                .iter()
+                .map(|(name, value)| (name.clone(), RlimitValue::from(*value)))
+                .collect();
+        }
+        if let Some(drop_capabilities) = self.drop_capabilities {
+            spec.sandbox.drop_capabilities = drop_capabilities;
+        }
+        if !self.capabilities.is_empty() {
+            spec.sandbox.capabilities = self.capabilities.clone();
+        }
+        if let Some(no_new_privs) = self.no_new_privs {
+            spec.sandbox.no_new_privs = no_new_privs;
+        }
+        if let Some(private_tmp) = self.private_tmp {
+            spec.sandbox.private_tmp = private_tmp;
+        }
+        if let Some(read_only_package) = self.read_only_package {
+            spec.sandbox.read_only_package = read_only_package;
+        }
+        if let Some(ref seccomp_profile) = self.seccomp_profile {
+            spec.sandbox.seccomp_profile = Some(PathBuf::from(seccomp_profile));
+        }
+        if let Some(ref log_format) = self.log_format {
+            spec.log.format = log_format.parse().unwrap_or_default();
+        }
+        if let Some(log_max_size) = self.log_max_size {
+            spec.log.max_size = log_max_size;
+        }
+        if let Some(log_max_age) = self.log_max_age {
+            spec.log.max_age = Some(log_max_age);
+        }
+        if let Some(log_retain) = self.log_retain {
+            spec.log.retain = log_retain;
+        }
+        if let Some(ref console) = self.console {
+            spec.console = console.parse().unwrap_or_default();
+        }
+        if !self.hook_timeouts.is_empty() {
+            spec.hook_timeouts = self
+                .hook_timeouts
+                .iter()
+                .map(|(hook, seconds)| (hook.clone(), *seconds))
+                .collect();
+        }
     }
 }
 
@@ -178,8 +432,30 @@ pub struct ServiceSpec {
         serialize_with = "serialize_using_to_string"
     )]
     pub desired_state: DesiredState,
-    pub health_check_interval: HealthCheckInterval,
     pub svc_encrypted_password: Option<String>,
+    pub config_source: Option<String>,
+    pub config_source_precedence: ExternalPrecedence,
+    pub strict_templates: bool,
+    /// Overrides the package's signal which asks the service to stop.
+    pub shutdown_signal: Option<String>,
+    /// Overrides the package's seconds the service is given to stop before it is killed.
+    pub shutdown_timeout: Option<u32>,
+    /// How the service's standard streams are connected.
+    pub console: Console,
+    // Fields below are serialized as TOML tables, which must follow every plain value.
+    pub health_check_interval: HealthCheckInterval,
+    /// Overrides the package's seconds each hook may run for before it is terminated, keyed by
+    /// hook name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub hook_timeouts: BTreeMap<String, u32>,
+    /// Memory, CPU, process count and rlimit limits the Launcher applies to the service.
+    #[serde(skip_serializing_if = "ResourceLimits::is_empty")]
+    pub limits: ResourceLimits,
+    /// Hardening the Launcher applies to the service.
+    #[serde(skip_serializing_if = "Sandbox::is_empty")]
+    pub sandbox: Sandbox,
+    /// Format, rotation and retention of the service's log file.
+    pub log: LogConfig,
 }
 
 impl ServiceSpec {
@@ -253,7 +529,14 @@ impl ServiceSpec {
     ///
     /// * If any required package binds are missing in service binds
     /// * If any given service binds are in neither required nor optional package binds
+    /// * If the shutdown timeout is longer than the Launcher allows
     pub fn validate(&self, package: &PackageInstall) -> Result<()> {
+        if let Some(timeout) = self.shutdown_timeout {
+            if timeout > launcher_client::MAX_SHUTDOWN_TIMEOUT {
+                return Err(sup_error!(Error::InvalidShutdownTimeout(timeout)));
+            }
+        }
+
         let mut svc_binds: HashSet<&str> =
             HashSet::from_iter(self.binds.iter().map(ServiceBind::name));
         let mut missing_req_binds = Vec::new();
@@ -307,6 +590,16 @@ impl Default for ServiceSpec {
             desired_state: DesiredState::default(),
             health_check_interval: HealthCheckInterval::default(),
             svc_encrypted_password: None,
+            config_source: None,
+            config_source_precedence: ExternalPrecedence::default(),
+            strict_templates: false,
+            shutdown_signal: None,
+            shutdown_timeout: None,
+            limits: ResourceLimits::default(),
+            sandbox: Sandbox::default(),
+            log: LogConfig::default(),
+            console: Console::default(),
+            hook_timeouts: BTreeMap::new(),
         }
     }
 }
@@ -477,6 +770,32 @@ mod test {
             config_from: Some(PathBuf::from("/only/for/development")),
             desired_state: DesiredState::Down,
             svc_encrypted_password: None,
+            config_source: Some(String::from("/etc/config/name")),
+            config_source_precedence: ExternalPrecedence::AboveUser,
+            strict_templates: true,
+            shutdown_signal: Some(String::from("QUIT")),
+            shutdown_timeout: Some(60),
+            limits: ResourceLimits {
+                memory_max: Some(536_870_912),
+                cpu_quota: Some(150),
+                rlimits: vec![(String::from("nofile"), RlimitValue::Limited(65536))]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+            sandbox: Sandbox {
+                drop_capabilities: true,
+                capabilities: vec![String::from("CAP_NET_BIND_SERVICE")],
+                private_tmp: true,
+                ..Default::default()
+            },
+            log: LogConfig {
+                format: LogFormat::Json,
+                max_age: Some(86400),
+                ..Default::default()
+            },
+            console: Console::Pty,
+            hook_timeouts: vec![(String::from("init"), 300)].into_iter().collect(),
         };
         let toml = spec.to_toml_string().unwrap();
 
@@ -495,6 +814,53 @@ mod test {
         assert!(toml.contains(r#"[health_check_interval]"#));
         assert!(toml.contains(r#"secs = 123"#));
         assert!(toml.contains(r#"nanos = 0"#));
+        assert!(toml.contains(r#"config_source = "/etc/config/name""#));
+        assert!(toml.contains(r#"config_source_precedence = "above-user""#));
+        assert!(toml.contains(r#"strict_templates = true"#));
+        assert!(toml.contains(r#"shutdown_signal = "QUIT""#));
+        assert!(toml.contains(r#"shutdown_timeout = 60"#));
+        assert!(toml.contains(r#"[limits]"#));
+        assert!(toml.contains(r#"memory_max = 536870912"#));
+        assert!(toml.contains(r#"cpu_quota = 150"#));
+        assert!(!toml.contains(r#"cpu_weight"#));
+        assert!(toml.contains(r#"[limits.rlimits]"#));
+        assert!(toml.contains(r#"nofile = 65536"#));
+        assert!(toml.contains(r#"[sandbox]"#));
+        assert!(toml.contains(r#"drop_capabilities = true"#));
+        assert!(toml.contains(r#"capabilities = ["CAP_NET_BIND_SERVICE"]"#));
+        assert!(toml.contains(r#"private_tmp = true"#));
+        assert!(toml.contains(r#"[log]"#));
+        assert!(toml.contains(r#"format = "json""#));
+        assert!(toml.contains(r#"max_size = 10485760"#));
+        assert!(toml.contains(r#"max_age = 86400"#));
+        assert!(toml.contains(r#"retain = 5"#));
+        assert!(toml.contains(r#"console = "pty""#));
+        assert!(toml.contains(r#"[hook_timeouts]"#));
+        assert!(toml.contains(r#"init = 300"#));
+    }
+
+    #[test]
+    fn service_spec_limits_round_trip_through_toml() {
+        let spec = ServiceSpec {
+            ident: PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+            limits: ResourceLimits {
+                pids_max: Some(512),
+                rlimits: vec![
+                    (String::from("core"), RlimitValue::Unlimited),
+                    (String::from("nofile"), RlimitValue::Limited(65536)),
+                ]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let toml = spec.to_toml_string().unwrap();
+        assert!(toml.contains(r#"core = "unlimited""#));
+        assert!(toml.contains(r#"nofile = 65536"#));
+
+        let parsed = ServiceSpec::from_str(&toml).unwrap();
+        assert_eq!(parsed.limits, spec.limits);
     }
 
     #[test]
@@ -657,6 +1023,16 @@ mod test {
             config_from: Some(PathBuf::from("/only/for/development")),
             desired_state: DesiredState::Down,
             svc_encrypted_password: None,
+            config_source: Some(String::from("/etc/config/name")),
+            config_source_precedence: ExternalPrecedence::AboveUser,
+            strict_templates: true,
+            shutdown_signal: Some(String::from("QUIT")),
+            shutdown_timeout: Some(60),
+            limits: ResourceLimits::default(),
+            sandbox: Sandbox::default(),
+            log: LogConfig::default(),
+            console: Console::default(),
+            hook_timeouts: BTreeMap::new(),
         };
         spec.to_file(&path).unwrap();
         let toml = string_from_file(path);
@@ -797,4 +1173,25 @@ mod test {
             Ok(_) => panic!("Spec should not validate"),
         }
     }
+
+    #[test]
+    fn service_spec_error_shutdown_timeout_too_long() {
+        let package = testing_package_install();
+
+        let mut spec = ServiceSpec::default_for(package.ident().clone());
+        spec.binds = vec![ServiceBind::from_str("database:postgresql.app@acmecorp").unwrap()];
+        spec.shutdown_timeout = Some(launcher_client::MAX_SHUTDOWN_TIMEOUT);
+        assert!(spec.validate(&package).is_ok());
+
+        spec.shutdown_timeout = Some(launcher_client::MAX_SHUTDOWN_TIMEOUT + 1);
+        match spec.validate(&package) {
+            Err(e) => match e.err {
+                InvalidShutdownTimeout(t) => {
+                    assert_eq!(launcher_client::MAX_SHUTDOWN_TIMEOUT + 1, t)
+                }
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("Spec should not validate"),
+        }
+    }
 }
@@ -34,12 +34,14 @@ use crate::{
         os::process::{self, Pid},
         service::ServiceGroup,
     },
-    launcher_client::LauncherCli,
+    launcher_client::{
+        self, LauncherCli, ResourceLimits, RunningService, Sandbox, ServiceLog, ShutdownConfig,
+    },
 };
 use serde::{ser::SerializeStruct, Serialize, Serializer};
 use time::{self, Timespec};
 
-use super::{ProcessState, ShutdownReason};
+use super::{Console, ProcessState, ShutdownReason};
 use crate::error::{Error, Result};
 
 static LOGKEY: &'static str = "SV";
@@ -107,6 +109,57 @@ impl Supervisor {
         false
     }
 
+    /// Take over the process the Launcher reported running for this service when the Supervisor
+    /// started, or forget any process recorded in a stale pidfile if it reported none. A process
+    /// which wasn't spawned the way `start` would spawn `pkg` now, for example because the
+    /// service was updated while no Supervisor was running, is forgotten too, and `false` is
+    /// returned so that the caller terminates it and the service is started afresh.
+    pub fn adopt(&mut self, running: Option<&RunningService>, pkg: &Pkg) -> bool {
+        match running {
+            Some(running) if Self::spawned_from(running, pkg) => {
+                debug!(
+                    "Adopting {} started as {} with PID {}",
+                    running.spawn.id, running.spawn.binary, running.pid
+                );
+                self.pid = Some(running.pid as Pid);
+                self.state = ProcessState::Up;
+                self.state_entered = Timespec::new(running.started_at, 0);
+                if let Err(err) = self.create_pidfile() {
+                    warn!("Unable to write pidfile for {}, {}", self.preamble, err);
+                }
+                true
+            }
+            Some(running) => {
+                outputln!(preamble self.preamble,
+                          "Restarting PID {}, it was not started from {}",
+                          running.pid, pkg.ident);
+                self.pid = None;
+                self.cleanup_pidfile();
+                false
+            }
+            None => {
+                self.pid = None;
+                self.cleanup_pidfile();
+                true
+            }
+        }
+    }
+
+    /// Have the Launcher terminate a process this service didn't adopt.
+    pub fn terminate_unadopted(&self, running: &RunningService, launcher: &LauncherCli) {
+        if let Err(err) = launcher.terminate(running.pid as Pid, &running.spawn.shutdown) {
+            warn!(
+                "Unable to terminate {} with PID {}, {}",
+                self.preamble, running.pid, err
+            );
+        }
+    }
+
+    /// Whether `running` runs `pkg`'s run hook with `pkg`'s environment.
+    fn spawned_from(running: &RunningService, pkg: &Pkg) -> bool {
+        Path::new(&running.spawn.binary) == pkg.svc_run && running.spawn.env == *pkg.env
+    }
+
     // NOTE: the &self argument is only used to get access to
     // self.preamble, and even then only for Linux :/
     #[cfg(unix)]
@@ -169,6 +222,12 @@ impl Supervisor {
         group: &ServiceGroup,
         launcher: &LauncherCli,
         svc_password: Option<T>,
+        limits: &ResourceLimits,
+        sandbox: &Sandbox,
+        log: &ServiceLog,
+        console: Console,
+        attach_path: &Path,
+        shutdown: &ShutdownConfig,
     ) -> Result<()>
     where
         T: ToString,
@@ -213,6 +272,12 @@ impl Supervisor {
             service_group_id, // Linux preferred
             svc_password,     // Windows optional
             (*pkg.env).clone(),
+            limits,
+            sandbox,
+            Some(log.clone()),
+            launcher_client::Console::from(console),
+            attach_path,
+            shutdown,
         )?;
         self.pid = Some(pid);
         self.create_pidfile()?;
@@ -234,7 +299,12 @@ impl Supervisor {
         (healthy, status)
     }
 
-    pub fn stop(&mut self, launcher: &LauncherCli, cause: ShutdownReason) -> Result<()> {
+    pub fn stop(
+        &mut self,
+        launcher: &LauncherCli,
+        cause: ShutdownReason,
+        shutdown: &ShutdownConfig,
+    ) -> Result<()> {
         if self.pid.is_none() {
             return Ok(());
         }
@@ -243,7 +313,8 @@ impl Supervisor {
             // we'll avoid this knowing that launcher will gratuitously kill off
             // all services as part of its shutdown routine
         } else {
-            launcher.terminate(self.pid.unwrap())?;
+            let shutdown_method = launcher.terminate(self.pid.unwrap(), shutdown)?;
+            outputln!(preamble self.preamble, "Shutdown OK: {}", shutdown_method);
         }
         self.cleanup_pidfile();
         self.change_state(ProcessState::Down);
@@ -256,12 +327,18 @@ impl Supervisor {
         group: &ServiceGroup,
         launcher: &LauncherCli,
         svc_password: Option<T>,
+        limits: &ResourceLimits,
+        sandbox: &Sandbox,
+        log: &ServiceLog,
+        console: Console,
+        attach_path: &Path,
+        shutdown: &ShutdownConfig,
     ) -> Result<()>
     where
         T: ToString,
     {
         match self.pid {
-            Some(pid) => match launcher.restart(pid) {
+            Some(pid) => match launcher.restart(pid, shutdown) {
                 Ok(pid) => {
                     self.pid = Some(pid);
                     self.create_pidfile()?;
@@ -274,7 +351,18 @@ impl Supervisor {
                     Err(sup_error!(Error::Launcher(err)))
                 }
             },
-            None => self.start(pkg, group, launcher, svc_password),
+            None => self.start(
+                pkg,
+                group,
+                launcher,
+                svc_password,
+                limits,
+                sandbox,
+                log,
+                console,
+                attach_path,
+                shutdown,
+            ),
         }
     }
 
@@ -355,3 +443,104 @@ where
         ))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, str::FromStr};
+
+    use crate::hcore::package::{PackageIdent, PackageInstall};
+    use tempfile::TempDir;
+
+    fn test_pkg() -> Pkg {
+        let ident = if cfg!(target_os = "linux") {
+            PackageIdent::new("core", "tree", Some("1.7.0"), Some("20180609045201"))
+        } else if cfg!(target_os = "windows") {
+            PackageIdent::new("core", "7zip", Some("16.04"), Some("20170131110814"))
+        } else {
+            panic!("This is being run on a platform that's not currently supported");
+        };
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("pkgs");
+        let install = PackageInstall::load(&ident, Some(&path)).expect("loading package");
+        Pkg::from_install(&install).expect("loading pkg")
+    }
+
+    /// A process the Launcher spawned from `pkg`'s run hook with `pkg`'s environment.
+    fn running_from(pkg: &Pkg) -> RunningService {
+        let mut running = RunningService::default();
+        running.pid = 4242;
+        running.started_at = 1_546_300_800;
+        running.spawn.binary = pkg.svc_run.to_string_lossy().into_owned();
+        running.spawn.env = (*pkg.env).clone();
+        running
+    }
+
+    /// A Supervisor whose pidfile, already written, is in `dir`.
+    fn supervisor_in(dir: &TempDir) -> Supervisor {
+        let mut supervisor = Supervisor::new(&ServiceGroup::from_str("tree.default").unwrap());
+        supervisor.pid_file = dir.path().join("PID");
+        fs::write(&supervisor.pid_file, "1234").expect("writing pidfile");
+        supervisor
+    }
+
+    #[test]
+    fn spawned_from_needs_the_same_run_hook_and_environment() {
+        let pkg = test_pkg();
+        let running = running_from(&pkg);
+        assert!(Supervisor::spawned_from(&running, &pkg));
+
+        let mut other_binary = running.clone();
+        other_binary.spawn.binary = "/hab/svc/other/run".to_string();
+        assert!(!Supervisor::spawned_from(&other_binary, &pkg));
+
+        let mut other_env = running;
+        other_env
+            .spawn
+            .env
+            .insert("PATH".to_string(), "/hab/pkgs/core/other/bin".to_string());
+        assert!(!Supervisor::spawned_from(&other_env, &pkg));
+    }
+
+    #[test]
+    fn adopting_a_process_spawned_from_the_package_records_its_pid() {
+        let tmp = TempDir::new().expect("creating temp dir");
+        let pkg = test_pkg();
+        let mut supervisor = supervisor_in(&tmp);
+
+        assert!(supervisor.adopt(Some(&running_from(&pkg)), &pkg));
+        assert_eq!(supervisor.pid, Some(4242));
+        assert_eq!(supervisor.state, ProcessState::Up);
+        assert_eq!(supervisor.state_entered.sec, 1_546_300_800);
+        assert_eq!(
+            read_pid(&supervisor.pid_file).expect("reading pidfile"),
+            4242
+        );
+    }
+
+    #[test]
+    fn a_process_spawned_from_another_release_is_not_adopted() {
+        let tmp = TempDir::new().expect("creating temp dir");
+        let pkg = test_pkg();
+        let mut supervisor = supervisor_in(&tmp);
+        let mut running = running_from(&pkg);
+        running.spawn.binary = "/hab/svc/other/run".to_string();
+
+        assert!(!supervisor.adopt(Some(&running), &pkg));
+        assert_eq!(supervisor.pid, None);
+        assert!(!supervisor.pid_file.exists());
+    }
+
+    #[test]
+    fn a_stale_pidfile_is_forgotten_when_nothing_is_running() {
+        let tmp = TempDir::new().expect("creating temp dir");
+        let pkg = test_pkg();
+        let mut supervisor = supervisor_in(&tmp);
+
+        assert!(supervisor.adopt(None, &pkg));
+        assert_eq!(supervisor.pid, None);
+        assert!(!supervisor.pid_file.exists());
+    }
+}
@@ -53,10 +53,35 @@ static LOGKEY: &'static str = "SU";
 const PERIOD_BYPASS_CHECK_ENVVAR: &str = "HAB_UPDATE_STRATEGY_FREQUENCY_BYPASS_CHECK";
 lazy_static! {
     static ref MIN_ALLOWED_PERIOD: Duration = Duration::seconds(60);
+    /// How long a package a service declined to update to isn't offered to it again.
+    static ref DECLINED_UPDATE_BACKOFF: Duration = Duration::minutes(10);
 }
 
 type UpdaterStateList = HashMap<ServiceGroup, UpdaterState>;
 
+/// A package a service declined to update to, for example because its pre-update hook vetoed
+/// the update. The package isn't offered to the service again until `retry_at`, although a newer
+/// one is.
+#[derive(Clone, Debug)]
+struct DeclinedUpdate {
+    ident: PackageIdent,
+    retry_at: SteadyTime,
+}
+
+impl DeclinedUpdate {
+    fn new(ident: PackageIdent) -> Self {
+        DeclinedUpdate {
+            ident,
+            retry_at: SteadyTime::now() + *DECLINED_UPDATE_BACKOFF,
+        }
+    }
+
+    /// Whether updating to `ident` is still declined.
+    fn declines(&self, ident: &PackageIdent) -> bool {
+        self.ident == *ident && SteadyTime::now() < self.retry_at
+    }
+}
+
 enum UpdaterState {
     AtOnce(Receiver<PackageInstall>, Sender<()>),
     Rolling(RollingState),
@@ -86,8 +111,15 @@ enum FollowerState {
 /// The ServiceUpdater is in charge of updating a Service when a more recent version of a package
 /// has been published to a depot or installed to the local package cache.
 /// To use an update strategy, the supervisor must be configured to watch a depot for new versions.
+///
+/// A service may decline an update, in which case it keeps running its current package. In a
+/// rolling update, a follower which declines the leader's package stays on its own, so the
+/// followers after it keep waiting for their turn and the leader keeps waiting for every member
+/// to run its package before looking for a newer one. The follower tries the leader's package
+/// again once `DECLINED_UPDATE_BACKOFF` has passed.
 pub struct ServiceUpdater {
     states: UpdaterStateList,
+    declined: HashMap<ServiceGroup, DeclinedUpdate>,
     butterfly: butterfly::Server,
 }
 
@@ -95,6 +127,7 @@ impl ServiceUpdater {
     pub fn new(butterfly: butterfly::Server) -> Self {
         ServiceUpdater {
             states: UpdaterStateList::default(),
+            declined: HashMap::new(),
             butterfly,
         }
     }
@@ -110,7 +143,8 @@ impl ServiceUpdater {
                     .entry(service.service_group.clone())
                     .or_insert_with(|| {
                         let (kill_tx, kill_rx) = channel();
-                        let rx = Worker::new(service).start(&service.service_group, None, kill_rx);
+                        let rx =
+                            Worker::new(service, None).start(&service.service_group, None, kill_rx);
                         UpdaterState::AtOnce(rx, kill_tx)
                     });
                 true
@@ -126,6 +160,7 @@ impl ServiceUpdater {
 
     /// Remove a `Service` from updates, e.g. if the service was unloaded.
     pub fn remove(&mut self, service: &Service) {
+        self.declined.remove(&service.service_group);
         match self.states.remove(&service.service_group) {
             Some(UpdaterState::AtOnce(_rx, kill_tx)) => {
                 if kill_tx.send(()).is_err() {
@@ -160,6 +195,22 @@ impl ServiceUpdater {
         }
     }
 
+    /// Describe where the given service group is in its update strategy. Returns `None` if the
+    /// service group has no update strategy.
+    pub fn update_state(&self, service_group: &ServiceGroup) -> Option<&'static str> {
+        self.states.get(service_group).map(|state| match *state {
+            UpdaterState::AtOnce(..) => "polling",
+            UpdaterState::Rolling(ref rs) => match *rs {
+                RollingState::AwaitingElection => "awaiting-election",
+                RollingState::InElection => "in-election",
+                RollingState::Leader(LeaderState::Polling(..)) => "leader-polling",
+                RollingState::Leader(LeaderState::Waiting) => "leader-waiting",
+                RollingState::Follower(FollowerState::Waiting) => "follower-waiting",
+                RollingState::Follower(FollowerState::Updating(..)) => "follower-updating",
+            },
+        })
+    }
+
     /// See if the given service has an update. Returns `true` if a
     /// new version was installed, thus signalling that the service
     /// should be restarted
@@ -170,17 +221,21 @@ impl ServiceUpdater {
         launcher: &LauncherCli,
     ) -> bool {
         let mut updated = false;
+        let declined = self.declined.get(&service.service_group).cloned();
         match self.states.get_mut(&service.service_group) {
             Some(&mut UpdaterState::AtOnce(ref mut rx, ref mut kill_tx)) => match rx.try_recv() {
                 Ok(package) => {
-                    service.update_package(package, launcher);
-                    return true;
+                    return update_service(&mut self.declined, service, package, launcher);
                 }
                 Err(TryRecvError::Empty) => return false,
                 Err(TryRecvError::Disconnected) => {
                     debug!("Service Updater worker has died; restarting...");
                     let (ktx, krx) = channel();
-                    *rx = Worker::new(service).start(&service.service_group, None, krx);
+                    *rx = Worker::new(service, declined.as_ref()).start(
+                        &service.service_group,
+                        None,
+                        krx,
+                    );
                     *kill_tx = ktx;
                 }
             },
@@ -240,44 +295,52 @@ impl ServiceUpdater {
                     LeaderState::Polling(ref mut rx, ref mut kill_tx) => match rx.try_recv() {
                         Ok(package) => {
                             debug!("Rolling Update, polling found a new package");
-                            service.update_package(package, launcher);
-                            updated = true;
+                            updated =
+                                update_service(&mut self.declined, service, package, launcher);
                         }
                         Err(TryRecvError::Empty) => return false,
                         Err(TryRecvError::Disconnected) => {
                             debug!("Service Updater worker has died; restarting...");
                             let (ktx, krx) = channel();
-                            *rx = Worker::new(service).start(&service.service_group, None, krx);
+                            *rx = Worker::new(service, declined.as_ref()).start(
+                                &service.service_group,
+                                None,
+                                krx,
+                            );
                             *kill_tx = ktx;
                         }
                     },
-                    LeaderState::Waiting => match census_ring
-                        .census_group_for(&service.service_group)
-                    {
-                        Some(census_group) => {
-                            if census_group
-                                .members()
-                                .any(|cm| cm.pkg != census_group.me().unwrap().pkg)
-                            {
-                                debug!("Update leader still waiting for followers...");
-                                return false;
+                    LeaderState::Waiting => {
+                        match census_ring.census_group_for(&service.service_group) {
+                            Some(census_group) => {
+                                if census_group
+                                    .members()
+                                    .any(|cm| cm.pkg != census_group.me().unwrap().pkg)
+                                {
+                                    debug!("Update leader still waiting for followers...");
+                                    return false;
+                                }
+                                let (kill_tx, kill_rx) = channel();
+                                let rx = Worker::new(service, declined.as_ref()).start(
+                                    &service.service_group,
+                                    None,
+                                    kill_rx,
+                                );
+                                *state = LeaderState::Polling(rx, kill_tx);
                             }
-                            let (kill_tx, kill_rx) = channel();
-                            let rx =
-                                Worker::new(service).start(&service.service_group, None, kill_rx);
-                            *state = LeaderState::Polling(rx, kill_tx);
+                            None => panic!(
+                                "Expected census list to have service group '{}'!",
+                                &*service.service_group
+                            ),
                         }
-                        None => panic!(
-                            "Expected census list to have service group '{}'!",
-                            &*service.service_group
-                        ),
-                    },
+                    }
                 }
                 if updated {
                     *state = LeaderState::Waiting;
                 }
             }
             Some(&mut UpdaterState::Rolling(RollingState::Follower(ref mut state))) => {
+                let mut finished = false;
                 match *state {
                     FollowerState::Waiting => {
                         match census_ring.census_group_for(&service.service_group) {
@@ -295,9 +358,17 @@ impl ServiceUpdater {
                                         debug!("We're in an update but it's not our turn");
                                         return false;
                                     }
+                                    if let (Some(declined), Some(ident)) =
+                                        (declined.as_ref(), leader.pkg.as_ref())
+                                    {
+                                        if declined.declines(ident) {
+                                            debug!("We declined the update to {} for now", ident);
+                                            return false;
+                                        }
+                                    }
                                     debug!("We're in an update and it's our turn");
                                     let (kill_tx, kill_rx) = channel();
-                                    let rx = Worker::new(service).start(
+                                    let rx = Worker::new(service, None).start(
                                         &service.service_group,
                                         leader.pkg.clone(),
                                         kill_rx,
@@ -316,15 +387,20 @@ impl ServiceUpdater {
                         match census_ring.census_group_for(&service.service_group) {
                             Some(census_group) => match rx.try_recv() {
                                 Ok(package) => {
-                                    service.update_package(package, launcher);
-                                    updated = true
+                                    updated = update_service(
+                                        &mut self.declined,
+                                        service,
+                                        package,
+                                        launcher,
+                                    );
+                                    finished = true;
                                 }
                                 Err(TryRecvError::Empty) => return false,
                                 Err(TryRecvError::Disconnected) => {
                                     debug!("Service Updater worker has died; restarting...");
                                     let package = census_group.update_leader().unwrap().pkg.clone();
                                     let (ktx, krx) = channel();
-                                    *rx = Worker::new(service).start(
+                                    *rx = Worker::new(service, None).start(
                                         &service.service_group,
                                         package,
                                         krx,
@@ -339,7 +415,7 @@ impl ServiceUpdater {
                         }
                     }
                 }
-                if updated {
+                if finished {
                     *state = FollowerState::Waiting;
                 }
             }
@@ -349,6 +425,27 @@ impl ServiceUpdater {
     }
 }
 
+/// Update `service` to `package`, remembering the package if the service declines it. Returns
+/// whether the service was updated.
+fn update_service(
+    declined: &mut HashMap<ServiceGroup, DeclinedUpdate>,
+    service: &mut Service,
+    package: PackageInstall,
+    launcher: &LauncherCli,
+) -> bool {
+    let ident = package.ident().clone();
+    if service.update_package(package, launcher) {
+        declined.remove(&service.service_group);
+        true
+    } else {
+        outputln!(preamble service.service_group,
+                  "Not updating to {} again for {} minutes",
+                  ident, DECLINED_UPDATE_BACKOFF.num_minutes());
+        declined.insert(service.service_group.clone(), DeclinedUpdate::new(ident));
+        false
+    }
+}
+
 /// Represents how far apart checks for updates to individual services
 /// are, in milliseconds.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -402,6 +499,8 @@ impl EnvConfig for ServiceUpdatePeriod {
 
 struct Worker {
     current: PackageIdent,
+    /// A package the service declined, which polling passes over.
+    declined: Option<DeclinedUpdate>,
     spec_ident: PackageIdent,
     builder_url: String,
     channel: ChannelIdent,
@@ -421,9 +520,10 @@ impl Periodic for Worker {
 }
 
 impl Worker {
-    fn new(service: &Service) -> Self {
+    fn new(service: &Service, declined: Option<&DeclinedUpdate>) -> Self {
         Worker {
             current: service.pkg.ident.clone(),
+            declined: declined.cloned(),
             spec_ident: service.spec_ident.clone(),
             builder_url: service.bldr_url.clone(),
             channel: service.channel.clone(),
@@ -551,7 +651,16 @@ impl Worker {
                     &self.channel,
                 ) {
                     Ok(maybe_newer_package) => {
-                        if self.current < *maybe_newer_package.ident() {
+                        if self
+                            .declined
+                            .as_ref()
+                            .map_or(false, |d| d.declines(maybe_newer_package.ident()))
+                        {
+                            debug!(
+                                "Package found {} was declined by the service",
+                                maybe_newer_package.ident()
+                            );
+                        } else if self.current < *maybe_newer_package.ident() {
                             outputln!(
                                 "Updating from {} to {}",
                                 self.current,
@@ -598,6 +707,7 @@ mod tests {
             current: "core/testing/1.0.0/20181109125930"
                 .parse()
                 .expect("Can't parse ident!"),
+            declined: None,
             spec_ident: "core/testing".parse().expect("Can't parse ident!"),
             builder_url: String::from("https://bldr.habitat.sh"),
             channel: ChannelIdent::stable(),
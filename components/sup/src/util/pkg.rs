@@ -20,6 +20,7 @@ use crate::{
         command::package::install::{
             InstallHookMode, InstallMode, InstallSource, LocalPackageUsage,
         },
+        trace,
         ui::UIWriter,
     },
     hcore::{
@@ -53,6 +54,9 @@ where
         Ok(v) => Some(v),
         Err(_) => None,
     };
+    let mut span = trace::span("package.install")
+        .attr("package_ident", install_source.as_ref())
+        .attr("channel", channel);
 
     common::command::package::install::start(
         ui,
@@ -77,7 +81,10 @@ where
         // in add_service so it is repetitive to run them here
         InstallHookMode::Ignore,
     )
-    .map_err(SupError::from)
+    .map_err(|err| {
+        span.set_error(&err);
+        SupError::from(err)
+    })
 }
 
 /// Given an InstallSource, install a new package only if an existing